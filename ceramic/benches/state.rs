@@ -0,0 +1,49 @@
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use dataverse_ceramic::commit::example;
+use dataverse_ceramic::StreamState;
+
+const LOG_LENGTHS: [usize; 3] = [10, 100, 1000];
+
+fn make_state(c: &mut Criterion) {
+	let runtime = tokio::runtime::Runtime::new().unwrap();
+	let mut group = c.benchmark_group("stream_state_make");
+	for len in LOG_LENGTHS {
+		let events = example::events(len);
+		group.bench_with_input(BenchmarkId::from_parameter(len), &events, |b, events| {
+			b.to_async(&runtime).iter_batched(
+				|| events.clone(),
+				|events| async move { StreamState::make(3, events).await.unwrap() },
+				BatchSize::SmallInput,
+			)
+		});
+	}
+	group.finish();
+}
+
+fn apply_patch(c: &mut Criterion) {
+	let content = serde_json::json!({
+		"text": "hello",
+		"images": ["https://example.com/0.png"],
+		"videos": [],
+		"encrypted": "{\"text\":false,\"images\":false,\"videos\":false}",
+		"createdAt": "2023-11-08T06:57:01.890Z",
+		"updatedAt": "2023-11-08T06:57:01.890Z",
+	});
+	let patch: json_patch::Patch = serde_json::from_value(serde_json::json!([
+		{ "op": "replace", "path": "/text", "value": "world" },
+		{ "op": "add", "path": "/images/1", "value": "https://example.com/1.png" },
+		{ "op": "replace", "path": "/updatedAt", "value": "2023-11-08T06:57:03.689Z" },
+	]))
+	.unwrap();
+
+	c.bench_function("json_patch", |b| {
+		b.iter_batched(
+			|| content.clone(),
+			|mut content| json_patch::patch(&mut content, &patch).unwrap(),
+			BatchSize::SmallInput,
+		)
+	});
+}
+
+criterion_group!(benches, make_state, apply_patch);
+criterion_main!(benches);
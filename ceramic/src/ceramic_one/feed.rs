@@ -0,0 +1,288 @@
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use ceramic_core::{Cid, StreamId, StreamIdType};
+use int_enum::IntEnum;
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use super::{Client, EventData};
+use crate::checkpoint::{Checkpoint, CheckpointStore};
+use crate::event::{Event, EventValue, EventsUploader, VerifyOption};
+use crate::interests::Interests;
+use crate::stream::single::MID_TYPE;
+use crate::Ceramic;
+
+/// Page of the event feed, the resume token continues after its last event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedPage {
+	pub events: Vec<EventData>,
+	pub resume_token: String,
+}
+
+impl Client {
+	/// Events of the node in the order it stored them, from the start of the
+	/// feed without a resume token
+	pub async fn feed(
+		&self,
+		ceramic: &Ceramic,
+		resume_at: Option<&str>,
+		limit: usize,
+	) -> anyhow::Result<FeedPage> {
+		let mut url = format!(
+			"{}?includeData=full&limit={}",
+			Self::url(&ceramic.endpoint, "feed/events"),
+			limit
+		);
+		if let Some(resume_at) = resume_at {
+			url = format!("{}&resumeAt={}", url, resume_at);
+		}
+		self.get(url).await
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedConfig {
+	/// events requested per page
+	pub limit: usize,
+	/// pause once the feed is caught up, or after a failed page
+	pub interval: Duration,
+	/// genesis events whose models are kept, the least recently used are
+	/// evicted past it
+	pub genesis_cache_size: NonZeroUsize,
+}
+
+impl Default for FeedConfig {
+	fn default() -> Self {
+		Self {
+			limit: 100,
+			interval: Duration::from_secs(5),
+			genesis_cache_size: NonZeroUsize::new(10_000).expect("cache size is not zero"),
+		}
+	}
+}
+
+/// Tails the event feed of a ceramic-one node, storing the events of the
+/// replicated models, as an alternative to syncing tips over pubsub
+pub struct FeedConsumer {
+	client: Client,
+	ceramic: Ceramic,
+	interests: Arc<Interests>,
+	uploader: Arc<dyn EventsUploader + Send + Sync>,
+	checkpoints: Arc<dyn CheckpointStore>,
+	config: FeedConfig,
+	/// models of the genesis events seen so far, data and anchor events only
+	/// link their genesis
+	genesis_models: Mutex<LruCache<Cid, StreamId>>,
+}
+
+impl FeedConsumer {
+	pub fn new(
+		ceramic: Ceramic,
+		interests: Arc<Interests>,
+		uploader: Arc<dyn EventsUploader + Send + Sync>,
+		checkpoints: Arc<dyn CheckpointStore>,
+		config: FeedConfig,
+	) -> Self {
+		Self {
+			client: Client::new(),
+			ceramic,
+			interests,
+			uploader,
+			checkpoints,
+			genesis_models: Mutex::new(LruCache::new(config.genesis_cache_size)),
+			config,
+		}
+	}
+
+	/// Checkpoint topic of the feed, one per node
+	pub fn topic(&self) -> String {
+		format!("feed:{}", self.ceramic.endpoint)
+	}
+
+	/// Model of the stream of the event, `None` if the event does not link a
+	/// valid genesis. Fails only when the genesis cannot be loaded from the node
+	async fn model(&self, event: &Event) -> anyhow::Result<Option<StreamId>> {
+		let genesis = match event.genesis() {
+			Ok(genesis) => genesis,
+			Err(err) => {
+				tracing::warn!(cid = event.cid.to_string(), "invalid feed event: {}", err);
+				return Ok(None);
+			}
+		};
+		if let Some(model) = self.genesis_models.lock().await.get(&genesis) {
+			return Ok(Some(model.clone()));
+		}
+		let genesis_event = match genesis == event.cid {
+			true => event.clone(),
+			false => self.client.load_event(&self.ceramic, &genesis).await?,
+		};
+		let model = match Self::genesis_model(&genesis_event) {
+			Ok(model) => model,
+			Err(err) => {
+				tracing::warn!(genesis = genesis.to_string(), "invalid genesis: {}", err);
+				return Ok(None);
+			}
+		};
+		self.genesis_models.lock().await.put(genesis, model.clone());
+		Ok(Some(model))
+	}
+
+	fn genesis_model(genesis: &Event) -> anyhow::Result<StreamId> {
+		match &genesis.value {
+			EventValue::Signed(signed) => Ok(signed
+				.payload()?
+				.header
+				.context("genesis without header")?
+				.model),
+			EventValue::Anchor(_) => anyhow::bail!("anchor event {} as genesis", genesis.cid),
+		}
+	}
+
+	/// Stream of the event if it belongs to a replicated model and is valid,
+	/// `None` for events of other models
+	pub async fn accept(&self, event: &Event) -> anyhow::Result<Option<StreamId>> {
+		let model = match self.model(event).await? {
+			Some(model) => model,
+			None => anyhow::bail!("invalid genesis of event {}", event.cid),
+		};
+		if !self.interests.contains(&model) {
+			return Ok(None);
+		}
+		Self::validate(event, model).map(Some)
+	}
+
+	/// Stream of an event of the model, failing if the event is invalid
+	fn validate(event: &Event, model: StreamId) -> anyhow::Result<StreamId> {
+		match &event.value {
+			EventValue::Signed(_) => {
+				event.verify_signature(vec![VerifyOption::ResourceModelsContain(model)])?;
+			}
+			EventValue::Anchor(anchor) => {
+				anchor.proof()?.context("anchor without proof block")?;
+			}
+		}
+		Ok(StreamId {
+			r#type: StreamIdType::from_int(MID_TYPE)?,
+			cid: event.genesis()?,
+		})
+	}
+
+	/// Consume one page of the feed, returns the number of events in it.
+	/// Invalid events are skipped, failing to resolve the model of an event or
+	/// to upload it leaves the resume token so the page is consumed again
+	pub async fn poll(&self) -> anyhow::Result<usize> {
+		let topic = self.topic();
+		let resume_at = self.checkpoints.load(&topic).await?.map(|x| x.seqno);
+		let page = self
+			.client
+			.feed(&self.ceramic, resume_at.as_deref(), self.config.limit)
+			.await?;
+
+		for data in &page.events {
+			let event = match Event::try_from(data) {
+				Ok(event) => event,
+				Err(err) => {
+					tracing::warn!(id = ?data.id, "failed to decode feed event: {}", err);
+					continue;
+				}
+			};
+			let model = match self.model(&event).await? {
+				Some(model) if self.interests.contains(&model) => model,
+				_ => continue,
+			};
+			let stream_id = match Self::validate(&event, model) {
+				Ok(stream_id) => stream_id,
+				Err(err) => {
+					tracing::warn!(cid = event.cid.to_string(), "invalid feed event: {}", err);
+					continue;
+				}
+			};
+			self.uploader
+				.upload_event(&self.ceramic, &stream_id, event)
+				.await?;
+		}
+
+		let checkpoint = Checkpoint::new(&topic, &page.resume_token);
+		self.checkpoints.save(&checkpoint).await?;
+		Ok(page.events.len())
+	}
+
+	/// Tail the feed until the task is dropped
+	pub async fn run(&self) {
+		loop {
+			match self.poll().await {
+				Ok(len) if len >= self.config.limit => continue,
+				Ok(_) => {}
+				Err(err) => tracing::warn!(topic = self.topic(), "failed to consume feed: {}", err),
+			}
+			tokio::time::sleep(self.config.interval).await;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::checkpoint::FileCheckpoints;
+	use crate::commit::example;
+	use crate::network::Network;
+
+	struct NullUploader;
+
+	#[async_trait::async_trait]
+	impl EventsUploader for NullUploader {
+		async fn upload_event(
+			&self,
+			_ceramic: &Ceramic,
+			_stream_id: &StreamId,
+			_event: Event,
+		) -> anyhow::Result<()> {
+			Ok(())
+		}
+	}
+
+	fn consumer(models: Vec<StreamId>) -> FeedConsumer {
+		let ceramic = Ceramic {
+			endpoint: "http://localhost:5101".to_string(),
+			network: Network::InMemory,
+			api: crate::CeramicApi::CeramicOne,
+		};
+		let checkpoints = FileCheckpoints::new(std::env::temp_dir().join("feed-checkpoints.json"));
+		FeedConsumer::new(
+			ceramic,
+			Arc::new(Interests::new(models)),
+			Arc::new(NullUploader),
+			Arc::new(checkpoints),
+			FeedConfig::default(),
+		)
+	}
+
+	#[tokio::test]
+	async fn test_accept() -> anyhow::Result<()> {
+		let genesis = example::genesis();
+		let consumer = consumer(vec![genesis.model_id()?]);
+		for event in example::events(2) {
+			let stream_id = consumer.accept(&event).await?;
+			assert_eq!(stream_id, Some(genesis.stream_id()?));
+		}
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_skip_other_models() -> anyhow::Result<()> {
+		let other: StreamId =
+			"kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju".parse()?;
+		let consumer = consumer(vec![other]);
+		// the data event resolves its model from the genesis seen before it,
+		// without loading the genesis from the node
+		for event in example::events(2) {
+			assert_eq!(consumer.accept(&event).await?, None);
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,207 @@
+pub mod feed;
+
+use ceramic_core::{Cid, StreamId};
+use dataverse_multibase::MultiBase64UrlString;
+use serde::{Deserialize, Serialize};
+
+use crate::event::{Car, Event, EventsLoader, EventsUploader};
+use crate::network::Network;
+use crate::retry::StatusError;
+use crate::Ceramic;
+
+/// Event as exchanged with the event api, a multibase CAR rooted at the event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventData {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub id: Option<String>,
+	pub data: String,
+}
+
+impl TryFrom<&Event> for EventData {
+	type Error = anyhow::Error;
+
+	fn try_from(event: &Event) -> Result<Self, Self::Error> {
+		let car = Car::try_from(event)?.to_vec()?;
+		Ok(Self {
+			id: None,
+			data: MultiBase64UrlString::encode(car).into(),
+		})
+	}
+}
+
+impl TryFrom<&EventData> for Event {
+	type Error = anyhow::Error;
+
+	fn try_from(value: &EventData) -> Result<Self, Self::Error> {
+		let (_, car) = multibase::decode(&value.data)?;
+		Car::decode(&car)?.event()
+	}
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NetworkInfo {
+	name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StreamTip {
+	event_cid: String,
+}
+
+/// Client of the ceramic-one http api, exchanging events as CARs over
+/// `/ceramic/events` instead of the commits of the js-ceramic api
+#[derive(Debug, Clone, Default)]
+pub struct Client {
+	client: reqwest::Client,
+}
+
+impl Client {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn with_http(client: reqwest::Client) -> Self {
+		Self { client }
+	}
+
+	pub(crate) fn url(endpoint: &str, path: &str) -> String {
+		format!("{}/ceramic/{}", endpoint.trim_end_matches('/'), path)
+	}
+
+	pub(crate) async fn get<T: serde::de::DeserializeOwned>(
+		&self,
+		url: String,
+	) -> anyhow::Result<T> {
+		let res = self.client.get(url).send().await?;
+		let status = res.status();
+		if !status.is_success() {
+			let message = res.text().await.unwrap_or_default();
+			anyhow::bail!(StatusError::new(status.as_u16(), message));
+		}
+		Ok(res.json().await?)
+	}
+
+	/// Network of the node if it serves the ceramic-one api, `None` for
+	/// js-ceramic nodes which have no liveness route
+	pub async fn detect(&self, endpoint: &str) -> anyhow::Result<Option<Network>> {
+		match self
+			.client
+			.get(Self::url(endpoint, "liveness"))
+			.send()
+			.await
+		{
+			Ok(res) if res.status().is_success() => {}
+			_ => return Ok(None),
+		}
+		let info: NetworkInfo = self.get(Self::url(endpoint, "config/network")).await?;
+		Ok(Some(info.name.parse()?))
+	}
+
+	pub async fn load_event(&self, ceramic: &Ceramic, cid: &Cid) -> anyhow::Result<Event> {
+		let url = Self::url(&ceramic.endpoint, &format!("events/{}", cid));
+		let data: EventData = self.get(url).await?;
+		let event = Event::try_from(&data)?;
+		if event.cid != *cid {
+			anyhow::bail!("node returned event {} for {}", event.cid, cid);
+		}
+		Ok(event)
+	}
+
+	async fn stream_tip(&self, ceramic: &Ceramic, stream_id: &StreamId) -> anyhow::Result<Cid> {
+		let url = Self::url(&ceramic.endpoint, &format!("streams/{}", stream_id));
+		let tip: StreamTip = self.get(url).await?;
+		Ok(tip.event_cid.parse()?)
+	}
+}
+
+#[async_trait::async_trait]
+impl EventsLoader for Client {
+	/// Events from genesis to the tip, walked back along `prev` from the tip
+	async fn load_events(
+		&self,
+		ceramic: &Ceramic,
+		stream_id: &StreamId,
+		tip: Option<Cid>,
+	) -> anyhow::Result<Vec<Event>> {
+		let mut cid = match tip {
+			Some(tip) => tip,
+			None => self.stream_tip(ceramic, stream_id).await?,
+		};
+		let mut events = Vec::new();
+		loop {
+			let event = self.load_event(ceramic, &cid).await?;
+			let prev = event.prev()?;
+			events.push(event);
+			match prev {
+				Some(prev) => cid = prev,
+				None => break,
+			}
+		}
+		events.reverse();
+		if events[0].cid != stream_id.cid {
+			anyhow::bail!("events of {} not of stream {}", events[0].cid, stream_id);
+		}
+		Ok(events)
+	}
+}
+
+#[async_trait::async_trait]
+impl EventsUploader for Client {
+	async fn upload_event(
+		&self,
+		ceramic: &Ceramic,
+		stream_id: &StreamId,
+		event: Event,
+	) -> anyhow::Result<()> {
+		let res = self
+			.client
+			.post(Self::url(&ceramic.endpoint, "events"))
+			.json(&EventData::try_from(&event)?)
+			.send()
+			.await?;
+		let status = res.status();
+		if !status.is_success() {
+			let message = res.text().await.unwrap_or_default();
+			anyhow::bail!(StatusError::new(status.as_u16(), message));
+		}
+		tracing::info!(
+			cid = event.cid.to_string(),
+			stream_id = stream_id.to_string(),
+			"published event"
+		);
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::commit::example;
+
+	#[test]
+	fn test_event_data() -> anyhow::Result<()> {
+		let genesis: Event = example::genesis().genesis.try_into()?;
+		let data = EventData::try_from(&genesis)?;
+		assert!(data.data.starts_with('u'));
+		assert_eq!(
+			serde_json::to_value(&data)?,
+			serde_json::json!({ "data": data.data })
+		);
+
+		let event = Event::try_from(&data)?;
+		assert_eq!(event.cid, genesis.cid);
+		assert_eq!(event.genesis()?, genesis.cid);
+		Ok(())
+	}
+
+	#[test]
+	fn test_url() {
+		assert_eq!(
+			Client::url("http://localhost:5101/", "events"),
+			"http://localhost:5101/ceramic/events"
+		);
+	}
+}
@@ -0,0 +1,271 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+/// Last pubsub message consumed on a topic
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Checkpoint {
+	pub topic: String,
+	pub seqno: String,
+	pub updated_at: DateTime<Utc>,
+	/// latest sequence number consumed of each publisher of the topic
+	#[serde(default)]
+	pub peers: BTreeMap<String, u64>,
+}
+
+impl Checkpoint {
+	pub fn new(topic: &str, seqno: &str) -> Self {
+		Self {
+			topic: topic.to_string(),
+			seqno: seqno.to_string(),
+			updated_at: Utc::now(),
+			peers: BTreeMap::new(),
+		}
+	}
+}
+
+/// Sequence number of a pubsub message, multibase encoded bytes as sent by
+/// kubo or 16 hex digits
+pub fn parse_seqno(seqno: &str) -> Option<u64> {
+	if seqno.len() == 16 {
+		if let Ok(seqno) = u64::from_str_radix(seqno, 16) {
+			return Some(seqno);
+		}
+	}
+	let (_, bytes) = multibase::decode(seqno).ok()?;
+	if bytes.is_empty() || bytes.len() > 8 {
+		return None;
+	}
+	let mut buf = [0u8; 8];
+	buf[8 - bytes.len()..].copy_from_slice(&bytes);
+	Some(u64::from_be_bytes(buf))
+}
+
+/// Persisted consumption offsets of pubsub topics
+#[async_trait::async_trait]
+pub trait CheckpointStore: Send + Sync {
+	async fn load(&self, topic: &str) -> anyhow::Result<Option<Checkpoint>>;
+	async fn save(&self, checkpoint: &Checkpoint) -> anyhow::Result<()>;
+}
+
+/// Json file of checkpoints keyed by topic
+pub struct FileCheckpoints {
+	path: PathBuf,
+	checkpoints: Mutex<Option<HashMap<String, Checkpoint>>>,
+}
+
+impl FileCheckpoints {
+	pub fn new(path: impl Into<PathBuf>) -> Self {
+		Self {
+			path: path.into(),
+			checkpoints: Mutex::new(None),
+		}
+	}
+
+	async fn read(&self) -> anyhow::Result<HashMap<String, Checkpoint>> {
+		match tokio::fs::read(&self.path).await {
+			Ok(content) => Ok(serde_json::from_slice(&content)?),
+			Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+			Err(err) => Err(err.into()),
+		}
+	}
+}
+
+#[async_trait::async_trait]
+impl CheckpointStore for FileCheckpoints {
+	async fn load(&self, topic: &str) -> anyhow::Result<Option<Checkpoint>> {
+		let mut checkpoints = self.checkpoints.lock().await;
+		if checkpoints.is_none() {
+			*checkpoints = Some(self.read().await?);
+		}
+		Ok(checkpoints.as_ref().and_then(|x| x.get(topic).cloned()))
+	}
+
+	async fn save(&self, checkpoint: &Checkpoint) -> anyhow::Result<()> {
+		let mut checkpoints = self.checkpoints.lock().await;
+		if checkpoints.is_none() {
+			*checkpoints = Some(self.read().await?);
+		}
+		let checkpoints = checkpoints.get_or_insert_with(HashMap::new);
+		checkpoints.insert(checkpoint.topic.clone(), checkpoint.clone());
+
+		let tmp = self.path.with_extension("tmp");
+		tokio::fs::write(&tmp, serde_json::to_vec(checkpoints)?).await?;
+		tokio::fs::rename(&tmp, &self.path).await?;
+		Ok(())
+	}
+}
+
+/// Consumed messages between two writes of a tracked checkpoint
+pub const SAVE_EVERY: usize = 100;
+
+/// Checkpoint of a pubsub topic updated as messages are consumed and written
+/// every `save_every` messages.
+///
+/// A message of a publisher past the next sequence number expected from it
+/// marks a gap, e.g. messages published while the node was offline. Sequence
+/// numbers of a publisher are shared by all its topics, so a publisher active
+/// on other topics shows gaps too, only costing extra reconciling.
+pub struct CheckpointTracker {
+	store: Arc<dyn CheckpointStore>,
+	save_every: usize,
+	state: Mutex<TrackerState>,
+}
+
+struct TrackerState {
+	checkpoint: Checkpoint,
+	latest: Option<u64>,
+	pending: usize,
+	gap: bool,
+}
+
+impl CheckpointTracker {
+	/// Track the topic from its stored checkpoint
+	pub async fn load(store: Arc<dyn CheckpointStore>, topic: &str) -> anyhow::Result<Self> {
+		let checkpoint = store.load(topic).await?;
+		let latest = checkpoint.as_ref().and_then(|x| parse_seqno(&x.seqno));
+		Ok(Self {
+			store,
+			save_every: SAVE_EVERY,
+			state: Mutex::new(TrackerState {
+				checkpoint: checkpoint.unwrap_or_else(|| Checkpoint::new(topic, "")),
+				latest,
+				pending: 0,
+				gap: false,
+			}),
+		})
+	}
+
+	pub fn with_save_every(mut self, save_every: usize) -> Self {
+		self.save_every = save_every.max(1);
+		self
+	}
+
+	/// Record a consumed message of the publisher, true if messages it
+	/// published before were missed. Messages are recorded in the order they
+	/// are received, as their handling may complete out of order
+	pub async fn record(&self, from: &str, seqno: &str) -> anyhow::Result<bool> {
+		let seqno = match parse_seqno(seqno) {
+			Some(seqno) => seqno,
+			None => anyhow::bail!("invalid seqno {}", seqno),
+		};
+		let mut state = self.state.lock().await;
+		let missed = match state.checkpoint.peers.get(from) {
+			Some(last) if seqno <= *last => return Ok(false),
+			Some(last) => seqno > last + 1,
+			None => false,
+		};
+		state.checkpoint.peers.insert(from.to_string(), seqno);
+		if state.latest.map_or(true, |latest| seqno > latest) {
+			state.latest = Some(seqno);
+			state.checkpoint.seqno = format!("{:016x}", seqno);
+		}
+		state.gap |= missed;
+		state.pending += 1;
+		if state.pending >= self.save_every {
+			self.save(&mut state).await?;
+		}
+		Ok(missed)
+	}
+
+	/// Write the messages recorded since the last write
+	pub async fn flush(&self) -> anyhow::Result<()> {
+		let mut state = self.state.lock().await;
+		if state.pending > 0 {
+			self.save(&mut state).await?;
+		}
+		Ok(())
+	}
+
+	/// Whether a gap was detected since the last call
+	pub async fn take_gap(&self) -> bool {
+		std::mem::take(&mut self.state.lock().await.gap)
+	}
+
+	/// Mark a gap left unfilled, to be taken again
+	pub async fn mark_gap(&self) {
+		self.state.lock().await.gap = true;
+	}
+
+	async fn save(&self, state: &mut TrackerState) -> anyhow::Result<()> {
+		state.checkpoint.updated_at = Utc::now();
+		self.store.save(&state.checkpoint).await?;
+		state.pending = 0;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn test_file_checkpoints() -> anyhow::Result<()> {
+		let path = std::env::temp_dir().join(format!(
+			"checkpoints-{}.json",
+			Utc::now().timestamp_nanos_opt().unwrap_or_default()
+		));
+		let store = FileCheckpoints::new(&path);
+		assert_eq!(store.load("/ceramic/inmemory").await?, None);
+
+		let checkpoint = Checkpoint::new("/ceramic/inmemory", "17a3c2b1e0f00001");
+		store.save(&checkpoint).await?;
+		store
+			.save(&Checkpoint::new("/ceramic/mainnet", "17a3c2b1e0f00002"))
+			.await?;
+
+		// reopen to read from disk
+		let store = FileCheckpoints::new(&path);
+		assert_eq!(store.load("/ceramic/inmemory").await?, Some(checkpoint));
+
+		tokio::fs::remove_file(&path).await?;
+		Ok(())
+	}
+
+	#[test]
+	fn test_parse_seqno() {
+		assert_eq!(parse_seqno("17a3c2b1e0f00001"), Some(0x17a3c2b1e0f00001));
+		assert_eq!(parse_seqno("uF6PCseDwAAE"), Some(0x17a3c2b1e0f00001));
+		assert_eq!(parse_seqno("not a seqno"), None);
+	}
+
+	#[tokio::test]
+	async fn test_tracker_gaps() -> anyhow::Result<()> {
+		let path = std::env::temp_dir().join(format!(
+			"tracked-checkpoints-{}.json",
+			Utc::now().timestamp_nanos_opt().unwrap_or_default()
+		));
+		let store = Arc::new(FileCheckpoints::new(&path));
+		let topic = "/ceramic/inmemory";
+		let tracker = CheckpointTracker::load(store.clone(), topic)
+			.await?
+			.with_save_every(2);
+
+		assert!(!tracker.record("peer-a", "0000000000000001").await?);
+		assert!(!tracker.record("peer-b", "0000000000000007").await?);
+		// written every two messages
+		let checkpoint = store.load(topic).await?.unwrap();
+		assert_eq!(checkpoint.seqno, "0000000000000007");
+		assert!(!tracker.record("peer-a", "0000000000000002").await?);
+		assert_eq!(store.load(topic).await?, Some(checkpoint));
+		tracker.flush().await?;
+		assert!(!tracker.take_gap().await);
+
+		// messages of peer-a published while offline are missed
+		let tracker = CheckpointTracker::load(store.clone(), topic).await?;
+		assert!(tracker.record("peer-a", "0000000000000005").await?);
+		// already consumed or published by a new peer
+		assert!(!tracker.record("peer-b", "0000000000000006").await?);
+		assert!(!tracker.record("peer-c", "0000000000000009").await?);
+		assert!(tracker.take_gap().await);
+		assert!(!tracker.take_gap().await);
+
+		tokio::fs::remove_file(&path).await?;
+		Ok(())
+	}
+}
@@ -0,0 +1,281 @@
+use std::collections::BTreeMap;
+
+use ceramic_core::StreamId;
+
+use crate::deploy::{deploy, DeployLock, ModelDeployer};
+use crate::model::{ModelDefinition, ModelRelationDefinition, ModelViewDefinition};
+use crate::network::Network;
+
+#[derive(Debug)]
+pub enum CompositeError {
+	DuplicateModel(String),
+	/// models referencing each other, none of them can be created first
+	DependencyCycle(Vec<String>),
+}
+
+impl std::fmt::Display for CompositeError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::DuplicateModel(name) => write!(f, "model {} defined twice in composite", name),
+			Self::DependencyCycle(names) => {
+				write!(f, "models {} reference each other", names.join(", "))
+			}
+		}
+	}
+}
+
+impl std::error::Error for CompositeError {}
+
+/// Models deployed together, whose relations, views and implemented
+/// interfaces reference the other models of the composite by name, replaced
+/// by their ids once created
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Composite {
+	models: Vec<ModelDefinition>,
+}
+
+impl Composite {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn with_model(mut self, definition: ModelDefinition) -> Self {
+		self.models.push(definition);
+		self
+	}
+
+	pub fn models(&self) -> &[ModelDefinition] {
+		&self.models
+	}
+
+	/// Names of the other models of the composite the definition references
+	fn dependencies<'a>(&self, definition: &'a ModelDefinition) -> Vec<&'a str> {
+		let relations = definition.relations.values().filter_map(|x| match x {
+			ModelRelationDefinition::Document { model } => Some(model.as_str()),
+			ModelRelationDefinition::Account => None,
+		});
+		let views = definition
+			.views
+			.values()
+			.filter_map(ModelViewDefinition::model);
+		let mut names: Vec<&str> = relations
+			.chain(views)
+			.chain(definition.implements.iter().map(String::as_str))
+			.filter(|name| self.models.iter().any(|x| x.name == *name))
+			.collect();
+		names.sort_unstable();
+		names.dedup();
+		names
+	}
+
+	/// Models ordered so that every model comes after the models it
+	/// references, keeping the order they were added in otherwise
+	pub fn deploy_order(&self) -> anyhow::Result<Vec<&ModelDefinition>> {
+		for (idx, model) in self.models.iter().enumerate() {
+			if self.models[..idx].iter().any(|x| x.name == model.name) {
+				anyhow::bail!(CompositeError::DuplicateModel(model.name.clone()));
+			}
+		}
+		let mut ordered: Vec<&ModelDefinition> = Vec::with_capacity(self.models.len());
+		while ordered.len() < self.models.len() {
+			let next = self.models.iter().find(|model| {
+				!ordered.iter().any(|x| x.name == model.name)
+					&& self
+						.dependencies(model)
+						.iter()
+						.all(|name| ordered.iter().any(|x| x.name == *name))
+			});
+			match next {
+				Some(model) => ordered.push(model),
+				None => {
+					let pending = self
+						.models
+						.iter()
+						.filter(|model| !ordered.iter().any(|x| x.name == model.name))
+						.map(|model| model.name.clone())
+						.collect();
+					anyhow::bail!(CompositeError::DependencyCycle(pending));
+				}
+			}
+		}
+		Ok(ordered)
+	}
+
+	/// Create and index the models in dependency order, reusing the ids of
+	/// unchanged definitions recorded in the lock, and return the id of every
+	/// model by name
+	pub async fn deploy(
+		&self,
+		deployer: &dyn ModelDeployer,
+		network: &Network,
+		lock: &mut DeployLock,
+	) -> anyhow::Result<BTreeMap<String, StreamId>> {
+		let mut ids = BTreeMap::new();
+		for model in self.deploy_order()? {
+			let definition = resolve(model, &ids);
+			definition.validate()?;
+			let deployed = deploy(deployer, network, &[definition], lock).await?;
+			for deployed in deployed {
+				ids.insert(model.name.clone(), deployed.model_id);
+			}
+		}
+		Ok(ids)
+	}
+}
+
+/// Replace the names of created models by their ids
+fn resolve(definition: &ModelDefinition, ids: &BTreeMap<String, StreamId>) -> ModelDefinition {
+	let resolve = |name: &mut String| {
+		if let Some(id) = ids.get(name.as_str()) {
+			*name = id.to_string();
+		}
+	};
+	let mut definition = definition.clone();
+	for relation in definition.relations.values_mut() {
+		if let ModelRelationDefinition::Document { model } = relation {
+			resolve(model);
+		}
+	}
+	for view in definition.views.values_mut() {
+		match view {
+			ModelViewDefinition::RelationDocument { model, .. }
+			| ModelViewDefinition::RelationFrom { model, .. }
+			| ModelViewDefinition::RelationCountFrom { model, .. } => resolve(model),
+			ModelViewDefinition::DocumentAccount | ModelViewDefinition::DocumentVersion => {}
+		}
+	}
+	definition.implements.iter_mut().for_each(resolve);
+	definition
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::model::ModelAccountRelation;
+	use ceramic_http_client::schemars::JsonSchema;
+	use ceramic_http_client::GetRootSchema;
+	use tokio::sync::Mutex;
+
+	#[derive(JsonSchema)]
+	#[schemars(crate = "ceramic_http_client::schemars")]
+	#[allow(dead_code)]
+	struct Post {
+		text: String,
+	}
+
+	impl GetRootSchema for Post {}
+
+	#[derive(JsonSchema)]
+	#[schemars(crate = "ceramic_http_client::schemars")]
+	#[allow(dead_code)]
+	struct Comment {
+		text: String,
+		post: String,
+	}
+
+	impl GetRootSchema for Comment {}
+
+	const MODEL_IDS: [&str; 2] = [
+		"kjzl6kcym7w8y8syiams0kvm3qwfnutk2szi0wlhvf6rr9lalzpibxed0qvotuy",
+		"kjzl6kcym7w8y9s94kcardbh5u0ao76bci07xnnxjw1ew3i4eackykj76uagqfk",
+	];
+
+	/// Records the definitions it creates
+	#[derive(Default)]
+	struct MockDeployer {
+		created: Mutex<Vec<serde_json::Value>>,
+	}
+
+	#[async_trait::async_trait]
+	impl ModelDeployer for MockDeployer {
+		async fn create_model(&self, definition: &ModelDefinition) -> anyhow::Result<StreamId> {
+			let mut created = self.created.lock().await;
+			created.push(serde_json::to_value(definition)?);
+			MODEL_IDS[created.len() - 1].parse()
+		}
+
+		async fn index_model(&self, _model_id: &StreamId) -> anyhow::Result<()> {
+			Ok(())
+		}
+	}
+
+	fn composite() -> anyhow::Result<Composite> {
+		let comment = ModelDefinition::new::<Comment>("comment", ModelAccountRelation::List)?
+			.with_relation(
+				"post",
+				ModelRelationDefinition::Document {
+					model: "post".to_string(),
+				},
+			);
+		let post = ModelDefinition::new::<Post>("post", ModelAccountRelation::List)?;
+		Ok(Composite::new().with_model(comment).with_model(post))
+	}
+
+	#[tokio::test]
+	async fn test_deploy_in_dependency_order() -> anyhow::Result<()> {
+		let composite = composite()?;
+		let order: Vec<&str> = composite
+			.deploy_order()?
+			.iter()
+			.map(|x| x.name.as_str())
+			.collect();
+		assert_eq!(order, vec!["post", "comment"]);
+
+		let deployer = MockDeployer::default();
+		let mut lock = DeployLock::default();
+		let ids = composite
+			.deploy(&deployer, &Network::TestnetClay, &mut lock)
+			.await?;
+		assert_eq!(ids["post"].to_string(), MODEL_IDS[0]);
+		assert_eq!(ids["comment"].to_string(), MODEL_IDS[1]);
+		let created = deployer.created.lock().await;
+		assert_eq!(created[1]["relations"]["post"]["model"], MODEL_IDS[0]);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_deploy_implemented_interface() -> anyhow::Result<()> {
+		let node = ModelDefinition::new::<Post>("node", ModelAccountRelation::List)?.as_interface();
+		let mut post = ModelDefinition::new::<Post>("post", ModelAccountRelation::List)?
+			.with_immutable_fields(&["text"]);
+		post.implements.push("node".to_string());
+		let composite = Composite::new().with_model(post).with_model(node);
+
+		let deployer = MockDeployer::default();
+		let mut lock = DeployLock::default();
+		composite
+			.deploy(&deployer, &Network::TestnetClay, &mut lock)
+			.await?;
+		let created = deployer.created.lock().await;
+		assert_eq!(created[0]["interface"], true);
+		assert_eq!(created[1]["implements"][0], MODEL_IDS[0]);
+		assert_eq!(created[1]["immutableFields"][0], "text");
+		Ok(())
+	}
+
+	#[test]
+	fn test_dependency_cycle() -> anyhow::Result<()> {
+		let post = ModelDefinition::new::<Comment>("post", ModelAccountRelation::List)?
+			.with_relation(
+				"post",
+				ModelRelationDefinition::Document {
+					model: "comment".to_string(),
+				},
+			);
+		let composite = composite()?.with_model(post);
+		let err = composite.deploy_order().unwrap_err();
+		assert!(matches!(
+			err.downcast_ref::<CompositeError>(),
+			Some(CompositeError::DuplicateModel(_))
+		));
+
+		let mut composite = composite;
+		composite.models.remove(1);
+		let err = composite.deploy_order().unwrap_err();
+		assert!(matches!(
+			err.downcast_ref::<CompositeError>(),
+			Some(CompositeError::DependencyCycle(names)) if names.len() == 2
+		));
+		Ok(())
+	}
+}
@@ -0,0 +1,365 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use ceramic_core::{Cid, StreamId, StreamIdType};
+use ceramic_event::JwkSigner;
+use ceramic_http_client::remote::CeramicRemoteHttpClient;
+use chrono::{DateTime, Utc};
+use dataverse_types_core::digest::{block_cid, DAG_CBOR};
+use int_enum::IntEnum;
+use libipld::cbor::DagCborCodec;
+use libipld::prelude::Codec;
+use libipld::Ipld;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::did::generate_did_str;
+use crate::event::metadata::sign_linked_block;
+use crate::event::EventsUploader;
+use crate::http::{self, ceramic_client};
+use crate::model::ModelDefinition;
+use crate::network::Network;
+use crate::redact::Secret;
+use crate::Ceramic;
+
+/// Stream type of models
+pub const MODEL_TYPE: u64 = 2;
+
+/// Stream id every model genesis names as its model
+const META_MODEL: &str = "kh4q0ozorrgaq2mezktnrmdwleo1d";
+
+/// Model id recorded for a definition on one network
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockedModel {
+	pub model_id: String,
+	/// hex sha256 of the definition the model was created from
+	pub definition_hash: String,
+	pub deployed_at: DateTime<Utc>,
+}
+
+/// Lockfile of deployed models, keyed by network name then model name
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeployLock {
+	pub networks: BTreeMap<String, BTreeMap<String, LockedModel>>,
+}
+
+impl DeployLock {
+	pub async fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+		match tokio::fs::read(path.as_ref()).await {
+			Ok(content) => Ok(serde_json::from_slice(&content)?),
+			Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+			Err(err) => Err(err.into()),
+		}
+	}
+
+	pub async fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+		let path = path.as_ref();
+		let tmp = path.with_extension("tmp");
+		tokio::fs::write(&tmp, serde_json::to_vec_pretty(self)?).await?;
+		tokio::fs::rename(&tmp, path).await?;
+		Ok(())
+	}
+
+	pub fn get(&self, network: &Network, name: &str) -> Option<&LockedModel> {
+		self.networks.get(&network.name())?.get(name)
+	}
+
+	fn insert(&mut self, network: &Network, name: &str, model: LockedModel) {
+		self.networks
+			.entry(network.name())
+			.or_default()
+			.insert(name.to_string(), model);
+	}
+}
+
+/// Genesis of a model stream controlled by `controller`, its content the
+/// definition as is, so fields ceramic-http-client does not know are kept
+pub fn model_genesis(
+	definition: &ModelDefinition,
+	controller: &str,
+) -> anyhow::Result<(Cid, Vec<u8>)> {
+	let meta_model: StreamId = META_MODEL.parse()?;
+	let header = BTreeMap::from([
+		(
+			"controllers".to_string(),
+			Ipld::List(vec![Ipld::String(controller.to_string())]),
+		),
+		("model".to_string(), Ipld::Bytes(meta_model.to_vec()?)),
+		("sep".to_string(), Ipld::String("model".to_string())),
+	]);
+	let node = Ipld::Map(BTreeMap::from([
+		("data".to_string(), libipld::serde::to_ipld(definition)?),
+		("header".to_string(), Ipld::Map(header)),
+	]));
+	let block = DagCborCodec.encode(&node)?;
+	Ok((block_cid(DAG_CBOR, &block), block))
+}
+
+/// Node side of a deployment
+#[async_trait::async_trait]
+pub trait ModelDeployer: Send + Sync {
+	async fn create_model(&self, definition: &ModelDefinition) -> anyhow::Result<StreamId>;
+	async fn index_model(&self, model_id: &StreamId) -> anyhow::Result<()>;
+}
+
+/// Deployer creating models with the `did:key` of an ed25519 seed (hex),
+/// which has to be an admin of the node to index them
+pub struct NodeDeployer {
+	ceramic: Ceramic,
+	pk: Secret<String>,
+	client: http::Client,
+	admin: CeramicRemoteHttpClient<JwkSigner>,
+}
+
+impl NodeDeployer {
+	pub async fn new(ceramic: Ceramic, pk: Secret<String>) -> anyhow::Result<Self> {
+		let admin = ceramic_client(&ceramic.endpoint, &pk).await?;
+		Ok(Self {
+			ceramic,
+			pk,
+			client: http::Client::new(),
+			admin,
+		})
+	}
+}
+
+#[async_trait::async_trait]
+impl ModelDeployer for NodeDeployer {
+	async fn create_model(&self, definition: &ModelDefinition) -> anyhow::Result<StreamId> {
+		let (payload, block) = model_genesis(definition, &generate_did_str(self.pk.expose())?)?;
+		let event = sign_linked_block(self.pk.expose(), payload, block)?;
+		let model_id = StreamId {
+			r#type: StreamIdType::from_int(MODEL_TYPE)?,
+			cid: event.cid,
+		};
+		self.client
+			.upload_event(&self.ceramic, &model_id, event)
+			.await?;
+		Ok(model_id)
+	}
+
+	async fn index_model(&self, model_id: &StreamId) -> anyhow::Result<()> {
+		self.admin.index_model(model_id).await
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Deployed {
+	pub name: String,
+	pub model_id: StreamId,
+	/// false if the id was reused from the lockfile
+	pub created: bool,
+}
+
+pub fn definition_hash(definition: &ModelDefinition) -> anyhow::Result<String> {
+	Ok(hex::encode(Sha256::digest(serde_json::to_vec(definition)?)))
+}
+
+/// Create the models missing on the network in the given order, reusing ids
+/// of unchanged definitions recorded in the lock. Models are indexed on every
+/// run, as indexing is idempotent on the node.
+pub async fn deploy(
+	deployer: &dyn ModelDeployer,
+	network: &Network,
+	definitions: &[ModelDefinition],
+	lock: &mut DeployLock,
+) -> anyhow::Result<Vec<Deployed>> {
+	let mut deployed = Vec::with_capacity(definitions.len());
+	for definition in definitions {
+		let name = definition.name.clone();
+		let hash = definition_hash(definition)?;
+		let (model_id, created) = match lock.get(network, &name) {
+			Some(locked) if locked.definition_hash == hash => (locked.model_id.parse()?, false),
+			_ => {
+				let model_id = deployer.create_model(definition).await?;
+				tracing::info!(
+					network = network.name(),
+					name,
+					model_id = model_id.to_string(),
+					"created model"
+				);
+				lock.insert(
+					network,
+					&name,
+					LockedModel {
+						model_id: model_id.to_string(),
+						definition_hash: hash,
+						deployed_at: Utc::now(),
+					},
+				);
+				(model_id, true)
+			}
+		};
+		deployer.index_model(&model_id).await?;
+		deployed.push(Deployed {
+			name,
+			model_id,
+			created,
+		});
+	}
+	Ok(deployed)
+}
+
+/// Deploy definitions written against the models of `from` onto `to`,
+/// rewriting relations to the ids of the already promoted models
+pub async fn promote(
+	deployer: &dyn ModelDeployer,
+	from: &Network,
+	to: &Network,
+	definitions: &[ModelDefinition],
+	lock: &mut DeployLock,
+) -> anyhow::Result<Vec<Deployed>> {
+	let mut id_map = HashMap::new();
+	let mut deployed = Vec::with_capacity(definitions.len());
+	for definition in definitions {
+		let definition = remap_model_ids(definition, &id_map)?;
+		let name = definition.name.clone();
+		let mut result = deploy(deployer, to, &[definition], lock).await?;
+		if let Some(source) = lock.get(from, &name) {
+			id_map.insert(source.model_id.clone(), result[0].model_id.to_string());
+		}
+		deployed.append(&mut result);
+	}
+	Ok(deployed)
+}
+
+/// Replace every string in the definition equal to a mapped model id
+fn remap_model_ids(
+	definition: &ModelDefinition,
+	id_map: &HashMap<String, String>,
+) -> anyhow::Result<ModelDefinition> {
+	fn remap(value: &mut Value, id_map: &HashMap<String, String>) {
+		match value {
+			Value::String(id) => {
+				if let Some(target) = id_map.get(id.as_str()) {
+					*id = target.clone();
+				}
+			}
+			Value::Array(values) => values.iter_mut().for_each(|x| remap(x, id_map)),
+			Value::Object(map) => map.values_mut().for_each(|x| remap(x, id_map)),
+			_ => {}
+		}
+	}
+
+	let mut value = serde_json::to_value(definition)?;
+	remap(&mut value, id_map);
+	Ok(serde_json::from_value(value)?)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::model::{ModelAccountRelation, ModelViewDefinition};
+	use ceramic_http_client::schemars::JsonSchema;
+	use ceramic_http_client::GetRootSchema;
+	use tokio::sync::Mutex;
+
+	#[derive(JsonSchema)]
+	#[schemars(crate = "ceramic_http_client::schemars")]
+	#[allow(dead_code)]
+	struct Post {
+		text: String,
+	}
+
+	impl GetRootSchema for Post {}
+
+	const MODEL_IDS: [&str; 2] = [
+		"kjzl6kcym7w8y8syiams0kvm3qwfnutk2szi0wlhvf6rr9lalzpibxed0qvotuy",
+		"kjzl6kcym7w8y9s94kcardbh5u0ao76bci07xnnxjw1ew3i4eackykj76uagqfk",
+	];
+
+	#[derive(Default)]
+	struct MockDeployer {
+		created: Mutex<usize>,
+	}
+
+	#[async_trait::async_trait]
+	impl ModelDeployer for MockDeployer {
+		async fn create_model(&self, _definition: &ModelDefinition) -> anyhow::Result<StreamId> {
+			let mut created = self.created.lock().await;
+			let model_id = MODEL_IDS[*created % MODEL_IDS.len()].parse()?;
+			*created += 1;
+			Ok(model_id)
+		}
+
+		async fn index_model(&self, _model_id: &StreamId) -> anyhow::Result<()> {
+			Ok(())
+		}
+	}
+
+	#[tokio::test]
+	async fn test_deploy_and_promote() -> anyhow::Result<()> {
+		let definition = ModelDefinition::new::<Post>("post", ModelAccountRelation::List)?;
+		let deployer = MockDeployer::default();
+		let mut lock = DeployLock::default();
+
+		let deployed = deploy(
+			&deployer,
+			&Network::TestnetClay,
+			&[definition.clone()],
+			&mut lock,
+		)
+		.await?;
+		assert!(deployed[0].created);
+		let deployed = deploy(
+			&deployer,
+			&Network::TestnetClay,
+			&[definition.clone()],
+			&mut lock,
+		)
+		.await?;
+		assert!(!deployed[0].created);
+		assert_eq!(*deployer.created.lock().await, 1);
+
+		let promoted = promote(
+			&deployer,
+			&Network::TestnetClay,
+			&Network::Mainnet,
+			&[definition],
+			&mut lock,
+		)
+		.await?;
+		assert!(promoted[0].created);
+		assert_eq!(
+			lock.get(&Network::Mainnet, "post")
+				.map(|x| x.model_id.as_str()),
+			Some(MODEL_IDS[1])
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_remap_model_ids() -> anyhow::Result<()> {
+		let definition = ModelDefinition::new::<Post>("post", ModelAccountRelation::List)?;
+		let id_map = HashMap::from([("post".to_string(), "article".to_string())]);
+		let remapped = remap_model_ids(&definition, &id_map)?;
+		assert_eq!(remapped.name, "article");
+		Ok(())
+	}
+
+	#[test]
+	fn test_model_genesis_keeps_definition() -> anyhow::Result<()> {
+		let interface_id: StreamId =
+			"kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju".parse()?;
+		let definition = ModelDefinition::new::<Post>("post", ModelAccountRelation::List)?
+			.implements(&interface_id)
+			.with_immutable_fields(&["text"])
+			.with_view(
+				"comments",
+				ModelViewDefinition::RelationCountFrom {
+					model: interface_id.to_string(),
+					property: "post".to_string(),
+				},
+			);
+		let controller = "did:key:z6Mkj9M6QgzPP3zPdHoCHEojiZSTd4kS53z2x9Hi8L9jgBM1";
+		let (cid, block) = model_genesis(&definition, controller)?;
+		assert_eq!(model_genesis(&definition, controller)?.0, cid);
+
+		let node: Ipld = DagCborCodec.decode(&block)?;
+		let data: ModelDefinition = libipld::serde::from_ipld(node.get("data")?.clone())?;
+		assert_eq!(data, definition);
+		Ok(())
+	}
+}
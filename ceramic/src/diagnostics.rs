@@ -0,0 +1,264 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use ceramic_core::{StreamId, StreamIdType};
+use chrono::{DateTime, Utc};
+use int_enum::IntEnum;
+#[cfg(feature = "kubo")]
+use libipld::multihash::{Code, MultihashDigest};
+#[cfg(feature = "kubo")]
+use libipld::Cid;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::event::EventsUploader;
+#[cfg(feature = "kubo")]
+use crate::kubo::{BlockUploader, CidLoader, PeerInfo};
+use crate::redact::Secret;
+use crate::stream::single::MID_TYPE;
+use crate::{http, Ceramic, StreamLoader};
+
+/// Upper bound of a single probe, slow dependencies are reported as failed
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[cfg(feature = "kubo")]
+const RAW_CODEC: u64 = 0x55;
+
+/// End-to-end check of one dependency, returning a short description of what
+/// was verified
+#[async_trait::async_trait]
+pub trait Probe: Send + Sync {
+	fn name(&self) -> &str;
+	async fn probe(&self) -> anyhow::Result<String>;
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckReport {
+	pub name: String,
+	pub ok: bool,
+	pub elapsed_ms: u64,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub detail: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmokeReport {
+	pub ok: bool,
+	pub started_at: DateTime<Utc>,
+	pub checks: Vec<CheckReport>,
+}
+
+/// Dependencies of a node deployment to exercise. Stores outside this crate
+/// (postgres, iroh) are passed as `probes`.
+#[derive(Default)]
+pub struct SmokeTestConfig {
+	pub ceramic: Option<CeramicProbe>,
+	#[cfg(feature = "kubo")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "kubo")))]
+	pub kubo: Option<KuboProbe>,
+	pub probes: Vec<Arc<dyn Probe>>,
+}
+
+/// `unique` bytes of the probe instance, so every run writes the same genesis
+/// and reuses its stream instead of creating one
+pub const PROBE_UNIQUE: &[u8] = b"dataverse-smoke-test";
+
+/// Writes the probe instance of `model_id` with `content` and reads it back.
+/// The genesis is deterministic, the node keeps a single probe stream per
+/// signer, model and content.
+pub struct CeramicProbe {
+	pub endpoint: String,
+	/// ed25519 seed in hex of the did:key signing the instance
+	pub pk: Secret<String>,
+	pub model_id: StreamId,
+	pub content: Value,
+}
+
+#[async_trait::async_trait]
+impl Probe for CeramicProbe {
+	fn name(&self) -> &str {
+		"ceramic"
+	}
+
+	async fn probe(&self) -> anyhow::Result<String> {
+		let ceramic = Ceramic::new(&self.endpoint).await?;
+		let client = http::Client::new();
+		let genesis = client.list_genesis_with_unique(
+			&self.pk,
+			&self.model_id,
+			&self.content,
+			PROBE_UNIQUE,
+		)?;
+		let stream_id = StreamId {
+			r#type: StreamIdType::from_int(MID_TYPE)?,
+			cid: genesis.cid,
+		};
+		client.upload_event(&ceramic, &stream_id, genesis).await?;
+		let state = client.load_stream_state(&ceramic, &stream_id, None).await?;
+		if state.content != self.content {
+			anyhow::bail!("content of {} differs from written", stream_id);
+		}
+		Ok(format!(
+			"wrote and read {} on {}",
+			stream_id,
+			ceramic.network.name()
+		))
+	}
+}
+
+/// Puts a raw block and gets it back
+#[cfg(feature = "kubo")]
+#[cfg_attr(docsrs, doc(cfg(feature = "kubo")))]
+pub struct KuboProbe {
+	pub endpoint: String,
+}
+
+#[cfg(feature = "kubo")]
+#[async_trait::async_trait]
+impl Probe for KuboProbe {
+	fn name(&self) -> &str {
+		"kubo"
+	}
+
+	async fn probe(&self) -> anyhow::Result<String> {
+		let client = crate::kubo::new(&self.endpoint);
+		let block = format!("dataverse smoke test {}", Utc::now().to_rfc3339()).into_bytes();
+		let cid = Cid::new_v1(RAW_CODEC, Code::Sha2_256.digest(&block));
+		client.block_upload(cid, block.clone()).await?;
+		if client.load_cid(&cid).await? != block {
+			anyhow::bail!("block {} differs from put", cid);
+		}
+		Ok(format!("put and got block {}", cid))
+	}
+}
+
+/// Reports the kubo node id and its connected peers, failing without peers
+/// as blocks of other nodes cannot be loaded then
+#[cfg(feature = "kubo")]
+#[cfg_attr(docsrs, doc(cfg(feature = "kubo")))]
+pub struct KuboSwarmProbe {
+	pub endpoint: String,
+}
+
+#[cfg(feature = "kubo")]
+#[async_trait::async_trait]
+impl Probe for KuboSwarmProbe {
+	fn name(&self) -> &str {
+		"kubo-swarm"
+	}
+
+	async fn probe(&self) -> anyhow::Result<String> {
+		let client = crate::kubo::new(&self.endpoint);
+		let id = client.kubo_id().await?;
+		let peers = client.kubo_peers().await?;
+		if peers.is_empty() {
+			anyhow::bail!("kubo {} has no connected peers", id.id);
+		}
+		Ok(format!(
+			"kubo {} at {} with {} connected peers",
+			id.id,
+			id.addresses.join(","),
+			peers.len()
+		))
+	}
+}
+
+async fn run_probe(probe: &dyn Probe) -> CheckReport {
+	let start = Instant::now();
+	let result = match tokio::time::timeout(PROBE_TIMEOUT, probe.probe()).await {
+		Ok(result) => result,
+		Err(_) => Err(anyhow::anyhow!("timed out after {:?}", PROBE_TIMEOUT)),
+	};
+	let elapsed_ms = start.elapsed().as_millis() as u64;
+	match result {
+		Ok(detail) => CheckReport {
+			name: probe.name().to_string(),
+			ok: true,
+			elapsed_ms,
+			detail: Some(detail),
+			error: None,
+		},
+		Err(err) => {
+			tracing::warn!(probe = probe.name(), "smoke test failed: {:#}", err);
+			CheckReport {
+				name: probe.name().to_string(),
+				ok: false,
+				elapsed_ms,
+				detail: None,
+				error: Some(format!("{:#}", err)),
+			}
+		}
+	}
+}
+
+/// Exercise each configured dependency in turn, failures are collected into
+/// the report instead of aborting the run
+pub async fn smoke_test(config: SmokeTestConfig) -> SmokeReport {
+	let started_at = Utc::now();
+	let mut probes: Vec<Arc<dyn Probe>> = vec![];
+	if let Some(ceramic) = config.ceramic {
+		probes.push(Arc::new(ceramic));
+	}
+	#[cfg(feature = "kubo")]
+	if let Some(kubo) = config.kubo {
+		probes.push(Arc::new(KuboSwarmProbe {
+			endpoint: kubo.endpoint.clone(),
+		}));
+		probes.push(Arc::new(kubo));
+	}
+	probes.extend(config.probes);
+
+	let mut checks = Vec::with_capacity(probes.len());
+	for probe in probes {
+		checks.push(run_probe(probe.as_ref()).await);
+	}
+	SmokeReport {
+		ok: checks.iter().all(|x| x.ok),
+		started_at,
+		checks,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	struct StaticProbe(&'static str, bool);
+
+	#[async_trait::async_trait]
+	impl Probe for StaticProbe {
+		fn name(&self) -> &str {
+			self.0
+		}
+
+		async fn probe(&self) -> anyhow::Result<String> {
+			match self.1 {
+				true => Ok("ok".to_string()),
+				false => anyhow::bail!("connection refused"),
+			}
+		}
+	}
+
+	#[tokio::test]
+	async fn test_smoke_report() {
+		let report = smoke_test(SmokeTestConfig {
+			probes: vec![
+				Arc::new(StaticProbe("postgres", true)),
+				Arc::new(StaticProbe("iroh", false)),
+			],
+			..Default::default()
+		})
+		.await;
+		assert!(!report.ok);
+		assert_eq!(report.checks.len(), 2);
+		assert!(report.checks[0].ok);
+		assert_eq!(
+			report.checks[1].error.as_deref(),
+			Some("connection refused")
+		);
+	}
+}
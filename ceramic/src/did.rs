@@ -1,6 +1,43 @@
 use anyhow::Result;
+use base64::{engine::general_purpose, Engine};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
 use multibase::Base;
+use serde_json::Value;
 use ssh_key::private::Ed25519Keypair;
+use ssi::jwk::{Base64urlUInt, OctetParams, Params, JWK};
+
+#[derive(Debug)]
+pub enum DidError {
+	UnsupportedDidMethod(String),
+	UnsupportedKeyType(String),
+	MalformedJwt,
+	MissingIssuer,
+	MissingExpiration,
+	/// audience the jwt was issued for, none if missing
+	AudienceMismatch(Option<String>),
+	JwtExpired,
+	JwtNotYetValid,
+}
+
+impl std::fmt::Display for DidError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::UnsupportedDidMethod(did) => write!(f, "unsupported did method: {}", did),
+			Self::UnsupportedKeyType(did) => write!(f, "unsupported key type of {}", did),
+			Self::MalformedJwt => write!(f, "malformed jwt"),
+			Self::MissingIssuer => write!(f, "jwt missing issuer"),
+			Self::MissingExpiration => write!(f, "jwt missing expiration"),
+			Self::AudienceMismatch(Some(aud)) => write!(f, "jwt issued for audience {}", aud),
+			Self::AudienceMismatch(None) => write!(f, "jwt missing audience"),
+			Self::JwtExpired => write!(f, "jwt expired"),
+			Self::JwtNotYetValid => write!(f, "jwt not valid yet"),
+		}
+	}
+}
+
+impl std::error::Error for DidError {}
 
 pub fn generate_did_str(pk: &str) -> Result<String> {
 	let seed: [u8; 32] = hex::decode(pk)?
@@ -17,6 +54,82 @@ pub fn generate_did_str(pk: &str) -> Result<String> {
 	))
 }
 
+/// Fails for controllers of another method than `did:key`, they sign through
+/// a cacao which cannot be issued from a seed
+pub fn ensure_did_key_controllers(controllers: &[String]) -> Result<()> {
+	match controllers.iter().find(|did| !did.starts_with("did:key:")) {
+		Some(did) => anyhow::bail!(DidError::UnsupportedDidMethod(did.clone())),
+		None => Ok(()),
+	}
+}
+
+/// Public key of an ed25519 `did:key`
+pub fn did_key_to_jwk(did: &str) -> Result<JWK> {
+	let key = match did.strip_prefix("did:key:") {
+		Some(key) => key.split('#').next().unwrap_or(key),
+		None => anyhow::bail!(DidError::UnsupportedDidMethod(did.to_string())),
+	};
+	let (_, bytes) = multibase::decode(key)?;
+	match bytes.strip_prefix(&[0xed, 0x01]) {
+		Some(public_key) if public_key.len() == 32 => Ok(JWK::from(Params::OKP(OctetParams {
+			curve: "Ed25519".to_string(),
+			public_key: Base64urlUInt(public_key.to_vec()),
+			private_key: None,
+		}))),
+		_ => anyhow::bail!(DidError::UnsupportedKeyType(did.to_string())),
+	}
+}
+
+pub struct DidJwt {
+	pub did: String,
+	pub claims: Value,
+}
+
+/// Verify a JWT signed by the `did:key` in its `iss` claim and issued for
+/// `audience`. The `exp` claim is required, time claims are checked with a
+/// tolerance of `skew`.
+pub fn verify_did_jwt(
+	token: &str,
+	audience: &str,
+	now: DateTime<Utc>,
+	skew: Duration,
+) -> Result<DidJwt> {
+	let payload = token.split('.').nth(1).ok_or(DidError::MalformedJwt)?;
+	let claims: Value = serde_json::from_slice(&general_purpose::URL_SAFE_NO_PAD.decode(payload)?)?;
+	let did = claims["iss"]
+		.as_str()
+		.ok_or(DidError::MissingIssuer)?
+		.to_string();
+
+	let jwk = did_key_to_jwk(&did)?;
+	ssi::jws::decode_verify(token, &jwk)?;
+
+	// `aud` is either one audience or a list of them
+	let audiences: Vec<&str> = match &claims["aud"] {
+		Value::String(aud) => vec![aud.as_str()],
+		Value::Array(auds) => auds.iter().filter_map(Value::as_str).collect(),
+		_ => vec![],
+	};
+	if !audiences.contains(&audience) {
+		let aud = (!audiences.is_empty()).then(|| audiences.join(", "));
+		anyhow::bail!(DidError::AudienceMismatch(aud));
+	}
+
+	let skew = skew.as_secs() as i64;
+	let exp = claims["exp"].as_i64().ok_or(DidError::MissingExpiration)?;
+	if exp + skew < now.timestamp() {
+		anyhow::bail!(DidError::JwtExpired);
+	}
+	for claim in ["iat", "nbf"] {
+		if let Some(valid_from) = claims[claim].as_i64() {
+			if valid_from - skew > now.timestamp() {
+				anyhow::bail!(DidError::JwtNotYetValid);
+			}
+		}
+	}
+	Ok(DidJwt { did, claims })
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -32,4 +145,87 @@ mod tests {
 		let pk = "invalid_public_key";
 		assert!(generate_did_str(pk).is_err());
 	}
+
+	#[test]
+	fn test_ensure_did_key_controllers() {
+		let did_key = "did:key:z6MkuBcU2NW8Yfd1pJKA8HeFxeojzujcNyhmTNkuhDEfpqKT".to_string();
+		let did_pkh = "did:pkh:eip155:1:0x312eA852726E3A9f633A0377c0ea882086d66666".to_string();
+		assert!(ensure_did_key_controllers(&[did_key.clone()]).is_ok());
+		let err = ensure_did_key_controllers(&[did_key, did_pkh]).unwrap_err();
+		assert!(matches!(
+			err.downcast_ref::<DidError>(),
+			Some(DidError::UnsupportedDidMethod(_))
+		));
+	}
+
+	#[test]
+	fn test_verify_did_jwt() {
+		let pk = "d160c4553ba7547cd5d66993d99329379a0c299a1bb1058abc5b874e0ba56375";
+		let did = generate_did_str(pk).unwrap();
+		let seed = hex::decode(pk).unwrap();
+		let keypair = Ed25519Keypair::from_seed(&seed.clone().try_into().unwrap());
+		let jwk = JWK::from(Params::OKP(OctetParams {
+			curve: "Ed25519".to_string(),
+			public_key: Base64urlUInt(keypair.public.0.to_vec()),
+			private_key: Some(Base64urlUInt(seed)),
+		}));
+
+		let sign = |claims: Value| {
+			ssi::jws::encode_sign(ssi::jwk::Algorithm::EdDSA, &claims.to_string(), &jwk).unwrap()
+		};
+		let aud = "https://fs.dataverse.art";
+
+		let now = Utc::now();
+		let token = sign(serde_json::json!({
+			"iss": did,
+			"aud": aud,
+			"iat": now.timestamp(),
+			"exp": now.timestamp() + 60,
+		}));
+
+		let verified = verify_did_jwt(&token, aud, now, Duration::ZERO);
+		assert!(verified.is_ok());
+		assert_eq!(verified.unwrap().did, did);
+
+		let expired = verify_did_jwt(
+			&token,
+			aud,
+			now + chrono::Duration::minutes(5),
+			Duration::ZERO,
+		);
+		assert!(expired.is_err());
+
+		// within the skew of the exp and iat claims
+		let skew = Duration::from_secs(5 * 60);
+		let seconds = chrono::Duration::seconds;
+		assert!(verify_did_jwt(&token, aud, now + seconds(360), skew).is_ok());
+		assert!(verify_did_jwt(&token, aud, now + seconds(361), skew).is_err());
+		assert!(verify_did_jwt(&token, aud, now - seconds(300), skew).is_ok());
+		assert!(verify_did_jwt(&token, aud, now - seconds(301), skew).is_err());
+
+		// issued for another service
+		let err = verify_did_jwt(&token, "https://other.app", now, Duration::ZERO).unwrap_err();
+		assert!(matches!(
+			err.downcast_ref::<DidError>(),
+			Some(DidError::AudienceMismatch(Some(_)))
+		));
+		let token = sign(serde_json::json!({
+			"iss": did,
+			"aud": ["https://other.app", aud],
+			"exp": now.timestamp() + 60,
+		}));
+		assert!(verify_did_jwt(&token, aud, now, Duration::ZERO).is_ok());
+		let token = sign(serde_json::json!({ "iss": did, "exp": now.timestamp() + 60 }));
+		assert!(verify_did_jwt(&token, aud, now, Duration::ZERO).is_err());
+
+		// tokens without expiration are rejected
+		let token = sign(serde_json::json!({ "iss": did, "aud": aud, "iat": now.timestamp() }));
+		let err = verify_did_jwt(&token, aud, now, Duration::ZERO).unwrap_err();
+		assert!(matches!(
+			err.downcast_ref::<DidError>(),
+			Some(DidError::MissingExpiration)
+		));
+
+		assert!(did_key_to_jwk("did:pkh:eip155:1:0x00").is_err());
+	}
 }
@@ -37,6 +37,22 @@ impl AnchorValue {
 		let data: Ipld = self.clone().into();
 		DagCborCodec.encode(&data)
 	}
+
+	/// Block timestamp of the anchor transaction, looked up with the rpc of the
+	/// proof chain
+	#[cfg(feature = "anchor-timestamp")]
+	pub async fn timestamp(&self) -> anyhow::Result<Option<i64>> {
+		match self.proof()? {
+			Some(proof) => Ok(Some(network::timestamp(proof).await?)),
+			None => Ok(None),
+		}
+	}
+
+	/// Anchor timestamps are not looked up without the `anchor-timestamp` feature
+	#[cfg(not(feature = "anchor-timestamp"))]
+	pub async fn timestamp(&self) -> anyhow::Result<Option<i64>> {
+		Ok(None)
+	}
 }
 
 impl StreamStateApplyer for AnchorValue {
@@ -168,6 +184,13 @@ mod tests {
 		assert!(proof.is_ok());
 	}
 
+	#[tokio::test]
+	async fn timestamp_without_proof() -> anyhow::Result<()> {
+		let anchor = AnchorValue::default();
+		assert_eq!(anchor.timestamp().await?, None);
+		Ok(())
+	}
+
 	#[test]
 	fn convert_tx_hash() {
 		let tx_cid: Cid = "bagjqcgzadnfurovpwv4pzlbpvtcy4ushtwr2zlsd3ilny55pwgiwm5f6ngmq"
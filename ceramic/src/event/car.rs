@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use dataverse_types_core::digest::verify_block;
+use libipld::cid::Cid;
+use libipld::{cbor::DagCborCodec, codec::Codec, ipld, Ipld};
+
+use super::errors::CarError;
+use super::{Event, EventValue, SignedValue, ToCid};
+
+/// Blocks of a CAR v1 with a single root, checked against their cids when
+/// decoded
+#[derive(Debug, Clone, PartialEq)]
+pub struct Car {
+	pub root: Cid,
+	pub blocks: HashMap<Cid, Vec<u8>>,
+}
+
+fn read_varint(cursor: &mut Cursor<&[u8]>) -> anyhow::Result<usize> {
+	let rest = &cursor.get_ref()[cursor.position() as usize..];
+	let (value, remain) = unsigned_varint::decode::usize(rest)?;
+	cursor.set_position(cursor.position() + (rest.len() - remain.len()) as u64);
+	Ok(value)
+}
+
+fn write_varint(buf: &mut Vec<u8>, value: usize) {
+	let mut varint = unsigned_varint::encode::usize_buffer();
+	buf.extend_from_slice(unsigned_varint::encode::usize(value, &mut varint));
+}
+
+/// Bytes of `len` after `start`, failing on lengths past the end of the CAR
+fn read_section(car: &[u8], start: usize, len: usize) -> anyhow::Result<&[u8]> {
+	let end = start.checked_add(len).ok_or(CarError::Truncated)?;
+	Ok(car.get(start..end).ok_or(CarError::Truncated)?)
+}
+
+impl Car {
+	/// Decode untrusted bytes, lengths are bounds checked so truncated or
+	/// garbage input fails instead of panicking
+	pub fn decode(car: &[u8]) -> anyhow::Result<Self> {
+		let mut cursor = Cursor::new(car);
+		let header_len = read_varint(&mut cursor)?;
+		let start = cursor.position() as usize;
+		let header = read_section(car, start, header_len)?;
+		let root = match DagCborCodec.decode::<Ipld>(header)? {
+			Ipld::Map(header) => match header.get("roots") {
+				Some(Ipld::List(roots)) => match roots.as_slice() {
+					[Ipld::Link(root)] => *root,
+					_ => anyhow::bail!(CarError::InvalidRoots),
+				},
+				_ => anyhow::bail!(CarError::InvalidRoots),
+			},
+			_ => anyhow::bail!(CarError::InvalidRoots),
+		};
+		cursor.set_position((start + header.len()) as u64);
+
+		let mut blocks = HashMap::new();
+		while (cursor.position() as usize) < car.len() {
+			let section_len = read_varint(&mut cursor)?;
+			let start = cursor.position() as usize;
+			let section = read_section(car, start, section_len)?;
+			// the cid is read from the section only, it cannot run past it
+			let mut reader = Cursor::new(section);
+			let cid = Cid::read_bytes(&mut reader)?;
+			let block = section[reader.position() as usize..].to_vec();
+			verify_block(&cid, &block)?;
+			blocks.insert(cid, block);
+			cursor.set_position((start + section.len()) as u64);
+		}
+		Ok(Self { root, blocks })
+	}
+
+	/// Encode with the root block first, so readers streaming the CAR get the
+	/// event before the blocks it links
+	pub fn to_vec(&self) -> anyhow::Result<Vec<u8>> {
+		let header = DagCborCodec.encode(&ipld!({
+			"roots": [self.root],
+			"version": 1,
+		}))?;
+		let mut buf = Vec::new();
+		write_varint(&mut buf, header.len());
+		buf.extend(header);
+
+		let root = self.blocks.get_key_value(&self.root);
+		let rest = self.blocks.iter().filter(|(cid, _)| **cid != self.root);
+		for (cid, block) in root.into_iter().chain(rest) {
+			let cid = cid.to_bytes();
+			write_varint(&mut buf, cid.len() + block.len());
+			buf.extend(cid);
+			buf.extend_from_slice(block);
+		}
+		Ok(buf)
+	}
+
+	pub fn block(&self, cid: &Cid) -> anyhow::Result<&Vec<u8>> {
+		match self.blocks.get(cid) {
+			Some(block) => Ok(block),
+			None => anyhow::bail!(CarError::MissingBlock(cid.to_string())),
+		}
+	}
+
+	/// Event at the root, signed events take their payload from the linked
+	/// block and their cacao from the remaining block, anchor events their
+	/// proof block
+	pub fn event(&self) -> anyhow::Result<Event> {
+		let block = self.block(&self.root)?.clone();
+		let value = match EventValue::decode(self.root.codec(), block)? {
+			EventValue::Signed(signed) => {
+				let linked = signed.payload_link()?;
+				let linked_block = self.block(&linked)?.clone();
+				let cacao_block = self
+					.blocks
+					.iter()
+					.find(|(cid, _)| **cid != self.root && **cid != linked)
+					.map(|(_, block)| block.clone());
+				EventValue::Signed(Box::new(SignedValue::new(
+					signed.jws,
+					Some(linked_block),
+					cacao_block,
+				)))
+			}
+			EventValue::Anchor(mut anchor) => {
+				anchor.proof_block = self.blocks.get(&anchor.proof).cloned();
+				EventValue::Anchor(anchor)
+			}
+		};
+		Ok(Event {
+			cid: self.root,
+			value,
+		})
+	}
+}
+
+impl TryFrom<&Event> for Car {
+	type Error = anyhow::Error;
+
+	fn try_from(event: &Event) -> Result<Self, Self::Error> {
+		let mut blocks = HashMap::new();
+		match &event.value {
+			EventValue::Signed(signed) => {
+				blocks.insert(event.cid, signed.jws.to_vec()?);
+				if let Some(linked_block) = &signed.linked_block {
+					blocks.insert(signed.payload_link()?, linked_block.clone());
+				}
+				if let Some(cacao_block) = signed.cacao_block() {
+					blocks.insert(signed.cacao_link()?, cacao_block.to_vec());
+				}
+			}
+			EventValue::Anchor(anchor) => {
+				blocks.insert(event.cid, anchor.to_vec()?);
+				if let Some(proof_block) = &anchor.proof_block {
+					blocks.insert(anchor.proof, proof_block.clone());
+				}
+			}
+		}
+		Ok(Self {
+			root: event.cid,
+			blocks,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::commit::example;
+
+	#[test]
+	fn test_event_roundtrip() -> anyhow::Result<()> {
+		for event in example::events(2) {
+			let car = Car::try_from(&event)?.to_vec()?;
+			let decoded = Car::decode(&car)?;
+			assert_eq!(decoded.root, event.cid);
+
+			let decoded = decoded.event()?;
+			assert_eq!(decoded.cid, event.cid);
+			match (decoded.value, event.value) {
+				(EventValue::Signed(decoded), EventValue::Signed(event)) => {
+					assert_eq!(decoded.jws.to_vec()?, event.jws.to_vec()?);
+					assert_eq!(decoded.linked_block, event.linked_block);
+					assert_eq!(decoded.cacao_block(), event.cacao_block());
+				}
+				_ => anyhow::bail!("expected signed events"),
+			}
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn test_root_first() -> anyhow::Result<()> {
+		let genesis: Event = example::genesis().genesis.try_into()?;
+		let car = Car::try_from(&genesis)?;
+		assert_eq!(car.blocks.len(), 3);
+		let bytes = car.to_vec()?;
+		let header_len = unsigned_varint::decode::usize(&bytes)?.0;
+		// header length varint, header, section length varint, then the root cid
+		let section = &bytes[1 + header_len..];
+		let (_, section) = unsigned_varint::decode::usize(section)?;
+		assert!(section.starts_with(&genesis.cid.to_bytes()));
+		Ok(())
+	}
+
+	#[test]
+	fn test_truncated() -> anyhow::Result<()> {
+		let genesis: Event = example::genesis().genesis.try_into()?;
+		let bytes = Car::try_from(&genesis)?.to_vec()?;
+		assert!(Car::decode(&bytes[..bytes.len() - 1]).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_garbage() -> anyhow::Result<()> {
+		assert!(Car::decode(&[]).is_err());
+		assert!(Car::decode(&[0xff; 64]).is_err());
+		// lengths overflowing the offsets
+		let mut huge = vec![];
+		write_varint(&mut huge, usize::MAX);
+		assert!(Car::decode(&huge).is_err());
+
+		let genesis: Event = example::genesis().genesis.try_into()?;
+		let bytes = Car::try_from(&genesis)?.to_vec()?;
+		let header_len = unsigned_varint::decode::usize(&bytes)?.0;
+		let header = bytes[..1 + header_len].to_vec();
+		let mut car = header.clone();
+		write_varint(&mut car, usize::MAX);
+		car.extend([0; 8]);
+		assert!(Car::decode(&car).is_err());
+		// a section shorter than the cid it starts with
+		let mut car = header;
+		write_varint(&mut car, 4);
+		car.extend(&genesis.cid.to_bytes()[..4]);
+		assert!(Car::decode(&car).is_err());
+		Ok(())
+	}
+}
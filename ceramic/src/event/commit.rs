@@ -1,11 +1,14 @@
 use std::str::FromStr;
 
 use anyhow::{Context, Ok};
+use base64::Engine;
 use ceramic_core::{Base64String, Jws, StreamId};
 use ceramic_core::{Cid, StreamIdType};
 use int_enum::IntEnum;
 use serde::{Deserialize, Serialize};
 
+use super::encoding::{EncodedBytes, STANDARD_ENGINE};
+use super::errors::CommitError;
 use super::jws::ToCid;
 use super::{Event, EventValue, Payload, SignedValue};
 
@@ -51,11 +54,11 @@ impl TryInto<SignedValue> for Content {
 	type Error = anyhow::Error;
 
 	fn try_into(self) -> Result<SignedValue, Self::Error> {
-		Ok(SignedValue {
-			jws: self.jws,
-			linked_block: Some(self.linked_block.to_vec()?),
-			cacao_block: Some(self.cacao_block.to_vec()?),
-		})
+		Ok(SignedValue::new(
+			self.jws,
+			Some(self.linked_block.decode_to_vec()?),
+			Some(self.cacao_block.decode_to_vec()?),
+		))
 	}
 }
 
@@ -78,12 +81,13 @@ where
 
 	fn try_from(value: Event) -> Result<Self, Self::Error> {
 		if let EventValue::Signed(signed) = value.value {
+			let (jws, linked_block, cacao_block) = signed.into_parts();
 			return Ok(Self {
 				header: None,
 				data: None,
-				jws: Some(signed.jws),
-				linked_block: signed.linked_block.map(Base64String::from),
-				cacao_block: signed.cacao_block.map(Base64String::from),
+				jws: Some(jws),
+				linked_block: linked_block.map(Base64String::from),
+				cacao_block: cacao_block.map(Base64String::from),
 			});
 		}
 		Err(anyhow::anyhow!("invalid event value"))
@@ -95,10 +99,13 @@ impl TryFrom<Event> for Content {
 
 	fn try_from(value: Event) -> Result<Self, Self::Error> {
 		if let EventValue::Signed(signed) = value.value {
+			let (jws, linked_block, cacao_block) = signed.into_parts();
+			let linked_block = linked_block.context("linked_block is none")?;
+			let cacao_block = cacao_block.context("cacao_block is none")?;
 			return Ok(Content {
-				jws: signed.jws,
-				linked_block: Base64String::from(signed.linked_block.unwrap()),
-				cacao_block: Base64String::from(signed.cacao_block.unwrap()),
+				jws,
+				linked_block: Base64String::from(linked_block),
+				cacao_block: Base64String::from(cacao_block),
 			});
 		}
 		Err(anyhow::anyhow!("invalid event value"))
@@ -107,7 +114,7 @@ impl TryFrom<Event> for Content {
 
 impl Content {
 	pub fn payload(&self) -> anyhow::Result<Payload> {
-		Payload::try_from(self.linked_block.to_vec()?)
+		Payload::try_from(self.linked_block.decode_to_vec()?)
 	}
 
 	pub fn cid(&self) -> anyhow::Result<Cid> {
@@ -115,7 +122,207 @@ impl Content {
 	}
 }
 
+/// Size limits of commit bodies accepted from api callers
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitLimits {
+	/// size of the whole json body
+	pub max_body_bytes: usize,
+	/// size of a decoded linked or cacao block
+	pub max_block_bytes: usize,
+}
+
+impl Default for CommitLimits {
+	fn default() -> Self {
+		Self {
+			max_body_bytes: 4 * 1024 * 1024,
+			max_block_bytes: 1024 * 1024,
+		}
+	}
+}
+
+impl CommitLimits {
+	pub fn check_body(&self, body: &[u8]) -> anyhow::Result<()> {
+		if body.len() > self.max_body_bytes {
+			anyhow::bail!(CommitError::BodyTooLarge(body.len(), self.max_body_bytes));
+		}
+		Ok(())
+	}
+
+	pub fn check_content(&self, content: &Content) -> anyhow::Result<()> {
+		for (name, block) in [
+			("linkedBlock", &content.linked_block),
+			("cacaoBlock", &content.cacao_block),
+		] {
+			let size = block.decoded_len();
+			if size > self.max_block_bytes {
+				anyhow::bail!(CommitError::BlockTooLarge(
+					name.to_string(),
+					size,
+					self.max_block_bytes
+				));
+			}
+		}
+		Ok(())
+	}
+}
+
+impl Genesis {
+	pub fn from_slice_limited(body: &[u8], limits: &CommitLimits) -> anyhow::Result<Self> {
+		limits.check_body(body)?;
+		let genesis: Self = serde_json::from_slice(body)?;
+		limits.check_content(&genesis.genesis)?;
+		Ok(genesis)
+	}
+}
+
+impl Data {
+	pub fn from_slice_limited(body: &[u8], limits: &CommitLimits) -> anyhow::Result<Self> {
+		limits.check_body(body)?;
+		let data: Self = serde_json::from_slice(body)?;
+		limits.check_content(&data.commit)?;
+		Ok(data)
+	}
+}
+
+/// Commit content whose blocks are decoded once while the body is parsed,
+/// the buffers are moved into the event without being re-encoded
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedContent {
+	pub jws: Jws,
+	#[serde(deserialize_with = "decode_block")]
+	pub linked_block: Vec<u8>,
+	#[serde(deserialize_with = "decode_block")]
+	pub cacao_block: Vec<u8>,
+}
+
+impl DecodedContent {
+	pub fn payload(&self) -> anyhow::Result<Payload> {
+		Payload::try_from(self.linked_block.as_slice())
+	}
+
+	pub fn cid(&self) -> anyhow::Result<Cid> {
+		self.jws.cid()
+	}
+
+	pub fn check_limits(&self, limits: &CommitLimits) -> anyhow::Result<()> {
+		for (name, block) in [
+			("linkedBlock", &self.linked_block),
+			("cacaoBlock", &self.cacao_block),
+		] {
+			if block.len() > limits.max_block_bytes {
+				anyhow::bail!(CommitError::BlockTooLarge(
+					name.to_string(),
+					block.len(),
+					limits.max_block_bytes
+				));
+			}
+		}
+		Ok(())
+	}
+}
+
+impl TryInto<Event> for DecodedContent {
+	type Error = anyhow::Error;
+
+	fn try_into(self) -> Result<Event, Self::Error> {
+		Ok(Event {
+			cid: self.jws.cid()?,
+			value: EventValue::Signed(Box::new(SignedValue::new(
+				self.jws,
+				Some(self.linked_block),
+				Some(self.cacao_block),
+			))),
+		})
+	}
+}
+
+impl From<DecodedContent> for Content {
+	fn from(value: DecodedContent) -> Self {
+		Self {
+			jws: value.jws,
+			linked_block: Base64String::from(value.linked_block),
+			cacao_block: Base64String::from(value.cacao_block),
+		}
+	}
+}
+
+fn decode_block<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+where
+	D: serde::Deserializer<'de>,
+{
+	struct BlockVisitor;
+
+	impl<'de> serde::de::Visitor<'de> for BlockVisitor {
+		type Value = Vec<u8>;
+
+		fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+			write!(f, "a base64 encoded block")
+		}
+
+		fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+			STANDARD_ENGINE.decode(v).map_err(E::custom)
+		}
+	}
+
+	deserializer.deserialize_str(BlockVisitor)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DecodedGenesis {
+	pub r#type: u64,
+	pub genesis: DecodedContent,
+	pub opts: serde_json::Value,
+}
+
+impl DecodedGenesis {
+	pub fn from_slice_limited(body: &[u8], limits: &CommitLimits) -> anyhow::Result<Self> {
+		limits.check_body(body)?;
+		let genesis: Self = serde_json::from_slice(body)?;
+		genesis.genesis.check_limits(limits)?;
+		Ok(genesis)
+	}
+
+	pub fn model_id(&self) -> anyhow::Result<StreamId> {
+		let payload = self.genesis.payload()?;
+		payload.header.map(|x| x.model).context("missing model id")
+	}
+
+	pub fn stream_id(&self) -> anyhow::Result<StreamId> {
+		let stream_id = StreamId {
+			r#type: StreamIdType::from_int(self.r#type)?,
+			cid: self.genesis.cid()?,
+		};
+		Ok(stream_id)
+	}
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedData {
+	pub stream_id: StreamId,
+	pub commit: DecodedContent,
+	pub opts: serde_json::Value,
+}
+
+impl DecodedData {
+	pub fn from_slice_limited(body: &[u8], limits: &CommitLimits) -> anyhow::Result<Self> {
+		limits.check_body(body)?;
+		let data: Self = serde_json::from_slice(body)?;
+		data.commit.check_limits(limits)?;
+		Ok(data)
+	}
+}
+
 pub mod example {
+	use std::collections::BTreeMap;
+
+	use libipld::cbor::DagCborCodec;
+	use libipld::multihash::{Code, MultihashDigest};
+	use libipld::prelude::Codec;
+	use libipld::Ipld;
+
 	use super::*;
 
 	pub fn genesis_value() -> serde_json::Value {
@@ -176,6 +383,41 @@ pub mod example {
 	pub fn data() -> Data {
 		serde_json::from_value(data_value()).unwrap()
 	}
+
+	/// ed25519 seed of the `did:key` signing synthetic commits
+	pub const SYNTHETIC_PK: &str =
+		"d160c4553ba7547cd5d66993d99329379a0c299a1bb1058abc5b874e0ba56375";
+
+	/// Data commit applying the json patch, signed without capability
+	pub fn data_event(
+		genesis: libipld::Cid,
+		prev: libipld::Cid,
+		patch: serde_json::Value,
+	) -> anyhow::Result<Event> {
+		let node = Ipld::Map(BTreeMap::from([
+			("data".to_string(), libipld::serde::to_ipld(patch)?),
+			("id".to_string(), Ipld::Link(genesis)),
+			("prev".to_string(), Ipld::Link(prev)),
+		]));
+		let block = DagCborCodec.encode(&node)?;
+		let cid = libipld::Cid::new_v1(0x71, Code::Sha2_256.digest(&block));
+		crate::event::metadata::sign_linked_block(SYNTHETIC_PK, cid, block)
+	}
+
+	/// Synthetic log of `len` events, the example genesis followed by data
+	/// commits replacing the text of the content
+	pub fn events(len: usize) -> Vec<Event> {
+		let genesis: Event = genesis().genesis.try_into().unwrap();
+		let mut events = vec![genesis];
+		for idx in 1..len {
+			let patch = serde_json::json!([
+				{ "op": "replace", "path": "/text", "value": format!("text {}", idx) }
+			]);
+			let prev = events[idx - 1].cid;
+			events.push(data_event(events[0].cid, prev, patch).unwrap());
+		}
+		events
+	}
 }
 
 #[cfg(test)]
@@ -276,4 +518,61 @@ mod tests {
 
 		Ok(())
 	}
+
+	#[test]
+	fn test_decoded_genesis_matches_content() -> anyhow::Result<()> {
+		let body = serde_json::to_vec(&example::genesis_value())?;
+		let decoded = DecodedGenesis::from_slice_limited(&body, &CommitLimits::default())?;
+		let genesis = example::genesis();
+
+		assert_eq!(decoded.stream_id()?, genesis.stream_id()?);
+		assert_eq!(decoded.model_id()?, genesis.model_id()?);
+		assert_eq!(
+			decoded.genesis.linked_block,
+			genesis.genesis.linked_block.to_vec()?
+		);
+
+		let event: Event = decoded.genesis.try_into()?;
+		let expected: Event = genesis.genesis.try_into()?;
+		assert_eq!(event.cid, expected.cid);
+		Ok(())
+	}
+
+	#[test]
+	fn test_decoded_data_block_limit() -> anyhow::Result<()> {
+		let body = serde_json::to_vec(&example::data_value())?;
+		let limits = CommitLimits {
+			max_block_bytes: 8,
+			..Default::default()
+		};
+		let err = DecodedData::from_slice_limited(&body, &limits).unwrap_err();
+		assert!(matches!(
+			err.downcast_ref::<CommitError>(),
+			Some(CommitError::BlockTooLarge(..))
+		));
+		Ok(())
+	}
+
+	#[test]
+	fn test_block_data_cacao() -> anyhow::Result<()> {
+		let genesis: Event = example::genesis().genesis.try_into()?;
+		let block: ceramic_http_client::api::BlockData<serde_json::Value> = genesis.try_into()?;
+		assert!(block.cacao_block.is_some());
+
+		// synthetic events carry no cacao, converting them must not panic
+		let synthetic = example::events(1).remove(0);
+		assert!(Content::try_from(synthetic).is_err());
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_synthetic_events() -> anyhow::Result<()> {
+		let events = example::events(5);
+		assert_eq!(events.len(), 5);
+
+		let state = crate::StreamState::make(3, events).await?;
+		assert_eq!(state.content["text"], "text 4");
+		assert_eq!(state.log.len(), 5);
+		Ok(())
+	}
 }
@@ -0,0 +1,132 @@
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::Engine;
+use ceramic_core::{Base64String, Base64UrlString, MultiBase32String};
+
+const INDIFFERENT: GeneralPurposeConfig = GeneralPurposeConfig::new()
+	.with_encode_padding(false)
+	.with_decode_padding_mode(DecodePaddingMode::Indifferent);
+
+/// base64 of blocks, accepting padded and unpadded input
+pub(crate) const STANDARD_ENGINE: GeneralPurpose =
+	GeneralPurpose::new(&alphabet::STANDARD, INDIFFERENT);
+
+/// base64url of jws payloads and signatures
+pub(crate) const URL_SAFE_ENGINE: GeneralPurpose =
+	GeneralPurpose::new(&alphabet::URL_SAFE, INDIFFERENT);
+
+/// Borrowing access to encoded strings, decoding into caller owned buffers
+/// instead of allocating per conversion
+pub trait EncodedBytes {
+	/// encoded form, without copying
+	fn as_bytes(&self) -> &[u8];
+
+	/// length of the decoded bytes, computed from the encoded length
+	fn decoded_len(&self) -> usize;
+
+	/// append the decoded bytes to the buffer
+	fn decode_into(&self, buf: &mut Vec<u8>) -> anyhow::Result<()>;
+
+	/// decode into the slice, returns the number of bytes written
+	fn decode_slice(&self, buf: &mut [u8]) -> anyhow::Result<usize>;
+
+	fn decode_to_vec(&self) -> anyhow::Result<Vec<u8>> {
+		let mut buf = Vec::with_capacity(self.decoded_len());
+		self.decode_into(&mut buf)?;
+		Ok(buf)
+	}
+}
+
+fn base64_decoded_len(encoded: &str) -> usize {
+	encoded.trim_end_matches('=').len() * 3 / 4
+}
+
+impl EncodedBytes for Base64String {
+	fn as_bytes(&self) -> &[u8] {
+		self.as_ref().as_bytes()
+	}
+
+	fn decoded_len(&self) -> usize {
+		base64_decoded_len(self.as_ref())
+	}
+
+	fn decode_into(&self, buf: &mut Vec<u8>) -> anyhow::Result<()> {
+		Ok(STANDARD_ENGINE.decode_vec(self.as_ref(), buf)?)
+	}
+
+	fn decode_slice(&self, buf: &mut [u8]) -> anyhow::Result<usize> {
+		Ok(STANDARD_ENGINE.decode_slice(self.as_ref(), buf)?)
+	}
+}
+
+impl EncodedBytes for Base64UrlString {
+	fn as_bytes(&self) -> &[u8] {
+		self.as_ref().as_bytes()
+	}
+
+	fn decoded_len(&self) -> usize {
+		base64_decoded_len(self.as_ref())
+	}
+
+	fn decode_into(&self, buf: &mut Vec<u8>) -> anyhow::Result<()> {
+		Ok(URL_SAFE_ENGINE.decode_vec(self.as_ref(), buf)?)
+	}
+
+	fn decode_slice(&self, buf: &mut [u8]) -> anyhow::Result<usize> {
+		Ok(URL_SAFE_ENGINE.decode_slice(self.as_ref(), buf)?)
+	}
+}
+
+/// multibase strings carry their base as a one character prefix
+impl EncodedBytes for MultiBase32String {
+	fn as_bytes(&self) -> &[u8] {
+		self.as_ref().as_bytes()
+	}
+
+	fn decoded_len(&self) -> usize {
+		self.as_ref().len().saturating_sub(1) * 5 / 8
+	}
+
+	fn decode_into(&self, buf: &mut Vec<u8>) -> anyhow::Result<()> {
+		let (_, bytes) = multibase::decode(self.as_ref())?;
+		buf.extend_from_slice(&bytes);
+		Ok(())
+	}
+
+	fn decode_slice(&self, buf: &mut [u8]) -> anyhow::Result<usize> {
+		let (_, bytes) = multibase::decode(self.as_ref())?;
+		match buf.get_mut(..bytes.len()) {
+			Some(buf) => buf.copy_from_slice(&bytes),
+			None => anyhow::bail!("output slice too small for {} bytes", bytes.len()),
+		}
+		Ok(bytes.len())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::commit::example;
+
+	#[test]
+	fn test_decode_base64() -> anyhow::Result<()> {
+		let content = example::genesis().genesis;
+		let expected = content.linked_block.to_vec()?;
+		assert_eq!(content.linked_block.decoded_len(), expected.len());
+		assert_eq!(content.linked_block.decode_to_vec()?, expected);
+
+		let mut buf = vec![0x01];
+		content.cacao_block.decode_into(&mut buf)?;
+		assert_eq!(buf[1..], content.cacao_block.to_vec()?);
+		Ok(())
+	}
+
+	#[test]
+	fn test_decode_jws_payload() -> anyhow::Result<()> {
+		let content = example::genesis().genesis;
+		let mut buf = [0u8; 64];
+		let len = content.jws.payload.decode_slice(&mut buf)?;
+		assert_eq!(&buf[..len], content.jws.payload.to_vec()?.as_slice());
+		Ok(())
+	}
+}
@@ -5,6 +5,16 @@ pub enum EventError {
 	InvalidGenesisError,
 	InvalidPreviousCid(String, String),
 	MissingLastLog,
+	/// issuer of the event and the controllers of the stream
+	ControllerMismatch(String, Vec<String>),
+	/// signer whose key does not verify the jws
+	InvalidSignature(String),
+	/// issuer whose address does not verify the cacao
+	InvalidCacaoSignature(String),
+	/// header and signature type of a cacao
+	UnsupportedCacao(String, String),
+	/// audience of the cacao and signer of the jws
+	CacaoAudienceMismatch(String, String),
 }
 
 impl std::fmt::Display for EventError {
@@ -17,6 +27,22 @@ impl std::fmt::Display for EventError {
 				write!(f, "invalid prev cid: {} != {}", prev, tip)
 			}
 			Self::MissingLastLog => write!(f, "missing last log"),
+			Self::ControllerMismatch(issuer, controllers) => write!(
+				f,
+				"event signed by {} instead of a controller in [{}]",
+				issuer,
+				controllers.join(", ")
+			),
+			Self::InvalidSignature(signer) => write!(f, "jws signature of {} is invalid", signer),
+			Self::InvalidCacaoSignature(issuer) => {
+				write!(f, "cacao signature of {} is invalid", issuer)
+			}
+			Self::UnsupportedCacao(header, signature) => {
+				write!(f, "unsupported cacao {} signed with {}", header, signature)
+			}
+			Self::CacaoAudienceMismatch(aud, signer) => {
+				write!(f, "cacao issued to {} instead of signer {}", aud, signer)
+			}
 		}
 	}
 }
@@ -52,3 +78,91 @@ impl std::fmt::Display for SignedValueError {
 }
 
 impl std::error::Error for SignedValueError {}
+
+#[derive(Debug)]
+pub enum FanoutError {
+	NoDestinations,
+	RequiredDestinationsFailed(Vec<(String, String)>),
+}
+
+impl std::fmt::Display for FanoutError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::NoDestinations => write!(f, "no destinations configured for fanout"),
+			Self::RequiredDestinationsFailed(failures) => {
+				write!(f, "required destinations failed:")?;
+				for (name, err) in failures {
+					write!(f, " [{}: {}]", name, err)?;
+				}
+				Ok(())
+			}
+		}
+	}
+}
+
+impl std::error::Error for FanoutError {}
+
+#[derive(Debug)]
+pub enum CommitError {
+	BodyTooLarge(usize, usize),
+	BlockTooLarge(String, usize, usize),
+}
+
+impl std::fmt::Display for CommitError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::BodyTooLarge(size, limit) => {
+				write!(f, "commit body of {} bytes exceeds limit {}", size, limit)
+			}
+			Self::BlockTooLarge(name, size, limit) => {
+				write!(f, "{} of {} bytes exceeds limit {}", name, size, limit)
+			}
+		}
+	}
+}
+
+impl std::error::Error for CommitError {}
+
+#[derive(Debug)]
+pub enum CarError {
+	Truncated,
+	InvalidRoots,
+	MissingBlock(String),
+}
+
+impl std::fmt::Display for CarError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Truncated => write!(f, "car is truncated"),
+			Self::InvalidRoots => write!(f, "car must have a single root"),
+			Self::MissingBlock(cid) => write!(f, "car missing block {}", cid),
+		}
+	}
+}
+
+impl std::error::Error for CarError {}
+
+#[derive(Debug)]
+pub enum WitnessError {
+	InvalidMerkleNode(String),
+	InvalidPath(String),
+	GenesisMismatch(String, String),
+	TipMismatch(String, String),
+}
+
+impl std::fmt::Display for WitnessError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::InvalidMerkleNode(cid) => write!(f, "invalid merkle node {}", cid),
+			Self::InvalidPath(path) => write!(f, "invalid anchor path {}", path),
+			Self::GenesisMismatch(id, genesis) => {
+				write!(f, "anchor of stream {} instead of {}", id, genesis)
+			}
+			Self::TipMismatch(anchored, tip) => {
+				write!(f, "anchor of commit {} instead of tip {}", anchored, tip)
+			}
+		}
+	}
+}
+
+impl std::error::Error for WitnessError {}
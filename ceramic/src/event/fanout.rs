@@ -0,0 +1,194 @@
+use std::sync::Arc;
+
+use ceramic_core::StreamId;
+use futures::future::join_all;
+
+use crate::event::errors::FanoutError;
+use crate::event::{Event, EventsUploader};
+use crate::Ceramic;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DestinationPolicy {
+	/// a failure fails the whole upload
+	Required,
+	/// a failure is only reported
+	BestEffort,
+}
+
+pub struct Destination {
+	pub name: String,
+	/// ceramic used for this destination, falls back to the one passed to
+	/// `upload_event` when empty
+	pub ceramic: Option<Ceramic>,
+	pub uploader: Arc<dyn EventsUploader + Send + Sync>,
+	pub policy: DestinationPolicy,
+}
+
+impl Destination {
+	pub fn new(
+		name: &str,
+		uploader: Arc<dyn EventsUploader + Send + Sync>,
+		policy: DestinationPolicy,
+	) -> Self {
+		Self {
+			name: name.to_string(),
+			ceramic: None,
+			uploader,
+			policy,
+		}
+	}
+
+	pub fn with_ceramic(self, ceramic: Ceramic) -> Self {
+		Self {
+			ceramic: Some(ceramic),
+			..self
+		}
+	}
+}
+
+#[derive(Debug, Default)]
+pub struct FanoutReport {
+	pub succeeded: Vec<String>,
+	pub failed: Vec<(String, DestinationPolicy, String)>,
+}
+
+impl FanoutReport {
+	pub fn is_partial(&self) -> bool {
+		!self.succeeded.is_empty() && !self.failed.is_empty()
+	}
+
+	pub fn required_failures(&self) -> Vec<(String, String)> {
+		self.failed
+			.iter()
+			.filter(|(_, policy, _)| *policy == DestinationPolicy::Required)
+			.map(|(name, _, err)| (name.clone(), err.clone()))
+			.collect()
+	}
+}
+
+/// EventsUploader publishing every event to several destinations, e.g. both
+/// mainnet and clay while a dapp migrates between networks
+pub struct FanoutUploader {
+	pub destinations: Vec<Destination>,
+}
+
+impl FanoutUploader {
+	pub fn new(destinations: Vec<Destination>) -> Self {
+		Self { destinations }
+	}
+
+	pub async fn publish(
+		&self,
+		ceramic: &Ceramic,
+		stream_id: &StreamId,
+		event: Event,
+	) -> anyhow::Result<FanoutReport> {
+		if self.destinations.is_empty() {
+			anyhow::bail!(FanoutError::NoDestinations);
+		}
+		let uploads = self.destinations.iter().map(|dest| {
+			let ceramic = dest.ceramic.as_ref().unwrap_or(ceramic);
+			dest.uploader
+				.upload_event(ceramic, stream_id, event.clone())
+		});
+		let results = join_all(uploads).await;
+
+		let mut report = FanoutReport::default();
+		for (dest, result) in self.destinations.iter().zip(results) {
+			match result {
+				Ok(_) => report.succeeded.push(dest.name.clone()),
+				Err(err) => {
+					tracing::warn!(
+						destination = dest.name,
+						stream_id = stream_id.to_string(),
+						policy = ?dest.policy,
+						"failed to publish event: {}",
+						err
+					);
+					report
+						.failed
+						.push((dest.name.clone(), dest.policy, err.to_string()));
+				}
+			}
+		}
+		Ok(report)
+	}
+}
+
+#[async_trait::async_trait]
+impl EventsUploader for FanoutUploader {
+	async fn upload_event(
+		&self,
+		ceramic: &Ceramic,
+		stream_id: &StreamId,
+		event: Event,
+	) -> anyhow::Result<()> {
+		let report = self.publish(ceramic, stream_id, event).await?;
+		let failures = report.required_failures();
+		if !failures.is_empty() {
+			anyhow::bail!(FanoutError::RequiredDestinationsFailed(failures));
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	use super::*;
+	use crate::network::Network;
+
+	struct Counter(AtomicUsize, bool);
+
+	#[async_trait::async_trait]
+	impl EventsUploader for Counter {
+		async fn upload_event(&self, _: &Ceramic, _: &StreamId, _: Event) -> anyhow::Result<()> {
+			self.0.fetch_add(1, Ordering::SeqCst);
+			match self.1 {
+				true => Ok(()),
+				false => anyhow::bail!("unreachable"),
+			}
+		}
+	}
+
+	#[tokio::test]
+	async fn fanout_partial_failure() -> anyhow::Result<()> {
+		let ceramic = Ceramic {
+			endpoint: "http://localhost:7007".to_string(),
+			network: Network::InMemory,
+			api: Default::default(),
+		};
+		let genesis = crate::commit::example::genesis();
+		let stream_id = genesis.stream_id()?;
+		let event: Event = genesis.genesis.try_into()?;
+
+		let ok = Arc::new(Counter(AtomicUsize::new(0), true));
+		let broken = Arc::new(Counter(AtomicUsize::new(0), false));
+		let fanout = FanoutUploader::new(vec![
+			Destination::new("mainnet", ok.clone(), DestinationPolicy::Required),
+			Destination::new("clay", broken.clone(), DestinationPolicy::BestEffort),
+		]);
+
+		let report = fanout.publish(&ceramic, &stream_id, event.clone()).await?;
+		assert!(report.is_partial());
+		assert_eq!(report.succeeded, vec!["mainnet".to_string()]);
+		assert!(report.required_failures().is_empty());
+		assert!(fanout
+			.upload_event(&ceramic, &stream_id, event.clone())
+			.await
+			.is_ok());
+		assert_eq!(ok.0.load(Ordering::SeqCst), 2);
+
+		let fanout = FanoutUploader::new(vec![Destination::new(
+			"clay",
+			broken,
+			DestinationPolicy::Required,
+		)]);
+		assert!(fanout
+			.upload_event(&ceramic, &stream_id, event)
+			.await
+			.is_err());
+		Ok(())
+	}
+}
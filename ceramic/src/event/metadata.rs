@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+
+use base64::{engine::general_purpose, Engine};
+use dag_jose::JsonWebSignature;
+use dataverse_types_core::digest::{block_cid, DAG_CBOR};
+use libipld::cbor::DagCborCodec;
+use libipld::prelude::Codec;
+use libipld::{cid::Cid, Ipld};
+use ssh_key::private::Ed25519Keypair;
+use ssi::jwk::{Algorithm, Base64urlUInt, OctetParams, Params, JWK};
+
+use super::jws::{Jws, ToCid};
+use super::{Event, SignedValue};
+use crate::did::generate_did_str;
+
+/// Linked block of a data commit replacing the controllers of a stream,
+/// returned with the cid to be signed as jws payload
+pub fn controller_update_block(
+	genesis: Cid,
+	prev: Cid,
+	controllers: &[String],
+) -> anyhow::Result<(Cid, Vec<u8>)> {
+	let header = BTreeMap::from([(
+		"controllers".to_string(),
+		Ipld::List(controllers.iter().cloned().map(Ipld::String).collect()),
+	)]);
+	let node = Ipld::Map(BTreeMap::from([
+		("data".to_string(), Ipld::List(vec![])),
+		("header".to_string(), Ipld::Map(header)),
+		("id".to_string(), Ipld::Link(genesis)),
+		("prev".to_string(), Ipld::Link(prev)),
+	]));
+	let block = DagCborCodec.encode(&node)?;
+	Ok((block_cid(DAG_CBOR, &block), block))
+}
+
+/// Protected header and signature of `payload` (base64url) signed with the
+/// ed25519 seed (hex) of a `did:key`
+pub(crate) fn sign_payload(pk: &str, payload: &str) -> anyhow::Result<(String, String)> {
+	let did = generate_did_str(pk)?;
+	let seed = hex::decode(pk)?;
+	let keypair =
+		Ed25519Keypair::from_seed(&seed.clone().try_into().expect("seed length is 32 bytes"));
+	let jwk = JWK::from(Params::OKP(OctetParams {
+		curve: "Ed25519".to_string(),
+		public_key: Base64urlUInt(keypair.public.0.to_vec()),
+		private_key: Some(Base64urlUInt(seed)),
+	}));
+
+	let kid = format!("{}#{}", did, did.trim_start_matches("did:key:"));
+	let protected = serde_json::json!({ "alg": "EdDSA", "kid": kid }).to_string();
+	let protected = general_purpose::URL_SAFE_NO_PAD.encode(protected);
+	let signing_input = format!("{}.{}", protected, payload);
+	let signature = ssi::jws::sign_bytes_b64(Algorithm::EdDSA, signing_input.as_bytes(), &jwk)?;
+	Ok((protected, signature))
+}
+
+/// Sign a linked block with the ed25519 seed (hex) of a `did:key` controller
+pub fn sign_linked_block(pk: &str, payload: Cid, linked_block: Vec<u8>) -> anyhow::Result<Event> {
+	let payload_str = general_purpose::URL_SAFE_NO_PAD.encode(payload.to_bytes());
+	let (protected, signature) = sign_payload(pk, &payload_str)?;
+
+	let jws = JsonWebSignature {
+		payload: payload_str,
+		signatures: vec![dag_jose::Signature {
+			header: Default::default(),
+			protected: Some(protected),
+			signature,
+		}],
+		link: payload,
+	};
+	let cid = jws.cid()?;
+	let Jws(jws) = jws.try_into()?;
+	Ok(Event {
+		cid,
+		value: SignedValue::new(jws, Some(linked_block), None).into(),
+	})
+}
+
+/// Signed data commit transferring a stream to a new controller
+pub fn controller_update_event(
+	pk: &str,
+	genesis: Cid,
+	prev: Cid,
+	new_controller: &str,
+) -> anyhow::Result<Event> {
+	let (payload, block) = controller_update_block(genesis, prev, &[new_controller.to_string()])?;
+	sign_linked_block(pk, payload, block)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::event::{EventValue, StreamStateApplyer};
+	use crate::stream::StreamState;
+
+	#[test]
+	fn test_controller_update_event() -> anyhow::Result<()> {
+		let pk = "d160c4553ba7547cd5d66993d99329379a0c299a1bb1058abc5b874e0ba56375";
+		let genesis: Cid = "bafyreih3skzznsyro5i4bsfsyquhcewyqmsra6cx47nj2eb6aerm2nihuu".parse()?;
+		let prev: Cid = "bafyreidnbzsaplrdpjx3schac4fjhwqjzv3kbvdswi52npq3kpdzpbv5qa".parse()?;
+		let new_controller = "did:pkh:eip155:1:0x312eA852726E3A9f633A0377c0ea882086d66666";
+
+		let event = controller_update_event(pk, genesis, prev, new_controller)?;
+		assert_eq!(event.genesis()?, genesis);
+		assert_eq!(event.prev()?, Some(prev));
+
+		let mut state = StreamState {
+			metadata: serde_json::json!({ "controllers": ["did:pkh:eip155:1:0x0"] }),
+			..Default::default()
+		};
+		if let EventValue::Signed(signed) = &event.value {
+			signed.apply_to(&mut state)?;
+		}
+		assert_eq!(state.controllers(), vec![new_controller.to_string()]);
+		Ok(())
+	}
+}
@@ -1,12 +1,18 @@
 pub mod anchor;
 pub mod cacao;
+pub mod car;
 pub mod commit;
+pub mod encoding;
 pub mod errors;
+pub mod fanout;
 pub mod ipld;
 pub mod jws;
+pub mod metadata;
 pub mod operator;
 pub mod signed;
+pub mod summary;
 pub mod verify;
+pub mod witness;
 
 use crate::stream::{LogType, StreamState};
 use anyhow::{Context, Result};
@@ -17,11 +23,15 @@ use libipld::{cbor::DagCborCodec, cid::Cid};
 use serde::{Deserialize, Serialize};
 
 pub use self::anchor::*;
+pub use self::car::Car;
+pub use self::encoding::EncodedBytes;
 pub use self::ipld::*;
 pub use self::jws::ToCid;
 pub use self::operator::*;
 pub use self::signed::*;
+pub use self::summary::CommitSummary;
 pub use self::verify::*;
+pub use self::witness::WitnessCar;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Event {
@@ -92,18 +102,20 @@ impl Event {
 			EventValue::Signed(signed) => {
 				signed.apply_to(state)?;
 
-				if let Some(cacao) = signed.cacao()? {
-					let exp = cacao.p.expiration_time()?;
-					state_log.expiration_time = exp.map(|x| x.timestamp());
-				}
+				let exp = signed.expiration_time()?;
+				state_log.expiration_time = exp.map(|x| x.timestamp());
 			}
 			EventValue::Anchor(anchor) => {
 				anchor.apply_to(state)?;
 
-				// if let Some(proof) = anchor.proof()? {
-				//     let timestamp = network::timestamp(proof).await?;
-				//     state_log.timestamp = Some(timestamp);
-				// };
+				match anchor.timestamp().await {
+					Ok(timestamp) => state_log.timestamp = timestamp,
+					Err(err) => tracing::warn!(
+						cid = self.cid.to_string(),
+						"failed to get anchor timestamp: {}",
+						err
+					),
+				}
 			}
 		};
 		state.log.push(state_log);
@@ -134,11 +146,11 @@ impl TryFrom<ceramic_http_client::api::Commit> for Event {
 			}),
 			ceramic_http_client::api::CommitValue::Signed(signed) => Ok(Event {
 				cid: value.cid.as_ref().try_into()?,
-				value: EventValue::Signed(Box::new(SignedValue {
-					jws: signed.jws,
-					linked_block: Some(signed.linked_block.to_vec()?),
-					cacao_block: None,
-				})),
+				value: EventValue::Signed(Box::new(SignedValue::new(
+					signed.jws,
+					Some(signed.linked_block.decode_to_vec()?),
+					None,
+				))),
 			}),
 		}
 	}
@@ -150,11 +162,7 @@ impl TryFrom<ceramic_core::Jws> for Event {
 	fn try_from(jws: ceramic_core::Jws) -> std::result::Result<Self, Self::Error> {
 		Ok(Self {
 			cid: jws.cid()?,
-			value: EventValue::Signed(Box::new(SignedValue {
-				jws,
-				linked_block: None,
-				cacao_block: None,
-			})),
+			value: EventValue::Signed(Box::new(SignedValue::new(jws, None, None))),
 		})
 	}
 }
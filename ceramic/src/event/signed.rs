@@ -1,23 +1,48 @@
+use crate::did::did_key_to_jwk;
+use crate::redact::REDACTED;
 use crate::stream::StreamState;
 use crate::EventValue;
 
+use std::sync::Arc;
+
 use anyhow::Result;
 use ceramic_core::{Base64String, StreamId};
+use chrono::{DateTime, Utc};
 use json_patch::Patch;
 use libipld::multihash::{Code, MultihashDigest};
 use libipld::prelude::Codec;
 use libipld::{cbor::DagCborCodec, cid::Cid, json::DagJsonCodec, Ipld};
+use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
 
 use super::cacao::CACAO;
+use super::encoding::EncodedBytes;
+use super::errors::EventError;
 use super::ipld::IpldAs;
 use super::{jws, StreamStateApplyer};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct SignedValue {
 	pub jws: ceramic_core::Jws,
 	pub linked_block: Option<Vec<u8>>,
-	pub cacao_block: Option<Vec<u8>>,
+	/// only replaced through [`SignedValue::set_cacao_block`], keeping the
+	/// decoded `cacao` in sync
+	cacao_block: Option<Vec<u8>>,
+	/// cacao decoded from `cacao_block` on first access, reset by
+	/// [`SignedValue::set_cacao_block`]
+	#[serde(skip)]
+	cacao: OnceCell<Arc<CACAO>>,
+}
+
+/// jws and blocks carry signatures and capabilities, only their sizes are printed
+impl std::fmt::Debug for SignedValue {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("SignedValue")
+			.field("jws", &format_args!("{}", REDACTED))
+			.field("linked_block", &self.linked_block.as_ref().map(Vec::len))
+			.field("cacao_block", &self.cacao_block.as_ref().map(Vec::len))
+			.finish()
+	}
 }
 
 impl Clone for SignedValue {
@@ -26,6 +51,7 @@ impl Clone for SignedValue {
 			jws: jws::clone_jws(&self.jws),
 			linked_block: self.linked_block.clone(),
 			cacao_block: self.cacao_block.clone(),
+			cacao: self.cacao.clone(),
 		}
 	}
 }
@@ -43,10 +69,8 @@ impl TryFrom<(Vec<u8>, Option<Vec<u8>>, Option<Vec<u8>>)> for SignedValue {
 		value: (Vec<u8>, Option<Vec<u8>>, Option<Vec<u8>>),
 	) -> std::prelude::v1::Result<Self, Self::Error> {
 		let (jws, linked_block, cacao_block) = value;
-		let mut signed: SignedValue = jws.try_into()?;
-		signed.linked_block = linked_block;
-		signed.cacao_block = cacao_block;
-		Ok(signed)
+		let super::jws::Jws(jws) = jws.try_into()?;
+		Ok(SignedValue::new(jws, linked_block, cacao_block))
 	}
 }
 
@@ -55,16 +79,50 @@ impl TryFrom<Vec<u8>> for SignedValue {
 
 	fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
 		let super::jws::Jws(jws) = value.try_into()?;
-
-		Ok(SignedValue {
-			jws,
-			linked_block: None,
-			cacao_block: None,
-		})
+		Ok(SignedValue::new(jws, None, None))
 	}
 }
 
 impl SignedValue {
+	pub fn new(
+		jws: ceramic_core::Jws,
+		linked_block: Option<Vec<u8>>,
+		cacao_block: Option<Vec<u8>>,
+	) -> Self {
+		Self {
+			jws,
+			linked_block,
+			cacao_block,
+			cacao: OnceCell::new(),
+		}
+	}
+
+	pub fn cacao_block(&self) -> Option<&[u8]> {
+		self.cacao_block.as_deref()
+	}
+
+	/// jws, linked block and cacao block of the event
+	pub fn into_parts(self) -> (ceramic_core::Jws, Option<Vec<u8>>, Option<Vec<u8>>) {
+		(self.jws, self.linked_block, self.cacao_block)
+	}
+
+	/// Replace the cacao block, dropping the cacao decoded from the previous one
+	pub fn set_cacao_block(&mut self, cacao_block: Option<Vec<u8>>) {
+		self.cacao_block = cacao_block;
+		self.cacao = OnceCell::new();
+	}
+
+	/// Attach the cacao the jws was signed under, the block must be the one
+	/// linked by the cap of the jws
+	pub fn attach_cacao_block(&mut self, cacao_block: Vec<u8>) -> anyhow::Result<()> {
+		let link = Cid::new_v1(0x71, Code::Sha2_256.digest(&cacao_block));
+		if self.cap()? != link {
+			anyhow::bail!("cacao not match jws cap");
+		}
+		self.set_cacao_block(Some(cacao_block));
+		Ok(())
+	}
+
 	pub fn payload(&self) -> anyhow::Result<Payload> {
 		match self.linked_block.clone() {
 			Some(linked_block) => {
@@ -87,7 +145,10 @@ impl SignedValue {
 		let protected = self.protected()?;
 		let res: serde_json::Value = serde_json::from_slice(&protected)?;
 
-		let cap = res["cap"].as_str().expect("cap not found");
+		let cap = match res["cap"].as_str() {
+			Some(cap) => cap,
+			None => anyhow::bail!("cap not found"),
+		};
 
 		let url = url::Url::parse(cap)?;
 
@@ -102,24 +163,82 @@ impl SignedValue {
 			.map_err(|_| anyhow::anyhow!("invalid cap host"))
 	}
 
+	/// DID signing the event, the issuer of the cacao or the did of the jws key
+	pub fn issuer(&self) -> anyhow::Result<String> {
+		if let Some(cacao) = self.cacao()? {
+			return Ok(cacao.p.iss.clone());
+		}
+		self.signer()
+	}
+
+	/// `did:key` of the `kid` that signed the jws, the session key when the
+	/// jws is signed under a cacao
+	pub fn signer(&self) -> anyhow::Result<String> {
+		let protected: serde_json::Value = serde_json::from_slice(&self.protected()?)?;
+		match protected["kid"].as_str() {
+			Some(kid) => Ok(kid.split('#').next().unwrap_or(kid).to_string()),
+			None => anyhow::bail!("kid not found"),
+		}
+	}
+
+	/// Check the jws is signed by the key of its `kid`, only ed25519 `did:key`
+	/// signers are supported
+	pub fn verify_jws(&self) -> anyhow::Result<()> {
+		let signer = self.signer()?;
+		let jwk = did_key_to_jwk(&signer)?;
+		let signature = match self.jws.signatures.first() {
+			Some(signature) => signature,
+			None => anyhow::bail!(EventError::InvalidSignature(signer)),
+		};
+		let protected = signature.protected.as_ref().map(ToString::to_string);
+		let compact = format!(
+			"{}.{}.{}",
+			protected.unwrap_or_default(),
+			self.jws.payload,
+			signature.signature
+		);
+		if ssi::jws::decode_verify(&compact, &jwk).is_err() {
+			anyhow::bail!(EventError::InvalidSignature(signer));
+		}
+		Ok(())
+	}
+
 	pub fn payload_link(&self) -> anyhow::Result<Cid> {
-		Ok(Cid::try_from(self.jws.payload.to_vec()?)?)
+		Ok(Cid::try_from(self.jws.payload.decode_to_vec()?)?)
 	}
 
 	pub fn cacao_link(&self) -> anyhow::Result<Cid> {
-		if let Some(cacao_block) = self.cacao_block.clone() {
-			return Ok(Cid::new_v1(0x71, Code::Sha2_256.digest(&cacao_block)));
+		if let Some(cacao_block) = &self.cacao_block {
+			return Ok(Cid::new_v1(0x71, Code::Sha2_256.digest(cacao_block)));
 		}
 		anyhow::bail!("cacao_block is none")
 	}
 
-	pub fn cacao(&self) -> anyhow::Result<Option<CACAO>> {
-		if let Some(cacao_block) = self.cacao_block.clone() {
-			let node: Ipld = DagCborCodec.decode(&cacao_block)?;
-			let cacao: CACAO = libipld::serde::from_ipld(node)?;
-			return Ok(Some(cacao));
+	/// Cacao of the signer, decoded once and shared by later calls
+	pub fn cacao(&self) -> anyhow::Result<Option<Arc<CACAO>>> {
+		let cacao_block = match &self.cacao_block {
+			Some(cacao_block) => cacao_block,
+			None => return Ok(None),
+		};
+		let cacao = self.cacao.get_or_try_init(|| -> anyhow::Result<_> {
+			let node: Ipld = DagCborCodec.decode(cacao_block)?;
+			Ok(Arc::new(libipld::serde::from_ipld(node)?))
+		})?;
+		Ok(Some(cacao.clone()))
+	}
+
+	pub fn expiration_time(&self) -> anyhow::Result<Option<DateTime<Utc>>> {
+		match self.cacao()? {
+			Some(cacao) => cacao.p.expiration_time(),
+			None => Ok(None),
 		}
-		Ok(None)
+	}
+
+	/// models the cacao grants access to, None without cacao
+	pub fn resource_models(&self) -> anyhow::Result<Option<Vec<StreamId>>> {
+		self.cacao()?
+			.map(|cacao| cacao.p.resource_models())
+			.transpose()
 	}
 
 	pub fn data(&self) -> anyhow::Result<serde_json::Value> {
@@ -136,6 +255,23 @@ impl SignedValue {
 		}
 	}
 
+	/// Controllers set by the header of a data commit
+	pub fn controllers_update(&self) -> anyhow::Result<Option<Vec<String>>> {
+		let linked_block = match &self.linked_block {
+			Some(linked_block) => linked_block,
+			None => return Ok(None),
+		};
+		let node: Ipld = DagCborCodec.decode(linked_block)?;
+		let controllers = match node
+			.get("header")
+			.and_then(|header| header.get("controllers"))
+		{
+			Ok(Ipld::List(list)) => list.iter().filter_map(IpldAs::<String>::as_some).collect(),
+			_ => return Ok(None),
+		};
+		Ok(Some(controllers))
+	}
+
 	pub fn is_gensis(&self) -> bool {
 		match &self.payload() {
 			Ok(payload) => payload.id.is_none(),
@@ -159,6 +295,11 @@ impl StreamStateApplyer for SignedValue {
 				}
 				// data commit
 				false => {
+					if let Some(controllers) = self.controllers_update()? {
+						if let Some(metadata) = stream_state.metadata.as_object_mut() {
+							metadata.insert("controllers".to_string(), controllers.into());
+						}
+					}
 					if let Some(data) = &payload.data {
 						let patch: json_patch::Patch = serde_json::from_value(data.clone())?;
 						if let Err(err) = json_patch::patch(&mut stream_state.content, &patch) {
@@ -192,7 +333,15 @@ impl TryFrom<Vec<u8>> for Payload {
 	type Error = anyhow::Error;
 
 	fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
-		let node: Ipld = DagCborCodec.decode(&value)?;
+		value.as_slice().try_into()
+	}
+}
+
+impl TryFrom<&[u8]> for Payload {
+	type Error = anyhow::Error;
+
+	fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+		let node: Ipld = DagCborCodec.decode(value)?;
 		TryFrom::try_from(&node)
 	}
 }
@@ -201,7 +350,7 @@ impl TryFrom<Base64String> for Payload {
 	type Error = anyhow::Error;
 
 	fn try_from(value: Base64String) -> Result<Self, Self::Error> {
-		value.to_vec()?.try_into()
+		value.decode_to_vec()?.try_into()
 	}
 }
 
@@ -285,6 +434,51 @@ impl TryFrom<&Ipld> for Header {
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::commit::example;
+
+	#[test]
+	fn cacao_decoded_once() -> anyhow::Result<()> {
+		let mut signed: SignedValue = example::genesis().genesis.try_into()?;
+		let cacao = signed.cacao()?.expect("genesis has cacao");
+		assert!(Arc::ptr_eq(&cacao, &signed.cacao()?.unwrap()));
+		assert_eq!(signed.expiration_time()?, cacao.p.expiration_time()?);
+
+		signed.set_cacao_block(None);
+		assert!(signed.cacao()?.is_none());
+		assert_eq!(signed.resource_models()?, None);
+		Ok(())
+	}
+
+	#[test]
+	fn attach_cacao_block() -> anyhow::Result<()> {
+		let mut signed: SignedValue = example::genesis().genesis.try_into()?;
+		let cacao_block = signed.cacao_block.clone().expect("genesis has cacao");
+		signed.set_cacao_block(None);
+		assert!(signed.attach_cacao_block(vec![0xa0]).is_err());
+		signed.attach_cacao_block(cacao_block)?;
+		assert!(signed.cacao()?.is_some());
+
+		// events signed by a did:key have no cap to attach a cacao to
+		let mut synthetic = match example::events(1).remove(0).value {
+			EventValue::Signed(signed) => signed,
+			_ => unreachable!(),
+		};
+		assert!(synthetic.attach_cacao_block(vec![0xa0]).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn payload_link_of_long_cid() -> anyhow::Result<()> {
+		// a sha2-512 cid takes 68 bytes
+		let block = DagCborCodec.encode(&Ipld::Map(Default::default()))?;
+		let cid = Cid::new_v1(0x71, Code::Sha2_512.digest(&block));
+		let event = crate::event::metadata::sign_linked_block(example::SYNTHETIC_PK, cid, block)?;
+		match event.value {
+			EventValue::Signed(signed) => assert_eq!(signed.payload_link()?, cid),
+			_ => unreachable!(),
+		}
+		Ok(())
+	}
 
 	#[test]
 	fn decode_payload_base64() {
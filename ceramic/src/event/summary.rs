@@ -0,0 +1,91 @@
+use chrono::{DateTime, Utc};
+use json_patch::PatchOperation;
+use serde::{Deserialize, Serialize};
+
+use super::{Event, EventValue};
+
+/// Commit of a stream as shown in a version history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitSummary {
+	pub cid: String,
+	/// log type of the commit, as in the log of the stream state
+	pub r#type: u64,
+	/// did signing the commit, none for anchor commits
+	pub author: Option<String>,
+	pub timestamp: Option<DateTime<Utc>>,
+	/// json pointers the commit writes, the top level fields of a genesis
+	pub changed_paths: Vec<String>,
+}
+
+fn patch_paths(patch: &[PatchOperation]) -> Vec<String> {
+	let mut paths = Vec::new();
+	for op in patch {
+		let (path, from) = match op {
+			PatchOperation::Add(op) => (&op.path, None),
+			PatchOperation::Remove(op) => (&op.path, None),
+			PatchOperation::Replace(op) => (&op.path, None),
+			PatchOperation::Move(op) => (&op.path, Some(&op.from)),
+			PatchOperation::Copy(op) => (&op.path, None),
+			PatchOperation::Test(_) => continue,
+		};
+		for path in from.into_iter().chain([path]) {
+			if !paths.contains(path) {
+				paths.push(path.clone());
+			}
+		}
+	}
+	paths
+}
+
+impl Event {
+	/// Summary of the commit, the timestamp is left to the caller as only the
+	/// store knows when the commit was received
+	pub fn summary(&self) -> anyhow::Result<CommitSummary> {
+		let (author, changed_paths) = match &self.value {
+			EventValue::Signed(signed) => {
+				let paths = match signed.is_gensis() {
+					true => match signed.data()? {
+						serde_json::Value::Object(data) => {
+							data.keys().map(|key| format!("/{}", key)).collect()
+						}
+						_ => vec![],
+					},
+					false => patch_paths(&signed.patch()?.0),
+				};
+				(Some(signed.issuer()?), paths)
+			}
+			EventValue::Anchor(_) => (None, vec![]),
+		};
+		Ok(CommitSummary {
+			cid: self.cid.to_string(),
+			r#type: self.log_type() as u64,
+			author,
+			timestamp: None,
+			changed_paths,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::commit::example;
+
+	#[test]
+	fn test_summary() -> anyhow::Result<()> {
+		let events = example::events(2);
+		let genesis = events[0].summary()?;
+		assert_eq!(genesis.r#type, 0);
+		assert_eq!(
+			genesis.author.as_deref(),
+			Some("did:pkh:eip155:1:0x312eA852726E3A9f633A0377c0ea882086d66666")
+		);
+		assert!(genesis.changed_paths.contains(&"/text".to_string()));
+
+		let data = events[1].summary()?;
+		assert_eq!(data.r#type, 1);
+		assert_eq!(data.changed_paths, vec!["/text"]);
+		Ok(())
+	}
+}
@@ -1,40 +1,88 @@
+use std::time::Duration;
+
 use ceramic_core::StreamId;
 use chrono::{DateTime, Utc};
 
+use super::errors::EventError;
 use super::{Event, EventValue};
 
+/// Tolerance of the cacao time checks, so clients whose clock is slightly
+/// ahead or behind are not rejected
+pub const DEFAULT_CLOCK_SKEW: Duration = Duration::from_secs(5 * 60);
+
 pub enum VerifyOption {
 	ResourceModelsContain(StreamId),
+	/// the cacao is valid at the time: issued and valid from before it, not
+	/// expired
 	ExpirationTimeBefore(DateTime<Utc>),
+	/// the signer is one of the controllers of the stream before the event
+	IssuerIn(Vec<String>),
+	/// tolerance of the time checks, `DEFAULT_CLOCK_SKEW` if not given
+	ClockSkew(Duration),
 }
 
 impl Event {
+	/// Verify the jws and the cacao of a signed event, then check them against
+	/// the options. Anchor events are not checked.
 	pub fn verify_signature(
 		&self,
 		opts: Vec<VerifyOption>,
 	) -> anyhow::Result<Option<DateTime<Utc>>> {
 		let mut expiration_time = None;
+		let skew = opts
+			.iter()
+			.find_map(|x| match x {
+				VerifyOption::ClockSkew(skew) => Some(*skew),
+				_ => None,
+			})
+			.unwrap_or(DEFAULT_CLOCK_SKEW);
+		let skew = chrono::Duration::from_std(skew)?;
 		if let EventValue::Signed(signed) = &self.value {
+			// the issuer is only trusted once the jws and the cacao delegating
+			// to its signer are verified
+			signed.verify_jws()?;
 			if let Some(cacao) = signed.cacao()? {
 				if signed.cap()? != signed.cacao_link()? {
 					anyhow::bail!("cacao not match jws cap");
 				}
+				let signer = signed.signer()?;
+				if cacao.p.aud != signer {
+					anyhow::bail!(EventError::CacaoAudienceMismatch(
+						cacao.p.aud.clone(),
+						signer
+					));
+				}
+				cacao.verify()?;
+			}
+			for ele in &opts {
+				if let VerifyOption::IssuerIn(controllers) = ele {
+					let issuer = signed.issuer()?;
+					if !controllers.contains(&issuer) {
+						anyhow::bail!(EventError::ControllerMismatch(issuer, controllers.clone()));
+					}
+				}
+			}
+			if let Some(cacao) = signed.cacao()? {
 				for ele in opts {
 					match ele {
 						VerifyOption::ResourceModelsContain(model) => {
-							let resource_models = cacao.p.resource_models()?;
-							if !resource_models.contains(&model) {
+							if !cacao.grants_model(&model)? {
 								anyhow::bail!("invalid resource model");
 							}
 						}
 						VerifyOption::ExpirationTimeBefore(before) => {
 							expiration_time = cacao.p.expiration_time()?;
 							if let Some(exp) = expiration_time {
-								if exp < before {
+								if exp + skew < before {
 									anyhow::bail!("jws commit expired");
 								}
 							}
+							let valid_from = [Some(cacao.p.issued_at()?), cacao.p.not_before()?];
+							if valid_from.into_iter().flatten().any(|x| x - skew > before) {
+								anyhow::bail!("cacao not valid yet");
+							}
 						}
+						VerifyOption::IssuerIn(_) | VerifyOption::ClockSkew(_) => {}
 					}
 				}
 			};
@@ -42,3 +90,97 @@ impl Event {
 		Ok(expiration_time)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::commit::example;
+	use crate::did::generate_did_str;
+	use crate::event::metadata::{controller_update_event, sign_payload};
+
+	const ACCOUNT: &str = "did:pkh:eip155:1:0x312eA852726E3A9f633A0377c0ea882086d66666";
+
+	fn verify_issuer(event: &Event, controllers: &[&str]) -> anyhow::Result<()> {
+		let controllers = controllers.iter().map(|x| x.to_string()).collect();
+		event.verify_signature(vec![VerifyOption::IssuerIn(controllers)])?;
+		Ok(())
+	}
+
+	#[test]
+	fn test_issuer_in_controllers() -> anyhow::Result<()> {
+		let genesis: Event = example::genesis().genesis.try_into()?;
+		verify_issuer(&genesis, &[ACCOUNT])?;
+		let err = verify_issuer(&genesis, &["did:key:z6Mk"]).unwrap_err();
+		assert!(matches!(
+			err.downcast_ref::<EventError>(),
+			Some(EventError::ControllerMismatch(..))
+		));
+		Ok(())
+	}
+
+	#[test]
+	fn test_clock_skew() -> anyhow::Result<()> {
+		let genesis: Event = example::genesis().genesis.try_into()?;
+		let exp: DateTime<Utc> = "2023-11-15T06:55:20.604Z".parse()?;
+		let iat: DateTime<Utc> = "2023-11-08T06:55:20.604Z".parse()?;
+		let second = chrono::Duration::seconds(1);
+		let skew = chrono::Duration::from_std(DEFAULT_CLOCK_SKEW)?;
+		let verify = |at: DateTime<Utc>, skew: Option<Duration>| {
+			let mut opts = vec![VerifyOption::ExpirationTimeBefore(at)];
+			opts.extend(skew.map(VerifyOption::ClockSkew));
+			genesis.verify_signature(opts)
+		};
+
+		assert!(verify(exp + skew, None).is_ok());
+		assert!(verify(exp + skew + second, None).is_err());
+		assert!(verify(iat - skew, None).is_ok());
+		assert!(verify(iat - skew - second, None).is_err());
+
+		assert!(verify(exp, Some(Duration::ZERO)).is_ok());
+		assert!(verify(exp + second, Some(Duration::ZERO)).is_err());
+		assert!(verify(iat - second, Some(Duration::ZERO)).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_tampered_signature() -> anyhow::Result<()> {
+		let events = example::events(2);
+		events[0].verify_signature(vec![])?;
+		events[1].verify_signature(vec![])?;
+
+		// jws of the data commit with the signature of another key
+		let other = "8f2a5bb2a8c5b2f2f7a1d0f0e6f1c2b3a4d5e6f708192a3b4c5d6e7f8091a2b3";
+		let mut tampered = events[1].clone();
+		if let EventValue::Signed(signed) = &mut tampered.value {
+			let payload = signed.jws.payload.to_string();
+			let (_, signature) = sign_payload(other, &payload)?;
+			signed.jws.signatures[0].signature = signature.into();
+		}
+		let err = tampered.verify_signature(vec![]).unwrap_err();
+		assert!(matches!(
+			err.downcast_ref::<EventError>(),
+			Some(EventError::InvalidSignature(_))
+		));
+		// a tampered event is rejected before its issuer is checked
+		let synthetic = generate_did_str(example::SYNTHETIC_PK)?;
+		assert!(verify_issuer(&tampered, &[&synthetic]).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_controller_rotation() -> anyhow::Result<()> {
+		let synthetic = generate_did_str(example::SYNTHETIC_PK)?;
+		let events = example::events(2);
+		// the update is signed by the controller it replaces
+		let update =
+			controller_update_event(example::SYNTHETIC_PK, events[0].cid, events[1].cid, ACCOUNT)?;
+		verify_issuer(&update, &[&synthetic])?;
+		assert!(verify_issuer(&update, &[ACCOUNT]).is_err());
+
+		// commits after the update must be signed by the new controller
+		let patch = serde_json::json!([{ "op": "replace", "path": "/text", "value": "late" }]);
+		let late = example::data_event(events[0].cid, update.cid, patch)?;
+		assert!(verify_issuer(&late, &[ACCOUNT]).is_err());
+		Ok(())
+	}
+}
@@ -0,0 +1,154 @@
+use libipld::cid::Cid;
+use libipld::{cbor::DagCborCodec, codec::Codec, Ipld};
+
+use super::car::Car;
+use super::errors::WitnessError;
+use super::{AnchorProof, AnchorValue, Event, EventValue};
+
+/// CAR returned by the anchor service for a request, rooted at the anchor
+/// commit and holding its proof and the merkle nodes from the proof root down
+/// to the anchored tip
+pub type WitnessCar = Car;
+
+impl Car {
+	/// Follow the anchor path from the proof root, returning the leaf cid
+	fn merkle_leaf(&self, root: Cid, path: &str) -> anyhow::Result<Cid> {
+		let mut node = root;
+		for segment in path.split('/').filter(|x| !x.is_empty()) {
+			let index: usize = segment
+				.parse()
+				.map_err(|_| WitnessError::InvalidPath(path.to_string()))?;
+			node = match DagCborCodec.decode::<Ipld>(self.block(&node)?)? {
+				Ipld::List(children) => match children.get(index) {
+					Some(Ipld::Link(child)) if index < 2 => *child,
+					_ => anyhow::bail!(WitnessError::InvalidPath(path.to_string())),
+				},
+				_ => anyhow::bail!(WitnessError::InvalidMerkleNode(node.to_string())),
+			};
+		}
+		Ok(node)
+	}
+
+	/// Anchor event of the stream with its proof block, checked to anchor
+	/// `tip` through the merkle path of the proof
+	pub fn anchor_event(&self, genesis: &Cid, tip: &Cid) -> anyhow::Result<Event> {
+		let mut anchor: AnchorValue = self.block(&self.root)?.clone().try_into()?;
+		if anchor.id != *genesis {
+			anyhow::bail!(WitnessError::GenesisMismatch(
+				anchor.id.to_string(),
+				genesis.to_string()
+			));
+		}
+		if anchor.prev != *tip {
+			anyhow::bail!(WitnessError::TipMismatch(
+				anchor.prev.to_string(),
+				tip.to_string()
+			));
+		}
+
+		let proof_block = self.block(&anchor.proof)?.clone();
+		let proof = libipld::serde::from_ipld::<AnchorProof>(DagCborCodec.decode(&proof_block)?)?;
+		let leaf = self.merkle_leaf(proof.root, &anchor.path)?;
+		if leaf != *tip {
+			anyhow::bail!(WitnessError::TipMismatch(leaf.to_string(), tip.to_string()));
+		}
+
+		anchor.proof_block = Some(proof_block);
+		Ok(Event {
+			cid: self.root,
+			value: EventValue::Anchor(Box::new(anchor)),
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashMap;
+
+	use dataverse_types_core::digest::{block_cid, DAG_CBOR};
+	use libipld::ipld;
+
+	use super::*;
+	use crate::commit::example;
+
+	fn put(blocks: &mut HashMap<Cid, Vec<u8>>, node: Ipld) -> anyhow::Result<Cid> {
+		let block = DagCborCodec.encode(&node)?;
+		let cid = block_cid(DAG_CBOR, &block);
+		blocks.insert(cid, block);
+		Ok(cid)
+	}
+
+	fn witness(genesis: Cid, tip: Cid) -> anyhow::Result<WitnessCar> {
+		let mut blocks = HashMap::new();
+		let sibling = put(&mut blocks, ipld!({ "sibling": true }))?;
+		let left = put(&mut blocks, ipld!([sibling, tip]))?;
+		let root = put(&mut blocks, ipld!([left, sibling]))?;
+		let tx_hash: Cid =
+			"bagjqcgzadnfurovpwv4pzlbpvtcy4ushtwr2zlsd3ilny55pwgiwm5f6ngmq".parse()?;
+		let proof = put(
+			&mut blocks,
+			ipld!({
+				"chainId": "eip155:1",
+				"root": root,
+				"txHash": tx_hash,
+				"txType": "f(bytes32)",
+			}),
+		)?;
+		let anchor = AnchorValue {
+			id: genesis,
+			prev: tip,
+			proof,
+			path: "0/1".to_string(),
+			proof_block: None,
+		};
+		let root = put(&mut blocks, anchor.into())?;
+		Ok(WitnessCar { root, blocks })
+	}
+
+	#[test]
+	fn test_anchor_event() -> anyhow::Result<()> {
+		let events = example::events(2);
+		let genesis = events[0].cid;
+		let tip = events[1].cid;
+		let car = witness(genesis, tip)?.to_vec()?;
+
+		let witness = WitnessCar::decode(&car)?;
+		let event = witness.anchor_event(&genesis, &tip)?;
+		assert_eq!(event.cid, witness.root);
+		assert_eq!(event.prev()?, Some(tip));
+		match event.value {
+			EventValue::Anchor(anchor) => {
+				assert_eq!(anchor.proof()?.map(|x| x.chain_id), Some("eip155:1".into()))
+			}
+			_ => anyhow::bail!("expected anchor event"),
+		}
+
+		assert!(witness.anchor_event(&genesis, &genesis).is_err());
+		assert!(witness.anchor_event(&tip, &tip).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_reject_tampered_block() -> anyhow::Result<()> {
+		let events = example::events(2);
+		let mut witness = witness(events[0].cid, events[1].cid)?;
+		let root = witness.root;
+		witness.blocks.get_mut(&root).unwrap().push(0);
+		assert!(WitnessCar::decode(&witness.to_vec()?).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_wrong_path() -> anyhow::Result<()> {
+		let events = example::events(2);
+		let (genesis, tip) = (events[0].cid, events[1].cid);
+		let mut witness = witness(genesis, tip)?;
+		let mut anchor: AnchorValue = witness.blocks[&witness.root].clone().try_into()?;
+		anchor.path = "1/0".to_string();
+		let block = DagCborCodec.encode(&Ipld::from(anchor))?;
+		witness.root = block_cid(DAG_CBOR, &block);
+		witness.blocks.insert(witness.root, block);
+		assert!(witness.anchor_event(&genesis, &tip).is_err());
+		Ok(())
+	}
+}
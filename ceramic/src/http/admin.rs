@@ -0,0 +1,230 @@
+use base64::{engine::general_purpose, Engine};
+use ceramic_core::StreamId;
+use chrono::{DateTime, Utc};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+
+use super::Client;
+use crate::event::metadata::sign_payload;
+use crate::redact::Secret;
+use crate::Ceramic;
+
+pub const MODEL_DATA_PATH: &str = "/api/v0/admin/modelData";
+pub const STATUS_PATH: &str = "/api/v0/admin/status";
+const CODE_PATH: &str = "/api/v0/admin/getCode";
+
+/// Request removing a model from the models indexed by the node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StopIndexingModelRequest {
+	pub model_id: StreamId,
+}
+
+impl StopIndexingModelRequest {
+	pub fn method(&self) -> Method {
+		Method::DELETE
+	}
+
+	pub fn url(&self, endpoint: &str) -> String {
+		format!("{}{}", endpoint.trim_end_matches('/'), MODEL_DATA_PATH)
+	}
+
+	/// Body signed into the admin jws, the node checks it against the request
+	pub fn request_body(&self) -> serde_json::Value {
+		serde_json::json!({ "models": [{ "streamID": self.model_id.to_string() }] })
+	}
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct AdminCode {
+	code: Secret<String>,
+}
+
+/// Payload of an admin jws, valid for one request as the node consumes the
+/// code
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AdminPayload<'a> {
+	code: &'a str,
+	request_path: &'a str,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	request_body: Option<&'a serde_json::Value>,
+}
+
+/// Compact jws of an admin request signed by the admin `did:key` of the node
+pub fn admin_jws(
+	pk: &Secret<String>,
+	code: &Secret<String>,
+	request_path: &str,
+	request_body: Option<&serde_json::Value>,
+) -> anyhow::Result<Secret<String>> {
+	let payload = serde_json::to_vec(&AdminPayload {
+		code: code.expose(),
+		request_path,
+		request_body,
+	})?;
+	let payload = general_purpose::URL_SAFE_NO_PAD.encode(payload);
+	let (protected, signature) = sign_payload(pk.expose(), &payload)?;
+	Ok(Secret::new(format!(
+		"{}.{}.{}",
+		protected, payload, signature
+	)))
+}
+
+/// Historical sync of models from a block range, the work of indexing them
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexingJob {
+	#[serde(default)]
+	pub models: Vec<String>,
+	pub from_block: Option<u64>,
+	pub to_block: Option<u64>,
+	pub current_block: Option<u64>,
+	pub created_at: Option<DateTime<Utc>>,
+}
+
+impl IndexingJob {
+	/// Range of the job and the block synced within it, none until the job
+	/// reports its range or if the range is inverted
+	fn position(&self) -> Option<(u64, u64, u64)> {
+		let (from, to) = (self.from_block?, self.to_block?);
+		if from > to {
+			return None;
+		}
+		let current = self.current_block.unwrap_or(from).clamp(from, to);
+		Some((from, current, to))
+	}
+
+	/// Blocks left to sync, none without a valid range
+	pub fn remaining_blocks(&self) -> Option<u64> {
+		let (_, current, to) = self.position()?;
+		Some(to - current)
+	}
+
+	/// Share of the block range synced, none without a valid range
+	pub fn progress(&self) -> Option<f64> {
+		let (from, current, to) = self.position()?;
+		match to - from {
+			0 => Some(1.0),
+			range => Some((current - from) as f64 / range as f64),
+		}
+	}
+}
+
+/// Indexing jobs of the node, the syncs of its admin status
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexingJobs {
+	#[serde(default)]
+	pub active_syncs: Vec<IndexingJob>,
+	#[serde(default)]
+	pub continuous_sync: Vec<IndexingJob>,
+	#[serde(default)]
+	pub pending_syncs: Vec<IndexingJob>,
+}
+
+impl Client {
+	pub fn create_stop_indexing_model_request(
+		&self,
+		model_id: &StreamId,
+	) -> StopIndexingModelRequest {
+		StopIndexingModelRequest {
+			model_id: model_id.clone(),
+		}
+	}
+
+	/// One time code the node requires in every admin jws
+	pub async fn admin_code(&self, ceramic: &Ceramic) -> anyhow::Result<Secret<String>> {
+		let url = format!("{}{}", ceramic.endpoint.trim_end_matches('/'), CODE_PATH);
+		let res: AdminCode = self.send(|client| client.get(&url)).await?;
+		Ok(res.code)
+	}
+
+	/// Stop indexing the model, `pk` is the seed of the admin did of the node
+	pub async fn stop_indexing_model(
+		&self,
+		ceramic: &Ceramic,
+		pk: &Secret<String>,
+		model_id: &StreamId,
+	) -> anyhow::Result<()> {
+		let req = self.create_stop_indexing_model_request(model_id);
+		let code = self.admin_code(ceramic).await?;
+		let jws = admin_jws(pk, &code, MODEL_DATA_PATH, Some(&req.request_body()))?;
+		let url = req.url(&ceramic.endpoint);
+		let body = serde_json::json!({ "jws": jws.expose() });
+		let _: serde_json::Value = self
+			.send(|client| client.request(req.method(), &url).json(&body))
+			.await?;
+		Ok(())
+	}
+
+	/// Indexing jobs of the node, `pk` is the seed of the admin did of the node
+	pub async fn indexing_jobs(
+		&self,
+		ceramic: &Ceramic,
+		pk: &Secret<String>,
+	) -> anyhow::Result<IndexingJobs> {
+		Ok(self.node_status(ceramic, pk).await?.syncs())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_stop_indexing_model_request() -> anyhow::Result<()> {
+		let model_id: StreamId =
+			"kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju".parse()?;
+		let req = Client::new().create_stop_indexing_model_request(&model_id);
+		assert_eq!(req.method(), Method::DELETE);
+		assert_eq!(
+			req.url("http://localhost:7007/"),
+			"http://localhost:7007/api/v0/admin/modelData"
+		);
+		assert_eq!(
+			req.request_body()["models"][0]["streamID"],
+			model_id.to_string()
+		);
+
+		let pk = Secret::new(
+			"d160c4553ba7547cd5d66993d99329379a0c299a1bb1058abc5b874e0ba56375".to_string(),
+		);
+		let code = Secret::new("code".to_string());
+		let jws = admin_jws(&pk, &code, MODEL_DATA_PATH, Some(&req.request_body()))?;
+		assert!(!format!("{:?}", jws).contains('.'));
+		let parts: Vec<&str> = jws.expose().split('.').collect();
+		assert_eq!(parts.len(), 3);
+		let payload: serde_json::Value =
+			serde_json::from_slice(&general_purpose::URL_SAFE_NO_PAD.decode(parts[1])?)?;
+		assert_eq!(payload["code"], "code");
+		assert_eq!(payload["requestPath"], MODEL_DATA_PATH);
+		Ok(())
+	}
+
+	#[test]
+	fn test_indexing_jobs() -> anyhow::Result<()> {
+		let jobs: IndexingJobs = serde_json::from_value(serde_json::json!({
+			"activeSyncs": [{
+				"models": ["kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju"],
+				"fromBlock": 100,
+				"toBlock": 200,
+				"currentBlock": 150,
+				"createdAt": "2024-04-22T03:14:07.000Z"
+			}],
+			"pendingSyncs": []
+		}))?;
+		assert_eq!(jobs.active_syncs[0].progress(), Some(0.5));
+		assert_eq!(jobs.active_syncs[0].remaining_blocks(), Some(50));
+		assert!(jobs.continuous_sync.is_empty());
+		assert_eq!(IndexingJob::default().progress(), None);
+		let inverted = IndexingJob {
+			from_block: Some(200),
+			to_block: Some(100),
+			current_block: Some(150),
+			..Default::default()
+		};
+		assert_eq!(inverted.progress(), None);
+		assert_eq!(inverted.remaining_blocks(), None);
+		Ok(())
+	}
+}
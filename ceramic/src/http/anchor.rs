@@ -0,0 +1,126 @@
+use std::time::Duration;
+
+use ceramic_core::StreamId;
+use int_enum::IntEnum;
+use serde::{Deserialize, Deserializer};
+
+use super::{CallOpts, Client};
+use crate::{AnchorStatus, Ceramic, StreamLoader, StreamState};
+
+/// Request asking the node to anchor the tip of a stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnchorRequest {
+	pub stream_id: StreamId,
+}
+
+impl AnchorRequest {
+	pub fn url(&self, endpoint: &str) -> String {
+		format!(
+			"{}/api/v0/streams/{}/anchor",
+			endpoint.trim_end_matches('/'),
+			self.stream_id
+		)
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnchorResponse {
+	pub stream_id: String,
+	#[serde(deserialize_with = "anchor_status")]
+	pub anchor_status: AnchorStatus,
+}
+
+/// Status by name as js-ceramic answers, or by number as older nodes do
+fn anchor_status<'de, D: Deserializer<'de>>(deserializer: D) -> Result<AnchorStatus, D::Error> {
+	#[derive(Deserialize)]
+	#[serde(untagged)]
+	enum Status {
+		Number(u64),
+		Name(AnchorStatus),
+	}
+	match Status::deserialize(deserializer)? {
+		Status::Number(status) => AnchorStatus::from_int(status).map_err(serde::de::Error::custom),
+		Status::Name(status) => Ok(status),
+	}
+}
+
+impl Client {
+	pub fn create_anchor_request(&self, stream_id: &StreamId) -> AnchorRequest {
+		AnchorRequest {
+			stream_id: stream_id.clone(),
+		}
+	}
+
+	pub async fn send_anchor_request(
+		&self,
+		ceramic: &Ceramic,
+		req: &AnchorRequest,
+	) -> anyhow::Result<AnchorResponse> {
+		let url = req.url(&ceramic.endpoint);
+		self.send(|client| client.post(&url)).await
+	}
+
+	/// Poll the state of the stream every `poll_interval` until its tip is
+	/// anchored, failing with `HttpError::Timeout` once `timeout` elapses
+	pub async fn wait_for_anchor(
+		&self,
+		ceramic: &Ceramic,
+		stream_id: &StreamId,
+		timeout: Duration,
+		poll_interval: Duration,
+	) -> anyhow::Result<StreamState> {
+		let poll = async {
+			loop {
+				let state = self.load_stream_state(ceramic, stream_id, None).await?;
+				if is_anchored(&state) {
+					return Ok(state);
+				}
+				tokio::time::sleep(poll_interval).await;
+			}
+		};
+		CallOpts::timeout(timeout).run(poll).await
+	}
+}
+
+/// The node reports the stream anchored or no commit follows its last anchor
+fn is_anchored(state: &StreamState) -> bool {
+	state.anchor_status == AnchorStatus::Anchored
+		|| state.logged_anchor_status() == AnchorStatus::Anchored
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn test_anchor_response() -> anyhow::Result<()> {
+		let stream_id = "kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju";
+		let req = Client::new().create_anchor_request(&stream_id.parse()?);
+		assert_eq!(
+			req.url("http://localhost:7007"),
+			format!("http://localhost:7007/api/v0/streams/{}/anchor", stream_id)
+		);
+
+		let res: AnchorResponse = serde_json::from_value(json!({
+			"streamId": stream_id,
+			"anchorStatus": "PENDING",
+		}))?;
+		assert_eq!(res.anchor_status, AnchorStatus::Pending);
+		let res: AnchorResponse = serde_json::from_value(json!({
+			"streamId": stream_id,
+			"anchorStatus": 3,
+		}))?;
+		assert_eq!(res.anchor_status, AnchorStatus::Anchored);
+		Ok(())
+	}
+
+	#[test]
+	fn test_is_anchored() {
+		let mut state = StreamState::default();
+		assert!(!is_anchored(&state));
+		state.anchor_status = AnchorStatus::Anchored;
+		assert!(is_anchored(&state));
+	}
+}
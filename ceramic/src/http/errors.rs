@@ -1,9 +1,13 @@
+use std::time::Duration;
+
 #[derive(Debug)]
 pub enum HttpError {
 	InvalidLogType,
 	StreamLoadError,
 	CeramicNotInNetworkError,
 	NullSignerSignError,
+	Timeout(Duration),
+	NoNode,
 }
 
 impl std::fmt::Display for HttpError {
@@ -13,6 +17,8 @@ impl std::fmt::Display for HttpError {
 			HttpError::CeramicNotInNetworkError => write!(f, "ceramic not in networks"),
 			HttpError::StreamLoadError => write!(f, "Failed to load stream"),
 			HttpError::NullSignerSignError => write!(f, "NullSigner cannot sign"),
+			HttpError::Timeout(timeout) => write!(f, "call timed out after {:?}", timeout),
+			HttpError::NoNode => write!(f, "no ceramic node to call"),
 		}
 	}
 }
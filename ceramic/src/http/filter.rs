@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+
+use ceramic_http_client::FilterQuery;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Condition on a field of the documents, named as in the wire format
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Operation {
+	IsNull(bool),
+	EqualTo(Value),
+	NotEqualTo(Value),
+	In(Vec<Value>),
+	NotIn(Vec<Value>),
+	LessThan(Value),
+	LessThanOrEqualTo(Value),
+	GreaterThan(Value),
+	GreaterThanOrEqualTo(Value),
+}
+
+/// Filters of the collection api, serialized as the `queryFilters` js-ceramic
+/// expects
+///
+/// ```
+/// use dataverse_ceramic::http::filter::Filter;
+///
+/// let filter = Filter::field("blue")
+/// 	.eq(5)
+/// 	.and(Filter::field("creator").eq("did:key:z6Mk"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Filter {
+	#[serde(rename = "where")]
+	Where(BTreeMap<String, Operation>),
+	#[serde(rename = "and")]
+	And(Vec<Filter>),
+	#[serde(rename = "or")]
+	Or(Vec<Filter>),
+	#[serde(rename = "not")]
+	Not(Box<Filter>),
+}
+
+/// Field a filter is built on, see `Filter::field`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+	name: String,
+}
+
+impl Field {
+	fn op(self, op: Operation) -> Filter {
+		Filter::Where(BTreeMap::from([(self.name, op)]))
+	}
+
+	pub fn eq(self, value: impl Into<Value>) -> Filter {
+		self.op(Operation::EqualTo(value.into()))
+	}
+
+	pub fn ne(self, value: impl Into<Value>) -> Filter {
+		self.op(Operation::NotEqualTo(value.into()))
+	}
+
+	pub fn is_in<V: Into<Value>>(self, values: impl IntoIterator<Item = V>) -> Filter {
+		self.op(Operation::In(values.into_iter().map(Into::into).collect()))
+	}
+
+	pub fn not_in<V: Into<Value>>(self, values: impl IntoIterator<Item = V>) -> Filter {
+		self.op(Operation::NotIn(
+			values.into_iter().map(Into::into).collect(),
+		))
+	}
+
+	pub fn lt(self, value: impl Into<Value>) -> Filter {
+		self.op(Operation::LessThan(value.into()))
+	}
+
+	pub fn lte(self, value: impl Into<Value>) -> Filter {
+		self.op(Operation::LessThanOrEqualTo(value.into()))
+	}
+
+	pub fn gt(self, value: impl Into<Value>) -> Filter {
+		self.op(Operation::GreaterThan(value.into()))
+	}
+
+	pub fn gte(self, value: impl Into<Value>) -> Filter {
+		self.op(Operation::GreaterThanOrEqualTo(value.into()))
+	}
+
+	pub fn is_null(self, null: bool) -> Filter {
+		self.op(Operation::IsNull(null))
+	}
+}
+
+impl Filter {
+	pub fn field(name: impl Into<String>) -> Field {
+		Field { name: name.into() }
+	}
+
+	/// Both filters, chained `and`s are flattened into one list
+	pub fn and(self, other: Filter) -> Filter {
+		match self {
+			Filter::And(mut filters) => {
+				filters.push(other);
+				Filter::And(filters)
+			}
+			filter => Filter::And(vec![filter, other]),
+		}
+	}
+
+	/// Either filter, chained `or`s are flattened into one list
+	pub fn or(self, other: Filter) -> Filter {
+		match self {
+			Filter::Or(mut filters) => {
+				filters.push(other);
+				Filter::Or(filters)
+			}
+			filter => Filter::Or(vec![filter, other]),
+		}
+	}
+}
+
+/// Filter of the same wire format, built with the query types of
+/// `ceramic_http_client`
+impl TryFrom<FilterQuery> for Filter {
+	type Error = serde_json::Error;
+
+	fn try_from(query: FilterQuery) -> Result<Self, Self::Error> {
+		serde_json::from_value(serde_json::to_value(query)?)
+	}
+}
+
+impl std::ops::Not for Filter {
+	type Output = Filter;
+
+	fn not(self) -> Filter {
+		match self {
+			Filter::Not(filter) => *filter,
+			filter => Filter::Not(Box::new(filter)),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn test_wire_format() -> anyhow::Result<()> {
+		let did = "did:pkh:eip155:1:0x312eA852726E3A9f633A0377c0ea882086d66666";
+		let filter = Filter::field("blue")
+			.eq(5)
+			.and(Filter::field("creator").eq(did))
+			.and(!Filter::field("tag").is_in(["a", "b"]));
+		assert_eq!(
+			serde_json::to_value(&filter)?,
+			json!({
+				"and": [
+					{ "where": { "blue": { "equalTo": 5 } } },
+					{ "where": { "creator": { "equalTo": did } } },
+					{ "not": { "where": { "tag": { "in": ["a", "b"] } } } },
+				]
+			})
+		);
+
+		let filter = Filter::field("size")
+			.gte(1)
+			.or(Filter::field("deleted").is_null(true));
+		assert_eq!(
+			serde_json::to_value(&filter)?,
+			json!({
+				"or": [
+					{ "where": { "size": { "greaterThanOrEqualTo": 1 } } },
+					{ "where": { "deleted": { "isNull": true } } },
+				]
+			})
+		);
+		assert_eq!(
+			serde_json::from_value::<Filter>(serde_json::to_value(&filter)?)?,
+			filter
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_double_not() {
+		let filter = Filter::field("blue").ne(5);
+		assert_eq!(!!filter.clone(), filter);
+	}
+}
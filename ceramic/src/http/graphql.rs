@@ -0,0 +1,273 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{CallOpts, Client};
+
+/// Query or mutation posted to a ComposeDB graphql server
+///
+/// ```
+/// use dataverse_ceramic::http::graphql::GraphQlRequest;
+///
+/// let request = GraphQlRequest::new("query Post($id: ID!) { node(id: $id) { id } }")
+/// 	.with_variables(serde_json::json!({ "id": "kjzl6kcym7w8y" }))
+/// 	.unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphQlRequest {
+	pub query: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub variables: Option<Value>,
+	/// operation to run when the document holds several
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub operation_name: Option<String>,
+}
+
+impl GraphQlRequest {
+	pub fn new(query: &str) -> Self {
+		Self {
+			query: query.to_string(),
+			variables: None,
+			operation_name: None,
+		}
+	}
+
+	pub fn with_variables(self, variables: impl Serialize) -> anyhow::Result<Self> {
+		Ok(Self {
+			variables: Some(serde_json::to_value(variables)?),
+			..self
+		})
+	}
+
+	pub fn with_operation_name(self, operation_name: &str) -> Self {
+		Self {
+			operation_name: Some(operation_name.to_string()),
+			..self
+		}
+	}
+
+	/// Whether the document holds a mutation, whatever operation is picked.
+	/// Only top-level keywords count, not strings, comments or field names
+	pub fn has_mutation(&self) -> bool {
+		let mut depth = 0usize;
+		let (mut in_string, mut in_comment, mut escaped) = (false, false, false);
+		let mut word = String::new();
+		for c in self.query.chars().chain(std::iter::once(' ')) {
+			if in_comment {
+				in_comment = c != '\n';
+				continue;
+			}
+			if in_string {
+				match (escaped, c) {
+					(false, '\\') => escaped = true,
+					(false, '"') => in_string = false,
+					_ => escaped = false,
+				}
+				continue;
+			}
+			if c.is_alphanumeric() || c == '_' {
+				word.push(c);
+				continue;
+			}
+			if depth == 0 && word == "mutation" {
+				return true;
+			}
+			word.clear();
+			match c {
+				'#' => in_comment = true,
+				'"' => in_string = true,
+				'{' | '(' => depth += 1,
+				'}' | ')' => depth = depth.saturating_sub(1),
+				_ => {}
+			}
+		}
+		false
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphQlLocation {
+	pub line: u64,
+	pub column: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphQlError {
+	pub message: String,
+	#[serde(default)]
+	pub locations: Vec<GraphQlLocation>,
+	/// fields and list indexes leading to the failed field
+	#[serde(default)]
+	pub path: Vec<Value>,
+	#[serde(default)]
+	pub extensions: Option<Value>,
+}
+
+impl std::fmt::Display for GraphQlError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let path: Vec<String> = self
+			.path
+			.iter()
+			.map(|x| match x {
+				Value::String(field) => field.clone(),
+				other => other.to_string(),
+			})
+			.collect();
+		match path.is_empty() {
+			true => write!(f, "{}", self.message),
+			false => write!(f, "{} at {}", self.message, path.join(".")),
+		}
+	}
+}
+
+/// Errors of a response, raised when the data alone is asked for
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphQlErrors(pub Vec<GraphQlError>);
+
+impl std::fmt::Display for GraphQlErrors {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let errors: Vec<String> = self.0.iter().map(ToString::to_string).collect();
+		write!(f, "graphql errors: {}", errors.join("; "))
+	}
+}
+
+impl std::error::Error for GraphQlErrors {}
+
+/// Response envelope, a field failing to resolve nulls it in `data` and adds
+/// an error, so both may be set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphQlResponse<T> {
+	pub data: Option<T>,
+	#[serde(default)]
+	pub errors: Vec<GraphQlError>,
+}
+
+impl<T> GraphQlResponse<T> {
+	/// The data if no error was returned
+	pub fn into_result(self) -> anyhow::Result<T> {
+		if !self.errors.is_empty() {
+			anyhow::bail!(GraphQlErrors(self.errors));
+		}
+		match self.data {
+			Some(data) => Ok(data),
+			None => anyhow::bail!("graphql response without data"),
+		}
+	}
+}
+
+impl Client {
+	/// Post the request to the graphql endpoint of a ComposeDB server, e.g.
+	/// `http://localhost:5005/graphql`, keeping partial data along with the
+	/// errors. Requests with a mutation are sent once, a replayed mutation
+	/// may create the same document twice
+	pub async fn graphql_response<T: DeserializeOwned>(
+		&self,
+		endpoint: &str,
+		request: &GraphQlRequest,
+		opts: CallOpts,
+	) -> anyhow::Result<GraphQlResponse<T>> {
+		let opts = match request.has_mutation() {
+			true => opts.no_retry(),
+			false => opts,
+		};
+		self.send_with_opts(|client| client.post(endpoint).json(request), opts)
+			.await
+	}
+
+	/// Data of the response, failing with `GraphQlErrors` if any was returned
+	pub async fn graphql<T: DeserializeOwned>(
+		&self,
+		endpoint: &str,
+		request: &GraphQlRequest,
+	) -> anyhow::Result<T> {
+		self.graphql_response(endpoint, request, CallOpts::default())
+			.await?
+			.into_result()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[derive(Debug, Deserialize, PartialEq)]
+	struct Node {
+		id: String,
+	}
+
+	#[derive(Debug, Deserialize, PartialEq)]
+	struct NodeData {
+		node: Option<Node>,
+	}
+
+	#[test]
+	fn test_request_format() -> anyhow::Result<()> {
+		let query = "query Node($id: ID!) { node(id: $id) { id } }";
+		let request = GraphQlRequest::new(query);
+		assert_eq!(serde_json::to_value(&request)?, json!({ "query": query }));
+
+		let request = request
+			.with_variables(json!({ "id": "kjzl6kcym7w8y" }))?
+			.with_operation_name("Node");
+		assert_eq!(
+			serde_json::to_value(&request)?,
+			json!({
+				"query": query,
+				"variables": { "id": "kjzl6kcym7w8y" },
+				"operationName": "Node",
+			})
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_has_mutation() {
+		let has_mutation = |query: &str| GraphQlRequest::new(query).has_mutation();
+		assert!(has_mutation(
+			"mutation CreatePost($i: CreatePostInput!) { createPost(input: $i) { document { id } } }"
+		));
+		assert!(has_mutation(
+			"query Node { node(id: \"k\") { id } }\nmutation { createPost { id } }"
+		));
+		assert!(!has_mutation(
+			"query Node($id: ID!) { node(id: $id) { id } }"
+		));
+		assert!(!has_mutation("{ mutation { id } }"));
+		assert!(!has_mutation(
+			"# mutation\nquery Search { posts(filter: \"mutation\") { id } }"
+		));
+	}
+
+	#[test]
+	fn test_response_envelope() -> anyhow::Result<()> {
+		let response: GraphQlResponse<NodeData> =
+			serde_json::from_value(json!({ "data": { "node": { "id": "kjzl6kcym7w8y" } } }))?;
+		assert_eq!(
+			response.into_result()?.node,
+			Some(Node {
+				id: "kjzl6kcym7w8y".to_string()
+			})
+		);
+
+		let response: GraphQlResponse<NodeData> = serde_json::from_value(json!({
+			"data": { "node": null },
+			"errors": [{
+				"message": "Invalid stream id",
+				"locations": [{ "line": 1, "column": 30 }],
+				"path": ["node"],
+			}],
+		}))?;
+		assert_eq!(response.data, Some(NodeData { node: None }));
+		let err = response.into_result().unwrap_err();
+		assert!(err.downcast_ref::<GraphQlErrors>().is_some());
+		assert_eq!(err.to_string(), "graphql errors: Invalid stream id at node");
+
+		let response: GraphQlResponse<NodeData> =
+			serde_json::from_value(json!({ "errors": [{ "message": "Syntax Error" }] }))?;
+		assert!(response.data.is_none());
+		assert!(response.into_result().is_err());
+		Ok(())
+	}
+}
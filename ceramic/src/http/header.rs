@@ -0,0 +1,197 @@
+use std::collections::BTreeMap;
+
+use ceramic_core::{StreamId, StreamIdType};
+use dataverse_types_core::digest::{block_cid, DAG_CBOR};
+use int_enum::IntEnum;
+use libipld::cbor::DagCborCodec;
+use libipld::prelude::Codec;
+use libipld::Ipld;
+use serde_json::{Map, Value};
+
+use super::Client;
+use crate::did::generate_did_str;
+use crate::event::metadata::sign_linked_block;
+use crate::event::{Event, EventsUploader};
+use crate::redact::Secret;
+use crate::stream::single::MID_TYPE;
+use crate::Ceramic;
+
+pub const DEFAULT_FAMILY: &str = "dataverse";
+
+/// Header fields of the genesis commits built by the client besides their
+/// controllers and model, the family groups the streams of an app on the node
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeaderOpts {
+	pub family: String,
+	pub tags: Vec<String>,
+	/// additional fields, the fields set by the client take precedence
+	pub fields: Map<String, Value>,
+}
+
+impl Default for HeaderOpts {
+	fn default() -> Self {
+		Self {
+			family: DEFAULT_FAMILY.to_string(),
+			tags: vec![],
+			fields: Map::new(),
+		}
+	}
+}
+
+impl HeaderOpts {
+	/// Header of a genesis as in its block, the model as stream id bytes
+	pub fn genesis_header(
+		&self,
+		controllers: &[String],
+		model: &StreamId,
+	) -> anyhow::Result<BTreeMap<String, Ipld>> {
+		let mut header = match libipld::serde::to_ipld(&self.fields)? {
+			Ipld::Map(fields) => fields,
+			_ => anyhow::bail!("header fields are not a map"),
+		};
+		header.insert(
+			"controllers".to_string(),
+			Ipld::List(controllers.iter().cloned().map(Ipld::String).collect()),
+		);
+		header.insert("model".to_string(), Ipld::Bytes(model.to_vec()?));
+		header.insert("sep".to_string(), Ipld::String("model".to_string()));
+		header.insert("family".to_string(), Ipld::String(self.family.clone()));
+		if !self.tags.is_empty() {
+			header.insert(
+				"tags".to_string(),
+				Ipld::List(self.tags.iter().cloned().map(Ipld::String).collect()),
+			);
+		}
+		Ok(header)
+	}
+}
+
+impl Client {
+	/// Header of a genesis of `model` under the header options of the client,
+	/// not for single instances whose genesis has to stay deterministic
+	pub fn genesis_header(
+		&self,
+		controllers: &[String],
+		model: &StreamId,
+	) -> anyhow::Result<BTreeMap<String, Ipld>> {
+		self.header.genesis_header(controllers, model)
+	}
+
+	/// Genesis of a list instance of `model` signed by the `did:key` of `pk`,
+	/// random `unique` bytes keep instances with the same content apart
+	pub fn list_genesis(
+		&self,
+		pk: &Secret<String>,
+		model: &StreamId,
+		content: &Value,
+	) -> anyhow::Result<Event> {
+		let unique = rand::random::<[u8; 12]>();
+		self.list_genesis_with_unique(pk, model, content, &unique)
+	}
+
+	/// Genesis of a list instance with the given `unique` bytes, the same
+	/// bytes and content always give the same stream
+	pub fn list_genesis_with_unique(
+		&self,
+		pk: &Secret<String>,
+		model: &StreamId,
+		content: &Value,
+		unique: &[u8],
+	) -> anyhow::Result<Event> {
+		let controller = generate_did_str(pk.expose())?;
+		let mut header = self.genesis_header(&[controller], model)?;
+		header.insert("unique".to_string(), Ipld::Bytes(unique.to_vec()));
+		let node = Ipld::Map(BTreeMap::from([
+			("data".to_string(), libipld::serde::to_ipld(content)?),
+			("header".to_string(), Ipld::Map(header)),
+		]));
+		let block = DagCborCodec.encode(&node)?;
+		sign_linked_block(pk.expose(), block_cid(DAG_CBOR, &block), block)
+	}
+
+	/// Create a list instance of `model` on the node, its genesis built under
+	/// the header options of the client
+	pub async fn create_list_instance(
+		&self,
+		ceramic: &Ceramic,
+		pk: &Secret<String>,
+		model: &StreamId,
+		content: &Value,
+	) -> anyhow::Result<StreamId> {
+		let genesis = self.list_genesis(pk, model, content)?;
+		let stream_id = StreamId {
+			r#type: StreamIdType::from_int(MID_TYPE)?,
+			cid: genesis.cid,
+		};
+		self.upload_event(ceramic, &stream_id, genesis).await?;
+		Ok(stream_id)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_genesis_header() -> anyhow::Result<()> {
+		let model: StreamId =
+			"kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju".parse()?;
+		let controllers = vec!["did:key:z6Mk".to_string()];
+
+		let string = |value: &str| Ipld::String(value.to_string());
+		let header = Client::new().genesis_header(&controllers, &model)?;
+		assert_eq!(header["family"], string(DEFAULT_FAMILY));
+		assert_eq!(header["model"], Ipld::Bytes(model.to_vec()?));
+		assert!(header.get("tags").is_none());
+
+		let client = Client::builder()
+			.family("notes")
+			.tags(vec!["draft".to_string()])
+			.header_field("family", "ignored")
+			.header_field("schema", "v1")
+			.build();
+		let header = client.genesis_header(&controllers, &model)?;
+		assert_eq!(header["family"], string("notes"));
+		assert_eq!(header["tags"], Ipld::List(vec![string("draft")]));
+		assert_eq!(header["schema"], string("v1"));
+		assert_eq!(
+			header["controllers"],
+			Ipld::List(vec![string("did:key:z6Mk")])
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_list_genesis() -> anyhow::Result<()> {
+		let model: StreamId =
+			"kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju".parse()?;
+		let pk = Secret::new(
+			"0c2b5b3f4b9e1f8ad6e0e5cd3a1e7b6c8d9f0a1b2c3d4e5f60718293a4b5c6d7".to_string(),
+		);
+		let client = Client::builder().family("notes").build();
+		let content = serde_json::json!({ "text": "hello" });
+
+		let genesis = client.list_genesis(&pk, &model, &content)?;
+		let signed = match &genesis.value {
+			crate::EventValue::Signed(signed) => signed,
+			_ => anyhow::bail!("genesis is not signed"),
+		};
+		let header = signed.payload()?.header.expect("genesis has a header");
+		assert_eq!(header.model, model);
+		assert_eq!(header.controllers, vec![generate_did_str(pk.expose())?]);
+		assert_eq!(header.unique.len(), 12);
+		assert_eq!(signed.data()?, content);
+
+		let node: Ipld = DagCborCodec.decode(signed.linked_block.as_ref().unwrap())?;
+		assert_eq!(
+			node.get("header")?.get("family")?,
+			&Ipld::String("notes".to_string())
+		);
+		let other = client.list_genesis(&pk, &model, &content)?;
+		assert_ne!(genesis.cid, other.cid);
+		let fixed = client.list_genesis_with_unique(&pk, &model, &content, b"fixed")?;
+		let again = client.list_genesis_with_unique(&pk, &model, &content, b"fixed")?;
+		assert_eq!(fixed.cid, again.cid);
+		Ok(())
+	}
+}
@@ -0,0 +1,92 @@
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tokio::sync::Semaphore;
+
+/// Throttle of the calls of a client and its clones, so a rate limited
+/// gateway is not sent more than `requests_per_sec` calls a second or
+/// `max_in_flight` concurrent calls. Every attempt of a retried call counts.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+	interval: Option<Duration>,
+	next: Mutex<Option<Instant>>,
+	in_flight: Option<Semaphore>,
+}
+
+impl RateLimiter {
+	pub fn new(requests_per_sec: Option<u32>, max_in_flight: Option<usize>) -> Self {
+		Self {
+			interval: requests_per_sec
+				.filter(|x| *x > 0)
+				.map(|x| Duration::from_secs(1) / x),
+			next: Mutex::new(None),
+			in_flight: max_in_flight.map(|x| Semaphore::new(x.max(1))),
+		}
+	}
+
+	/// Delay before the next call may start, reserving its slot
+	fn reserve(&self, interval: Duration) -> Duration {
+		let mut next = self.next.lock().unwrap();
+		let now = Instant::now();
+		let at = next.map_or(now, |next| next.max(now));
+		*next = Some(at + interval);
+		at - now
+	}
+
+	/// Run the call once a slot is free
+	pub async fn run<T, F>(&self, call: F) -> T
+	where
+		F: Future<Output = T>,
+	{
+		let _permit = match &self.in_flight {
+			Some(in_flight) => Some(
+				in_flight
+					.acquire()
+					.await
+					.expect("semaphore is never closed"),
+			),
+			None => None,
+		};
+		if let Some(interval) = self.interval {
+			let delay = self.reserve(interval);
+			if !delay.is_zero() {
+				tokio::time::sleep(delay).await;
+			}
+		}
+		call.await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	use super::*;
+
+	#[tokio::test]
+	async fn test_requests_per_sec() {
+		let limiter = RateLimiter::new(Some(20), None);
+		let started = Instant::now();
+		for _ in 0..3 {
+			limiter.run(async {}).await;
+		}
+		assert!(started.elapsed() >= Duration::from_millis(100));
+	}
+
+	#[tokio::test]
+	async fn test_max_in_flight() {
+		let limiter = RateLimiter::new(None, Some(2));
+		let (running, peak) = (AtomicUsize::new(0), AtomicUsize::new(0));
+		let calls = (0..5).map(|_| {
+			limiter.run(async {
+				let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+				peak.fetch_max(now, Ordering::SeqCst);
+				tokio::time::sleep(Duration::from_millis(10)).await;
+				running.fetch_sub(1, Ordering::SeqCst);
+			})
+		});
+		futures::future::join_all(calls).await;
+		assert_eq!(peak.load(Ordering::SeqCst), 2);
+	}
+}
@@ -1,26 +1,107 @@
+pub mod admin;
+pub mod anchor;
 mod errors;
+pub mod filter;
+#[cfg(feature = "graphql")]
+#[cfg_attr(docsrs, doc(cfg(feature = "graphql")))]
+pub mod graphql;
+pub mod header;
+pub mod limit;
+pub mod multi;
+pub mod multiquery;
+pub mod pin;
+pub mod query;
+pub mod single;
+pub mod status;
+#[cfg(feature = "kubo")]
 mod task;
 
+#[cfg(feature = "kubo")]
+#[cfg_attr(docsrs, doc(cfg(feature = "kubo")))]
 pub use task::*;
 
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
 use anyhow::{Context, Result};
 use ceramic_core::{Base64UrlString, Cid, StreamId};
 use ceramic_event::{DidDocument, JwkSigner};
 use ceramic_http_client::{api, remote::CeramicRemoteHttpClient, FilterQuery};
-use errors::HttpError;
-use int_enum::IntEnum;
+pub use errors::HttpError;
+use futures::TryStreamExt;
+use header::HeaderOpts;
 use json_patch::{patch, Patch};
+use limit::RateLimiter;
 use ssi::jwk::Algorithm;
 
+use crate::redact::Secret;
+use crate::retry::{RetryPolicy, StatusError};
 use crate::{
-	did::generate_did_str,
-	event::{Event, EventsLoader, EventsUploader},
+	did::{ensure_did_key_controllers, generate_did_str},
+	event::{metadata::controller_update_event, Event, EventsLoader, EventsUploader},
 	network::{Chain, Network},
-	stream::StreamState,
-	AnchorStatus, Ceramic, LogType, StreamAnchorRequester, StreamLoader, StreamsLoader,
+	stream::{Reservoir, StreamState},
+	AnchorStatus, Ceramic, CeramicApi, LogType, StreamAnchorRequester, StreamLoader,
+	StreamsLoader,
 };
 
-pub struct Client {}
+pub const STREAMS_PATH: &str = "/api/v0/streams";
+pub const COMMITS_PATH: &str = "/api/v0/commits";
+pub const CHAINS_PATH: &str = "/api/v0/node/chains";
+
+fn api_url(endpoint: &str, path: &str) -> String {
+	format!("{}{}", endpoint.trim_end_matches('/'), path)
+}
+
+/// Client of the js-ceramic http api, every remote call retried under its
+/// retry policy
+#[derive(Debug, Clone)]
+pub struct Client {
+	retry: RetryPolicy,
+	http: reqwest::Client,
+	timeout: Option<Duration>,
+	header: HeaderOpts,
+	limiter: Arc<RateLimiter>,
+}
+
+/// Options of a single call, unset options fall back to the client's
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CallOpts {
+	/// Deadline of the whole call, retries included
+	pub timeout: Option<Duration>,
+	/// send the call once, for calls that are not safe to replay
+	pub no_retry: bool,
+}
+
+impl CallOpts {
+	pub fn timeout(timeout: Duration) -> Self {
+		Self {
+			timeout: Some(timeout),
+			..Default::default()
+		}
+	}
+
+	pub fn no_retry(self) -> Self {
+		Self {
+			no_retry: true,
+			..self
+		}
+	}
+
+	/// Run the call, failing with `HttpError::Timeout` once the timeout elapses
+	pub async fn run<T, F>(&self, call: F) -> anyhow::Result<T>
+	where
+		F: Future<Output = anyhow::Result<T>>,
+	{
+		match self.timeout {
+			Some(timeout) => tokio::time::timeout(timeout, call)
+				.await
+				.map_err(|_| HttpError::Timeout(timeout))?,
+			None => call.await,
+		}
+	}
+}
 
 impl Default for Client {
     fn default() -> Self {
@@ -28,9 +109,144 @@ impl Default for Client {
     }
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct ClientBuilder {
+	retry: RetryPolicy,
+	http: Option<reqwest::Client>,
+	timeout: Option<Duration>,
+	header: HeaderOpts,
+	requests_per_sec: Option<u32>,
+	max_in_flight: Option<usize>,
+}
+
+impl ClientBuilder {
+	pub fn retry(self, retry: RetryPolicy) -> Self {
+		Self { retry, ..self }
+	}
+
+	/// Client sending every request, configured with proxies, root
+	/// certificates or pool limits
+	pub fn http_client(self, http: reqwest::Client) -> Self {
+		Self {
+			http: Some(http),
+			..self
+		}
+	}
+
+	/// Deadline of every call of the client, retries included
+	pub fn timeout(self, timeout: Duration) -> Self {
+		Self {
+			timeout: Some(timeout),
+			..self
+		}
+	}
+
+	/// Calls started per second by the client and its clones at most
+	pub fn rate_limit(self, requests_per_sec: u32) -> Self {
+		Self {
+			requests_per_sec: Some(requests_per_sec),
+			..self
+		}
+	}
+
+	/// Concurrent calls of the client and its clones at most
+	pub fn max_in_flight(self, max_in_flight: usize) -> Self {
+		Self {
+			max_in_flight: Some(max_in_flight),
+			..self
+		}
+	}
+
+	/// Family of the genesis headers built by the client
+	pub fn family(mut self, family: impl Into<String>) -> Self {
+		self.header.family = family.into();
+		self
+	}
+
+	pub fn tags(mut self, tags: Vec<String>) -> Self {
+		self.header.tags = tags;
+		self
+	}
+
+	/// Additional field of the genesis headers built by the client
+	pub fn header_field(
+		mut self,
+		key: impl Into<String>,
+		value: impl Into<serde_json::Value>,
+	) -> Self {
+		self.header.fields.insert(key.into(), value.into());
+		self
+	}
+
+	pub fn build(self) -> Client {
+		Client {
+			retry: self.retry,
+			http: self.http.unwrap_or_default(),
+			timeout: self.timeout,
+			header: self.header,
+			limiter: Arc::new(RateLimiter::new(self.requests_per_sec, self.max_in_flight)),
+		}
+	}
+}
+
 impl Client {
 	pub fn new() -> Self {
-		Self {}
+		Self::builder().build()
+	}
+
+	pub fn builder() -> ClientBuilder {
+		ClientBuilder::default()
+	}
+
+	/// Options of a call, the client's timeout if the call sets none
+	pub(crate) fn call_opts(&self, opts: CallOpts) -> CallOpts {
+		CallOpts {
+			timeout: opts.timeout.or(self.timeout),
+			no_retry: opts.no_retry,
+		}
+	}
+
+	/// Run the call under the retry policy, every attempt throttled by the
+	/// rate limiter
+	pub(crate) async fn throttled<T, F, Fut>(&self, mut call: F) -> anyhow::Result<T>
+	where
+		F: FnMut() -> Fut,
+		Fut: Future<Output = anyhow::Result<T>>,
+	{
+		let limiter = &self.limiter;
+		self.retry.run(|| limiter.run(call())).await
+	}
+
+	/// Send the request `req` builds, retried on transient failures, and
+	/// decode the json response
+	pub(crate) async fn send<T, F>(&self, req: F) -> anyhow::Result<T>
+	where
+		T: serde::de::DeserializeOwned,
+		F: Fn(&reqwest::Client) -> reqwest::RequestBuilder,
+	{
+		self.send_with_opts(req, CallOpts::default()).await
+	}
+
+	pub(crate) async fn send_with_opts<T, F>(&self, req: F, opts: CallOpts) -> anyhow::Result<T>
+	where
+		T: serde::de::DeserializeOwned,
+		F: Fn(&reqwest::Client) -> reqwest::RequestBuilder,
+	{
+		let (req, client) = (&req, &self.http);
+		let send = move || async move {
+			let res = req(client).send().await?;
+			let status = res.status();
+			if !status.is_success() {
+				let message = res.text().await.unwrap_or_default();
+				anyhow::bail!(StatusError::new(status.as_u16(), message));
+			}
+			Ok(res.json().await?)
+		};
+		let opts = self.call_opts(opts);
+		match opts.no_retry {
+			true => opts.run(self.limiter.run(send())).await,
+			false => opts.run(self.throttled(send)).await,
+		}
 	}
 
 	pub fn init(ceramic: &str) -> anyhow::Result<CeramicHTTPClient> {
@@ -38,6 +254,8 @@ impl Client {
 		Ok(CeramicRemoteHttpClient::new(NullSigner::new(), ceramic_url))
 	}
 
+	/// States of every document of the model, paging through the collection
+	/// api so every page goes through the rate limiter
 	pub async fn query_model(
 		&self,
 		ceramic: &Ceramic,
@@ -45,20 +263,40 @@ impl Client {
 		model_id: &StreamId,
 		query: Option<FilterQuery>,
 	) -> anyhow::Result<Vec<StreamState>> {
-		let http_client = Self::init(&ceramic.endpoint)?;
-		let edges = http_client.query_all(account, model_id, query).await?;
-		let mut streams = Vec::new();
-		for edge in edges {
-			if let Some(node) = edge.node {
-				streams.push(node.try_into()?);
-			}
-		}
-		Ok(streams)
+		let filter = query.map(filter::Filter::try_from).transpose()?;
+		self.query_model_with(ceramic, account, model_id, filter)
+			.await
 	}
 
-	pub async fn chains(ceramic: &str) -> anyhow::Result<Vec<Chain>> {
-		let http_client = Self::init(ceramic)?;
-		let chains = http_client.chains().await?.supported_chains;
+	/// `query_model` with the in-tree filter, bounded by the client's timeout
+	/// as a whole
+	pub(crate) async fn query_model_with(
+		&self,
+		ceramic: &Ceramic,
+		account: Option<String>,
+		model_id: &StreamId,
+		filter: Option<filter::Filter>,
+	) -> anyhow::Result<Vec<StreamState>> {
+		let query = query::CollectionQuery {
+			account,
+			query_filters: filter,
+			..query::CollectionQuery::new(model_id.clone())
+		};
+		let call = self
+			.query_stream(ceramic, query)
+			.try_filter_map(|edge| async move { Ok(edge.node) })
+			.try_collect();
+		self.call_opts(CallOpts::default()).run(call).await
+	}
+
+	async fn supported_chains(&self, ceramic: &str) -> anyhow::Result<Vec<String>> {
+		let url = api_url(ceramic, CHAINS_PATH);
+		let res: api::SupportedChainsResponse = self.send(|client| client.get(&url)).await?;
+		Ok(res.supported_chains)
+	}
+
+	pub async fn chains(&self, ceramic: &str) -> anyhow::Result<Vec<Chain>> {
+		let chains = self.supported_chains(ceramic).await?;
 		let chains = chains
 			.iter()
 			.map(|ele| ele.parse())
@@ -66,9 +304,30 @@ impl Client {
 		Ok(chains)
 	}
 
-	pub async fn network(ceramic: &str) -> anyhow::Result<Network> {
-		let http_client = Self::init(ceramic)?;
-		let chains = http_client.chains().await?.supported_chains;
+	/// Rotate the controller of a stream with a data commit signed by the
+	/// current `did:key` controller (ed25519 seed in hex). Streams controlled
+	/// by other did methods are rejected
+	pub async fn transfer_controller(
+		&self,
+		ceramic: &Ceramic,
+		pk: &str,
+		stream_id: &StreamId,
+		new_controller: &str,
+	) -> anyhow::Result<Event> {
+		let state = self
+			.get_with_opts(ceramic, stream_id, CallOpts::default())
+			.await?;
+		ensure_did_key_controllers(&state.controllers())?;
+		let events = self.load_events(ceramic, stream_id, None).await?;
+		let genesis = events.first().context(HttpError::StreamLoadError)?.cid;
+		let prev = events.last().context(HttpError::StreamLoadError)?.cid;
+		let event = controller_update_event(pk, genesis, prev, new_controller)?;
+		self.upload_event(ceramic, stream_id, event.clone()).await?;
+		Ok(event)
+	}
+
+	pub async fn network(&self, ceramic: &str) -> anyhow::Result<Network> {
+		let chains = self.supported_chains(ceramic).await?;
 		let chain = chains
 			.first()
 			.context(HttpError::CeramicNotInNetworkError)?
@@ -83,12 +342,16 @@ impl EventsLoader for Client {
 		&self,
 		ceramic: &Ceramic,
 		stream_id: &StreamId,
-		_tip: Option<Cid>,
+		tip: Option<Cid>,
 	) -> anyhow::Result<Vec<Event>> {
-		let http_client = Self::init(&ceramic.endpoint)?;
-		let commits = http_client.commits(stream_id).await?.commits;
+		if ceramic.api == CeramicApi::CeramicOne {
+			let client = crate::ceramic_one::Client::with_http(self.http.clone());
+			return client.load_events(ceramic, stream_id, tip).await;
+		}
+		let url = format!("{}/{}", api_url(&ceramic.endpoint, COMMITS_PATH), stream_id);
+		let res: api::CommitsResponse = self.send(|client| client.get(&url)).await?;
 		let mut events = vec![];
-		for commit in commits {
+		for commit in res.commits {
 			events.push(commit.try_into()?)
 		}
 		Ok(events)
@@ -103,20 +366,23 @@ impl EventsUploader for Client {
 		stream_id: &StreamId,
 		commit: Event,
 	) -> anyhow::Result<()> {
-		let http_client = Self::init(&ceramic.endpoint)?;
+		if ceramic.api == CeramicApi::CeramicOne {
+			let client = crate::ceramic_one::Client::with_http(self.http.clone());
+			return client.upload_event(ceramic, stream_id, commit).await;
+		}
 		match commit.log_type() {
 			LogType::Genesis => {
-				let req = api::CreateRequest {
+				let req = api::CreateRequest::<serde_json::Value> {
 					r#type: stream_id.r#type,
 					block: commit.clone().try_into()?,
 				};
+				let url = api_url(&ceramic.endpoint, STREAMS_PATH);
+				let _: serde::de::IgnoredAny =
+					self.send(|client| client.post(&url).json(&req)).await?;
 
 				let cid = commit.cid.to_string();
 				let stream_id = stream_id.to_string();
-				match http_client.create_stream(req).await {
-					Ok(_) => tracing::info!(cid, stream_id, "publish genesis"),
-					Err(err) => tracing::error!(cid, stream_id, ?err, "failed to publish genesis"),
-				};
+				tracing::info!(cid, stream_id, "publish genesis");
 			}
 			LogType::Signed => {
 				let req = api::UpdateRequest {
@@ -124,13 +390,13 @@ impl EventsUploader for Client {
 					stream_id: stream_id.try_into()?,
 					block: commit.clone().try_into()?,
 				};
+				let url = api_url(&ceramic.endpoint, COMMITS_PATH);
+				let _: serde::de::IgnoredAny =
+					self.send(|client| client.post(&url).json(&req)).await?;
 
 				let cid = commit.cid.to_string();
 				let stream_id = stream_id.to_string();
-				match http_client.updat_stream(req).await {
-					Ok(_) => tracing::info!(cid, stream_id, "publish data"),
-					Err(err) => tracing::error!(cid, stream_id, ?err, "failed to publish data"),
-				};
+				tracing::info!(cid, stream_id, "publish data");
 			}
 			_ => anyhow::bail!(HttpError::InvalidLogType),
 		};
@@ -138,16 +404,17 @@ impl EventsUploader for Client {
 	}
 }
 
-#[async_trait::async_trait]
-impl StreamLoader for Client {
-	async fn load_stream_state(
+impl Client {
+	/// State of the stream, bounded by the options of the call
+	pub async fn get_with_opts(
 		&self,
 		ceramic: &Ceramic,
 		stream_id: &StreamId,
-		_tip: Option<Cid>,
+		opts: CallOpts,
 	) -> anyhow::Result<StreamState> {
-		let ceramic = Self::init(&ceramic.endpoint)?;
-		let stream = ceramic.get(stream_id).await?;
+		let url = format!("{}/{}", api_url(&ceramic.endpoint, STREAMS_PATH), stream_id);
+		let stream: api::StreamsResponse =
+			self.send_with_opts(|client| client.get(&url), opts).await?;
 		let state = stream
 			.state
 			.context(HttpError::StreamLoadError)?
@@ -156,6 +423,19 @@ impl StreamLoader for Client {
 	}
 }
 
+#[async_trait::async_trait]
+impl StreamLoader for Client {
+	async fn load_stream_state(
+		&self,
+		ceramic: &Ceramic,
+		stream_id: &StreamId,
+		_tip: Option<Cid>,
+	) -> anyhow::Result<StreamState> {
+		self.get_with_opts(ceramic, stream_id, CallOpts::default())
+			.await
+	}
+}
+
 #[async_trait::async_trait]
 impl StreamsLoader for Client {
 	async fn load_stream_states(
@@ -166,6 +446,26 @@ impl StreamsLoader for Client {
 	) -> anyhow::Result<Vec<StreamState>> {
 		self.query_model(ceramic, account, model_id, None).await
 	}
+
+	/// Reservoir sample over the pages of the collection api, holding at most
+	/// `n` states
+	async fn sample(
+		&self,
+		ceramic: &Ceramic,
+		model_id: &StreamId,
+		n: usize,
+		seed: u64,
+	) -> anyhow::Result<Vec<StreamState>> {
+		let query = query::CollectionQuery::new(model_id.clone());
+		let mut edges = Box::pin(self.query_stream(ceramic, query));
+		let mut reservoir = Reservoir::new(n, seed);
+		while let Some(edge) = edges.try_next().await? {
+			if let Some(state) = edge.node {
+				reservoir.push(state);
+			}
+		}
+		Ok(reservoir.into_items())
+	}
 }
 
 #[async_trait::async_trait]
@@ -175,16 +475,19 @@ impl StreamAnchorRequester for Client {
 		ceramic: &Ceramic,
 		stream_id: &StreamId,
 	) -> anyhow::Result<AnchorStatus> {
-		let http_client = Self::init(&ceramic.endpoint)?;
-		let status = http_client.request_anchor(stream_id).await?;
-		Ok(AnchorStatus::from_int(status.anchor_status)?)
+		let req = self.create_anchor_request(stream_id);
+		let res = self.send_anchor_request(ceramic, &req).await?;
+		Ok(res.anchor_status)
 	}
 }
 
-pub async fn ceramic_client(ceramic: &str, pk: &str) -> Result<CeramicRemoteHttpClient<JwkSigner>> {
-	let did = generate_did_str(pk)?;
+pub async fn ceramic_client(
+	ceramic: &str,
+	pk: &Secret<String>,
+) -> Result<CeramicRemoteHttpClient<JwkSigner>> {
+	let did = generate_did_str(pk.expose())?;
 	let did = DidDocument::new(&did);
-	let signer = JwkSigner::new(did, pk).await?;
+	let signer = JwkSigner::new(did, pk.expose()).await?;
 
 	let ceramic_url = url::Url::parse(ceramic)?;
 	Ok(CeramicRemoteHttpClient::new(signer, ceramic_url))
@@ -284,6 +587,38 @@ mod tests {
 		);
 	}
 
+	#[tokio::test]
+	async fn test_call_opts() {
+		let opts = CallOpts::timeout(Duration::from_millis(10));
+		let err = opts
+			.run(std::future::pending::<anyhow::Result<()>>())
+			.await
+			.unwrap_err();
+		assert!(matches!(
+			err.downcast_ref::<HttpError>(),
+			Some(HttpError::Timeout(_))
+		));
+
+		let client = Client::builder().timeout(Duration::from_secs(1)).build();
+		assert_eq!(
+			client.call_opts(CallOpts::default()).timeout,
+			Some(Duration::from_secs(1))
+		);
+		assert_eq!(client.call_opts(opts), opts);
+	}
+
+	#[test]
+	fn test_api_url() {
+		assert_eq!(
+			api_url("http://localhost:7007/", STREAMS_PATH),
+			"http://localhost:7007/api/v0/streams"
+		);
+		assert_eq!(
+			api_url("http://localhost:7007", CHAINS_PATH),
+			"http://localhost:7007/api/v0/node/chains"
+		);
+	}
+
 	#[tokio::test]
 	async fn load_events() {
 		let client = Client::new();
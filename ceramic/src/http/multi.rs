@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use ceramic_core::{Cid, StreamId};
+
+use super::{CallOpts, Client, HttpError};
+use crate::event::{Event, EventsLoader, EventsUploader};
+use crate::retry::ErrorClass;
+use crate::{Ceramic, CeramicApi, StreamLoader, StreamState};
+
+/// Upper bound of the health check of one node
+pub const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn health_url(ceramic: &Ceramic) -> String {
+	let endpoint = ceramic.endpoint.trim_end_matches('/');
+	match ceramic.api {
+		CeramicApi::JsCeramic => format!("{}/api/v0/node/healthcheck", endpoint),
+		CeramicApi::CeramicOne => format!("{}/ceramic/liveness", endpoint),
+	}
+}
+
+/// Client of several nodes of one network. Reads go round robin to the
+/// healthy nodes, writes of a stream stick to one node, and a call failing
+/// on a transient error marks its node unhealthy and moves to the next one.
+pub struct MultiClient {
+	client: Client,
+	nodes: Vec<Ceramic>,
+	healthy: Vec<AtomicBool>,
+	next: AtomicUsize,
+	sticky: Mutex<HashMap<StreamId, usize>>,
+}
+
+impl MultiClient {
+	pub fn new(client: Client, nodes: Vec<Ceramic>) -> anyhow::Result<Self> {
+		if nodes.is_empty() {
+			anyhow::bail!(HttpError::NoNode);
+		}
+		Ok(Self {
+			client,
+			healthy: nodes.iter().map(|_| AtomicBool::new(true)).collect(),
+			nodes,
+			next: AtomicUsize::new(0),
+			sticky: Mutex::new(HashMap::new()),
+		})
+	}
+
+	/// Connect to every endpoint, failing if one of them cannot be reached
+	pub async fn connect(client: Client, endpoints: &[&str]) -> anyhow::Result<Self> {
+		let mut nodes = Vec::with_capacity(endpoints.len());
+		for endpoint in endpoints {
+			nodes.push(Ceramic::new(endpoint).await?);
+		}
+		Self::new(client, nodes)
+	}
+
+	pub fn nodes(&self) -> &[Ceramic] {
+		&self.nodes
+	}
+
+	pub fn is_healthy(&self, idx: usize) -> bool {
+		self.healthy[idx].load(Ordering::Relaxed)
+	}
+
+	fn mark(&self, idx: usize, healthy: bool) {
+		let was = self.healthy[idx].swap(healthy, Ordering::Relaxed);
+		if was != healthy {
+			tracing::warn!(
+				endpoint = self.nodes[idx].endpoint,
+				healthy,
+				"node health changed"
+			);
+		}
+	}
+
+	/// Check every node, returns the number of healthy nodes
+	pub async fn check_health(&self) -> usize {
+		let opts = CallOpts::timeout(HEALTH_CHECK_TIMEOUT);
+		let mut count = 0;
+		for (idx, ceramic) in self.nodes.iter().enumerate() {
+			let url = health_url(ceramic);
+			let check = async {
+				self.client
+					.http
+					.get(&url)
+					.send()
+					.await?
+					.error_for_status()?;
+				Ok::<_, anyhow::Error>(())
+			};
+			let healthy = opts.run(check).await.is_ok();
+			self.mark(idx, healthy);
+			count += healthy as usize;
+		}
+		count
+	}
+
+	/// Check the nodes every `interval` until the task is dropped
+	pub async fn run_health_checks(&self, interval: Duration) {
+		loop {
+			self.check_health().await;
+			tokio::time::sleep(interval).await;
+		}
+	}
+
+	/// Next healthy node in round robin, any node if none is healthy as the
+	/// health may be outdated
+	fn next_node(&self) -> usize {
+		let len = self.nodes.len();
+		let start = self.next.fetch_add(1, Ordering::Relaxed);
+		(0..len)
+			.map(|offset| (start + offset) % len)
+			.find(|idx| self.is_healthy(*idx))
+			.unwrap_or(start % len)
+	}
+
+	/// Node the writes of the stream stick to while it is healthy
+	fn write_node(&self, stream_id: &StreamId) -> usize {
+		let mut sticky = self.sticky.lock().unwrap();
+		match sticky.get(stream_id) {
+			Some(idx) if self.is_healthy(*idx) => *idx,
+			_ => {
+				let idx = self.next_node();
+				sticky.insert(stream_id.clone(), idx);
+				idx
+			}
+		}
+	}
+
+	/// Run the call on the nodes from `first` on until one succeeds. Only
+	/// transient errors move to the next node, a node rejecting the call
+	/// would be followed by the others
+	async fn failover<T, F, Fut>(&self, first: usize, call: F) -> anyhow::Result<(usize, T)>
+	where
+		F: Fn(Ceramic) -> Fut,
+		Fut: Future<Output = anyhow::Result<T>>,
+	{
+		let mut idx = first;
+		let mut tried = 0;
+		loop {
+			match call(self.nodes[idx].clone()).await {
+				Ok(result) => {
+					self.mark(idx, true);
+					return Ok((idx, result));
+				}
+				Err(err) => {
+					match self.client.retry.classify(&err) {
+						ErrorClass::Fatal => return Err(err),
+						ErrorClass::Retryable => self.mark(idx, false),
+						// the node is up, only busy
+						ErrorClass::RateLimited => {}
+					}
+					tried += 1;
+					if tried >= self.nodes.len() {
+						return Err(err);
+					}
+					tracing::warn!(
+						endpoint = self.nodes[idx].endpoint,
+						"failing over: {:#}",
+						err
+					);
+					idx = self.next_node();
+				}
+			}
+		}
+	}
+
+	pub async fn load_events(
+		&self,
+		stream_id: &StreamId,
+		tip: Option<Cid>,
+	) -> anyhow::Result<Vec<Event>> {
+		let call = |ceramic: Ceramic| async move {
+			self.client.load_events(&ceramic, stream_id, tip).await
+		};
+		let (_, events) = self.failover(self.next_node(), call).await?;
+		Ok(events)
+	}
+
+	pub async fn load_stream_state(
+		&self,
+		stream_id: &StreamId,
+		tip: Option<Cid>,
+	) -> anyhow::Result<StreamState> {
+		let call = |ceramic: Ceramic| async move {
+			self.client
+				.load_stream_state(&ceramic, stream_id, tip)
+				.await
+		};
+		let (_, state) = self.failover(self.next_node(), call).await?;
+		Ok(state)
+	}
+
+	/// Upload the event to the node of the stream, the stream sticks to the
+	/// node taking over if it fails
+	pub async fn upload_event(&self, stream_id: &StreamId, event: Event) -> anyhow::Result<()> {
+		let event = &event;
+		let call = |ceramic: Ceramic| async move {
+			self.client
+				.upload_event(&ceramic, stream_id, event.clone())
+				.await
+		};
+		let (idx, _) = self.failover(self.write_node(stream_id), call).await?;
+		self.sticky.lock().unwrap().insert(stream_id.clone(), idx);
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::network::Network;
+	use crate::retry::StatusError;
+
+	fn multi_client(len: usize) -> MultiClient {
+		let nodes = (0..len)
+			.map(|idx| Ceramic {
+				endpoint: format!("http://localhost:{}", 7007 + idx),
+				network: Network::InMemory,
+				api: CeramicApi::JsCeramic,
+			})
+			.collect();
+		MultiClient::new(Client::new(), nodes).unwrap()
+	}
+
+	#[test]
+	fn test_round_robin() {
+		let client = multi_client(3);
+		assert_eq!(
+			(0..4).map(|_| client.next_node()).collect::<Vec<_>>(),
+			vec![0, 1, 2, 0]
+		);
+		client.mark(2, false);
+		assert_eq!(
+			(0..3).map(|_| client.next_node()).collect::<Vec<_>>(),
+			vec![1, 0, 0]
+		);
+	}
+
+	#[test]
+	fn test_sticky_writes() -> anyhow::Result<()> {
+		let stream_id: StreamId =
+			"kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju".parse()?;
+		let client = multi_client(3);
+		let idx = client.write_node(&stream_id);
+		client.next_node();
+		assert_eq!(client.write_node(&stream_id), idx);
+
+		client.mark(idx, false);
+		assert_ne!(client.write_node(&stream_id), idx);
+		assert!(MultiClient::new(Client::new(), vec![]).is_err());
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_failover_on_transient_errors() {
+		let client = multi_client(3);
+		let tried = AtomicUsize::new(0);
+		let call = |status: u16| {
+			let tried = &tried;
+			move |_: Ceramic| async move {
+				tried.fetch_add(1, Ordering::Relaxed);
+				Err::<(), _>(StatusError::new(status, String::new()).into())
+			}
+		};
+
+		// a rejected call fails on the first node, which stays healthy
+		assert!(client.failover(0, call(400)).await.is_err());
+		assert_eq!(tried.swap(0, Ordering::Relaxed), 1);
+		assert!(client.is_healthy(0));
+
+		assert!(client.failover(0, call(503)).await.is_err());
+		assert_eq!(tried.load(Ordering::Relaxed), 3);
+		assert!((0..3).all(|idx| !client.is_healthy(idx)));
+	}
+}
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use ceramic_core::StreamId;
+use ceramic_http_client::api;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::Client;
+use crate::Ceramic;
+
+/// Streams requested by one multiquery, larger lookups are split
+pub const MULTIQUERY_BATCH: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MultiQuery {
+	pub stream_id: StreamId,
+}
+
+/// Request loading the states of several streams at once
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MultiQueryRequest {
+	pub queries: Vec<MultiQuery>,
+}
+
+impl MultiQueryRequest {
+	pub fn url(&self, endpoint: &str) -> String {
+		format!("{}/api/v0/multiqueries", endpoint.trim_end_matches('/'))
+	}
+}
+
+impl Client {
+	pub fn create_multiquery_request(&self, stream_ids: &[StreamId]) -> MultiQueryRequest {
+		MultiQueryRequest {
+			queries: stream_ids
+				.iter()
+				.map(|stream_id| MultiQuery {
+					stream_id: stream_id.clone(),
+				})
+				.collect(),
+		}
+	}
+
+	/// Responses by stream as `get` returns them, streams the node failed to
+	/// load are left out
+	pub async fn send_multiquery_request(
+		&self,
+		ceramic: &Ceramic,
+		req: &MultiQueryRequest,
+	) -> anyhow::Result<HashMap<StreamId, api::StreamsResponse>> {
+		let url = req.url(&ceramic.endpoint);
+		// the node answers with the bare states keyed by stream id
+		let states: HashMap<String, Value> =
+			self.send(|client| client.post(&url).json(req)).await?;
+		states
+			.into_iter()
+			.map(|(stream_id, state)| {
+				let response = serde_json::json!({ "streamId": stream_id, "state": state });
+				Ok((stream_id.parse()?, serde_json::from_value(response)?))
+			})
+			.collect()
+	}
+
+	/// Responses of the streams in batches of `MULTIQUERY_BATCH` instead of
+	/// one request per stream
+	pub async fn multi_get(
+		&self,
+		ceramic: &Ceramic,
+		stream_ids: &[StreamId],
+	) -> anyhow::Result<HashMap<StreamId, api::StreamsResponse>> {
+		let mut result = HashMap::with_capacity(stream_ids.len());
+		for batch in stream_ids.chunks(MULTIQUERY_BATCH) {
+			let req = self.create_multiquery_request(batch);
+			result.extend(self.send_multiquery_request(ceramic, &req).await?);
+		}
+		Ok(result)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_multiquery_request() -> anyhow::Result<()> {
+		let stream_id: StreamId =
+			"kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju".parse()?;
+		let req = Client::new().create_multiquery_request(&[stream_id.clone()]);
+		assert_eq!(
+			req.url("http://localhost:7007/"),
+			"http://localhost:7007/api/v0/multiqueries"
+		);
+		assert_eq!(
+			serde_json::to_value(&req)?,
+			serde_json::json!({ "queries": [{ "streamId": stream_id.to_string() }] })
+		);
+		Ok(())
+	}
+}
@@ -0,0 +1,111 @@
+use ceramic_core::StreamId;
+use reqwest::Method;
+use serde::Deserialize;
+
+use super::Client;
+use crate::Ceramic;
+
+/// Request adding or removing the pin of a stream on the node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinRequest {
+	pub stream_id: StreamId,
+	pub pin: bool,
+}
+
+impl PinRequest {
+	pub fn method(&self) -> Method {
+		match self.pin {
+			true => Method::POST,
+			false => Method::DELETE,
+		}
+	}
+
+	pub fn url(&self, endpoint: &str) -> String {
+		format!(
+			"{}/api/v0/pins/{}",
+			endpoint.trim_end_matches('/'),
+			self.stream_id
+		)
+	}
+}
+
+/// Pin state of the stream after a pin request
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PinResponse {
+	pub stream_id: String,
+	pub is_pinned: bool,
+}
+
+impl Client {
+	pub fn create_pin_request(&self, stream_id: &StreamId) -> PinRequest {
+		PinRequest {
+			stream_id: stream_id.clone(),
+			pin: true,
+		}
+	}
+
+	pub fn create_unpin_request(&self, stream_id: &StreamId) -> PinRequest {
+		PinRequest {
+			stream_id: stream_id.clone(),
+			pin: false,
+		}
+	}
+
+	pub async fn send_pin_request(
+		&self,
+		ceramic: &Ceramic,
+		req: &PinRequest,
+	) -> anyhow::Result<PinResponse> {
+		let url = req.url(&ceramic.endpoint);
+		self.send(|client| client.request(req.method(), &url)).await
+	}
+
+	pub async fn pin_stream(
+		&self,
+		ceramic: &Ceramic,
+		stream_id: &StreamId,
+	) -> anyhow::Result<PinResponse> {
+		let req = self.create_pin_request(stream_id);
+		self.send_pin_request(ceramic, &req).await
+	}
+
+	pub async fn unpin_stream(
+		&self,
+		ceramic: &Ceramic,
+		stream_id: &StreamId,
+	) -> anyhow::Result<PinResponse> {
+		let req = self.create_unpin_request(stream_id);
+		self.send_pin_request(ceramic, &req).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_pin_request() -> anyhow::Result<()> {
+		let stream_id: StreamId =
+			"kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju".parse()?;
+		let client = Client::new();
+
+		let pin = client.create_pin_request(&stream_id);
+		assert_eq!(pin.method(), Method::POST);
+		assert_eq!(
+			pin.url("http://localhost:7007/"),
+			format!("http://localhost:7007/api/v0/pins/{}", stream_id)
+		);
+		assert_eq!(
+			client.create_unpin_request(&stream_id).method(),
+			Method::DELETE
+		);
+
+		let res: PinResponse = serde_json::from_value(serde_json::json!({
+			"streamId": stream_id.to_string(),
+			"isPinned": true,
+		}))?;
+		assert!(res.is_pinned);
+		Ok(())
+	}
+}
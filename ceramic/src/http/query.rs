@@ -0,0 +1,322 @@
+use std::future::Future;
+
+use anyhow::Context;
+use ceramic_core::StreamId;
+use futures::{Stream, TryStreamExt};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::filter::Filter;
+use super::{CallOpts, Client};
+use crate::{Ceramic, StreamState};
+
+/// Forward pagination of the collection api, from the start without a cursor
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pagination {
+	pub first: u32,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub after: Option<String>,
+}
+
+impl Default for Pagination {
+	fn default() -> Self {
+		Self {
+			first: 100,
+			after: None,
+		}
+	}
+}
+
+/// Documents of a model, of one account if given, matching the filters
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionQuery {
+	pub model: StreamId,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub account: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub query_filters: Option<Filter>,
+	#[serde(flatten)]
+	pub pagination: Pagination,
+}
+
+impl CollectionQuery {
+	pub fn new(model: StreamId) -> Self {
+		Self {
+			model,
+			account: None,
+			query_filters: None,
+			pagination: Default::default(),
+		}
+	}
+
+	pub fn with_filter(mut self, filter: Filter) -> Self {
+		self.query_filters = Some(filter);
+		self
+	}
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PageInfo {
+	pub has_next_page: bool,
+	pub has_previous_page: bool,
+	pub start_cursor: Option<String>,
+	pub end_cursor: Option<String>,
+}
+
+/// Edge of a page, the node is `None` for documents the node could not load
+#[derive(Debug, Clone, Deserialize)]
+pub struct QueryEdge {
+	pub cursor: String,
+	pub node: Option<StreamState>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryResponse {
+	pub edges: Vec<QueryEdge>,
+	pub page_info: PageInfo,
+}
+
+/// How `query_as` handles documents whose content is not a `T`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DecodeMode {
+	/// fail the whole page
+	#[default]
+	Strict,
+	/// drop the edge and count it as skipped
+	Lenient,
+}
+
+#[derive(Debug, Clone)]
+pub struct TypedQueryDocument<T> {
+	pub stream_id: StreamId,
+	pub content: T,
+}
+
+#[derive(Debug, Clone)]
+pub struct TypedQueryEdge<T> {
+	pub cursor: String,
+	pub node: Option<TypedQueryDocument<T>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TypedQueryResponse<T> {
+	pub edges: Vec<TypedQueryEdge<T>>,
+	pub page_info: PageInfo,
+	/// documents dropped in lenient mode
+	pub skipped: usize,
+}
+
+impl<T: DeserializeOwned> TypedQueryResponse<T> {
+	pub fn decode(page: QueryResponse, mode: DecodeMode) -> anyhow::Result<Self> {
+		let mut edges = Vec::new();
+		let mut skipped = 0;
+		for edge in page.edges {
+			let state = match edge.node {
+				Some(state) => state,
+				None => {
+					edges.push(TypedQueryEdge {
+						cursor: edge.cursor,
+						node: None,
+					});
+					continue;
+				}
+			};
+			let stream_id = state.stream_id()?;
+			let content = match serde_json::from_value::<T>(state.content) {
+				Ok(content) => content,
+				Err(err) if mode == DecodeMode::Lenient => {
+					tracing::warn!(
+						stream_id = stream_id.to_string(),
+						"skip undeserializable document: {}",
+						err
+					);
+					skipped += 1;
+					continue;
+				}
+				Err(err) => {
+					return Err(err).with_context(|| format!("failed to decode {}", stream_id))
+				}
+			};
+			edges.push(TypedQueryEdge {
+				cursor: edge.cursor,
+				node: Some(TypedQueryDocument { stream_id, content }),
+			});
+		}
+		Ok(Self {
+			edges,
+			page_info: page.page_info,
+			skipped,
+		})
+	}
+}
+
+/// Edges of every page, fetching the next page once the edges of the previous
+/// one are consumed
+fn paginate<F, Fut>(
+	query: CollectionQuery,
+	fetch: F,
+) -> impl Stream<Item = anyhow::Result<QueryEdge>>
+where
+	F: Fn(CollectionQuery) -> Fut,
+	Fut: Future<Output = anyhow::Result<QueryResponse>>,
+{
+	futures::stream::try_unfold(Some(query), move |query| {
+		let page = query.clone().map(&fetch);
+		async move {
+			let (mut query, page) = match (query, page) {
+				(Some(query), Some(page)) => (query, page.await?),
+				_ => return Ok(None),
+			};
+			let next = match page.page_info.end_cursor {
+				Some(cursor) if page.page_info.has_next_page && !page.edges.is_empty() => {
+					query.pagination.after = Some(cursor);
+					Some(query)
+				}
+				_ => None,
+			};
+			let edges = futures::stream::iter(page.edges.into_iter().map(Ok));
+			Ok::<_, anyhow::Error>(Some((edges, next)))
+		}
+	})
+	.try_flatten()
+}
+
+impl Client {
+	/// One page of the collection api of js-ceramic
+	pub async fn query(
+		&self,
+		ceramic: &Ceramic,
+		query: &CollectionQuery,
+	) -> anyhow::Result<QueryResponse> {
+		self.query_with_opts(ceramic, query, CallOpts::default())
+			.await
+	}
+
+	pub async fn query_with_opts(
+		&self,
+		ceramic: &Ceramic,
+		query: &CollectionQuery,
+		opts: CallOpts,
+	) -> anyhow::Result<QueryResponse> {
+		let url = format!(
+			"{}/api/v0/collection",
+			ceramic.endpoint.trim_end_matches('/')
+		);
+		self.send_with_opts(|client| client.post(&url).json(query), opts)
+			.await
+	}
+
+	/// One page of the collection api with the content of documents
+	/// deserialized as `T`
+	pub async fn query_as<T: DeserializeOwned>(
+		&self,
+		ceramic: &Ceramic,
+		query: &CollectionQuery,
+		mode: DecodeMode,
+	) -> anyhow::Result<TypedQueryResponse<T>> {
+		let page = self.query(ceramic, query).await?;
+		TypedQueryResponse::decode(page, mode)
+	}
+
+	/// Every edge of the query starting at its cursor, lazily fetching pages
+	/// of `query.pagination.first` edges so large models are processed
+	/// without buffering them
+	pub fn query_stream<'a>(
+		&'a self,
+		ceramic: &'a Ceramic,
+		query: CollectionQuery,
+	) -> impl Stream<Item = anyhow::Result<QueryEdge>> + 'a {
+		paginate(query, move |query| async move {
+			self.query(ceramic, &query).await
+		})
+	}
+
+	/// States of every document of the model matching the filter
+	pub async fn query_filtered(
+		&self,
+		ceramic: &Ceramic,
+		model_id: &StreamId,
+		filter: Filter,
+	) -> anyhow::Result<Vec<StreamState>> {
+		self.query_model_with(ceramic, None, model_id, Some(filter))
+			.await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[derive(Debug, Deserialize)]
+	struct Post {
+		title: String,
+	}
+
+	fn page() -> QueryResponse {
+		let state = |content: serde_json::Value| {
+			json!({
+				"type": 3,
+				"content": content,
+				"log": [{ "cid": "bagcqcerayswtqarydm2rgeh37yir45ccvfkj3qhwhfmu4vdjjrtny5l4rpia", "type": 0 }],
+				"metadata": {},
+				"signature": 2,
+				"anchorStatus": "ANCHORED",
+				"doctype": "MID",
+			})
+		};
+		serde_json::from_value(json!({
+			"edges": [
+				{ "cursor": "a", "node": state(json!({ "title": "hello" })) },
+				{ "cursor": "b", "node": null },
+				{ "cursor": "c", "node": state(json!({ "body": "no title" })) },
+			],
+			"pageInfo": { "hasNextPage": true, "hasPreviousPage": false, "endCursor": "c" },
+		}))
+		.unwrap()
+	}
+
+	#[tokio::test]
+	async fn test_paginate() -> anyhow::Result<()> {
+		let model: StreamId =
+			"kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju".parse()?;
+		let fetched = std::sync::Mutex::new(Vec::new());
+		let edges = paginate(CollectionQuery::new(model), |query| {
+			fetched.lock().unwrap().push(query.pagination.after.clone());
+			let mut page = page();
+			if query.pagination.after.is_some() {
+				page.page_info.has_next_page = false;
+			}
+			async move { Ok(page) }
+		});
+		let cursors: Vec<String> = edges.map_ok(|x| x.cursor).try_collect().await?;
+		assert_eq!(cursors, vec!["a", "b", "c", "a", "b", "c"]);
+		assert_eq!(*fetched.lock().unwrap(), vec![None, Some("c".to_string())]);
+		Ok(())
+	}
+
+	#[test]
+	fn test_decode_strict() {
+		assert!(TypedQueryResponse::<Post>::decode(page(), DecodeMode::Strict).is_err());
+	}
+
+	#[test]
+	fn test_decode_lenient() -> anyhow::Result<()> {
+		let page = TypedQueryResponse::<Post>::decode(page(), DecodeMode::Lenient)?;
+		assert_eq!(page.skipped, 1);
+		assert_eq!(page.edges.len(), 2);
+		assert_eq!(
+			page.edges[0]
+				.node
+				.as_ref()
+				.map(|x| x.content.title.as_str()),
+			Some("hello")
+		);
+		assert!(page.edges[1].node.is_none());
+		assert_eq!(page.page_info.end_cursor.as_deref(), Some("c"));
+		Ok(())
+	}
+}
@@ -0,0 +1,97 @@
+use base64::{engine::general_purpose, Engine};
+use ceramic_core::StreamId;
+use serde::{Deserialize, Serialize};
+
+use super::Client;
+use crate::stream::single::{load_single_instance, single_stream_id, MID_TYPE};
+use crate::{Ceramic, StreamLoader, StreamState};
+
+/// Request creating the single instance of a model from its deterministic
+/// genesis, the genesis serialized the way js-ceramic expects
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CreateSingleRequest {
+	pub r#type: u64,
+	pub genesis: serde_json::Value,
+}
+
+impl CreateSingleRequest {
+	pub fn url(&self, endpoint: &str) -> String {
+		format!("{}/api/v0/streams", endpoint.trim_end_matches('/'))
+	}
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateSingleResponse {
+	state: StreamState,
+}
+
+impl Client {
+	pub fn create_single_request(
+		&self,
+		model_id: &StreamId,
+		controller: &str,
+	) -> anyhow::Result<CreateSingleRequest> {
+		Ok(CreateSingleRequest {
+			r#type: MID_TYPE,
+			genesis: serde_json::json!({
+				"data": null,
+				"header": {
+					"controllers": [controller],
+					"model": general_purpose::STANDARD_NO_PAD.encode(model_id.to_vec()?),
+					"sep": "model",
+				},
+			}),
+		})
+	}
+
+	/// State of the single instance of `model_id` controlled by `controller`,
+	/// its stream id derived locally without creating it
+	pub async fn get_single_instance(
+		&self,
+		ceramic: &Ceramic,
+		model_id: &StreamId,
+		controller: &str,
+	) -> anyhow::Result<StreamState> {
+		let stream_id = single_stream_id(model_id, controller)?;
+		self.load_stream_state(ceramic, &stream_id, None).await
+	}
+
+	/// State of the single instance, creating it from its deterministic
+	/// genesis if ceramic answers that it does not exist
+	pub async fn get_or_create_single_instance(
+		&self,
+		ceramic: &Ceramic,
+		model_id: &StreamId,
+		controller: &str,
+	) -> anyhow::Result<StreamState> {
+		if let Some(state) = load_single_instance(self, ceramic, model_id, controller).await? {
+			return Ok(state);
+		}
+		let req = self.create_single_request(model_id, controller)?;
+		let url = req.url(&ceramic.endpoint);
+		let res: CreateSingleResponse = self.send(|client| client.post(&url).json(&req)).await?;
+		Ok(res.state)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_create_single_request() -> anyhow::Result<()> {
+		let model_id: StreamId =
+			"kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju".parse()?;
+		let account = "did:pkh:eip155:1:0x312eA852726E3A9f633A0377c0ea882086d66666";
+		let req = Client::new().create_single_request(&model_id, account)?;
+		assert_eq!(
+			req.url("http://localhost:7007/"),
+			"http://localhost:7007/api/v0/streams"
+		);
+		assert_eq!(req.r#type, MID_TYPE);
+		assert_eq!(req.genesis["header"]["controllers"][0], account);
+		assert_eq!(req.genesis["header"]["sep"], "model");
+		Ok(())
+	}
+}
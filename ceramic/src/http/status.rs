@@ -0,0 +1,152 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::admin::{admin_jws, IndexingJobs, STATUS_PATH};
+use super::Client;
+use crate::network::Chain;
+use crate::redact::Secret;
+use crate::Ceramic;
+
+/// Admin status of a node, the strings js-ceramic reports parsed where
+/// monitoring needs them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeStatus {
+	pub run_id: String,
+	pub uptime_ms: u64,
+	pub network: String,
+	pub anchor: AnchorServiceStatus,
+	#[serde(default)]
+	pub ipfs: Option<IpfsStatus>,
+	#[serde(default, rename = "composeDB")]
+	pub compose_db: Option<ComposeDbStatus>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnchorServiceStatus {
+	pub anchor_service_url: String,
+	#[serde(default)]
+	pub ethereum_rpc_endpoint: Option<String>,
+	/// caip-2 id, `eip155:1` on mainnet
+	pub chain_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IpfsStatus {
+	pub peer_id: String,
+	#[serde(default)]
+	pub addresses: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComposeDbStatus {
+	#[serde(default)]
+	pub indexed_models: Vec<String>,
+	#[serde(default)]
+	pub syncs: Option<IndexingJobs>,
+}
+
+impl NodeStatus {
+	pub fn chain(&self) -> anyhow::Result<Chain> {
+		self.anchor.chain_id.parse()
+	}
+
+	/// Start of the run of the node, from its uptime at `now`
+	pub fn started_at(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+		now - Duration::milliseconds(self.uptime_ms as i64)
+	}
+
+	pub fn syncs(&self) -> IndexingJobs {
+		self.compose_db
+			.as_ref()
+			.and_then(|x| x.syncs.clone())
+			.unwrap_or_default()
+	}
+
+	/// Whether the node is still syncing the history of indexed models
+	pub fn is_syncing(&self) -> bool {
+		let syncs = self.syncs();
+		!syncs.active_syncs.is_empty() || !syncs.pending_syncs.is_empty()
+	}
+
+	/// Blocks left to sync over the active and pending syncs
+	pub fn sync_lag(&self) -> u64 {
+		let syncs = self.syncs();
+		syncs
+			.active_syncs
+			.iter()
+			.chain(&syncs.pending_syncs)
+			.filter_map(|job| job.remaining_blocks())
+			.sum()
+	}
+}
+
+impl Client {
+	/// Admin status of the node, `pk` is the seed of the admin did of the node
+	pub async fn node_status(
+		&self,
+		ceramic: &Ceramic,
+		pk: &Secret<String>,
+	) -> anyhow::Result<NodeStatus> {
+		let code = self.admin_code(ceramic).await?;
+		let jws = admin_jws(pk, &code, STATUS_PATH, None)?;
+		let url = format!("{}{}", ceramic.endpoint.trim_end_matches('/'), STATUS_PATH);
+		let auth = format!("Basic {}", jws.expose());
+		self.send(|client| {
+			client
+				.get(&url)
+				.header(reqwest::header::AUTHORIZATION, &auth)
+		})
+		.await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_node_status() -> anyhow::Result<()> {
+		let status: NodeStatus = serde_json::from_value(serde_json::json!({
+			"runId": "5d2b1f0e",
+			"uptimeMs": 60000,
+			"network": "mainnet",
+			"anchor": {
+				"anchorServiceUrl": "https://cas.3boxlabs.com",
+				"ethereumRpcEndpoint": null,
+				"chainId": "eip155:1"
+			},
+			"ipfs": { "peerId": "12D3KooW", "addresses": [] },
+			"composeDB": {
+				"indexedModels": ["kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju"],
+				"syncs": {
+					"activeSyncs": [{
+						"models": [],
+						"fromBlock": 100,
+						"toBlock": 200,
+						"currentBlock": 150,
+						"createdAt": "2024-04-22T03:14:07.000Z"
+					}],
+					"continuousSync": [],
+					"pendingSyncs": [{ "models": [], "fromBlock": 200, "toBlock": 220 }]
+				}
+			}
+		}))?;
+		assert_eq!(status.chain()?, Chain::EthereumMainnet);
+		let now: DateTime<Utc> = "2024-04-22T03:15:07Z".parse()?;
+		assert_eq!(
+			status.started_at(now),
+			"2024-04-22T03:14:07Z".parse::<DateTime<Utc>>()?
+		);
+		assert!(status.is_syncing());
+		assert_eq!(status.sync_lag(), 70);
+		assert_eq!(
+			status.syncs().active_syncs[0].created_at,
+			Some("2024-04-22T03:14:07Z".parse()?)
+		);
+		Ok(())
+	}
+}
@@ -0,0 +1,86 @@
+use std::collections::HashSet;
+use std::sync::RwLock;
+
+use ceramic_core::StreamId;
+use tokio::sync::broadcast;
+
+/// Change of the replicated models, announced to subscribers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterestChange {
+	Added(StreamId),
+	Removed(StreamId),
+}
+
+/// Models replicated by this node. Pubsub updates, the ceramic-one feed and
+/// backfills skip streams of other models, and subscribers backfill or prune
+/// models as they are added or removed at runtime.
+pub struct Interests {
+	models: RwLock<HashSet<StreamId>>,
+	changes: broadcast::Sender<InterestChange>,
+}
+
+impl Interests {
+	pub fn new(models: impl IntoIterator<Item = StreamId>) -> Self {
+		let (changes, _) = broadcast::channel(64);
+		Self {
+			models: RwLock::new(models.into_iter().collect()),
+			changes,
+		}
+	}
+
+	pub fn contains(&self, model_id: &StreamId) -> bool {
+		self.models.read().unwrap().contains(model_id)
+	}
+
+	pub fn models(&self) -> Vec<StreamId> {
+		self.models.read().unwrap().iter().cloned().collect()
+	}
+
+	/// Returns false if the model was already replicated
+	pub fn add(&self, model_id: StreamId) -> bool {
+		let added = self.models.write().unwrap().insert(model_id.clone());
+		if added {
+			let _ = self.changes.send(InterestChange::Added(model_id));
+		}
+		added
+	}
+
+	/// Returns false if the model was not replicated
+	pub fn remove(&self, model_id: &StreamId) -> bool {
+		let removed = self.models.write().unwrap().remove(model_id);
+		if removed {
+			let _ = self.changes.send(InterestChange::Removed(model_id.clone()));
+		}
+		removed
+	}
+
+	/// Changes after the call, a lagging receiver misses the oldest ones
+	pub fn subscribe(&self) -> broadcast::Receiver<InterestChange> {
+		self.changes.subscribe()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_interests() -> anyhow::Result<()> {
+		let model: StreamId =
+			"kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju".parse()?;
+		let interests = Interests::new(vec![]);
+		let mut changes = interests.subscribe();
+
+		assert!(!interests.contains(&model));
+		assert!(interests.add(model.clone()));
+		assert!(!interests.add(model.clone()));
+		assert!(interests.contains(&model));
+		assert!(interests.remove(&model));
+		assert!(!interests.remove(&model));
+
+		assert_eq!(changes.try_recv()?, InterestChange::Added(model.clone()));
+		assert_eq!(changes.try_recv()?, InterestChange::Removed(model));
+		assert!(changes.try_recv().is_err());
+		Ok(())
+	}
+}
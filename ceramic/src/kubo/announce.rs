@@ -0,0 +1,248 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use ceramic_core::{Cid, StreamId};
+use tokio::sync::mpsc;
+
+use crate::event::{Event, EventValue, EventsUploader};
+use crate::retry::StatusError;
+use crate::Ceramic;
+
+/// Announces provider records of blocks, making them findable by other nodes
+#[async_trait::async_trait]
+pub trait Provider: Send + Sync {
+	async fn provide(&self, cid: &Cid) -> anyhow::Result<()>;
+}
+
+/// Provider announcing through the routing api of kubo
+pub struct KuboProvider {
+	endpoint: String,
+	client: reqwest::Client,
+}
+
+impl KuboProvider {
+	pub fn new(endpoint: &str) -> Self {
+		Self {
+			endpoint: endpoint.trim_end_matches('/').to_string(),
+			client: reqwest::Client::new(),
+		}
+	}
+}
+
+#[async_trait::async_trait]
+impl Provider for KuboProvider {
+	async fn provide(&self, cid: &Cid) -> anyhow::Result<()> {
+		let url = format!("{}/api/v0/routing/provide", self.endpoint);
+		let res = self
+			.client
+			.post(url)
+			.query(&[("arg", cid.to_string())])
+			.send()
+			.await?;
+		let status = res.status();
+		if !status.is_success() {
+			let message = res.text().await.unwrap_or_default();
+			anyhow::bail!(StatusError::new(status.as_u16(), message));
+		}
+		Ok(())
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnounceConfig {
+	/// blocks announced per batch
+	pub batch_size: usize,
+	/// pause between batches, bounding the announce rate
+	pub interval: Duration,
+	/// blocks waiting to be announced, further blocks are dropped
+	pub queue_capacity: usize,
+}
+
+impl Default for AnnounceConfig {
+	fn default() -> Self {
+		Self {
+			batch_size: 32,
+			interval: Duration::from_secs(1),
+			queue_capacity: 4096,
+		}
+	}
+}
+
+/// Counters of announced blocks
+#[derive(Debug, Default)]
+pub struct AnnounceMetrics {
+	pub announced: AtomicU64,
+	pub failed: AtomicU64,
+	/// blocks not queued as the queue was full
+	pub dropped: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AnnounceStats {
+	pub announced: u64,
+	pub failed: u64,
+	pub dropped: u64,
+}
+
+impl AnnounceMetrics {
+	pub fn snapshot(&self) -> AnnounceStats {
+		AnnounceStats {
+			announced: self.announced.load(Ordering::Relaxed),
+			failed: self.failed.load(Ordering::Relaxed),
+			dropped: self.dropped.load(Ordering::Relaxed),
+		}
+	}
+}
+
+/// Background queue announcing blocks in batches, failures are counted and
+/// logged but not retried
+#[derive(Clone)]
+pub struct Announcer {
+	sender: mpsc::Sender<Cid>,
+	metrics: Arc<AnnounceMetrics>,
+}
+
+impl Announcer {
+	pub fn spawn(provider: Arc<dyn Provider>, config: AnnounceConfig) -> Self {
+		let (sender, receiver) = mpsc::channel(config.queue_capacity.max(1));
+		let metrics = Arc::new(AnnounceMetrics::default());
+		tokio::spawn(run(provider, config, receiver, metrics.clone()));
+		Self { sender, metrics }
+	}
+
+	pub fn announce(&self, cids: impl IntoIterator<Item = Cid>) {
+		for cid in cids {
+			if self.sender.try_send(cid).is_err() {
+				self.metrics.dropped.fetch_add(1, Ordering::Relaxed);
+			}
+		}
+	}
+
+	pub fn metrics(&self) -> AnnounceStats {
+		self.metrics.snapshot()
+	}
+}
+
+async fn run(
+	provider: Arc<dyn Provider>,
+	config: AnnounceConfig,
+	mut receiver: mpsc::Receiver<Cid>,
+	metrics: Arc<AnnounceMetrics>,
+) {
+	let batch_size = config.batch_size.max(1);
+	while let Some(first) = receiver.recv().await {
+		let mut batch = vec![first];
+		while batch.len() < batch_size {
+			match receiver.try_recv() {
+				Ok(cid) => batch.push(cid),
+				Err(_) => break,
+			}
+		}
+		for cid in batch {
+			match provider.provide(&cid).await {
+				Ok(_) => {
+					metrics.announced.fetch_add(1, Ordering::Relaxed);
+				}
+				Err(err) => {
+					metrics.failed.fetch_add(1, Ordering::Relaxed);
+					tracing::warn!(cid = cid.to_string(), "failed to announce block: {}", err);
+				}
+			}
+		}
+		tokio::time::sleep(config.interval).await;
+	}
+}
+
+/// Blocks of the event written to kubo on upload
+fn event_blocks(event: &Event) -> Vec<Cid> {
+	let mut cids = vec![event.cid];
+	if let EventValue::Signed(signed) = &event.value {
+		if signed.linked_block.is_some() {
+			cids.extend(signed.payload_link().ok());
+		}
+		if signed.cacao_block().is_some() {
+			cids.extend(signed.cacao_link().ok());
+		}
+	}
+	cids
+}
+
+/// Uploader announcing the blocks of uploaded events
+pub struct AnnouncingUploader<T> {
+	pub inner: T,
+	pub announcer: Announcer,
+}
+
+#[async_trait::async_trait]
+impl<T: EventsUploader + Send + Sync> EventsUploader for AnnouncingUploader<T> {
+	async fn upload_event(
+		&self,
+		ceramic: &Ceramic,
+		stream_id: &StreamId,
+		event: Event,
+	) -> anyhow::Result<()> {
+		let cids = event_blocks(&event);
+		self.inner.upload_event(ceramic, stream_id, event).await?;
+		self.announcer.announce(cids);
+		Ok(())
+	}
+
+	async fn upload_events(
+		&self,
+		ceramic: &Ceramic,
+		stream_id: &StreamId,
+		events: Vec<Event>,
+	) -> anyhow::Result<()> {
+		let cids: Vec<Cid> = events.iter().flat_map(event_blocks).collect();
+		self.inner.upload_events(ceramic, stream_id, events).await?;
+		self.announcer.announce(cids);
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Mutex;
+
+	use super::*;
+	use crate::commit::example;
+
+	#[derive(Default)]
+	struct MockProvider {
+		provided: Mutex<Vec<Cid>>,
+	}
+
+	#[async_trait::async_trait]
+	impl Provider for MockProvider {
+		async fn provide(&self, cid: &Cid) -> anyhow::Result<()> {
+			self.provided.lock().unwrap().push(*cid);
+			Ok(())
+		}
+	}
+
+	#[tokio::test]
+	async fn test_announcer() -> anyhow::Result<()> {
+		let provider = Arc::new(MockProvider::default());
+		let config = AnnounceConfig {
+			interval: Duration::from_millis(1),
+			..Default::default()
+		};
+		let announcer = Announcer::spawn(provider.clone(), config);
+
+		let genesis: Event = example::genesis().genesis.try_into()?;
+		let cids = event_blocks(&genesis);
+		assert_eq!(cids.len(), 3);
+		announcer.announce(cids.clone());
+
+		for _ in 0..100 {
+			if announcer.metrics().announced == 3 {
+				break;
+			}
+			tokio::time::sleep(Duration::from_millis(10)).await;
+		}
+		assert_eq!(announcer.metrics().announced, 3);
+		assert_eq!(*provider.provided.lock().unwrap(), cids);
+		Ok(())
+	}
+}
@@ -4,6 +4,7 @@ use base64::Engine;
 use bytes::Bytes;
 use ceramic_core::{Cid, StreamId};
 use ceramic_kubo_rpc_server::{IdPostResponse, PubsubPubPostResponse, PubsubSubPostResponse};
+use dataverse_multibase::MultiBase64UrlString;
 use futures_util::StreamExt;
 use libipld::cbor::DagCborCodec;
 use libipld::codec::Codec;
@@ -11,20 +12,29 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sha2::{Digest, Sha256};
 
-use crate::{network::Network, Ceramic};
+use crate::{interests::Interests, network::Network, Ceramic};
 
-use super::{pubsub::Message, store, Client};
+use super::checkpoint::CheckpointTracker;
+use super::{pubsub::Message, Client, TipStore};
 
 #[async_trait::async_trait]
 pub trait MessageSubscriber: MessageResponsePublisher {
-	async fn subscribe(&self, store: Arc<dyn store::Store>, network: Network)
-		-> anyhow::Result<()>;
+	/// Consume updates of the network, of the `interests` models only if given
+	async fn subscribe(
+		&self,
+		store: Arc<dyn TipStore>,
+		checkpoints: Option<Arc<CheckpointTracker>>,
+		interests: Option<Arc<Interests>>,
+		network: Network,
+	) -> anyhow::Result<()>;
 
 	async fn kubo_message_handler(
 		&self,
 		kubo_id: Arc<String>,
 		network: Network,
-		store: Arc<dyn store::Store>,
+		store: Arc<dyn TipStore>,
+		checkpoints: Option<Arc<CheckpointTracker>>,
+		interests: Option<Arc<Interests>>,
 		event: Result<Bytes, Box<dyn std::error::Error + Send + Sync>>,
 	) -> () {
 		let msg_resp = match event {
@@ -44,12 +54,21 @@ pub trait MessageSubscriber: MessageResponsePublisher {
 		if msg_resp.from == *kubo_id {
 			return;
 		}
+		if let Some(checkpoints) = &checkpoints {
+			// recorded before handling, in the order messages are received
+			let seqno = &msg_resp.seqno;
+			match checkpoints.record(&msg_resp.from, seqno).await {
+				Ok(true) => tracing::info!(?network, seqno, "missed messages of publisher"),
+				Ok(false) => {}
+				Err(err) => tracing::warn!(?network, seqno, "failed to save checkpoint: {}", err),
+			}
+		}
 
 		if let Ok((_, msg_data)) = multibase::decode(msg_resp.data) {
 			if let Ok(msg) = serde_json::from_slice::<Message>(&msg_data) {
 				tracing::info!(?network, ?msg, "kubo sub receive msg");
 				if let Err(err) = self
-					.ceramic_message_handler(network, store, msg.clone())
+					.ceramic_message_handler(network, store, interests, msg.clone())
 					.await
 				{
 					tracing::error!(?network, ?msg, "ceramic message handler error: {}", err)
@@ -61,13 +80,14 @@ pub trait MessageSubscriber: MessageResponsePublisher {
 	async fn ceramic_message_handler(
 		&self,
 		network: Network,
-		store: Arc<dyn store::Store>,
+		store: Arc<dyn TipStore>,
+		interests: Option<Arc<Interests>>,
 		msg: Message,
 	) -> anyhow::Result<()> {
 		match msg {
 			Message::Query { id, stream } => {
 				let stream_id: StreamId = stream.parse()?;
-				if let Some(tip) = store.get(Some(id.clone()), Some(stream_id.clone())).await? {
+				if let Some(tip) = store.get_tip(&stream_id).await? {
 					tracing::info!(?network, ?id, ?stream, ?tip, "query stored response");
 					if let Err(err) = self.publish_response(&network, &id, &stream_id, &tip).await {
 						tracing::error!(?network, ?id, ?stream, "publish response error: {}", err)
@@ -75,28 +95,28 @@ pub trait MessageSubscriber: MessageResponsePublisher {
 				}
 			}
 			Message::Response { id, tips } => {
-				if store.get(Some(id.clone()), None).await?.is_some() {
+				if store.has_query(&id).await? {
 					for (stream_id, tip) in tips {
-						let push = store
-							.push(Some(id.clone()), Some(stream_id.parse()?), tip.parse()?)
-							.await;
+						let push = store.set_tip(&stream_id.parse()?, tip.parse()?).await;
 						if let Err(err) = push {
 							tracing::error!(stream_id = id, "store push error: {}", err)
 						}
 					}
 				};
 			}
-			Message::Update {
-				stream,
-				tip,
-				model: _,
-			} => {
-				if let Some(tip_old) = store.get(None, Some(stream.parse()?)).await? {
+			Message::Update { stream, tip, model } => {
+				if let (Some(interests), Some(model)) = (&interests, &model) {
+					if !interests.contains(&model.parse()?) {
+						return Ok(());
+					}
+				}
+				let stream_id: StreamId = stream.parse()?;
+				if let Some(tip_old) = store.get_tip(&stream_id).await? {
 					if tip_old.to_string() == tip {
 						tracing::info!(?network, ?stream, ?tip, "update tip not changed");
 						return Ok(());
 					}
-					if let Err(err) = store.push(None, Some(stream.parse()?), tip.parse()?).await {
+					if let Err(err) = store.set_tip(&stream_id, tip.parse()?).await {
 						tracing::error!(stream, tip, "store push error: {}", err)
 					}
 				}
@@ -111,7 +131,9 @@ pub trait MessageSubscriber: MessageResponsePublisher {
 impl MessageSubscriber for Client {
 	async fn subscribe(
 		&self,
-		store: Arc<dyn store::Store>,
+		store: Arc<dyn TipStore>,
+		checkpoints: Option<Arc<CheckpointTracker>>,
+		interests: Option<Arc<Interests>>,
 		network: Network,
 	) -> anyhow::Result<()> {
 		let sub = self.pubsub_sub_post(network.kubo_topic()).await?;
@@ -128,10 +150,22 @@ impl MessageSubscriber for Client {
 		if let PubsubSubPostResponse::Success(body) = sub {
 			let store = Arc::clone(&store);
 			let kube_id = Arc::new(kube_id);
+			let tracker = checkpoints.clone();
 			let handler = body.for_each_concurrent(None, move |event| {
-				self.kubo_message_handler(kube_id.clone(), network, store.clone(), event)
+				self.kubo_message_handler(
+					kube_id.clone(),
+					network,
+					store.clone(),
+					checkpoints.clone(),
+					interests.clone(),
+					event,
+				)
 			});
 			handler.await;
+			// checkpoints are written in batches, keep the last messages
+			if let Some(tracker) = tracker {
+				tracker.flush().await?;
+			}
 			return Ok(());
 		}
 		anyhow::bail!("subscribe failed")
@@ -146,7 +180,7 @@ pub trait MessagePublisher {
 #[async_trait::async_trait]
 impl MessagePublisher for Client {
 	async fn publish_message(&self, topic: &str, msg: Vec<u8>) -> anyhow::Result<()> {
-		let en_topic = multibase::encode(multibase::Base::Base64Url, topic);
+		let en_topic = MultiBase64UrlString::encode(topic).into();
 		let file = swagger::ByteArray(msg);
 		let res = self.pubsub_pub_post(en_topic, file).await?;
 		match res {
@@ -1,11 +1,15 @@
+pub mod announce;
 pub mod cache;
 pub mod message;
+pub mod peers;
 pub mod pubsub;
 pub mod store;
 pub mod task;
 
+pub use crate::checkpoint;
 pub use cache::Cached;
-pub use store::Store;
+pub use peers::{KuboId, KuboPeer, PeerInfo};
+pub use store::{LegacyTipStore, MemoryTipStore, Store, StoreAdapter, TipStore};
 
 use ceramic_core::{Cid, StreamId};
 use ceramic_kubo_rpc_server::models;
@@ -15,6 +19,7 @@ use int_enum::IntEnum;
 use swagger::{AuthData, ByteArray, ContextBuilder, EmptyContext, Push, XSpanIdString};
 
 use crate::event::{self, Event, EventsLoader, EventsUploader, ToCid};
+use crate::retry::{RetryPolicy, StatusError};
 use crate::{Ceramic, StreamLoader, StreamState};
 
 use self::message::MessageUpdatePublisher;
@@ -53,25 +58,10 @@ pub trait CidLoader {
 	}
 
 	async fn load_cid_with_retry(&self, cid: &Cid, max_retries: u32) -> anyhow::Result<Vec<u8>> {
-		let mut retries = 0;
-
-		loop {
-			match self.load_cid(cid).await {
-				Ok(result) => return Ok(result),
-				Err(err) if retries < max_retries => {
-					retries += 1;
-					tracing::warn!(
-						cid = cid.to_string(),
-						?err,
-						"Failed to load CID, retrying... ({}/{})",
-						retries,
-						max_retries
-					);
-					continue;
-				}
-				Err(e) => return Err(e),
-			}
-		}
+		RetryPolicy::default()
+			.with_max_retries(max_retries)
+			.run(|| self.load_cid(cid))
+			.await
 	}
 }
 
@@ -86,11 +76,11 @@ impl CidLoader for Client {
 			BlockGetPostResponse::Success(bytes) => bytes.to_vec(),
 			BlockGetPostResponse::BadRequest(err) => {
 				tracing::warn!(?err, cid = cid.to_string(), "bad request");
-				anyhow::bail!("bad request: {:?}", err);
+				anyhow::bail!(StatusError::new(400, format!("bad request: {:?}", err)));
 			}
 			BlockGetPostResponse::InternalError(err) => {
 				tracing::warn!(?err, cid = cid.to_string(), "internal error");
-				anyhow::bail!("internal error: {:?}", err);
+				anyhow::bail!(StatusError::new(500, format!("internal error: {:?}", err)));
 			}
 		};
 
@@ -117,7 +107,10 @@ impl BlockUploader for Client {
 			}
 			BlockPutPostResponse::BadRequest(err) => {
 				tracing::warn!(error = err.message, "bailed to post block: {:?}", err);
-				anyhow::bail!("Failed to post block: {:?}", err)
+				anyhow::bail!(StatusError::new(
+					400,
+					format!("Failed to post block: {:?}", err)
+				))
 			}
 		}
 	}
@@ -133,8 +126,8 @@ impl<T: BlockUploader + AnchorRuester + MessageUpdatePublisher + Send + Sync> Ev
 	) -> anyhow::Result<()> {
 		match &commit.value {
 			event::EventValue::Signed(signed) => {
-				if let Some(cacao_block) = &signed.cacao_block {
-					self.block_upload(signed.cacao_link()?, cacao_block.clone())
+				if let Some(cacao_block) = signed.cacao_block() {
+					self.block_upload(signed.cacao_link()?, cacao_block.to_vec())
 						.await?;
 				}
 				if let Some(linked_block) = &signed.linked_block {
@@ -200,7 +193,8 @@ impl<T: CidLoader + Send + Sync> EventsLoader for T {
 				event::EventValue::Signed(signed) => {
 					signed.linked_block =
 						Some(self.load_cid_retry_3_times(&signed.payload_link()?).await?);
-					signed.cacao_block = Some(self.load_cid_retry_3_times(&signed.cap()?).await?);
+					let cacao_block = self.load_cid_retry_3_times(&signed.cap()?).await?;
+					signed.set_cacao_block(Some(cacao_block));
 				}
 				event::EventValue::Anchor(anchor) => {
 					anchor.proof_block = Some(self.load_cid_retry_3_times(&anchor.proof).await?)
@@ -0,0 +1,60 @@
+use ceramic_kubo_rpc_server::{IdPostResponse, SwarmPeersPostResponse};
+use serde::{Deserialize, Serialize};
+
+use super::Client;
+use crate::retry::StatusError;
+
+/// Identity of the kubo node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KuboId {
+	pub id: String,
+	pub addresses: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KuboPeer {
+	pub peer: String,
+	pub addr: String,
+}
+
+/// Swarm of the kubo node, stream loading mostly fails for lack of peers
+#[async_trait::async_trait]
+pub trait PeerInfo {
+	async fn kubo_id(&self) -> anyhow::Result<KuboId>;
+	async fn kubo_peers(&self) -> anyhow::Result<Vec<KuboPeer>>;
+}
+
+#[async_trait::async_trait]
+impl PeerInfo for Client {
+	async fn kubo_id(&self) -> anyhow::Result<KuboId> {
+		match self.id_post(None).await? {
+			IdPostResponse::Success(id) => Ok(KuboId {
+				id: id.id,
+				addresses: id.addresses,
+			}),
+			IdPostResponse::BadRequest(err) => anyhow::bail!(StatusError::new(
+				400,
+				format!("failed to get kubo id: {}", err.message)
+			)),
+		}
+	}
+
+	async fn kubo_peers(&self) -> anyhow::Result<Vec<KuboPeer>> {
+		match self.swarm_peers_post().await? {
+			SwarmPeersPostResponse::Success(res) => Ok(res
+				.peers
+				.into_iter()
+				.map(|x| KuboPeer {
+					peer: x.peer,
+					addr: x.addr,
+				})
+				.collect()),
+			SwarmPeersPostResponse::BadRequest(err) => anyhow::bail!(StatusError::new(
+				400,
+				format!("failed to list kubo peers: {}", err.message)
+			)),
+		}
+	}
+}
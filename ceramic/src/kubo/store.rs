@@ -1,5 +1,26 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
 use ceramic_core::{Cid, StreamId};
+use tokio::sync::RwLock;
+
+/// Latest known tips of streams, answering and updated by pubsub messages
+#[async_trait::async_trait]
+pub trait TipStore: Sync + Send {
+	async fn get_tip(&self, stream_id: &StreamId) -> anyhow::Result<Option<Cid>>;
+	async fn set_tip(&self, stream_id: &StreamId, tip: Cid) -> anyhow::Result<()>;
+	/// streams tracked by the store, reconciled after missed pubsub updates
+	async fn stream_ids(&self) -> anyhow::Result<Vec<StreamId>> {
+		Ok(vec![])
+	}
+	/// whether a pubsub query with the id was sent by this node, tips of
+	/// responses to other queries are ignored
+	async fn has_query(&self, _id: &str) -> anyhow::Result<bool> {
+		Ok(false)
+	}
+}
 
+/// Tip store keyed by optional query ids, superseded by [`TipStore`]
 #[async_trait::async_trait]
 pub trait Store: Sync + Send {
 	async fn get(
@@ -13,4 +34,144 @@ pub trait Store: Sync + Send {
 		stream_id: Option<StreamId>,
 		tip: Cid,
 	) -> anyhow::Result<()>;
+	/// streams tracked by the store, reconciled after missed pubsub updates
+	async fn stream_ids(&self) -> anyhow::Result<Vec<StreamId>> {
+		Ok(vec![])
+	}
+}
+
+/// Implementation of a [`Store`] used as a [`TipStore`]
+pub struct LegacyTipStore(pub Arc<dyn Store>);
+
+#[async_trait::async_trait]
+impl TipStore for LegacyTipStore {
+	async fn get_tip(&self, stream_id: &StreamId) -> anyhow::Result<Option<Cid>> {
+		self.0.get(None, Some(stream_id.clone())).await
+	}
+
+	async fn set_tip(&self, stream_id: &StreamId, tip: Cid) -> anyhow::Result<()> {
+		self.0.push(None, Some(stream_id.clone()), tip).await
+	}
+
+	async fn stream_ids(&self) -> anyhow::Result<Vec<StreamId>> {
+		self.0.stream_ids().await
+	}
+
+	async fn has_query(&self, id: &str) -> anyhow::Result<bool> {
+		Ok(self.0.get(Some(id.to_string()), None).await?.is_some())
+	}
+}
+
+/// [`TipStore`] exposed to callers of the [`Store`] interface
+pub struct StoreAdapter(pub Arc<dyn TipStore>);
+
+#[async_trait::async_trait]
+impl Store for StoreAdapter {
+	async fn get(
+		&self,
+		id: Option<String>,
+		stream_id: Option<StreamId>,
+	) -> anyhow::Result<Option<Cid>> {
+		match (id, stream_id) {
+			(_, Some(stream_id)) => self.0.get_tip(&stream_id).await,
+			(Some(id), None) => match self.0.has_query(&id).await? {
+				// marker of a known query, only checked for presence
+				true => Ok(Some(Cid::default())),
+				false => Ok(None),
+			},
+			(None, None) => Ok(None),
+		}
+	}
+
+	async fn push(
+		&self,
+		_id: Option<String>,
+		stream_id: Option<StreamId>,
+		tip: Cid,
+	) -> anyhow::Result<()> {
+		match stream_id {
+			Some(stream_id) => self.0.set_tip(&stream_id, tip).await,
+			None => Ok(()),
+		}
+	}
+
+	async fn stream_ids(&self) -> anyhow::Result<Vec<StreamId>> {
+		self.0.stream_ids().await
+	}
+}
+
+/// Tips held in memory, for tests and nodes without persistent stores
+#[derive(Default)]
+pub struct MemoryTipStore {
+	tips: RwLock<HashMap<StreamId, Cid>>,
+	queries: RwLock<Vec<String>>,
+}
+
+impl MemoryTipStore {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// remember a query sent by this node, accepting tips of its responses
+	pub async fn add_query(&self, id: &str) {
+		self.queries.write().await.push(id.to_string());
+	}
+}
+
+#[async_trait::async_trait]
+impl TipStore for MemoryTipStore {
+	async fn get_tip(&self, stream_id: &StreamId) -> anyhow::Result<Option<Cid>> {
+		Ok(self.tips.read().await.get(stream_id).cloned())
+	}
+
+	async fn set_tip(&self, stream_id: &StreamId, tip: Cid) -> anyhow::Result<()> {
+		self.tips.write().await.insert(stream_id.clone(), tip);
+		Ok(())
+	}
+
+	async fn stream_ids(&self) -> anyhow::Result<Vec<StreamId>> {
+		Ok(self.tips.read().await.keys().cloned().collect())
+	}
+
+	async fn has_query(&self, id: &str) -> anyhow::Result<bool> {
+		Ok(self.queries.read().await.iter().any(|x| x == id))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::commit::example;
+
+	#[tokio::test]
+	async fn test_memory_tip_store() -> anyhow::Result<()> {
+		let genesis = example::genesis();
+		let stream_id = genesis.stream_id()?;
+		let store = MemoryTipStore::new();
+		assert_eq!(store.get_tip(&stream_id).await?, None);
+
+		store.set_tip(&stream_id, genesis.cid).await?;
+		assert_eq!(store.get_tip(&stream_id).await?, Some(genesis.cid));
+		assert_eq!(store.stream_ids().await?, vec![stream_id.clone()]);
+
+		store.add_query("query").await;
+		assert!(store.has_query("query").await?);
+		assert!(!store.has_query("other").await?);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_adapters_round_trip() -> anyhow::Result<()> {
+		let genesis = example::genesis();
+		let stream_id = genesis.stream_id()?;
+		let memory = Arc::new(MemoryTipStore::new());
+		memory.add_query("query").await;
+
+		let legacy = LegacyTipStore(Arc::new(StoreAdapter(memory.clone())));
+		legacy.set_tip(&stream_id, genesis.cid).await?;
+		assert_eq!(legacy.get_tip(&stream_id).await?, Some(genesis.cid));
+		assert!(legacy.has_query("query").await?);
+		assert!(!legacy.has_query("other").await?);
+		Ok(())
+	}
 }
@@ -1,17 +1,23 @@
-use ceramic_core::Cid;
+use ceramic_core::{Cid, StreamId};
 use fang::async_trait;
 use fang::asynk::async_queue::AsyncQueueable;
 use fang::serde::{Deserialize, Serialize};
 use fang::typetag;
 use fang::AsyncRunnable;
 use fang::FangError;
+use futures_util::StreamExt;
 use std::sync::OnceLock;
 
+use super::checkpoint::CheckpointTracker;
 use super::message::MessagePublisher;
-use super::{BlockUploader, Client};
+use super::{BlockUploader, Client, TipStore};
+use crate::{Ceramic, StreamLoader};
 
 static KUBO: OnceLock<Client> = OnceLock::new();
 
+/// Streams reconciled at once by a gap fill
+pub const RECONCILE_CONCURRENCY: usize = 8;
+
 pub fn init_kubo(base_path: &str) {
 	KUBO.get_or_init(|| super::new(base_path));
 }
@@ -92,3 +98,66 @@ impl AsyncRunnable for UpdateMessagePublishHandler {
 		true
 	}
 }
+
+/// Refresh the stored tip of a stream from the ceramic node, true if the
+/// stored tip was behind
+pub async fn reconcile_tip<L: StreamLoader + ?Sized>(
+	loader: &L,
+	store: &dyn TipStore,
+	ceramic: &Ceramic,
+	stream_id: &StreamId,
+) -> anyhow::Result<bool> {
+	let state = loader.load_stream_state(ceramic, stream_id, None).await?;
+	let tip = match state.commit_ids()?.last() {
+		Some(commit_id) => commit_id.tip,
+		None => anyhow::bail!("empty log of stream {}", stream_id),
+	};
+	if store.get_tip(stream_id).await? == Some(tip) {
+		return Ok(false);
+	}
+	store.set_tip(stream_id, tip).await?;
+	Ok(true)
+}
+
+/// Reconcile the stored streams once the tracker detected missed pubsub
+/// messages, as updates published meanwhile are not replayed. Run after
+/// consuming messages, e.g. on an interval. A gap is kept for the next run
+/// if a stream failed to reconcile. Returns the number of updated tips.
+pub async fn gap_fill<L: StreamLoader + ?Sized>(
+	loader: &L,
+	store: &dyn TipStore,
+	tracker: &CheckpointTracker,
+	ceramic: &Ceramic,
+) -> anyhow::Result<usize> {
+	if !tracker.take_gap().await {
+		return Ok(0);
+	}
+	let topic = ceramic.network.pubsub_topic();
+	let stream_ids = match store.stream_ids().await {
+		Ok(stream_ids) => stream_ids,
+		Err(err) => {
+			tracker.mark_gap().await;
+			return Err(err);
+		}
+	};
+	tracing::info!(topic, streams = stream_ids.len(), "gap fill");
+
+	let results: Vec<anyhow::Result<bool>> = futures_util::stream::iter(&stream_ids)
+		.map(|stream_id| async move {
+			let result = reconcile_tip(loader, store, ceramic, stream_id).await;
+			if let Err(err) = &result {
+				tracing::warn!(stream_id = stream_id.to_string(), ?err, "reconciling tip");
+			}
+			result
+		})
+		.buffer_unordered(RECONCILE_CONCURRENCY)
+		.collect()
+		.await;
+	if results.iter().any(|result| result.is_err()) {
+		tracker.mark_gap().await;
+	}
+	Ok(results
+		.into_iter()
+		.filter(|result| matches!(result, Ok(true)))
+		.count())
+}
@@ -1,8 +1,38 @@
+//! Ceramic streams and events of dataverse.
+//!
+//! Features:
+//! - `http` (default): js-ceramic and ceramic-one http api clients, the
+//!   minimal build
+//! - `graphql`: client of the graphql endpoint of ComposeDB servers
+//! - `kubo` (default): kubo rpc client with block cache, pubsub and queued
+//!   uploads, pulls in the swagger generated client and fang
+//! - `anchor-timestamp`: block timestamps of anchor transactions, requires eth
+//!   rpc
+//! - `redact-fields`: field formatter of `tracing_subscriber` redacting
+//!   sensitive fields, for the subscriber installed by the binary
+
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+#[cfg(not(feature = "http"))]
+compile_error!("dataverse-ceramic requires the `http` feature");
+
+pub mod ceramic_one;
+pub mod checkpoint;
+pub mod composite;
+pub mod deploy;
+pub mod diagnostics;
 pub mod did;
 pub mod event;
 pub mod http;
+pub mod interests;
+#[cfg(feature = "kubo")]
+#[cfg_attr(docsrs, doc(cfg(feature = "kubo")))]
 pub mod kubo;
+pub mod model;
 pub mod network;
+pub mod publisher;
+pub mod redact;
+pub mod retry;
 pub mod stream;
 
 pub use ceramic_core::StreamId;
@@ -11,16 +41,40 @@ pub use event::{Event, EventValue, EventsLoader, EventsUploader};
 use serde::{Deserialize, Serialize};
 pub use stream::*;
 
+/// Http api served by the node, events are loaded and uploaded through the
+/// matching client
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CeramicApi {
+	#[default]
+	JsCeramic,
+	CeramicOne,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Ceramic {
 	pub endpoint: String,
 	pub network: network::Network,
+	#[serde(default)]
+	pub api: CeramicApi,
 }
 
 impl Ceramic {
+	/// Connect to the node, detecting whether it serves the ceramic-one api
 	pub async fn new(endpoint: &str) -> anyhow::Result<Self> {
-		let network = http::Client::network(endpoint).await?;
+		if let Some(network) = ceramic_one::Client::new().detect(endpoint).await? {
+			return Ok(Self {
+				endpoint: endpoint.into(),
+				network,
+				api: CeramicApi::CeramicOne,
+			});
+		}
+		let network = http::Client::new().network(endpoint).await?;
 		let endpoint = endpoint.into();
-		Ok(Self { endpoint, network })
+		Ok(Self {
+			endpoint,
+			network,
+			api: CeramicApi::JsCeramic,
+		})
 	}
 }
@@ -0,0 +1,653 @@
+use std::collections::BTreeMap;
+
+use ceramic_core::StreamId;
+use ceramic_http_client::GetRootSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Version of model definitions supporting interfaces
+pub const MODEL_VERSION: &str = "2.0";
+
+#[derive(Debug)]
+pub enum ModelDefinitionError {
+	InvalidImplements(String),
+	UnknownField(String),
+	InterfaceNotAllowed,
+	/// view or relation and the model it references
+	InvalidModelId(String, String),
+	/// view whose property is not a document relation to its model
+	InvalidViewProperty(String, String),
+}
+
+impl std::fmt::Display for ModelDefinitionError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::InvalidImplements(id) => write!(f, "implemented model {} is not a stream id", id),
+			Self::UnknownField(field) => write!(f, "field {} not in model schema", field),
+			Self::InterfaceNotAllowed => {
+				write!(f, "interfaces require model version {}", MODEL_VERSION)
+			}
+			Self::InvalidModelId(name, model) => {
+				write!(f, "{} references {} which is not a stream id", name, model)
+			}
+			Self::InvalidViewProperty(view, property) => write!(
+				f,
+				"property {} of view {} is not a document relation to its model",
+				property, view
+			),
+		}
+	}
+}
+
+impl std::error::Error for ModelDefinitionError {}
+
+#[derive(Debug)]
+pub enum SchemaPathError {
+	PathNotAllowed(String),
+	/// path and the types the schema allows there
+	TypeMismatch(String, String),
+}
+
+impl std::fmt::Display for SchemaPathError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::PathNotAllowed(path) => write!(f, "path {} not allowed by model schema", path),
+			Self::TypeMismatch(path, types) => write!(f, "value at {} is not {}", path, types),
+		}
+	}
+}
+
+impl std::error::Error for SchemaPathError {}
+
+/// Local `$ref`s followed at most this many times, so cyclic refs end
+const MAX_REF_DEPTH: usize = 32;
+
+/// Schema a local `$ref` of the root schema points to
+fn resolve<'a>(root: &'a Value, mut schema: &'a Value) -> &'a Value {
+	for _ in 0..MAX_REF_DEPTH {
+		let target = schema
+			.get("$ref")
+			.and_then(Value::as_str)
+			.and_then(|reference| reference.strip_prefix('#'))
+			.and_then(|pointer| root.pointer(pointer));
+		match target {
+			Some(target) => schema = target,
+			None => break,
+		}
+	}
+	schema
+}
+
+/// Branches of the schema after following its `$ref`, one per `anyOf` branch.
+/// Keywords beside `anyOf` are not considered
+fn branches<'a>(root: &'a Value, schema: &'a Value) -> Vec<&'a Value> {
+	let mut branches = vec![];
+	let mut pending = vec![(resolve(root, schema), 0)];
+	while let Some((schema, depth)) = pending.pop() {
+		match schema.get("anyOf").and_then(Value::as_array) {
+			Some(any_of) if depth < MAX_REF_DEPTH => pending.extend(
+				any_of
+					.iter()
+					.rev()
+					.map(|branch| (resolve(root, branch), depth + 1)),
+			),
+			Some(_) => {}
+			None => branches.push(schema),
+		}
+	}
+	branches
+}
+
+/// Types of the schema, `None` if it does not constrain the type
+fn types(schema: &Value) -> Option<Vec<&str>> {
+	match schema.get("type") {
+		Some(Value::String(r#type)) => Some(vec![r#type]),
+		Some(Value::Array(types)) => Some(types.iter().filter_map(Value::as_str).collect()),
+		_ => None,
+	}
+}
+
+fn is_scalar(schema: &Value) -> bool {
+	types(schema).map_or(false, |types| {
+		!types.is_empty()
+			&& types.iter().all(|r#type| {
+				matches!(
+					*r#type,
+					"string" | "number" | "integer" | "boolean" | "null"
+				)
+			})
+	})
+}
+
+enum Step<'a> {
+	Schema(&'a Value),
+	Unconstrained,
+	NotAllowed,
+}
+
+/// Schema of the child `token` of a value of the schema
+fn step<'a>(schema: &'a Value, token: &str) -> Step<'a> {
+	if is_scalar(schema) {
+		return Step::NotAllowed;
+	}
+	match schema.get("items") {
+		Some(items) => match token == "-" || token.parse::<usize>().is_ok() {
+			true => Step::Schema(items),
+			false => Step::NotAllowed,
+		},
+		None => match schema.get("properties").and_then(|x| x.get(token)) {
+			Some(property) => Step::Schema(property),
+			None => match schema.get("additionalProperties") {
+				Some(Value::Bool(false)) => Step::NotAllowed,
+				Some(additional) if additional.is_object() => Step::Schema(additional),
+				_ => Step::Unconstrained,
+			},
+		},
+	}
+}
+
+/// Schemas the value at the json pointer may match, one per `anyOf` branch
+/// allowing the path. `None` where a branch leaves the value unconstrained
+pub fn schema_at<'a>(
+	root: &'a Value,
+	pointer: &str,
+) -> Result<Option<Vec<&'a Value>>, SchemaPathError> {
+	let mut schemas = branches(root, root);
+	for token in pointer.split('/').skip(1) {
+		let token = token.replace("~1", "/").replace("~0", "~");
+		let mut next = vec![];
+		for schema in schemas {
+			match step(schema, &token) {
+				Step::Schema(schema) => next.extend(branches(root, schema)),
+				Step::Unconstrained => return Ok(None),
+				Step::NotAllowed => {}
+			}
+		}
+		if next.is_empty() {
+			return Err(SchemaPathError::PathNotAllowed(pointer.to_string()));
+		}
+		schemas = next;
+	}
+	Ok(Some(schemas))
+}
+
+fn type_matches(value: &Value, r#type: &str) -> bool {
+	match r#type {
+		"string" => value.is_string(),
+		"number" => value.is_number(),
+		// json does not tell 3 from 3.0, integral floats are integers
+		"integer" => value.as_f64().map_or(false, |x| x.fract() == 0.0),
+		"boolean" => value.is_boolean(),
+		"object" => value.is_object(),
+		"array" => value.is_array(),
+		"null" => value.is_null(),
+		_ => true,
+	}
+}
+
+/// Check the path is allowed by the schema and the type of the value written
+/// there, nested values are not checked. The value has to match the type of
+/// one `anyOf` branch
+pub fn validate_value_at(
+	root: &Value,
+	pointer: &str,
+	value: &Value,
+) -> Result<(), SchemaPathError> {
+	let schemas = match schema_at(root, pointer)? {
+		Some(schemas) => schemas,
+		None => return Ok(()),
+	};
+	let mut expected: Vec<&str> = vec![];
+	for schema in schemas {
+		let allowed = match types(schema) {
+			Some(allowed) => allowed,
+			None => return Ok(()),
+		};
+		if allowed.iter().any(|r#type| type_matches(value, r#type)) {
+			return Ok(());
+		}
+		for r#type in allowed {
+			if !expected.contains(&r#type) {
+				expected.push(r#type);
+			}
+		}
+	}
+	Err(SchemaPathError::TypeMismatch(
+		pointer.to_string(),
+		expected.join(" or "),
+	))
+}
+
+/// Check every path of the instance content against the schema of its model
+pub fn validate_instance(root: &Value, content: &Value) -> Result<(), SchemaPathError> {
+	validate_tree(root, "", content)
+}
+
+fn validate_tree(root: &Value, pointer: &str, value: &Value) -> Result<(), SchemaPathError> {
+	validate_value_at(root, pointer, value)?;
+	match value {
+		Value::Object(fields) => {
+			for (key, value) in fields {
+				let token = key.replace('~', "~0").replace('/', "~1");
+				validate_tree(root, &format!("{}/{}", pointer, token), value)?;
+			}
+		}
+		Value::Array(items) => {
+			for (index, item) in items.iter().enumerate() {
+				validate_tree(root, &format!("{}/{}", pointer, index), item)?;
+			}
+		}
+		_ => {}
+	}
+	Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ModelAccountRelation {
+	List,
+	Single,
+	/// one instance per account and values of the fields
+	Set {
+		fields: Vec<String>,
+	},
+	/// interfaces have no instances
+	None,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ModelViewDefinition {
+	DocumentAccount,
+	DocumentVersion,
+	/// document the relation `property` of this model points to
+	RelationDocument {
+		model: String,
+		property: String,
+	},
+	/// instances of `model` whose relation `property` points to this document
+	RelationFrom {
+		model: String,
+		property: String,
+	},
+	/// number of instances of `model` whose relation `property` points to
+	/// this document
+	RelationCountFrom {
+		model: String,
+		property: String,
+	},
+}
+
+impl ModelViewDefinition {
+	/// Model referenced by a relation view
+	pub fn model(&self) -> Option<&str> {
+		match self {
+			Self::DocumentAccount | Self::DocumentVersion => None,
+			Self::RelationDocument { model, .. }
+			| Self::RelationFrom { model, .. }
+			| Self::RelationCountFrom { model, .. } => Some(model),
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ModelRelationDefinition {
+	Account,
+	/// the property holds the stream id of an instance of `model`
+	Document {
+		model: String,
+	},
+}
+
+/// Content of a model stream as js-ceramic creates it, mirroring the
+/// definition of ceramic-http-client with the fields of version 2 models
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelDefinition {
+	pub version: String,
+	pub name: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub description: Option<String>,
+	/// interfaces are implemented by other models instead of holding instances
+	#[serde(default)]
+	pub interface: bool,
+	/// ids of the interfaces the model implements
+	#[serde(default)]
+	pub implements: Vec<String>,
+	pub schema: Value,
+	pub account_relation: ModelAccountRelation,
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub relations: BTreeMap<String, ModelRelationDefinition>,
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub views: BTreeMap<String, ModelViewDefinition>,
+	/// fields which cannot be changed once the instance is created
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub immutable_fields: Vec<String>,
+}
+
+impl ModelDefinition {
+	pub fn new<T: GetRootSchema>(
+		name: &str,
+		account_relation: ModelAccountRelation,
+	) -> anyhow::Result<Self> {
+		Ok(Self {
+			version: MODEL_VERSION.to_string(),
+			name: name.to_string(),
+			description: None,
+			interface: false,
+			implements: vec![],
+			schema: serde_json::to_value(T::root_schema())?,
+			account_relation,
+			relations: BTreeMap::new(),
+			views: BTreeMap::new(),
+			immutable_fields: vec![],
+		})
+	}
+
+	pub fn with_description(self, description: &str) -> Self {
+		Self {
+			description: Some(description.to_string()),
+			..self
+		}
+	}
+
+	/// Turn the model into an interface, which has no instances
+	pub fn as_interface(self) -> Self {
+		Self {
+			interface: true,
+			account_relation: ModelAccountRelation::None,
+			..self
+		}
+	}
+
+	pub fn implements(mut self, interface_id: &StreamId) -> Self {
+		self.implements.push(interface_id.to_string());
+		self
+	}
+
+	pub fn with_view(mut self, name: &str, view: ModelViewDefinition) -> Self {
+		self.views.insert(name.to_string(), view);
+		self
+	}
+
+	pub fn with_relation(mut self, property: &str, relation: ModelRelationDefinition) -> Self {
+		self.relations.insert(property.to_string(), relation);
+		self
+	}
+
+	pub fn with_immutable_fields(self, fields: &[&str]) -> Self {
+		Self {
+			immutable_fields: fields.iter().map(|x| x.to_string()).collect(),
+			..self
+		}
+	}
+
+	/// Properties of the schema, empty for a schema without properties
+	pub fn properties(&self) -> Vec<&str> {
+		match self.schema.get("properties").and_then(Value::as_object) {
+			Some(properties) => properties.keys().map(String::as_str).collect(),
+			None => vec![],
+		}
+	}
+
+	/// Fields no data commit may patch, the immutable fields and the
+	/// properties the schema annotates `readOnly`
+	pub fn locked_fields(&self) -> Vec<String> {
+		let mut fields = self.immutable_fields.clone();
+		if let Some(properties) = self.schema.get("properties").and_then(Value::as_object) {
+			for (name, property) in properties {
+				let read_only = property.get("readOnly").and_then(Value::as_bool);
+				if read_only == Some(true) && !fields.contains(name) {
+					fields.push(name.clone());
+				}
+			}
+		}
+		fields
+	}
+
+	pub fn validate(&self) -> anyhow::Result<()> {
+		if (self.interface || !self.implements.is_empty()) && self.version != MODEL_VERSION {
+			anyhow::bail!(ModelDefinitionError::InterfaceNotAllowed);
+		}
+		for id in &self.implements {
+			if id.parse::<StreamId>().is_err() {
+				anyhow::bail!(ModelDefinitionError::InvalidImplements(id.clone()));
+			}
+		}
+		let properties = self.properties();
+		for field in &self.immutable_fields {
+			if !properties.contains(&field.as_str()) {
+				anyhow::bail!(ModelDefinitionError::UnknownField(field.clone()));
+			}
+		}
+		for (property, relation) in &self.relations {
+			if !properties.contains(&property.as_str()) {
+				anyhow::bail!(ModelDefinitionError::UnknownField(property.clone()));
+			}
+			if let ModelRelationDefinition::Document { model } = relation {
+				if model.parse::<StreamId>().is_err() {
+					anyhow::bail!(ModelDefinitionError::InvalidModelId(
+						property.clone(),
+						model.clone()
+					));
+				}
+			}
+		}
+		for (name, view) in &self.views {
+			if let Some(model) = view.model() {
+				if model.parse::<StreamId>().is_err() {
+					anyhow::bail!(ModelDefinitionError::InvalidModelId(
+						name.clone(),
+						model.to_string()
+					));
+				}
+			}
+			// the property of reverse relations is on the other model, only
+			// forward relations can be checked against this definition
+			if let ModelViewDefinition::RelationDocument { model, property } = view {
+				let relation = ModelRelationDefinition::Document {
+					model: model.clone(),
+				};
+				if self.relations.get(property) != Some(&relation) {
+					anyhow::bail!(ModelDefinitionError::InvalidViewProperty(
+						name.clone(),
+						property.clone()
+					));
+				}
+			}
+		}
+		Ok(())
+	}
+}
+
+impl TryFrom<&ceramic_http_client::ModelDefinition> for ModelDefinition {
+	type Error = anyhow::Error;
+
+	fn try_from(value: &ceramic_http_client::ModelDefinition) -> Result<Self, Self::Error> {
+		Ok(serde_json::from_value(serde_json::to_value(value)?)?)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ceramic_http_client::schemars::JsonSchema;
+
+	#[derive(JsonSchema)]
+	#[schemars(crate = "ceramic_http_client::schemars")]
+	#[allow(dead_code)]
+	struct Post {
+		text: String,
+		author: String,
+	}
+
+	impl GetRootSchema for Post {}
+
+	#[test]
+	fn test_model_definition() -> anyhow::Result<()> {
+		let interface_id: StreamId =
+			"kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju".parse()?;
+		let definition = ModelDefinition::new::<Post>("post", ModelAccountRelation::List)?
+			.implements(&interface_id)
+			.with_view("author", ModelViewDefinition::DocumentAccount)
+			.with_immutable_fields(&["author"]);
+		definition.validate()?;
+
+		let value = serde_json::to_value(&definition)?;
+		assert_eq!(value["version"], MODEL_VERSION);
+		assert_eq!(value["interface"], false);
+		assert_eq!(value["implements"][0], interface_id.to_string());
+		assert_eq!(value["immutableFields"][0], "author");
+		assert_eq!(value["views"]["author"]["type"], "documentAccount");
+		assert_eq!(value["accountRelation"]["type"], "list");
+		assert_eq!(
+			serde_json::from_value::<ModelDefinition>(value)?,
+			definition
+		);
+
+		let interface =
+			ModelDefinition::new::<Post>("post", ModelAccountRelation::List)?.as_interface();
+		assert_eq!(interface.account_relation, ModelAccountRelation::None);
+		assert!(definition
+			.clone()
+			.with_immutable_fields(&["title"])
+			.validate()
+			.is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_relation_views() -> anyhow::Result<()> {
+		let post_id = "kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju";
+		let relation = ModelRelationDefinition::Document {
+			model: post_id.to_string(),
+		};
+		let view = ModelViewDefinition::RelationDocument {
+			model: post_id.to_string(),
+			property: "text".to_string(),
+		};
+		let comment = ModelDefinition::new::<Post>("comment", ModelAccountRelation::List)?
+			.with_relation("text", relation)
+			.with_view("post", view.clone())
+			.with_view(
+				"replies",
+				ModelViewDefinition::RelationCountFrom {
+					model: post_id.to_string(),
+					property: "replyTo".to_string(),
+				},
+			);
+		comment.validate()?;
+		let value = serde_json::to_value(&comment)?;
+		assert_eq!(
+			value["views"]["post"],
+			serde_json::json!({ "type": "relationDocument", "model": post_id, "property": "text" })
+		);
+		assert_eq!(value["relations"]["text"]["type"], "document");
+
+		let unrelated = ModelDefinition::new::<Post>("comment", ModelAccountRelation::List)?
+			.with_view("post", view);
+		assert!(unrelated.validate().is_err());
+		let invalid = comment.with_view(
+			"author",
+			ModelViewDefinition::RelationFrom {
+				model: "post".to_string(),
+				property: "author".to_string(),
+			},
+		);
+		assert!(invalid.validate().is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_validate_value_at() {
+		let schema = serde_json::json!({
+			"type": "object",
+			"additionalProperties": false,
+			"properties": {
+				"text": { "type": "string" },
+				"tags": { "type": "array", "items": { "type": "string" } },
+				"meta": { "$ref": "#/$defs/Meta" },
+			},
+			"$defs": {
+				"Meta": {
+					"type": "object",
+					"additionalProperties": false,
+					"properties": { "views": { "type": "integer" } },
+				},
+			},
+		});
+		let ok = |path: &str, value: Value| validate_value_at(&schema, path, &value).is_ok();
+		assert!(ok("/text", Value::from("hello")));
+		assert!(ok("/tags/-", Value::from("rust")));
+		assert!(ok("/meta/views", Value::from(3)));
+		assert!(!ok("/text", Value::from(1)));
+		assert!(!ok("/foo/bar/baz", Value::from(1)));
+		assert!(!ok("/meta/likes", Value::from(1)));
+		assert!(!ok("/text/0", Value::from("h")));
+		assert!(!ok("/tags/first", Value::from("rust")));
+	}
+
+	#[test]
+	fn test_validate_any_of() {
+		let schema = serde_json::json!({
+			"type": "object",
+			"additionalProperties": false,
+			"properties": {
+				"count": { "type": ["integer", "null"] },
+				"meta": { "anyOf": [{ "$ref": "#/$defs/Meta" }, { "type": "null" }] },
+				"scalar": { "type": ["string", "number"] },
+			},
+			"$defs": {
+				"Meta": {
+					"type": "object",
+					"additionalProperties": false,
+					"properties": { "views": { "type": "integer" } },
+				},
+			},
+		});
+		let ok = |path: &str, value: Value| validate_value_at(&schema, path, &value).is_ok();
+		assert!(ok("/count", serde_json::json!(3.0)));
+		assert!(ok("/count", Value::Null));
+		assert!(!ok("/count", serde_json::json!(3.5)));
+		assert!(!ok("/count", Value::from("3")));
+		assert!(ok("/meta", Value::Null));
+		assert!(ok("/meta", serde_json::json!({ "views": 1 })));
+		assert!(!ok("/meta", Value::from(1)));
+		assert!(ok("/meta/views", Value::from(1)));
+		assert!(!ok("/meta/likes", Value::from(1)));
+		assert!(!ok("/scalar/0", Value::from(1)));
+		assert!(matches!(
+			validate_value_at(&schema, "/meta", &Value::from(1)),
+			Err(SchemaPathError::TypeMismatch(_, types)) if types == "object or null"
+		));
+	}
+
+	#[test]
+	fn test_validate_instance() {
+		let schema = serde_json::json!({
+			"type": "object",
+			"additionalProperties": false,
+			"properties": {
+				"text": { "type": "string" },
+				"tags": { "type": "array", "items": { "type": "string" } },
+			},
+		});
+		let ok = |content: Value| validate_instance(&schema, &content).is_ok();
+		assert!(ok(serde_json::json!({ "text": "hello", "tags": ["rust"] })));
+		assert!(!ok(serde_json::json!({ "text": 1 })));
+		assert!(!ok(serde_json::json!({ "tags": ["rust", 2] })));
+		assert!(!ok(serde_json::json!({ "text": "hello", "likes": 3 })));
+		assert!(!ok(serde_json::json!([])));
+	}
+
+	#[test]
+	fn test_locked_fields() -> anyhow::Result<()> {
+		let mut definition = ModelDefinition::new::<Post>("post", ModelAccountRelation::List)?
+			.with_immutable_fields(&["author"]);
+		definition.schema["properties"]["text"]["readOnly"] = Value::Bool(true);
+		definition.schema["properties"]["author"]["readOnly"] = Value::Bool(true);
+		assert_eq!(definition.locked_fields(), vec!["author", "text"]);
+		Ok(())
+	}
+}
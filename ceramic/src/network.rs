@@ -1,10 +1,12 @@
-use std::{collections::HashMap, str::FromStr, sync::Arc};
+use std::{collections::HashMap, num::NonZeroUsize, str::FromStr, sync::Arc};
 
 use anyhow::Context;
+use dataverse_multibase::MultiBase64UrlString;
 use ethers_core::types::{Block, Transaction};
 use ethers_providers::{Http, Middleware, Provider};
 use futures_util::FutureExt;
 use int_enum::IntEnum;
+use lru::LruCache;
 use once_cell::sync::Lazy;
 use primitive_types::H256;
 use serde::{Deserialize, Serialize};
@@ -78,6 +80,24 @@ impl From<ceramic_core::Network> for Network {
 	}
 }
 
+impl FromStr for Network {
+	type Err = anyhow::Error;
+
+	/// Parse the name of the network, as in [`Network::name`]
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"mainnet" => Ok(Network::Mainnet),
+			"testnet-clay" => Ok(Network::TestnetClay),
+			"dev-unstable" => Ok(Network::DevUnstable),
+			"inmemory" => Ok(Network::InMemory),
+			_ => match s.strip_prefix("local-").map(str::parse) {
+				Some(Ok(id)) => Ok(Network::Local(id)),
+				_ => anyhow::bail!("invalid network {}", s),
+			},
+		}
+	}
+}
+
 impl Network {
 	pub fn public(&self) -> bool {
 		match self {
@@ -89,18 +109,22 @@ impl Network {
 		}
 	}
 
+	pub fn name(&self) -> String {
+		match self {
+			Network::Mainnet => "mainnet".to_string(),
+			Network::TestnetClay => "testnet-clay".to_string(),
+			Network::DevUnstable => "dev-unstable".to_string(),
+			Network::Local(i) => format!("local-{}", i),
+			Network::InMemory => "inmemory".to_string(),
+		}
+	}
+
 	pub fn kubo_topic(&self) -> String {
-		multibase::encode(multibase::Base::Base64Url, self.pubsub_topic())
+		MultiBase64UrlString::encode(self.pubsub_topic()).into()
 	}
 
 	pub fn pubsub_topic(&self) -> String {
-		match self {
-			Network::Mainnet => "/ceramic/mainnet".to_string(),
-			Network::TestnetClay => "/ceramic/testnet-clay".to_string(),
-			Network::DevUnstable => "/ceramic/dev-unstable".to_string(),
-			Network::Local(i) => format!("/ceramic/local-{}", i),
-			Network::InMemory => "/ceramic/inmemory".to_owned(),
-		}
+		format!("/ceramic/{}", self.name())
 	}
 
 	pub fn chain(&self) -> Chain {
@@ -214,14 +238,29 @@ impl ProviderMiddleware {
 	}
 }
 
+/// Anchor transactions whose block timestamps are kept, the least recently
+/// used are evicted past it
+pub const TIMESTAMP_CACHE_SIZE: usize = 10_000;
+
+static TIMESTAMP_STORE: Lazy<Mutex<LruCache<H256, i64>>> = Lazy::new(|| {
+	let cap = NonZeroUsize::new(TIMESTAMP_CACHE_SIZE).expect("cache size is not zero");
+	Mutex::new(LruCache::new(cap))
+});
+
+/// Timestamp of the block including the anchor transaction
 pub async fn timestamp(proof: AnchorProof) -> anyhow::Result<i64> {
-	let provider = provider(proof.chain()?).await?;
 	let tx_hash = proof.tx_hash()?;
+	if let Some(timestamp) = TIMESTAMP_STORE.lock().await.get(&tx_hash) {
+		return Ok(*timestamp);
+	}
+	let provider = provider(proof.chain()?).await?;
 
 	let tx = provider.get_transaction(tx_hash).await?;
 	let block_hash = tx.block_hash.context("no block hash")?;
 	let block = provider.get_block(block_hash).await?;
-	Ok(block.timestamp.as_u64() as i64)
+	let timestamp = block.timestamp.as_u64() as i64;
+	TIMESTAMP_STORE.lock().await.put(tx_hash, timestamp);
+	Ok(timestamp)
 }
 
 #[cfg(test)]
@@ -230,6 +269,15 @@ mod tests {
 
 	use super::*;
 
+	#[test]
+	fn test_network_name() -> anyhow::Result<()> {
+		for network in [Network::Mainnet, Network::Local(3), Network::InMemory] {
+			assert_eq!(network.name().parse::<Network>()?.name(), network.name());
+		}
+		assert!("local-x".parse::<Network>().is_err());
+		Ok(())
+	}
+
 	#[tokio::test]
 	async fn test_chain_id() {
 		let chain = Chain::EthereumMainnet;
@@ -0,0 +1,227 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use ceramic_core::{Cid, StreamId};
+use chrono::{DateTime, Utc};
+use ethers_core::abi::{self, Token};
+use ethers_core::types::{Address, Bytes, TransactionRequest, H256, U64};
+use ethers_core::utils::id;
+use ethers_providers::{Http, Middleware, Provider};
+use serde::{Deserialize, Serialize};
+
+/// Function of the attestation contract receiving proven computations
+pub const ATTEST_SIGNATURE: &str = "attest(bytes,bytes)";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublisherConfig {
+	/// rpc signing with `from`, e.g. a node with an unlocked account or a
+	/// signer proxy
+	pub rpc: String,
+	pub contract: Address,
+	pub from: Address,
+	/// blocks including and following the transaction before it is confirmed
+	pub confirmations: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AttestationStatus {
+	Pending,
+	Confirmed,
+	Failed,
+}
+
+impl AttestationStatus {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Self::Pending => "pending",
+			Self::Confirmed => "confirmed",
+			Self::Failed => "failed",
+		}
+	}
+}
+
+impl FromStr for AttestationStatus {
+	type Err = anyhow::Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"pending" => Ok(Self::Pending),
+			"confirmed" => Ok(Self::Confirmed),
+			"failed" => Ok(Self::Failed),
+			_ => anyhow::bail!("invalid attestation status {}", s),
+		}
+	}
+}
+
+/// Receipt of a proven computation over a commit of a stream
+#[derive(Debug, Clone)]
+pub struct Attestation {
+	pub stream_id: StreamId,
+	pub commit: Cid,
+	pub journal: Vec<u8>,
+	pub seal: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttestationRecord {
+	pub tx_hash: H256,
+	pub chain_id: u64,
+	pub contract: Address,
+	pub stream_id: StreamId,
+	pub commit: Cid,
+	pub status: AttestationStatus,
+	pub block_number: Option<u64>,
+	pub created_at: DateTime<Utc>,
+	pub updated_at: DateTime<Utc>,
+}
+
+/// Mapping of attestation transactions to the attested stream commits
+#[async_trait::async_trait]
+pub trait AttestationStore: Send + Sync {
+	/// Insert the record or update the one of its transaction
+	async fn save_attestation(&self, record: &AttestationRecord) -> anyhow::Result<()>;
+
+	async fn load_attestation(&self, tx_hash: &H256) -> anyhow::Result<Option<AttestationRecord>>;
+
+	/// Attestations of the stream, of `commit` only if given
+	async fn load_attestations(
+		&self,
+		stream_id: &StreamId,
+		commit: Option<&Cid>,
+	) -> anyhow::Result<Vec<AttestationRecord>>;
+}
+
+pub fn attest_calldata(journal: &[u8], seal: &[u8]) -> Bytes {
+	let mut data = id(ATTEST_SIGNATURE).to_vec();
+	data.extend(abi::encode(&[
+		Token::Bytes(journal.to_vec()),
+		Token::Bytes(seal.to_vec()),
+	]));
+	data.into()
+}
+
+#[async_trait::async_trait]
+pub trait AttestationPublisher: Send + Sync {
+	/// Submit the attestation, recorded as pending until its transaction is
+	/// confirmed
+	async fn publish(&self, attestation: &Attestation) -> anyhow::Result<AttestationRecord>;
+}
+
+/// Submits attestations to the contract and follows their transactions
+pub struct Publisher {
+	config: PublisherConfig,
+	provider: Provider<Http>,
+	chain_id: u64,
+	store: Arc<dyn AttestationStore>,
+}
+
+impl Publisher {
+	pub async fn new(
+		config: PublisherConfig,
+		store: Arc<dyn AttestationStore>,
+	) -> anyhow::Result<Self> {
+		let provider = Provider::<Http>::try_from(config.rpc.as_str())?;
+		let chain_id = provider.get_chainid().await?.as_u64();
+		Ok(Self {
+			config,
+			provider,
+			chain_id,
+			store,
+		})
+	}
+
+	/// Update the status of a pending attestation from its receipt
+	pub async fn refresh(&self, tx_hash: &H256) -> anyhow::Result<AttestationRecord> {
+		let mut record = match self.store.load_attestation(tx_hash).await? {
+			Some(record) => record,
+			None => anyhow::bail!("attestation {:?} not found", tx_hash),
+		};
+		if record.status != AttestationStatus::Pending {
+			return Ok(record);
+		}
+
+		if let Some(receipt) = self.provider.get_transaction_receipt(*tx_hash).await? {
+			record.block_number = receipt.block_number.map(|x| x.as_u64());
+			if receipt.status == Some(U64::zero()) {
+				record.status = AttestationStatus::Failed;
+			} else if let Some(block_number) = record.block_number {
+				let current = self.provider.get_block_number().await?.as_u64();
+				if current + 1 >= block_number + self.config.confirmations {
+					record.status = AttestationStatus::Confirmed;
+				}
+			}
+		}
+		record.updated_at = Utc::now();
+		self.store.save_attestation(&record).await?;
+		Ok(record)
+	}
+}
+
+#[async_trait::async_trait]
+impl AttestationPublisher for Publisher {
+	async fn publish(&self, attestation: &Attestation) -> anyhow::Result<AttestationRecord> {
+		let tx = TransactionRequest::new()
+			.from(self.config.from)
+			.to(self.config.contract)
+			.data(attest_calldata(&attestation.journal, &attestation.seal));
+		let tx_hash = self.provider.send_transaction(tx, None).await?.tx_hash();
+		tracing::info!(
+			stream_id = attestation.stream_id.to_string(),
+			commit = attestation.commit.to_string(),
+			tx_hash = format!("{:?}", tx_hash),
+			"submitted attestation"
+		);
+
+		let now = Utc::now();
+		let record = AttestationRecord {
+			tx_hash,
+			chain_id: self.chain_id,
+			contract: self.config.contract,
+			stream_id: attestation.stream_id.clone(),
+			commit: attestation.commit,
+			status: AttestationStatus::Pending,
+			block_number: None,
+			created_at: now,
+			updated_at: now,
+		};
+		self.store.save_attestation(&record).await?;
+		Ok(record)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use ethers_core::abi::ParamType;
+
+	use super::*;
+
+	#[test]
+	fn test_attest_calldata() -> anyhow::Result<()> {
+		let data = attest_calldata(b"journal", b"seal");
+		assert_eq!(data[..4], id(ATTEST_SIGNATURE));
+		let tokens = abi::decode(&[ParamType::Bytes, ParamType::Bytes], &data[4..])?;
+		assert_eq!(
+			tokens,
+			vec![
+				Token::Bytes(b"journal".to_vec()),
+				Token::Bytes(b"seal".to_vec())
+			]
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_attestation_status() -> anyhow::Result<()> {
+		for status in [
+			AttestationStatus::Pending,
+			AttestationStatus::Confirmed,
+			AttestationStatus::Failed,
+		] {
+			assert_eq!(status.as_str().parse::<AttestationStatus>()?, status);
+		}
+		assert!("unknown".parse::<AttestationStatus>().is_err());
+		Ok(())
+	}
+}
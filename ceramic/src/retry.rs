@@ -0,0 +1,330 @@
+use std::future::Future;
+use std::time::Duration;
+
+use crate::did::DidError;
+use crate::event::errors::{CommitError, EventError, JwsError, SignedValueError};
+use crate::http::HttpError;
+
+/// Whether an operation failing with an error is worth repeating
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+	/// transient, e.g. timeouts, connection resets, 5xx
+	Retryable,
+	/// the same input fails again, e.g. invalid commits, 4xx
+	Fatal,
+	/// the remote asked to slow down
+	RateLimited,
+}
+
+/// Status returned by a remote service, kept on the error chain so callers
+/// can classify it after it crossed an `anyhow` boundary
+#[derive(Debug)]
+pub struct StatusError {
+	pub status: u16,
+	pub message: String,
+}
+
+impl StatusError {
+	pub fn new(status: u16, message: impl Into<String>) -> Self {
+		Self {
+			status,
+			message: message.into(),
+		}
+	}
+}
+
+impl std::fmt::Display for StatusError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "status {}: {}", self.status, self.message)
+	}
+}
+
+impl std::error::Error for StatusError {}
+
+pub trait Classify {
+	fn classify(&self) -> ErrorClass;
+}
+
+fn classify_status(status: u16) -> ErrorClass {
+	match status {
+		429 => ErrorClass::RateLimited,
+		408 | 500..=599 => ErrorClass::Retryable,
+		_ => ErrorClass::Fatal,
+	}
+}
+
+fn classify_io(err: &std::io::Error) -> ErrorClass {
+	use std::io::ErrorKind::*;
+	match err.kind() {
+		NotFound | PermissionDenied | InvalidInput | InvalidData | Unsupported => ErrorClass::Fatal,
+		_ => ErrorClass::Retryable,
+	}
+}
+
+/// Classified by the first typed error found on the chain, unknown errors are
+/// retryable as before the classification existed
+impl Classify for anyhow::Error {
+	fn classify(&self) -> ErrorClass {
+		for cause in self.chain() {
+			if let Some(err) = cause.downcast_ref::<StatusError>() {
+				return classify_status(err.status);
+			}
+			if let Some(err) = cause.downcast_ref::<std::io::Error>() {
+				return classify_io(err);
+			}
+			if cause.is::<tokio::time::error::Elapsed>() {
+				return ErrorClass::Retryable;
+			}
+			if let Some(err) = cause.downcast_ref::<HttpError>() {
+				return match err {
+					HttpError::StreamLoadError => ErrorClass::Retryable,
+					_ => ErrorClass::Fatal,
+				};
+			}
+			if cause.is::<EventError>()
+				|| cause.is::<CommitError>()
+				|| cause.is::<JwsError>()
+				|| cause.is::<SignedValueError>()
+				|| cause.is::<DidError>()
+				|| cause.is::<serde_json::Error>()
+			{
+				return ErrorClass::Fatal;
+			}
+		}
+		ErrorClass::Retryable
+	}
+}
+
+/// Whether the remote answered that the requested stream or event does not
+/// exist, as opposed to failing to look it up. js-ceramic answers a stream it
+/// cannot find without a state, see [`HttpError::StreamLoadError`]
+pub fn is_not_found(err: &anyhow::Error) -> bool {
+	// the http error is attached as context, found by anyhow but not on the
+	// chain of sources
+	if matches!(
+		err.downcast_ref::<HttpError>(),
+		Some(HttpError::StreamLoadError)
+	) {
+		return true;
+	}
+	err.chain().any(|cause| {
+		matches!(cause.downcast_ref::<StatusError>(), Some(err) if err.status == 404)
+			|| matches!(
+				cause.downcast_ref::<std::io::Error>(),
+				Some(err) if err.kind() == std::io::ErrorKind::NotFound
+			)
+	})
+}
+
+/// Exponential backoff bounded by `max_delay`, giving up on fatal errors
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+	pub max_retries: u32,
+	pub base_delay: Duration,
+	pub max_delay: Duration,
+	/// delay multiplier of rate limited attempts
+	pub rate_limit_factor: u32,
+	/// fraction of each delay randomly cut, spreading retries of clients
+	/// failing together
+	pub jitter: f64,
+	/// statuses retried, other statuses are fatal. Without it statuses are
+	/// classified as usual
+	pub retry_on: Option<Vec<u16>>,
+}
+
+impl Default for RetryPolicy {
+	fn default() -> Self {
+		Self {
+			max_retries: 3,
+			base_delay: Duration::from_millis(200),
+			max_delay: Duration::from_secs(10),
+			rate_limit_factor: 5,
+			jitter: 0.0,
+			retry_on: None,
+		}
+	}
+}
+
+impl RetryPolicy {
+	pub fn with_max_retries(self, max_retries: u32) -> Self {
+		Self {
+			max_retries,
+			..self
+		}
+	}
+
+	pub fn with_jitter(self, jitter: f64) -> Self {
+		Self {
+			jitter: jitter.clamp(0.0, 1.0),
+			..self
+		}
+	}
+
+	pub fn with_retry_on(self, statuses: Vec<u16>) -> Self {
+		Self {
+			retry_on: Some(statuses),
+			..self
+		}
+	}
+
+	/// Class of the error, statuses checked against `retry_on` if set
+	pub fn classify(&self, err: &anyhow::Error) -> ErrorClass {
+		let statuses = match &self.retry_on {
+			Some(statuses) => statuses,
+			None => return err.classify(),
+		};
+		let status = err
+			.chain()
+			.find_map(|cause| cause.downcast_ref::<StatusError>());
+		match status {
+			Some(status) if statuses.contains(&status.status) => {
+				match classify_status(status.status) {
+					ErrorClass::RateLimited => ErrorClass::RateLimited,
+					_ => ErrorClass::Retryable,
+				}
+			}
+			Some(_) => ErrorClass::Fatal,
+			None => err.classify(),
+		}
+	}
+
+	/// delay before retry `attempt` (starting at 1), None if not retried
+	pub fn delay(&self, attempt: u32, class: ErrorClass) -> Option<Duration> {
+		if attempt > self.max_retries {
+			return None;
+		}
+		let factor = match class {
+			ErrorClass::Fatal => return None,
+			ErrorClass::Retryable => 1,
+			ErrorClass::RateLimited => self.rate_limit_factor,
+		};
+		let delay = self
+			.base_delay
+			.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+			.saturating_mul(factor);
+		Some(delay.min(self.max_delay))
+	}
+
+	pub async fn run<T, F, Fut>(&self, mut f: F) -> anyhow::Result<T>
+	where
+		F: FnMut() -> Fut,
+		Fut: Future<Output = anyhow::Result<T>>,
+	{
+		let mut attempt = 0;
+		loop {
+			match f().await {
+				Ok(result) => return Ok(result),
+				Err(err) => {
+					attempt += 1;
+					let class = self.classify(&err);
+					match self.delay(attempt, class) {
+						Some(delay) => {
+							let delay = delay.mul_f64(1.0 - self.jitter * rand::random::<f64>());
+							tracing::warn!(
+								?class,
+								attempt,
+								max_retries = self.max_retries,
+								"retrying in {:?}: {:#}",
+								delay,
+								err
+							);
+							tokio::time::sleep(delay).await;
+						}
+						None => return Err(err),
+					}
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use anyhow::Context;
+
+	#[test]
+	fn test_classify() {
+		let err = anyhow::anyhow!(StatusError::new(503, "unavailable")).context("load block");
+		assert_eq!(err.classify(), ErrorClass::Retryable);
+		let err = anyhow::anyhow!(StatusError::new(429, "too many requests"));
+		assert_eq!(err.classify(), ErrorClass::RateLimited);
+		let err = anyhow::anyhow!(StatusError::new(400, "bad request"));
+		assert_eq!(err.classify(), ErrorClass::Fatal);
+
+		let err: anyhow::Result<()> = Err(EventError::InvalidGenesisError).context("apply");
+		assert_eq!(err.unwrap_err().classify(), ErrorClass::Fatal);
+		assert_eq!(anyhow::anyhow!("unknown").classify(), ErrorClass::Retryable);
+	}
+
+	#[test]
+	fn test_is_not_found() {
+		let err = anyhow::anyhow!(StatusError::new(404, "not found")).context("load stream");
+		assert!(is_not_found(&err));
+		assert!(!is_not_found(&anyhow::anyhow!(StatusError::new(
+			503,
+			"unavailable"
+		))));
+		assert!(!is_not_found(&anyhow::anyhow!("connection reset")));
+
+		let err = None::<()>
+			.context(HttpError::StreamLoadError)
+			.context("load stream")
+			.unwrap_err();
+		assert!(is_not_found(&err));
+	}
+
+	#[tokio::test]
+	async fn test_retry_policy() {
+		let policy = RetryPolicy {
+			base_delay: Duration::from_millis(1),
+			..Default::default()
+		};
+		assert_eq!(
+			policy.delay(2, ErrorClass::Retryable),
+			Some(Duration::from_millis(2))
+		);
+		assert_eq!(policy.delay(1, ErrorClass::Fatal), None);
+		assert_eq!(policy.delay(4, ErrorClass::Retryable), None);
+
+		let mut calls = 0;
+		let result: anyhow::Result<()> = policy
+			.run(|| {
+				calls += 1;
+				async { anyhow::bail!(StatusError::new(400, "bad request")) }
+			})
+			.await;
+		assert!(result.is_err());
+		assert_eq!(calls, 1);
+
+		let mut calls = 0;
+		let result = policy
+			.run(|| {
+				calls += 1;
+				let calls = calls;
+				async move {
+					match calls {
+						1 => anyhow::bail!(StatusError::new(502, "bad gateway")),
+						_ => Ok(calls),
+					}
+				}
+			})
+			.await;
+		assert_eq!(result.unwrap(), 2);
+	}
+
+	#[test]
+	fn test_retry_on() {
+		let policy = RetryPolicy::default().with_retry_on(vec![404, 503]);
+		let err = anyhow::anyhow!(StatusError::new(404, "not found yet"));
+		assert_eq!(policy.classify(&err), ErrorClass::Retryable);
+		let err = anyhow::anyhow!(StatusError::new(503, "unavailable"));
+		assert_eq!(policy.classify(&err), ErrorClass::Retryable);
+		let err = anyhow::anyhow!(StatusError::new(502, "bad gateway"));
+		assert_eq!(policy.classify(&err), ErrorClass::Fatal);
+		assert_eq!(
+			policy.classify(&anyhow::anyhow!("connection reset")),
+			ErrorClass::Retryable
+		);
+	}
+}
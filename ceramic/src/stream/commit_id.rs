@@ -1,7 +1,7 @@
 use std::{fmt::Display, io::Write, str::FromStr};
 
 use ceramic_core::{Cid, StreamId};
-use multibase::Base;
+use dataverse_multibase::MultiBase36String;
 use unsigned_varint::{decode, encode};
 
 #[derive(PartialEq, Debug)]
@@ -49,11 +49,9 @@ impl CommitId {
 
 impl Display for CommitId {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-		if let Ok(b) = self.to_vec() {
-			let s = multibase::encode(Base::Base36Lower, b);
-			write!(f, "{}", s)
-		} else {
-			Err(std::fmt::Error)
+		match self.to_vec() {
+			Ok(b) => write!(f, "{}", MultiBase36String::encode(b)),
+			Err(_) => Err(std::fmt::Error),
 		}
 	}
 }
@@ -0,0 +1,247 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use ceramic_core::Cid;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::event::{Event, EventValue};
+
+/// Policy picking the branch of a forked stream, ties of every policy go to
+/// the longest branch, then to the lowest tip cid
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LogSelector {
+	/// Ceramic's resolution, an anchored branch wins over an unanchored one.
+	/// Anchor times are not compared as they require an eth rpc
+	#[default]
+	Ceramic,
+	/// The branch with the most events
+	LongestLog,
+	/// The branch last signed with the most recently issued session of the
+	/// controller
+	LatestSession,
+}
+
+/// Issue time of the session signing the last signed event of the branch
+fn session_issued_at(log: &[Event]) -> anyhow::Result<Option<DateTime<Utc>>> {
+	for event in log.iter().rev() {
+		if let EventValue::Signed(signed) = &event.value {
+			return match signed.cacao()? {
+				Some(cacao) => Ok(Some(cacao.p.issued_at()?)),
+				None => Ok(None),
+			};
+		}
+	}
+	Ok(None)
+}
+
+/// Events of a stream linked by their prev, a stream is forked once two
+/// branches sharing a prev carry data events
+#[derive(Debug, Clone, Default)]
+pub struct CommitDag {
+	events: HashMap<Cid, Event>,
+	children: HashMap<Cid, Vec<Cid>>,
+}
+
+impl CommitDag {
+	pub fn new(events: impl IntoIterator<Item = Event>) -> anyhow::Result<Self> {
+		let mut dag = Self::default();
+		for event in events {
+			dag.insert(event)?;
+		}
+		Ok(dag)
+	}
+
+	/// Returns false if the event was already in the dag
+	pub fn insert(&mut self, event: Event) -> anyhow::Result<bool> {
+		if self.events.contains_key(&event.cid) {
+			return Ok(false);
+		}
+		if let Some(prev) = event.prev()? {
+			self.children.entry(prev).or_default().push(event.cid);
+		}
+		self.events.insert(event.cid, event);
+		Ok(true)
+	}
+
+	pub fn contains(&self, cid: &Cid) -> bool {
+		self.events.contains_key(cid)
+	}
+
+	/// Events without children, the tips of every branch ordered by cid
+	pub fn heads(&self) -> Vec<Cid> {
+		let mut heads: Vec<Cid> = self
+			.events
+			.keys()
+			.filter(|cid| !self.children.contains_key(cid))
+			.cloned()
+			.collect();
+		heads.sort();
+		heads
+	}
+
+	/// Whether the branch starting at the event has a data event, branches of
+	/// anchors only leave the content as it was
+	fn has_data(&self, cid: Cid) -> bool {
+		let mut pending = vec![cid];
+		while let Some(cid) = pending.pop() {
+			if matches!(
+				self.events.get(&cid).map(|event| &event.value),
+				Some(EventValue::Signed(_))
+			) {
+				return true;
+			}
+			if let Some(children) = self.children.get(&cid) {
+				pending.extend(children);
+			}
+		}
+		false
+	}
+
+	/// Whether the tips of the branches after the event diverge, e.g. not a
+	/// commit anchored twice
+	fn diverges(&self, children: &[Cid]) -> bool {
+		children
+			.iter()
+			.filter(|child| self.has_data(**child))
+			.count() > 1
+	}
+
+	/// Events which more than one branch with data events follows, ordered by
+	/// cid
+	pub fn forks(&self) -> Vec<Cid> {
+		let mut forks: Vec<Cid> = self
+			.children
+			.iter()
+			.filter(|(_, children)| self.diverges(children))
+			.map(|(cid, _)| *cid)
+			.collect();
+		forks.sort();
+		forks
+	}
+
+	pub fn is_forked(&self) -> bool {
+		self.children
+			.values()
+			.any(|children| self.diverges(children))
+	}
+
+	/// Tip of the branch the selector picks, `None` for an empty dag
+	pub fn select(&self, selector: LogSelector) -> anyhow::Result<Option<Cid>> {
+		let mut best = None;
+		for head in self.heads() {
+			let log = self.log(head)?;
+			let rank = match selector {
+				LogSelector::Ceramic => {
+					let anchored = log
+						.iter()
+						.any(|event| matches!(event.value, EventValue::Anchor(_)));
+					(anchored, None, log.len(), Reverse(head))
+				}
+				LogSelector::LongestLog => (false, None, log.len(), Reverse(head)),
+				LogSelector::LatestSession => {
+					(false, session_issued_at(&log)?, log.len(), Reverse(head))
+				}
+			};
+			if best.as_ref().map_or(true, |(best, _)| rank > *best) {
+				best = Some((rank, head));
+			}
+		}
+		Ok(best.map(|(_, head)| head))
+	}
+
+	/// Branch of the tip, from the genesis to the tip
+	pub fn log(&self, tip: Cid) -> anyhow::Result<Vec<Event>> {
+		let mut result = Vec::new();
+		let mut cid = Some(tip);
+		while let Some(current) = cid {
+			let event = match self.events.get(&current) {
+				Some(event) => event,
+				None => anyhow::bail!("missing event {} of branch {}", current, tip),
+			};
+			result.push(event.clone());
+			cid = event.prev()?;
+		}
+		result.reverse();
+		Ok(result)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::commit::example;
+
+	#[test]
+	fn test_linear() -> anyhow::Result<()> {
+		let events = example::events(3);
+		let dag = CommitDag::new(events.clone())?;
+		assert!(!dag.is_forked());
+		assert_eq!(dag.heads(), vec![events[2].cid]);
+		let log: Vec<Cid> = dag.log(events[2].cid)?.iter().map(|x| x.cid).collect();
+		assert_eq!(log, events.iter().map(|x| x.cid).collect::<Vec<_>>());
+		Ok(())
+	}
+
+	#[test]
+	fn test_fork() -> anyhow::Result<()> {
+		let events = example::events(3);
+		let patch = serde_json::json!([{ "op": "replace", "path": "/text", "value": "branch" }]);
+		let branch = example::data_event(events[0].cid, events[1].cid, patch)?;
+		let mut dag = CommitDag::new(events.clone())?;
+		assert!(dag.insert(branch.clone())?);
+		assert!(!dag.insert(branch.clone())?);
+
+		assert!(dag.is_forked());
+		assert_eq!(dag.forks(), vec![events[1].cid]);
+		let mut heads = vec![events[2].cid, branch.cid];
+		heads.sort();
+		assert_eq!(dag.heads(), heads);
+		assert_eq!(dag.log(branch.cid)?.len(), 3);
+		Ok(())
+	}
+
+	#[test]
+	fn test_anchored_twice() -> anyhow::Result<()> {
+		let events = example::events(2);
+		let anchor = |cid: &str| Event {
+			cid: cid.parse().unwrap(),
+			value: crate::event::AnchorValue {
+				id: events[0].cid,
+				prev: events[1].cid,
+				..Default::default()
+			}
+			.into(),
+		};
+		let mut dag = CommitDag::new(events.clone())?;
+		dag.insert(anchor(
+			"bafyreih3skzznsyro5i4bsfsyquhcewyqmsra6cx47nj2eb6aerm2nihuu",
+		))?;
+		dag.insert(anchor(
+			"bafyreidnbzsaplrdpjx3schac4fjhwqjzv3kbvdswi52npq3kpdzpbv5qa",
+		))?;
+
+		// both tips hold the same content
+		assert_eq!(dag.heads().len(), 2);
+		assert!(!dag.is_forked());
+		assert!(dag.forks().is_empty());
+		Ok(())
+	}
+
+	#[test]
+	fn test_select() -> anyhow::Result<()> {
+		let events = example::events(3);
+		let patch = serde_json::json!([{ "op": "replace", "path": "/text", "value": "branch" }]);
+		let branch = example::data_event(events[0].cid, events[0].cid, patch)?;
+		let mut dag = CommitDag::new(events.clone())?;
+		dag.insert(branch.clone())?;
+
+		assert_eq!(dag.select(LogSelector::LongestLog)?, Some(events[2].cid));
+		assert_eq!(dag.select(LogSelector::Ceramic)?, Some(events[2].cid));
+		// synthetic commits carry no session, the longest branch wins the tie
+		assert_eq!(dag.select(LogSelector::LatestSession)?, Some(events[2].cid));
+		assert_eq!(CommitDag::default().select(LogSelector::Ceramic)?, None);
+		Ok(())
+	}
+}
@@ -1,6 +1,9 @@
 pub mod commit_id;
+pub mod dag;
 pub mod operator;
 pub mod patch;
+pub mod registry;
+pub mod single;
 pub mod stream_id;
 
 use super::commit_id::CommitId;
@@ -8,7 +11,9 @@ use super::stream_id::StreamIdType;
 use crate::event::{Event, VerifyOption};
 use ceramic_core::{Cid, MultiBase32String, StreamId};
 use ceramic_http_client::api::StateLog;
+use chrono::{DateTime, TimeZone, Utc};
 use int_enum::IntEnum;
+pub use dag::{CommitDag, LogSelector};
 pub use operator::*;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -167,6 +172,36 @@ impl StreamState {
 		})
 	}
 
+	/// Anchor status implied by the log, anchored if no commit follows the
+	/// latest anchor. Unlike `anchor_status` it holds for states made from
+	/// stored events.
+	pub fn logged_anchor_status(&self) -> AnchorStatus {
+		match self.log.last() {
+			Some(log) if log.r#type == LogType::Anchor.int_value() => AnchorStatus::Anchored,
+			_ => AnchorStatus::Pending,
+		}
+	}
+
+	/// Time of the latest anchor in the log, if the node reported it
+	pub fn anchored_at(&self) -> Option<DateTime<Utc>> {
+		self.log
+			.iter()
+			.rev()
+			.filter(|log| log.r#type == LogType::Anchor.int_value())
+			.find_map(|log| log.timestamp)
+			.and_then(|timestamp| Utc.timestamp_opt(timestamp, 0).single())
+	}
+
+	/// Time the instances of a model are ordered by, the anchored time falls
+	/// back to the `updatedAt` of the content for streams not anchored yet
+	pub fn sort_time(&self, sort_by: SortBy) -> Option<DateTime<Utc>> {
+		let updated_at = || self.content.get("updatedAt")?.as_str()?.parse().ok();
+		match sort_by {
+			SortBy::AnchoredAt => self.anchored_at().or_else(updated_at),
+			SortBy::UpdatedAt => updated_at(),
+		}
+	}
+
 	pub fn commit_ids(&self) -> anyhow::Result<Vec<CommitId>> {
 		let mut commit_ids = vec![];
 		let stream_id = self.stream_id()?;
@@ -204,6 +239,25 @@ mod tests {
 
 	use super::*;
 
+	#[test]
+	fn test_logged_anchor_status() {
+		let log = |r#type: LogType| StateLog {
+			cid: "bafyreih3skzznsyro5i4bsfsyquhcewyqmsra6cx47nj2eb6aerm2nihuu".to_string(),
+			r#type: r#type.int_value(),
+			timestamp: None,
+			expiration_time: None,
+		};
+		let mut state = StreamState {
+			log: vec![log(LogType::Genesis)],
+			..Default::default()
+		};
+		assert_eq!(state.logged_anchor_status(), AnchorStatus::Pending);
+		state.log.push(log(LogType::Anchor));
+		assert_eq!(state.logged_anchor_status(), AnchorStatus::Anchored);
+		state.log.push(log(LogType::Signed));
+		assert_eq!(state.logged_anchor_status(), AnchorStatus::Pending);
+	}
+
 	#[test]
 	fn test_serialize_anchor_status() {
 		let status = AnchorStatus::Anchored;
@@ -317,5 +371,10 @@ mod tests {
 				.unwrap()
 			],
 		);
+		assert_eq!(
+			data.anchored_at(),
+			Utc.timestamp_opt(1680629255, 0).single()
+		);
+		assert_eq!(data.sort_time(SortBy::AnchoredAt), data.anchored_at());
 	}
 }
@@ -2,12 +2,32 @@ use crate::event::{Event, EventsLoader, EventsUploader};
 use crate::{AnchorStatus, Ceramic, StreamState};
 use ceramic_core::{Cid, StreamId};
 use int_enum::IntEnum;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
 #[async_trait::async_trait]
 pub trait StreamOperator: StreamLoader + EventsUploader + Send + Sync {}
 
 impl<T: StreamLoader + EventsUploader> StreamOperator for T {}
 
+/// Order of the instances of a model, newest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SortBy {
+	AnchoredAt,
+	UpdatedAt,
+}
+
+/// Ordered page of the instances of a model
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatesPage {
+	pub sort_by: SortBy,
+	pub offset: usize,
+	pub limit: usize,
+}
+
 #[async_trait::async_trait]
 pub trait StreamsLoader: StreamLoader {
 	async fn load_stream_states(
@@ -16,6 +36,75 @@ pub trait StreamsLoader: StreamLoader {
 		account: Option<String>,
 		model_id: &StreamId,
 	) -> anyhow::Result<Vec<StreamState>>;
+
+	/// Page of the instances of the model. The default orders every loaded
+	/// instance, stores override it to order and page in their query
+	async fn load_stream_states_page(
+		&self,
+		ceramic: &Ceramic,
+		account: Option<String>,
+		model_id: &StreamId,
+		page: StatesPage,
+	) -> anyhow::Result<Vec<StreamState>> {
+		let mut states = self.load_stream_states(ceramic, account, model_id).await?;
+		states.sort_by_key(|state| std::cmp::Reverse(state.sort_time(page.sort_by)));
+		Ok(states
+			.into_iter()
+			.skip(page.offset)
+			.take(page.limit)
+			.collect())
+	}
+
+	/// Up to `n` pseudo-random instances of the model, the same seed picks the
+	/// same instances of an unchanged model
+	async fn sample(
+		&self,
+		ceramic: &Ceramic,
+		model_id: &StreamId,
+		n: usize,
+		seed: u64,
+	) -> anyhow::Result<Vec<StreamState>> {
+		let mut reservoir = Reservoir::new(n, seed);
+		for state in self.load_stream_states(ceramic, None, model_id).await? {
+			reservoir.push(state);
+		}
+		Ok(reservoir.into_items())
+	}
+}
+
+/// Uniform sample of `n` items of a sequence of unknown length, in one pass
+pub struct Reservoir<T> {
+	n: usize,
+	seen: usize,
+	items: Vec<T>,
+	rng: StdRng,
+}
+
+impl<T> Reservoir<T> {
+	pub fn new(n: usize, seed: u64) -> Self {
+		Self {
+			n,
+			seen: 0,
+			items: Vec::with_capacity(n),
+			rng: StdRng::seed_from_u64(seed),
+		}
+	}
+
+	pub fn push(&mut self, item: T) {
+		self.seen += 1;
+		if self.items.len() < self.n {
+			self.items.push(item);
+			return;
+		}
+		let idx = self.rng.gen_range(0..self.seen);
+		if idx < self.n {
+			self.items[idx] = item;
+		}
+	}
+
+	pub fn into_items(self) -> Vec<T> {
+		self.items
+	}
 }
 
 #[async_trait::async_trait]
@@ -29,6 +118,29 @@ pub trait StreamLoader: EventsLoader + Sync + Send {
 		let events = self.load_events(ceramic, stream_id, tip).await?;
 		StreamState::make(stream_id.r#type.int_value(), events).await
 	}
+
+	/// Load only the given top-level fields of the stream content
+	async fn load_fields(
+		&self,
+		ceramic: &Ceramic,
+		stream_id: &StreamId,
+		fields: &[&str],
+	) -> anyhow::Result<Map<String, Value>> {
+		let state = self.load_stream_state(ceramic, stream_id, None).await?;
+		Ok(project_fields(&state.content, fields))
+	}
+}
+
+pub fn project_fields(content: &Value, fields: &[&str]) -> Map<String, Value> {
+	let mut result = Map::new();
+	if let Value::Object(content) = content {
+		for field in fields {
+			if let Some(value) = content.get(*field) {
+				result.insert(field.to_string(), value.clone());
+			}
+		}
+	}
+	result
 }
 
 #[async_trait::async_trait]
@@ -104,4 +216,68 @@ impl<T: StreamsLoader + Send + Sync> StreamsLoader for CachedStreamLoader<T> {
 			.load_stream_states(ceramic, account, model_id)
 			.await
 	}
+
+	async fn load_stream_states_page(
+		&self,
+		ceramic: &Ceramic,
+		account: Option<String>,
+		model_id: &StreamId,
+		page: StatesPage,
+	) -> anyhow::Result<Vec<StreamState>> {
+		self.loader
+			.load_stream_states_page(ceramic, account, model_id, page)
+			.await
+	}
+
+	async fn sample(
+		&self,
+		ceramic: &Ceramic,
+		model_id: &StreamId,
+		n: usize,
+		seed: u64,
+	) -> anyhow::Result<Vec<StreamState>> {
+		self.loader.sample(ceramic, model_id, n, seed).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+
+	#[test]
+	fn project_content_fields() {
+		let content = json!({
+			"fileName": "a.txt",
+			"updatedAt": "2024-01-01T00:00:00Z",
+			"contentId": "kjzl6kcym7w8y8syiams0kvm3qwfnutk2szi0wlhvf6rr9lalzpibxed0qvotuy",
+		});
+		let fields = project_fields(&content, &["fileName", "updatedAt", "missing"]);
+		assert_eq!(
+			Value::Object(fields),
+			json!({"fileName": "a.txt", "updatedAt": "2024-01-01T00:00:00Z"})
+		);
+		assert!(project_fields(&Value::Null, &["fileName"]).is_empty());
+	}
+
+	#[test]
+	fn test_reservoir_sample() {
+		let sample = |seed| {
+			let mut reservoir = Reservoir::new(10, seed);
+			for item in 0..1000 {
+				reservoir.push(item);
+			}
+			reservoir.into_items()
+		};
+		let items = sample(1);
+		assert_eq!(items.len(), 10);
+		assert_eq!(items, sample(1));
+		assert_ne!(items, sample(2));
+		assert!(items.iter().any(|item| *item >= 10));
+
+		let mut reservoir = Reservoir::new(10, 1);
+		reservoir.push(0);
+		assert_eq!(reservoir.into_items(), vec![0]);
+	}
 }
@@ -0,0 +1,368 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use ceramic_core::StreamId;
+use ceramic_http_client::GetRootSchema;
+use futures::lock::Mutex;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use super::{StreamLoader, StreamState};
+use crate::model::validate_instance;
+use crate::Ceramic;
+
+#[derive(Debug)]
+pub enum RegistryError {
+	ModelNotRegistered(StreamId),
+	StreamWithoutModel,
+	TypeMismatch(String),
+	ModelWithoutSchema(StreamId),
+	/// model and why the content does not match it
+	InvalidInstance(StreamId, String),
+}
+
+impl std::fmt::Display for RegistryError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::ModelNotRegistered(model_id) => write!(f, "model {} not registered", model_id),
+			Self::StreamWithoutModel => write!(f, "stream has no model"),
+			Self::TypeMismatch(type_name) => write!(f, "decoded value is not {}", type_name),
+			Self::ModelWithoutSchema(model_id) => write!(f, "model {} has no schema", model_id),
+			Self::InvalidInstance(model_id, reason) => {
+				write!(f, "content does not match model {}: {}", model_id, reason)
+			}
+		}
+	}
+}
+
+impl std::error::Error for RegistryError {}
+
+type Decoder = fn(Value) -> anyhow::Result<Box<dyn Any + Send + Sync>>;
+
+fn decode<T: DeserializeOwned + Send + Sync + 'static>(
+	content: Value,
+) -> anyhow::Result<Box<dyn Any + Send + Sync>> {
+	Ok(Box::new(serde_json::from_value::<T>(content)?))
+}
+
+/// Rust type a model decodes into
+#[derive(Clone)]
+pub struct RegistryEntry {
+	pub type_name: &'static str,
+	/// hex sha256 of the json root schema of the type
+	pub schema_hash: String,
+	/// schema of the model stream for models inferred without a Rust type,
+	/// their content decodes into a `serde_json::Value`
+	pub schema: Option<Value>,
+	decoder: Decoder,
+}
+
+impl std::fmt::Debug for RegistryEntry {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("RegistryEntry")
+			.field("type_name", &self.type_name)
+			.field("schema_hash", &self.schema_hash)
+			.field("schema", &self.schema)
+			.finish()
+	}
+}
+
+/// Stream content decoded into the type registered for its model
+#[derive(Debug)]
+pub struct Registered {
+	pub model_id: StreamId,
+	pub type_name: &'static str,
+	value: Box<dyn Any + Send + Sync>,
+}
+
+impl Registered {
+	pub fn is<T: 'static>(&self) -> bool {
+		self.value.is::<T>()
+	}
+
+	pub fn downcast<T: 'static>(self) -> anyhow::Result<T> {
+		match self.value.downcast::<T>() {
+			Ok(value) => Ok(*value),
+			Err(_) => anyhow::bail!(RegistryError::TypeMismatch(
+				std::any::type_name::<T>().to_string()
+			)),
+		}
+	}
+}
+
+pub fn schema_hash<T: GetRootSchema>() -> anyhow::Result<String> {
+	let schema = serde_json::to_vec(&T::root_schema())?;
+	Ok(hex::encode(Sha256::digest(schema)))
+}
+
+/// Client-side map of model ids to the Rust types generating their schemas,
+/// replacing hand-written matches on model ids in consumers
+#[derive(Debug, Clone, Default)]
+pub struct SchemaRegistry {
+	entries: HashMap<StreamId, RegistryEntry>,
+}
+
+impl SchemaRegistry {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn register<T>(&mut self, model_id: StreamId) -> anyhow::Result<()>
+	where
+		T: GetRootSchema + DeserializeOwned + Send + Sync + 'static,
+	{
+		let entry = RegistryEntry {
+			type_name: std::any::type_name::<T>(),
+			schema_hash: schema_hash::<T>()?,
+			schema: None,
+			decoder: decode::<T>,
+		};
+		self.entries.insert(model_id, entry);
+		Ok(())
+	}
+
+	/// Register a model by its json schema, without a Rust type
+	pub fn register_schema(&mut self, model_id: StreamId, schema: Value) -> anyhow::Result<()> {
+		let entry = RegistryEntry {
+			type_name: std::any::type_name::<Value>(),
+			schema_hash: hex::encode(Sha256::digest(serde_json::to_vec(&schema)?)),
+			schema: Some(schema),
+			decoder: decode::<Value>,
+		};
+		self.entries.insert(model_id, entry);
+		Ok(())
+	}
+
+	/// Entry of the model, registering the schema of the model stream if the
+	/// model is unknown
+	pub async fn infer<L: StreamLoader + ?Sized>(
+		&mut self,
+		loader: &L,
+		ceramic: &Ceramic,
+		model_id: &StreamId,
+	) -> anyhow::Result<&RegistryEntry> {
+		if !self.entries.contains_key(model_id) {
+			let schema = model_schema(loader, ceramic, model_id).await?;
+			self.register_schema(model_id.clone(), schema)?;
+		}
+		Ok(&self.entries[model_id])
+	}
+
+	pub fn with<T>(mut self, model_id: StreamId) -> anyhow::Result<Self>
+	where
+		T: GetRootSchema + DeserializeOwned + Send + Sync + 'static,
+	{
+		self.register::<T>(model_id)?;
+		Ok(self)
+	}
+
+	pub fn get(&self, model_id: &StreamId) -> Option<&RegistryEntry> {
+		self.entries.get(model_id)
+	}
+
+	pub fn entries(&self) -> impl Iterator<Item = (&StreamId, &RegistryEntry)> {
+		self.entries.iter()
+	}
+
+	/// Decode the stream content with the type registered for its model,
+	/// validating it first against the schema of an inferred model
+	pub fn decode_as_registered(&self, state: &StreamState) -> anyhow::Result<Registered> {
+		let model_id = match state.model()? {
+			Some(model_id) => model_id,
+			None => anyhow::bail!(RegistryError::StreamWithoutModel),
+		};
+		let entry = match self.entries.get(&model_id) {
+			Some(entry) => entry,
+			None => anyhow::bail!(RegistryError::ModelNotRegistered(model_id)),
+		};
+		let invalid = |err: &dyn std::fmt::Display| {
+			RegistryError::InvalidInstance(model_id.clone(), err.to_string())
+		};
+		if let Some(schema) = &entry.schema {
+			validate_instance(schema, &state.content).map_err(|err| invalid(&err))?;
+		}
+		let value = (entry.decoder)(state.content.clone()).map_err(|err| invalid(&err))?;
+		Ok(Registered {
+			model_id,
+			type_name: entry.type_name,
+			value,
+		})
+	}
+
+	/// Decode the stream content, inferring the schema of an unknown model
+	/// from its model stream first
+	pub async fn decode_or_infer<L: StreamLoader + ?Sized>(
+		&mut self,
+		loader: &L,
+		ceramic: &Ceramic,
+		state: &StreamState,
+	) -> anyhow::Result<Registered> {
+		if let Some(model_id) = state.model()? {
+			self.infer(loader, ceramic, &model_id).await?;
+		}
+		self.decode_as_registered(state)
+	}
+}
+
+/// Json schema of the model stream, to infer the entry of an unknown model
+async fn model_schema<L: StreamLoader + ?Sized>(
+	loader: &L,
+	ceramic: &Ceramic,
+	model_id: &StreamId,
+) -> anyhow::Result<Value> {
+	let model = loader.load_stream_state(ceramic, model_id, None).await?;
+	match model.content.get("schema") {
+		Some(schema) if schema.is_object() => {
+			tracing::info!(model_id = model_id.to_string(), "infer schema of model");
+			Ok(schema.clone())
+		}
+		_ => anyhow::bail!(RegistryError::ModelWithoutSchema(model_id.clone())),
+	}
+}
+
+/// Schema registry shared by the paths saving and syncing instances, so the
+/// schema of an unknown model is inferred once per node
+#[derive(Clone)]
+pub struct SharedSchemaRegistry(Arc<Mutex<SchemaRegistry>>);
+
+impl SharedSchemaRegistry {
+	pub fn new(registry: SchemaRegistry) -> Self {
+		Self(Arc::new(Mutex::new(registry)))
+	}
+
+	/// Check the stream content matches its model, inferring the schema of an
+	/// unknown model. Fails with [`RegistryError::InvalidInstance`] on
+	/// content not matching it
+	pub async fn validate<L: StreamLoader + ?Sized>(
+		&self,
+		loader: &L,
+		ceramic: &Ceramic,
+		state: &StreamState,
+	) -> anyhow::Result<()> {
+		if let Some(model_id) = state.model()? {
+			// the model stream is loaded without holding the registry, a
+			// concurrent inference of the same model keeps the first entry
+			if self.0.lock().await.get(&model_id).is_none() {
+				let schema = model_schema(loader, ceramic, &model_id).await?;
+				let mut registry = self.0.lock().await;
+				if registry.get(&model_id).is_none() {
+					registry.register_schema(model_id, schema)?;
+				}
+			}
+		}
+		self.0.lock().await.decode_as_registered(state)?;
+		Ok(())
+	}
+}
+
+/// Whether the error is content not matching its model, rather than a
+/// failure to look the model up
+pub fn is_invalid_instance(err: &anyhow::Error) -> bool {
+	matches!(
+		err.downcast_ref::<RegistryError>(),
+		Some(RegistryError::InvalidInstance(..))
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ceramic_http_client::schemars::JsonSchema;
+	use serde::Deserialize;
+
+	#[derive(Debug, Deserialize, JsonSchema)]
+	#[schemars(crate = "ceramic_http_client::schemars")]
+	struct Post {
+		text: String,
+	}
+
+	impl GetRootSchema for Post {}
+
+	#[derive(Debug, Deserialize, JsonSchema)]
+	#[schemars(crate = "ceramic_http_client::schemars")]
+	struct Profile {
+		name: String,
+	}
+
+	impl GetRootSchema for Profile {}
+
+	#[test]
+	fn test_registry() -> anyhow::Result<()> {
+		let post_model: StreamId =
+			"kjzl6kcym7w8y8syiams0kvm3qwfnutk2szi0wlhvf6rr9lalzpibxed0qvotuy".parse()?;
+		let profile_model: StreamId =
+			"kjzl6kcym7w8y9s94kcardbh5u0ao76bci07xnnxjw1ew3i4eackykj76uagqfk".parse()?;
+		let registry = SchemaRegistry::new()
+			.with::<Post>(post_model.clone())?
+			.with::<Profile>(profile_model.clone())?;
+
+		let post = registry.get(&post_model).unwrap();
+		let profile = registry.get(&profile_model).unwrap();
+		assert!(post.type_name.ends_with("Post"));
+		assert_eq!(post.schema_hash, schema_hash::<Post>()?);
+		assert_ne!(post.schema_hash, profile.schema_hash);
+
+		let value = (post.decoder)(serde_json::json!({ "text": "hello" }))?;
+		assert_eq!(value.downcast_ref::<Post>().unwrap().text, "hello");
+		assert!((profile.decoder)(serde_json::json!({ "text": "hello" })).is_err());
+		let value = (profile.decoder)(serde_json::json!({ "name": "alice" }))?;
+		assert_eq!(value.downcast_ref::<Profile>().unwrap().name, "alice");
+		Ok(())
+	}
+
+	#[test]
+	fn test_register_schema() -> anyhow::Result<()> {
+		let model_id: StreamId =
+			"kjzl6kcym7w8y8syiams0kvm3qwfnutk2szi0wlhvf6rr9lalzpibxed0qvotuy".parse()?;
+		let schema = serde_json::json!({
+			"type": "object",
+			"properties": { "text": { "type": "string" } },
+		});
+		let mut registry = SchemaRegistry::new();
+		registry.register_schema(model_id.clone(), schema.clone())?;
+
+		let entry = registry.get(&model_id).unwrap();
+		assert_eq!(entry.schema, Some(schema));
+		let value = (entry.decoder)(serde_json::json!({ "text": "hello" }))?;
+		assert_eq!(
+			value.downcast_ref::<Value>().unwrap()["text"],
+			serde_json::json!("hello")
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_validate_inferred_instance() -> anyhow::Result<()> {
+		let model_id: StreamId =
+			"kjzl6kcym7w8y8syiams0kvm3qwfnutk2szi0wlhvf6rr9lalzpibxed0qvotuy".parse()?;
+		let mut registry = SchemaRegistry::new();
+		registry.register_schema(
+			model_id.clone(),
+			serde_json::json!({
+				"type": "object",
+				"additionalProperties": false,
+				"properties": { "text": { "type": "string" } },
+			}),
+		)?;
+		let state = |content: Value| StreamState {
+			metadata: serde_json::json!({ "model": model_id.to_string() }),
+			content,
+			..Default::default()
+		};
+
+		let registered =
+			registry.decode_as_registered(&state(serde_json::json!({ "text": "hello" })))?;
+		assert_eq!(registered.model_id, model_id);
+		let err = registry
+			.decode_as_registered(&state(serde_json::json!({ "text": 1 })))
+			.unwrap_err();
+		assert!(is_invalid_instance(&err));
+		let err = registry
+			.decode_as_registered(&state(serde_json::json!({ "likes": 3 })))
+			.unwrap_err();
+		assert!(is_invalid_instance(&err));
+		Ok(())
+	}
+}
@@ -0,0 +1,111 @@
+use std::collections::BTreeMap;
+
+use ceramic_core::{Cid, StreamId, StreamIdType};
+use ceramic_http_client::api::StateLog;
+use dataverse_types_core::digest::{block_cid, DAG_CBOR};
+use int_enum::IntEnum;
+use libipld::cbor::DagCborCodec;
+use libipld::prelude::Codec;
+use libipld::Ipld;
+
+use super::{LogType, StreamLoader, StreamState};
+use crate::retry::is_not_found;
+use crate::Ceramic;
+
+/// Stream type of model instance documents
+pub const MID_TYPE: u64 = 3;
+
+/// Unsigned genesis of the instance of a single account relation model, the
+/// same for every write of `controller` so its stream id is deterministic
+pub fn single_genesis(model_id: &StreamId, controller: &str) -> anyhow::Result<(Cid, Vec<u8>)> {
+	let header = BTreeMap::from([
+		(
+			"controllers".to_string(),
+			Ipld::List(vec![Ipld::String(controller.to_string())]),
+		),
+		("model".to_string(), Ipld::Bytes(model_id.to_vec()?)),
+		("sep".to_string(), Ipld::String("model".to_string())),
+	]);
+	let node = Ipld::Map(BTreeMap::from([
+		("data".to_string(), Ipld::Null),
+		("header".to_string(), Ipld::Map(header)),
+	]));
+	let block = DagCborCodec.encode(&node)?;
+	Ok((block_cid(DAG_CBOR, &block), block))
+}
+
+/// Stream id of the single instance of `model_id` controlled by `controller`
+pub fn single_stream_id(model_id: &StreamId, controller: &str) -> anyhow::Result<StreamId> {
+	let (cid, _) = single_genesis(model_id, controller)?;
+	Ok(StreamId {
+		r#type: StreamIdType::from_int(MID_TYPE)?,
+		cid,
+	})
+}
+
+/// State of a single instance before its first data commit, without content
+pub fn single_genesis_state(model_id: &StreamId, controller: &str) -> anyhow::Result<StreamState> {
+	let (cid, _) = single_genesis(model_id, controller)?;
+	Ok(StreamState {
+		r#type: MID_TYPE,
+		content: serde_json::Value::Null,
+		log: vec![StateLog {
+			cid: cid.to_string(),
+			r#type: LogType::Genesis.int_value(),
+			timestamp: None,
+			expiration_time: None,
+		}],
+		metadata: serde_json::json!({
+			"controllers": [controller],
+			"model": model_id.to_string(),
+		}),
+		doctype: "MID".to_string(),
+		..Default::default()
+	})
+}
+
+/// State of the single instance of `model_id` controlled by `controller`,
+/// `None` if ceramic answers that it does not exist yet. Failing to load it
+/// otherwise is an error, creating the instance from its genesis then would
+/// fork it
+pub async fn load_single_instance<L: StreamLoader + ?Sized>(
+	loader: &L,
+	ceramic: &Ceramic,
+	model_id: &StreamId,
+	controller: &str,
+) -> anyhow::Result<Option<StreamState>> {
+	let stream_id = single_stream_id(model_id, controller)?;
+	match loader.load_stream_state(ceramic, &stream_id, None).await {
+		Ok(state) => Ok(Some(state)),
+		Err(err) if is_not_found(&err) => {
+			tracing::debug!(
+				stream_id = stream_id.to_string(),
+				"single instance not created yet: {}",
+				err
+			);
+			Ok(None)
+		}
+		Err(err) => Err(err),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_single_stream_id() -> anyhow::Result<()> {
+		let model_id: StreamId =
+			"kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju".parse()?;
+		let account = "did:pkh:eip155:1:0x312eA852726E3A9f633A0377c0ea882086d66666";
+		let stream_id = single_stream_id(&model_id, account)?;
+		assert_eq!(stream_id, single_stream_id(&model_id, account)?);
+		assert_ne!(stream_id, single_stream_id(&model_id, "did:key:z6Mk")?);
+
+		let state = single_genesis_state(&model_id, account)?;
+		assert_eq!(state.stream_id()?, stream_id);
+		assert_eq!(state.controllers(), vec![account.to_string()]);
+		assert_eq!(state.must_model()?, model_id);
+		Ok(())
+	}
+}
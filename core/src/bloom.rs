@@ -0,0 +1,378 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use ceramic_core::{Cid, StreamId};
+use dataverse_ceramic::kubo::TipStore;
+
+use crate::stream::{Stream, StreamStore};
+
+const SHARDS: usize = 16;
+const HASHES: u64 = 4;
+
+/// Bits per expected stream, about 2% false positives with 4 hashes
+const BITS_PER_ITEM: usize = 8;
+
+struct Shard {
+	bits: Vec<u64>,
+}
+
+impl Shard {
+	fn new(bits: usize) -> Self {
+		Self {
+			bits: vec![0; bits.div_ceil(64).max(1)],
+		}
+	}
+
+	fn len(&self) -> u64 {
+		self.bits.len() as u64 * 64
+	}
+
+	fn positions(&self, (h1, h2): (u64, u64)) -> impl Iterator<Item = (usize, u64)> {
+		let len = self.len();
+		(0..HASHES).map(move |i| {
+			let bit = h1.wrapping_add(i.wrapping_mul(h2)) % len;
+			((bit / 64) as usize, 1 << (bit % 64))
+		})
+	}
+
+	fn insert(&mut self, hashes: (u64, u64)) {
+		for (word, mask) in self.positions(hashes) {
+			self.bits[word] |= mask;
+		}
+	}
+
+	fn contains(&self, hashes: (u64, u64)) -> bool {
+		self.positions(hashes)
+			.all(|(word, mask)| self.bits[word] & mask != 0)
+	}
+}
+
+fn hashes(stream_id: &StreamId) -> (u64, u64) {
+	let mut hasher = DefaultHasher::new();
+	stream_id.hash(&mut hasher);
+	let h1 = hasher.finish();
+	h1.hash(&mut hasher);
+	(h1, hasher.finish() | 1)
+}
+
+/// Sharded bloom filter of stream ids, answering "definitely unknown" without
+/// a store lookup. Ids are never removed, deleted streams stay maybe-present.
+pub struct StreamFilter {
+	shards: Vec<RwLock<Shard>>,
+}
+
+impl StreamFilter {
+	pub fn with_capacity(capacity: usize) -> Self {
+		let bits = capacity.max(1024) * BITS_PER_ITEM / SHARDS;
+		Self {
+			shards: (0..SHARDS).map(|_| RwLock::new(Shard::new(bits))).collect(),
+		}
+	}
+
+	fn shard(&self, hashes: (u64, u64)) -> &RwLock<Shard> {
+		&self.shards[(hashes.0 >> 60) as usize % SHARDS]
+	}
+
+	pub fn insert(&self, stream_id: &StreamId) {
+		let hashes = hashes(stream_id);
+		self.shard(hashes).write().unwrap().insert(hashes);
+	}
+
+	/// false if the stream was never inserted, true if it probably was
+	pub fn maybe_contains(&self, stream_id: &StreamId) -> bool {
+		let hashes = hashes(stream_id);
+		self.shard(hashes).read().unwrap().contains(hashes)
+	}
+}
+
+/// Store answering lookups of unknown streams from a filter of the stored
+/// ids. Writes through the store are added right away, streams written by
+/// other nodes sharing the store are answered as unknown until the filter is
+/// refreshed, see `run_refresh`.
+pub struct FilteredStore<T> {
+	store: T,
+	filter: RwLock<Arc<StreamFilter>>,
+	/// ids saved while the filter is rebuilt, the rebuilt filter may have
+	/// listed the ids before they were saved
+	rebuilding: Mutex<Option<Vec<StreamId>>>,
+	refreshing: tokio::sync::Mutex<()>,
+}
+
+impl<T: StreamStore> FilteredStore<T> {
+	pub async fn new(store: T) -> anyhow::Result<Self> {
+		let filter = Self::build(&store).await?;
+		Ok(Self {
+			store,
+			filter: RwLock::new(Arc::new(filter)),
+			rebuilding: Mutex::new(None),
+			refreshing: Default::default(),
+		})
+	}
+
+	async fn build(store: &T) -> anyhow::Result<StreamFilter> {
+		let stream_ids = store.list_stream_ids().await?;
+		let filter = StreamFilter::with_capacity(stream_ids.len() * 2);
+		for stream_id in &stream_ids {
+			filter.insert(stream_id);
+		}
+		log::info!("stream filter rebuilt with {} streams", stream_ids.len());
+		Ok(filter)
+	}
+
+	/// Rebuild the filter from the ids of the store, picking up streams
+	/// written around it and dropping removed ones
+	pub async fn refresh(&self) -> anyhow::Result<()> {
+		let _refreshing = self.refreshing.lock().await;
+		*self.rebuilding.lock().unwrap() = Some(vec![]);
+		let built = Self::build(&self.store).await;
+		// saves wait for the swap, so none lands in the replaced filter only
+		let mut rebuilding = self.rebuilding.lock().unwrap();
+		let saved = rebuilding.take().unwrap_or_default();
+		let filter = built?;
+		for stream_id in &saved {
+			filter.insert(stream_id);
+		}
+		*self.filter.write().unwrap() = Arc::new(filter);
+		Ok(())
+	}
+
+	/// Add a saved stream to the filter, and to the one being rebuilt
+	fn insert(&self, stream_id: &StreamId) {
+		let mut rebuilding = self.rebuilding.lock().unwrap();
+		self.filter().insert(stream_id);
+		if let Some(saved) = rebuilding.as_mut() {
+			saved.push(stream_id.clone());
+		}
+	}
+
+	/// Refresh the filter every `interval` until the task is dropped, bounding
+	/// how long a negative can be stale
+	pub async fn run_refresh(&self, interval: Duration) {
+		loop {
+			tokio::time::sleep(interval).await;
+			if let Err(err) = self.refresh().await {
+				log::warn!("failed to refresh stream filter: {}", err);
+			}
+		}
+	}
+
+	fn filter(&self) -> Arc<StreamFilter> {
+		self.filter.read().unwrap().clone()
+	}
+
+	/// false if the stream is surely not stored, true if it probably is
+	pub fn maybe_contains(&self, stream_id: &StreamId) -> bool {
+		self.filter().maybe_contains(stream_id)
+	}
+
+	pub fn inner(&self) -> &T {
+		&self.store
+	}
+}
+
+#[async_trait::async_trait]
+impl<T: StreamStore> StreamStore for FilteredStore<T> {
+	async fn save_stream(&self, stream: &Stream) -> anyhow::Result<()> {
+		self.store.save_stream(stream).await?;
+		self.insert(&stream.stream_id()?);
+		Ok(())
+	}
+
+	async fn load_stream(&self, stream_id: &StreamId) -> anyhow::Result<Option<Stream>> {
+		if !self.maybe_contains(stream_id) {
+			return Ok(None);
+		}
+		self.store.load_stream(stream_id).await
+	}
+
+	async fn list_all_streams(&self) -> anyhow::Result<Vec<Stream>> {
+		self.store.list_all_streams().await
+	}
+
+	async fn list_model_streams(&self, model_id: &StreamId) -> anyhow::Result<Vec<Stream>> {
+		self.store.list_model_streams(model_id).await
+	}
+
+	async fn list_stream_ids(&self) -> anyhow::Result<Vec<StreamId>> {
+		self.store.list_stream_ids().await
+	}
+
+	/// removed streams stay in the filter until it is refreshed, lookups of
+	/// them fall through to the store
+	async fn remove_model_streams(&self, model_id: &StreamId) -> anyhow::Result<usize> {
+		self.store.remove_model_streams(model_id).await
+	}
+
+	async fn remove_stream(&self, stream_id: &StreamId) -> anyhow::Result<bool> {
+		self.store.remove_stream(stream_id).await
+	}
+
+	async fn purge_stream(&self, stream_id: &StreamId) -> anyhow::Result<bool> {
+		self.store.purge_stream(stream_id).await
+	}
+
+	async fn add_stream_label(&self, stream_id: &StreamId, label: &str) -> anyhow::Result<()> {
+		self.store.add_stream_label(stream_id, label).await
+	}
+
+	async fn remove_stream_label(&self, stream_id: &StreamId, label: &str) -> anyhow::Result<bool> {
+		self.store.remove_stream_label(stream_id, label).await
+	}
+
+	async fn stream_labels(&self, stream_id: &StreamId) -> anyhow::Result<Vec<String>> {
+		self.store.stream_labels(stream_id).await
+	}
+
+	async fn streams_with_label(&self, label: &str) -> anyhow::Result<Vec<StreamId>> {
+		self.store.streams_with_label(label).await
+	}
+
+	async fn filter_labeled(
+		&self,
+		label: &str,
+		stream_ids: &[StreamId],
+	) -> anyhow::Result<Vec<StreamId>> {
+		self.store.filter_labeled(label, stream_ids).await
+	}
+}
+
+/// Pubsub queries and updates of streams the node does not store are
+/// answered from the filter without a lookup
+#[async_trait::async_trait]
+impl<T: StreamStore + TipStore> TipStore for FilteredStore<T> {
+	async fn get_tip(&self, stream_id: &StreamId) -> anyhow::Result<Option<Cid>> {
+		if !self.maybe_contains(stream_id) {
+			return Ok(None);
+		}
+		self.store.get_tip(stream_id).await
+	}
+
+	async fn set_tip(&self, stream_id: &StreamId, tip: Cid) -> anyhow::Result<()> {
+		self.store.set_tip(stream_id, tip).await
+	}
+
+	async fn stream_ids(&self) -> anyhow::Result<Vec<StreamId>> {
+		TipStore::stream_ids(&self.store).await
+	}
+
+	async fn has_query(&self, id: &str) -> anyhow::Result<bool> {
+		self.store.has_query(id).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::str::FromStr;
+
+	use int_enum::IntEnum;
+
+	use super::*;
+	use crate::testing::MemoryStreams;
+
+	#[test]
+	fn test_stream_filter() {
+		let stream_a =
+			StreamId::from_str("kjzl6kcym7w8y8syiams0kvm3qwfnutk2szi0wlhvf6rr9lalzpibxed0qvotuy")
+				.unwrap();
+		let stream_b =
+			StreamId::from_str("kjzl6kcym7w8y9s94kcardbh5u0ao76bci07xnnxjw1ew3i4eackykj76uagqfk")
+				.unwrap();
+		let filter = StreamFilter::with_capacity(16);
+		assert!(!filter.maybe_contains(&stream_a));
+
+		filter.insert(&stream_a);
+		assert!(filter.maybe_contains(&stream_a));
+		assert!(!filter.maybe_contains(&stream_b));
+	}
+
+	#[tokio::test]
+	async fn test_refresh_stale_negative() -> anyhow::Result<()> {
+		let stream_id =
+			StreamId::from_str("kjzl6kcym7w8y8syiams0kvm3qwfnutk2szi0wlhvf6rr9lalzpibxed0qvotuy")?;
+		let stream = Stream {
+			r#type: stream_id.r#type.int_value(),
+			dapp_id: uuid::Uuid::nil(),
+			genesis: stream_id.cid,
+			tip: stream_id.cid,
+			account: None,
+			model: None,
+			content: serde_json::Value::Null,
+			published: Default::default(),
+			anchored_at: None,
+			forked: false,
+		};
+		let store = FilteredStore::new(MemoryStreams::default()).await?;
+
+		// written by another node sharing the store
+		store.inner().save_stream(&stream).await?;
+		assert!(store.load_stream(&stream_id).await?.is_none());
+
+		store.refresh().await?;
+		assert!(store.load_stream(&stream_id).await?.is_some());
+		Ok(())
+	}
+
+	/// Store listing its ids, then waiting for a permit before returning them
+	struct GatedIds {
+		streams: MemoryStreams,
+		gate: tokio::sync::Semaphore,
+	}
+
+	#[async_trait::async_trait]
+	impl StreamStore for GatedIds {
+		async fn save_stream(&self, stream: &Stream) -> anyhow::Result<()> {
+			self.streams.save_stream(stream).await
+		}
+
+		async fn load_stream(&self, stream_id: &StreamId) -> anyhow::Result<Option<Stream>> {
+			self.streams.load_stream(stream_id).await
+		}
+
+		async fn list_all_streams(&self) -> anyhow::Result<Vec<Stream>> {
+			self.streams.list_all_streams().await
+		}
+
+		async fn list_stream_ids(&self) -> anyhow::Result<Vec<StreamId>> {
+			let stream_ids = self.streams.list_stream_ids().await?;
+			self.gate.acquire().await?.forget();
+			Ok(stream_ids)
+		}
+	}
+
+	#[tokio::test]
+	async fn test_save_during_refresh() -> anyhow::Result<()> {
+		let stream_id =
+			StreamId::from_str("kjzl6kcym7w8y8syiams0kvm3qwfnutk2szi0wlhvf6rr9lalzpibxed0qvotuy")?;
+		let stream = Stream {
+			r#type: stream_id.r#type.int_value(),
+			dapp_id: uuid::Uuid::nil(),
+			genesis: stream_id.cid,
+			tip: stream_id.cid,
+			account: None,
+			model: None,
+			content: serde_json::Value::Null,
+			published: Default::default(),
+			anchored_at: None,
+			forked: false,
+		};
+		let gated = GatedIds {
+			streams: MemoryStreams::default(),
+			gate: tokio::sync::Semaphore::new(1),
+		};
+		let store = FilteredStore::new(gated).await?;
+
+		// saved after the rebuild listed the ids, before it swaps the filter
+		let save = async {
+			store.save_stream(&stream).await?;
+			store.inner().gate.add_permits(1);
+			anyhow::Ok(())
+		};
+		let (refreshed, saved) = tokio::join!(store.refresh(), save);
+		refreshed?;
+		saved?;
+		assert!(store.maybe_contains(&stream_id));
+		Ok(())
+	}
+}
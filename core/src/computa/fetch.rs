@@ -0,0 +1,234 @@
+use anyhow::Context;
+use ceramic_core::{Cid, StreamId};
+use dataverse_ceramic::event::{ToCid, VerifyOption};
+use dataverse_ceramic::{Ceramic, Event, EventValue, EventsLoader};
+use dataverse_types_core::chain::{encode_events, verify_chain, EventBlocks};
+use ethers_core::abi::{self, Token};
+
+/// Events of a stream from genesis to `tip`, verified and encoded for guests
+#[derive(Debug, Clone, PartialEq)]
+pub struct FetchedStream {
+	pub stream_id: StreamId,
+	pub tip: Cid,
+	/// `dataverse_types_core::chain::encode_events` of the events
+	pub events: Vec<u8>,
+}
+
+impl FetchedStream {
+	/// `(bytes streamId, bytes tip, bytes events)`
+	pub fn to_token(&self) -> anyhow::Result<Token> {
+		Ok(Token::Tuple(vec![
+			Token::Bytes(self.stream_id.to_vec()?),
+			Token::Bytes(self.tip.to_bytes()),
+			Token::Bytes(self.events.clone()),
+		]))
+	}
+}
+
+pub fn event_blocks(event: &Event) -> anyhow::Result<EventBlocks> {
+	Ok(match &event.value {
+		EventValue::Signed(signed) => EventBlocks {
+			envelope: signed.jws.to_vec()?,
+			linked: signed.linked_block.clone(),
+		},
+		EventValue::Anchor(anchor) => EventBlocks {
+			envelope: anchor.to_vec()?,
+			linked: None,
+		},
+	})
+}
+
+fn genesis_model(events: &[Event]) -> anyhow::Result<StreamId> {
+	match events.first().map(|x| &x.value) {
+		Some(EventValue::Signed(signed)) => Ok(signed
+			.payload()?
+			.header
+			.context("genesis without header")?
+			.model),
+		_ => anyhow::bail!("stream without signed genesis"),
+	}
+}
+
+/// Load the events of the stream and check them as the guest does, so a
+/// payload failing in the guest is rejected before proving. The signatures
+/// of the events, and the cacaos delegating to their signers, are verified
+/// as when a state is made from them
+pub async fn fetch_stream<L: EventsLoader + ?Sized>(
+	loader: &L,
+	ceramic: &Ceramic,
+	stream_id: &StreamId,
+	tip: Option<Cid>,
+) -> anyhow::Result<FetchedStream> {
+	let events = loader.load_events(ceramic, stream_id, tip).await?;
+	let tip = events.last().context("stream without events")?.cid;
+	let model = genesis_model(&events)?;
+	for event in &events {
+		event
+			.verify_signature(vec![VerifyOption::ResourceModelsContain(model.clone())])
+			.with_context(|| format!("invalid signature of event {}", event.cid))?;
+	}
+	let blocks = events
+		.iter()
+		.map(event_blocks)
+		.collect::<anyhow::Result<Vec<_>>>()?;
+	let events = encode_events(&blocks);
+
+	let model = dataverse_types_core::StreamId::from_bytes(&model.to_vec()?)?;
+	verify_chain(&events, &tip, &model)?;
+	Ok(FetchedStream {
+		stream_id: stream_id.clone(),
+		tip,
+		events,
+	})
+}
+
+/// Abi encoding of `(bytes data, (bytes streamId, bytes tip, bytes events)[])`,
+/// streams in the order of the query
+pub fn encode_payload(data: &[u8], streams: &[FetchedStream]) -> anyhow::Result<Vec<u8>> {
+	let streams = streams
+		.iter()
+		.map(FetchedStream::to_token)
+		.collect::<anyhow::Result<Vec<_>>>()?;
+	Ok(abi::encode(&[
+		Token::Bytes(data.to_vec()),
+		Token::Array(streams),
+	]))
+}
+
+#[cfg(test)]
+mod tests {
+	use dataverse_ceramic::commit::example;
+	use ethers_core::abi::ParamType;
+
+	use super::*;
+
+	struct ExampleLoader(Vec<Event>);
+
+	impl Default for ExampleLoader {
+		fn default() -> Self {
+			Self(example::events(3))
+		}
+	}
+
+	#[async_trait::async_trait]
+	impl EventsLoader for ExampleLoader {
+		async fn load_events(
+			&self,
+			_ceramic: &Ceramic,
+			_stream_id: &StreamId,
+			_tip: Option<Cid>,
+		) -> anyhow::Result<Vec<Event>> {
+			Ok(self.0.clone())
+		}
+	}
+
+	fn ceramic() -> Ceramic {
+		Ceramic {
+			endpoint: "http://localhost:7007".to_string(),
+			network: dataverse_ceramic::network::Network::InMemory,
+			api: Default::default(),
+		}
+	}
+
+	#[test]
+	fn test_encode_payload_layout() -> anyhow::Result<()> {
+		let payload = encode_payload(&[0xab], &[])?;
+		let words: Vec<String> = payload.chunks(32).map(hex::encode).collect();
+		assert_eq!(
+			words,
+			vec![
+				// offset of data
+				format!("{:064x}", 0x40),
+				// offset of streams
+				format!("{:064x}", 0x80),
+				// data
+				format!("{:064x}", 1),
+				format!("ab{}", "0".repeat(62)),
+				// streams
+				format!("{:064x}", 0),
+			]
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_encode_payload() -> anyhow::Result<()> {
+		let stream_id: StreamId =
+			"kjzl6kcym7w8y9pqrvjg79e54jk1jbintgfkmunbjil3dskk7meaavrqy5bugdf".parse()?;
+		let stream = FetchedStream {
+			tip: stream_id.cid,
+			stream_id,
+			events: vec![0x01, 0x02],
+		};
+		let expected = concat!(
+			// offset of data
+			"0000000000000000000000000000000000000000000000000000000000000040",
+			// offset of streams
+			"0000000000000000000000000000000000000000000000000000000000000080",
+			// data
+			"0000000000000000000000000000000000000000000000000000000000000001",
+			"ab00000000000000000000000000000000000000000000000000000000000000",
+			// streams
+			"0000000000000000000000000000000000000000000000000000000000000001",
+			// offset of the tuple
+			"0000000000000000000000000000000000000000000000000000000000000020",
+			// offsets of streamId, tip, events
+			"0000000000000000000000000000000000000000000000000000000000000060",
+			"00000000000000000000000000000000000000000000000000000000000000c0",
+			"0000000000000000000000000000000000000000000000000000000000000120",
+			// streamId
+			"0000000000000000000000000000000000000000000000000000000000000028",
+			"ce01030185011220c1e124c4b46939c3cbaeaf48fc6c03f077c595fdaf5da9b7",
+			"2588d885137e6d63000000000000000000000000000000000000000000000000",
+			// tip
+			"0000000000000000000000000000000000000000000000000000000000000025",
+			"0185011220c1e124c4b46939c3cbaeaf48fc6c03f077c595fdaf5da9b72588d8",
+			"85137e6d63000000000000000000000000000000000000000000000000000000",
+			// events
+			"0000000000000000000000000000000000000000000000000000000000000002",
+			"0102000000000000000000000000000000000000000000000000000000000000",
+		);
+		assert_eq!(hex::encode(encode_payload(&[0xab], &[stream])?), expected);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_fetch_stream() -> anyhow::Result<()> {
+		let stream_id = example::genesis().stream_id()?;
+		let fetched = fetch_stream(&ExampleLoader::default(), &ceramic(), &stream_id, None).await?;
+		assert_eq!(fetched.tip, example::events(3)[2].cid);
+
+		let payload = encode_payload(&[0xab], &[fetched.clone()])?;
+		let tokens = abi::decode(
+			&[
+				ParamType::Bytes,
+				ParamType::Array(Box::new(ParamType::Tuple(vec![
+					ParamType::Bytes,
+					ParamType::Bytes,
+					ParamType::Bytes,
+				]))),
+			],
+			&payload,
+		)?;
+		assert_eq!(tokens[0], Token::Bytes(vec![0xab]));
+		assert_eq!(tokens[1], Token::Array(vec![fetched.to_token()?]));
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_fetch_forged_signature() -> anyhow::Result<()> {
+		let stream_id = example::genesis().stream_id()?;
+		let mut events = example::events(3);
+		// the signature of another payload of the same signer
+		let signature = match &events[2].value {
+			EventValue::Signed(signed) => signed.jws.signatures.clone(),
+			_ => anyhow::bail!("data event is not signed"),
+		};
+		if let EventValue::Signed(signed) = &mut events[1].value {
+			signed.jws.signatures = signature;
+		}
+		let fetched = fetch_stream(&ExampleLoader(events), &ceramic(), &stream_id, None).await;
+		assert!(fetched.is_err());
+		Ok(())
+	}
+}
@@ -0,0 +1,473 @@
+pub mod fetch;
+pub mod zkvm;
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::Context;
+use ceramic_core::{Cid, StreamId};
+use chrono::{DateTime, Utc};
+use dataverse_ceramic::publisher::{Attestation, AttestationPublisher};
+use dataverse_ceramic::{Ceramic, StreamLoader};
+use fang::async_trait;
+use fang::asynk::async_queue::AsyncQueueable;
+use fang::typetag;
+use fang::AsyncRunnable;
+use fang::FangError;
+use serde::{Deserialize, Serialize};
+
+use self::fetch::{encode_payload, fetch_stream};
+use crate::task::{missing_context, ContextTask};
+
+/// Failed steps of a job before it is marked as failed
+pub const MAX_ATTEMPTS: i32 = 5;
+
+/// Received -> Fetched -> Proved -> Published, or Failed once a step has
+/// failed `MAX_ATTEMPTS` times
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum QueryJobState {
+	Received,
+	Fetched,
+	Proved,
+	Published,
+	Failed,
+}
+
+impl QueryJobState {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Self::Received => "received",
+			Self::Fetched => "fetched",
+			Self::Proved => "proved",
+			Self::Published => "published",
+			Self::Failed => "failed",
+		}
+	}
+
+	pub fn is_terminal(&self) -> bool {
+		matches!(self, Self::Published | Self::Failed)
+	}
+}
+
+impl FromStr for QueryJobState {
+	type Err = anyhow::Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"received" => Ok(Self::Received),
+			"fetched" => Ok(Self::Fetched),
+			"proved" => Ok(Self::Proved),
+			"published" => Ok(Self::Published),
+			"failed" => Ok(Self::Failed),
+			_ => anyhow::bail!("invalid query job state {}", s),
+		}
+	}
+}
+
+/// Computation requested by a query, persisted with the output of each step
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryJob {
+	pub query_id: String,
+	/// abi encoded arguments of the query
+	pub data: Vec<u8>,
+	/// streams read by the computation, the first is the attested one
+	pub stream_ids: Vec<StreamId>,
+	pub state: QueryJobState,
+	/// tips of `stream_ids` when fetched
+	pub tips: Vec<Cid>,
+	pub payload: Option<Vec<u8>>,
+	pub journal: Option<Vec<u8>>,
+	pub seal: Option<Vec<u8>>,
+	pub tx_hash: Option<String>,
+	pub attempts: i32,
+	pub error: Option<String>,
+	pub created_at: DateTime<Utc>,
+	pub updated_at: DateTime<Utc>,
+}
+
+impl QueryJob {
+	pub fn new(query_id: String, data: Vec<u8>, stream_ids: Vec<StreamId>) -> Self {
+		let now = Utc::now();
+		Self {
+			query_id,
+			data,
+			stream_ids,
+			state: QueryJobState::Received,
+			tips: vec![],
+			payload: None,
+			journal: None,
+			seal: None,
+			tx_hash: None,
+			attempts: 0,
+			error: None,
+			created_at: now,
+			updated_at: now,
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryJobStatus {
+	pub query_id: String,
+	pub state: QueryJobState,
+	pub attempts: i32,
+	pub error: Option<String>,
+	pub tx_hash: Option<String>,
+	pub updated_at: DateTime<Utc>,
+}
+
+impl From<&QueryJob> for QueryJobStatus {
+	fn from(job: &QueryJob) -> Self {
+		Self {
+			query_id: job.query_id.clone(),
+			state: job.state,
+			attempts: job.attempts,
+			error: job.error.clone(),
+			tx_hash: job.tx_hash.clone(),
+			updated_at: job.updated_at,
+		}
+	}
+}
+
+#[async_trait]
+pub trait QueryJobStore: Send + Sync {
+	/// Insert the job or update the one of its query
+	async fn save_job(&self, job: &QueryJob) -> anyhow::Result<()>;
+
+	async fn load_job(&self, query_id: &str) -> anyhow::Result<Option<QueryJob>>;
+}
+
+pub struct Receipt {
+	pub journal: Vec<u8>,
+	pub seal: Vec<u8>,
+}
+
+#[async_trait]
+pub trait Prover: Send + Sync {
+	async fn prove(&self, payload: &[u8]) -> anyhow::Result<Receipt>;
+}
+
+pub struct QueryJobRunner {
+	pub ceramic: Ceramic,
+	pub loader: Arc<dyn StreamLoader>,
+	pub prover: Arc<dyn Prover>,
+	pub publisher: Arc<dyn AttestationPublisher>,
+	pub store: Arc<dyn QueryJobStore>,
+}
+
+impl QueryJobRunner {
+	/// Persist the job of a query and queue it, a query already received is
+	/// returned as is
+	pub async fn submit(
+		&self,
+		queue: &mut dyn AsyncQueueable,
+		query_id: &str,
+		data: Vec<u8>,
+		stream_ids: Vec<StreamId>,
+	) -> anyhow::Result<QueryJob> {
+		if let Some(job) = self.store.load_job(query_id).await? {
+			return Ok(job);
+		}
+		let job = QueryJob::new(query_id.to_string(), data, stream_ids);
+		self.store.save_job(&job).await?;
+		queue
+			.insert_task(&QueryJobHandler {
+				query_id: query_id.to_string(),
+			})
+			.await?;
+		Ok(job)
+	}
+
+	pub async fn job_status(&self, query_id: &str) -> anyhow::Result<Option<QueryJobStatus>> {
+		let job = self.store.load_job(query_id).await?;
+		Ok(job.as_ref().map(QueryJobStatus::from))
+	}
+
+	async fn step(&self, job: &mut QueryJob) -> anyhow::Result<()> {
+		match job.state {
+			QueryJobState::Received => {
+				let mut streams = Vec::with_capacity(job.stream_ids.len());
+				for stream_id in &job.stream_ids {
+					let stream =
+						fetch_stream(self.loader.as_ref(), &self.ceramic, stream_id, None).await?;
+					streams.push(stream);
+				}
+				job.tips = streams.iter().map(|x| x.tip).collect();
+				job.payload = Some(encode_payload(&job.data, &streams)?);
+				job.state = QueryJobState::Fetched;
+			}
+			QueryJobState::Fetched => {
+				let payload = job.payload.as_deref().context("missing payload")?;
+				let receipt = self.prover.prove(payload).await?;
+				job.journal = Some(receipt.journal);
+				job.seal = Some(receipt.seal);
+				job.state = QueryJobState::Proved;
+			}
+			QueryJobState::Proved => {
+				let attestation = Attestation {
+					stream_id: job.stream_ids.first().context("no stream queried")?.clone(),
+					commit: *job.tips.first().context("no stream fetched")?,
+					journal: job.journal.clone().context("missing journal")?,
+					seal: job.seal.clone().context("missing seal")?,
+				};
+				let record = self.publisher.publish(&attestation).await?;
+				job.tx_hash = Some(format!("{:?}", record.tx_hash));
+				job.state = QueryJobState::Published;
+			}
+			QueryJobState::Published | QueryJobState::Failed => {}
+		}
+		Ok(())
+	}
+
+	/// Advance the job to a terminal state, saving it after each step so a
+	/// retry resumes from the failed step
+	pub async fn run(&self, query_id: &str) -> anyhow::Result<QueryJobState> {
+		let mut job = match self.store.load_job(query_id).await? {
+			Some(job) => job,
+			None => anyhow::bail!("query job {} not found", query_id),
+		};
+		while !job.state.is_terminal() {
+			let result = self.step(&mut job).await;
+			job.updated_at = Utc::now();
+			match result {
+				Ok(_) => {
+					job.error = None;
+					self.store.save_job(&job).await?;
+				}
+				Err(err) => {
+					job.attempts += 1;
+					job.error = Some(format!("{:#}", err));
+					if job.attempts >= MAX_ATTEMPTS {
+						job.state = QueryJobState::Failed;
+					}
+					self.store.save_job(&job).await?;
+					if !job.state.is_terminal() {
+						return Err(err);
+					}
+				}
+			}
+		}
+		Ok(job.state)
+	}
+}
+
+/// Queued run of a query job, run by a `ContextWorker<QueryJobHandler>`
+/// holding the runner
+#[derive(fang::serde::Serialize, fang::serde::Deserialize)]
+#[serde(crate = "fang::serde")]
+pub struct QueryJobHandler {
+	pub query_id: String,
+}
+
+#[async_trait]
+impl ContextTask for QueryJobHandler {
+	type Context = QueryJobRunner;
+
+	const TASK_TYPE: &'static str = "query_job";
+	const MAX_RETRIES: i32 = MAX_ATTEMPTS;
+
+	async fn run_with(&self, runner: &QueryJobRunner) -> anyhow::Result<()> {
+		match runner.run(&self.query_id).await {
+			Ok(state) => {
+				log::info!("query job {} {}", self.query_id, state.as_str());
+				Ok(())
+			}
+			Err(err) => {
+				log::warn!("query job {} failed: {:#}", self.query_id, err);
+				Err(err)
+			}
+		}
+	}
+}
+
+#[async_trait]
+#[typetag::serde]
+impl AsyncRunnable for QueryJobHandler {
+	async fn run(&self, _queue: &mut dyn AsyncQueueable) -> Result<(), FangError> {
+		Err(missing_context(Self::TASK_TYPE))
+	}
+
+	fn task_type(&self) -> String {
+		Self::TASK_TYPE.to_string()
+	}
+
+	fn uniq(&self) -> bool {
+		true
+	}
+
+	fn max_retries(&self) -> i32 {
+		MAX_ATTEMPTS
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashMap;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	use dataverse_ceramic::commit::example;
+	use dataverse_ceramic::event::Event;
+	use dataverse_ceramic::publisher::{AttestationRecord, AttestationStatus};
+	use dataverse_ceramic::EventsLoader;
+	use tokio::sync::Mutex;
+
+	use super::*;
+
+	#[derive(Default)]
+	struct MemoryJobStore(Mutex<HashMap<String, QueryJob>>);
+
+	#[async_trait]
+	impl QueryJobStore for MemoryJobStore {
+		async fn save_job(&self, job: &QueryJob) -> anyhow::Result<()> {
+			let mut jobs = self.0.lock().await;
+			jobs.insert(job.query_id.clone(), job.clone());
+			Ok(())
+		}
+
+		async fn load_job(&self, query_id: &str) -> anyhow::Result<Option<QueryJob>> {
+			Ok(self.0.lock().await.get(query_id).cloned())
+		}
+	}
+
+	struct ExampleLoader;
+
+	#[async_trait]
+	impl EventsLoader for ExampleLoader {
+		async fn load_events(
+			&self,
+			_ceramic: &Ceramic,
+			_stream_id: &StreamId,
+			_tip: Option<Cid>,
+		) -> anyhow::Result<Vec<Event>> {
+			Ok(example::events(2))
+		}
+	}
+
+	impl StreamLoader for ExampleLoader {}
+
+	/// Fails the first `failures` proofs
+	struct FlakyProver {
+		failures: usize,
+		calls: AtomicUsize,
+	}
+
+	#[async_trait]
+	impl Prover for FlakyProver {
+		async fn prove(&self, payload: &[u8]) -> anyhow::Result<Receipt> {
+			if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+				anyhow::bail!("prover unavailable");
+			}
+			Ok(Receipt {
+				journal: payload.to_vec(),
+				seal: vec![1],
+			})
+		}
+	}
+
+	struct MockPublisher;
+
+	#[async_trait]
+	impl AttestationPublisher for MockPublisher {
+		async fn publish(&self, attestation: &Attestation) -> anyhow::Result<AttestationRecord> {
+			Ok(AttestationRecord {
+				tx_hash: Default::default(),
+				chain_id: 1,
+				contract: Default::default(),
+				stream_id: attestation.stream_id.clone(),
+				commit: attestation.commit,
+				status: AttestationStatus::Pending,
+				block_number: None,
+				created_at: Utc::now(),
+				updated_at: Utc::now(),
+			})
+		}
+	}
+
+	fn runner(failures: usize, store: Arc<MemoryJobStore>) -> QueryJobRunner {
+		QueryJobRunner {
+			ceramic: Ceramic {
+				endpoint: "http://localhost:7007".to_string(),
+				network: dataverse_ceramic::network::Network::InMemory,
+				api: Default::default(),
+			},
+			loader: Arc::new(ExampleLoader),
+			prover: Arc::new(FlakyProver {
+				failures,
+				calls: AtomicUsize::new(0),
+			}),
+			publisher: Arc::new(MockPublisher),
+			store,
+		}
+	}
+
+	#[tokio::test]
+	async fn test_run_query_job() -> anyhow::Result<()> {
+		let store = Arc::new(MemoryJobStore::default());
+		let stream_id = example::genesis().stream_id()?;
+		let job = QueryJob::new("q1".to_string(), vec![0xab], vec![stream_id]);
+		store.save_job(&job).await?;
+
+		let runner = runner(1, store.clone());
+		assert!(runner.run("q1").await.is_err());
+		let status = runner.job_status("q1").await?.unwrap();
+		assert_eq!(status.state, QueryJobState::Fetched);
+		assert_eq!(status.attempts, 1);
+		assert_eq!(status.error.as_deref(), Some("prover unavailable"));
+
+		assert_eq!(runner.run("q1").await?, QueryJobState::Published);
+		let job = store.load_job("q1").await?.unwrap();
+		assert_eq!(job.error, None);
+		assert!(job.tx_hash.is_some());
+		assert_eq!(job.tips.len(), 1);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_query_job_fails_after_max_attempts() -> anyhow::Result<()> {
+		let store = Arc::new(MemoryJobStore::default());
+		let stream_id = example::genesis().stream_id()?;
+		let job = QueryJob::new("q2".to_string(), vec![], vec![stream_id]);
+		store.save_job(&job).await?;
+
+		let runner = runner(usize::MAX, store);
+		for _ in 1..MAX_ATTEMPTS {
+			assert!(runner.run("q2").await.is_err());
+		}
+		assert_eq!(runner.run("q2").await?, QueryJobState::Failed);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_handler_runs_with_runner() -> anyhow::Result<()> {
+		let store = Arc::new(MemoryJobStore::default());
+		let stream_id = example::genesis().stream_id()?;
+		let job = QueryJob::new("q3".to_string(), vec![], vec![stream_id]);
+		store.save_job(&job).await?;
+
+		let handler = QueryJobHandler {
+			query_id: "q3".to_string(),
+		};
+		assert_eq!(handler.task_type(), QueryJobHandler::TASK_TYPE);
+		handler.run_with(&runner(0, store.clone())).await?;
+		let job = store.load_job("q3").await?.unwrap();
+		assert_eq!(job.state, QueryJobState::Published);
+		Ok(())
+	}
+
+	#[test]
+	fn test_query_job_state() -> anyhow::Result<()> {
+		for state in [
+			QueryJobState::Received,
+			QueryJobState::Fetched,
+			QueryJobState::Proved,
+			QueryJobState::Published,
+			QueryJobState::Failed,
+		] {
+			assert_eq!(state.as_str().parse::<QueryJobState>()?, state);
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,199 @@
+use std::str::FromStr;
+
+use ethers_core::abi::Token;
+use ethers_core::types::{H160, U256};
+use ethers_core::utils::to_checksum;
+use serde_json::Value;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ValueError {
+	InvalidAddress(String),
+	InvalidChecksum(String),
+	InvalidUint(String),
+	UnexpectedType(&'static str, Value),
+	MissingField(String),
+}
+
+impl std::fmt::Display for ValueError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::InvalidAddress(value) => write!(f, "invalid address {}", value),
+			Self::InvalidChecksum(value) => write!(f, "invalid address checksum {}", value),
+			Self::InvalidUint(value) => write!(f, "invalid uint256 {}", value),
+			Self::UnexpectedType(expected, value) => {
+				write!(f, "expected {}, got {}", expected, value)
+			}
+			Self::MissingField(key) => write!(f, "missing field {}", key),
+		}
+	}
+}
+
+impl std::error::Error for ValueError {}
+
+/// Address input of a guest program, from a `0x` hex string which must match
+/// its EIP-55 checksum when in mixed case
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Address(pub H160);
+
+impl Address {
+	pub fn to_token(&self) -> Token {
+		Token::Address(self.0)
+	}
+}
+
+impl FromStr for Address {
+	type Err = ValueError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let digits = s.strip_prefix("0x").unwrap_or(s);
+		let bytes = match hex::decode(digits) {
+			Ok(bytes) if bytes.len() == 20 => bytes,
+			_ => return Err(ValueError::InvalidAddress(s.to_string())),
+		};
+		let address = H160::from_slice(&bytes);
+		let mixed_case = digits.chars().any(|x| x.is_ascii_lowercase())
+			&& digits.chars().any(|x| x.is_ascii_uppercase());
+		if mixed_case && to_checksum(&address, None)[2..] != *digits {
+			return Err(ValueError::InvalidChecksum(s.to_string()));
+		}
+		Ok(Self(address))
+	}
+}
+
+impl TryFrom<&Value> for Address {
+	type Error = ValueError;
+
+	fn try_from(value: &Value) -> Result<Self, Self::Error> {
+		match value {
+			Value::String(s) => s.parse(),
+			_ => Err(ValueError::UnexpectedType("address string", value.clone())),
+		}
+	}
+}
+
+impl TryFrom<Value> for Address {
+	type Error = ValueError;
+
+	fn try_from(value: Value) -> Result<Self, Self::Error> {
+		Self::try_from(&value)
+	}
+}
+
+/// Uint256 input of a guest program, from a decimal or `0x` hex string or a
+/// non-negative integer, rejecting values above `U256::MAX`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Uint(pub U256);
+
+impl Uint {
+	pub fn to_token(&self) -> Token {
+		Token::Uint(self.0)
+	}
+}
+
+impl FromStr for Uint {
+	type Err = ValueError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let value = match s.strip_prefix("0x") {
+			Some(digits) if !digits.is_empty() && digits.len() <= 64 => {
+				U256::from_str_radix(digits, 16).ok()
+			}
+			Some(_) => None,
+			None => U256::from_dec_str(s).ok(),
+		};
+		value
+			.map(Self)
+			.ok_or_else(|| ValueError::InvalidUint(s.to_string()))
+	}
+}
+
+impl TryFrom<&Value> for Uint {
+	type Error = ValueError;
+
+	fn try_from(value: &Value) -> Result<Self, Self::Error> {
+		match value {
+			Value::String(s) => s.parse(),
+			Value::Number(n) => match n.as_u64() {
+				Some(n) => Ok(Self(U256::from(n))),
+				None => Err(ValueError::InvalidUint(n.to_string())),
+			},
+			_ => Err(ValueError::UnexpectedType("uint256", value.clone())),
+		}
+	}
+}
+
+impl TryFrom<Value> for Uint {
+	type Error = ValueError;
+
+	fn try_from(value: Value) -> Result<Self, Self::Error> {
+		Self::try_from(&value)
+	}
+}
+
+/// Input of a guest program from a top-level field of stream content, e.g.
+/// `from_content::<Address>(&state.content, "owner")`
+pub fn from_content<T>(content: &Value, key: &str) -> Result<T, ValueError>
+where
+	T: for<'a> TryFrom<&'a Value, Error = ValueError>,
+{
+	match content.get(key) {
+		Some(value) => T::try_from(value),
+		None => Err(ValueError::MissingField(key.to_string())),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+
+	#[test]
+	fn test_address() {
+		let checksummed = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+		let address: Address = checksummed.parse().unwrap();
+		assert_eq!(to_checksum(&address.0, None), checksummed);
+		assert_eq!(checksummed.to_lowercase().parse::<Address>(), Ok(address));
+		assert_eq!(
+			"0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD".parse::<Address>(),
+			Err(ValueError::InvalidChecksum(
+				"0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD".to_string()
+			))
+		);
+		assert!("0x5aAeb6".parse::<Address>().is_err());
+		assert_eq!(address.to_token(), Token::Address(address.0));
+	}
+
+	#[test]
+	fn test_uint() {
+		assert_eq!("1000".parse::<Uint>(), Ok(Uint(U256::from(1000))));
+		assert_eq!("0x3e8".parse::<Uint>(), Ok(Uint(U256::from(1000))));
+		assert_eq!(Uint::try_from(json!(1000)), Ok(Uint(U256::from(1000))));
+		let max = U256::MAX.to_string();
+		assert_eq!(max.parse::<Uint>(), Ok(Uint(U256::MAX)));
+		let overflow = format!("{}0", max);
+		assert!(overflow.parse::<Uint>().is_err());
+		assert!(format!("0x1{}", "0".repeat(64)).parse::<Uint>().is_err());
+		assert!(Uint::try_from(json!(-1)).is_err());
+		assert!(Uint::try_from(json!(1.5)).is_err());
+	}
+
+	#[test]
+	fn test_from_content() {
+		let content = json!({
+			"owner": "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed",
+			"amount": "42",
+		});
+		let owner: Address = from_content(&content, "owner").unwrap();
+		let amount: Uint = from_content(&content, "amount").unwrap();
+		assert_eq!(amount.to_token(), Token::Uint(U256::from(42)));
+		assert_eq!(
+			Token::Tuple(vec![owner.to_token(), amount.to_token()]),
+			Token::Tuple(vec![Token::Address(owner.0), Token::Uint(U256::from(42))])
+		);
+		assert_eq!(
+			from_content::<Uint>(&content, "missing"),
+			Err(ValueError::MissingField("missing".to_string()))
+		);
+	}
+}
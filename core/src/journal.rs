@@ -0,0 +1,254 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use chrono::{DateTime, Utc};
+use dataverse_ceramic::event::{Event, EventsUploader};
+use dataverse_ceramic::{Ceramic, StreamId};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+/// Commit accepted by the write path but maybe not uploaded yet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+	pub ceramic: Ceramic,
+	pub stream_id: StreamId,
+	pub event: Event,
+	pub accepted_at: DateTime<Utc>,
+}
+
+/// Write-ahead journal of accepted commits, replayed on startup
+#[async_trait::async_trait]
+pub trait CommitJournal: Send + Sync {
+	async fn append(&self, entry: &JournalEntry) -> anyhow::Result<()>;
+	/// mark the commit as uploaded
+	async fn complete(&self, cid: &str) -> anyhow::Result<()>;
+	/// accepted commits not completed, deduplicated by commit cid
+	async fn pending(&self) -> anyhow::Result<Vec<JournalEntry>>;
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+enum Record {
+	Accept { entry: JournalEntry },
+	Complete { cid: String },
+}
+
+/// Records written between two compactions of a [`FileJournal`] by default
+pub const COMPACT_EVERY: usize = 1000;
+
+/// Append-only json lines file, compacted once `compact_every` records were
+/// written since the last compaction
+pub struct FileJournal {
+	path: PathBuf,
+	lock: Mutex<()>,
+	written: AtomicUsize,
+	compact_every: usize,
+}
+
+impl FileJournal {
+	pub fn new(path: impl Into<PathBuf>) -> Self {
+		Self {
+			path: path.into(),
+			lock: Mutex::new(()),
+			written: AtomicUsize::new(0),
+			compact_every: COMPACT_EVERY,
+		}
+	}
+
+	pub fn with_compact_every(self, compact_every: usize) -> Self {
+		Self {
+			compact_every: compact_every.max(1),
+			..self
+		}
+	}
+
+	async fn write(&self, record: &Record) -> anyhow::Result<()> {
+		let mut line = serde_json::to_vec(record)?;
+		line.push(b'\n');
+		let _guard = self.lock.lock().await;
+		let mut file = tokio::fs::OpenOptions::new()
+			.create(true)
+			.append(true)
+			.open(&self.path)
+			.await?;
+		file.write_all(&line).await?;
+		file.sync_data().await?;
+		self.written.fetch_add(1, Ordering::SeqCst);
+		Ok(())
+	}
+
+	async fn records(&self) -> anyhow::Result<Vec<Record>> {
+		let content = match tokio::fs::read_to_string(&self.path).await {
+			Ok(content) => content,
+			Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+			Err(err) => return Err(err.into()),
+		};
+		let mut records = vec![];
+		for line in content.lines().filter(|line| !line.trim().is_empty()) {
+			match serde_json::from_str(line) {
+				Ok(record) => records.push(record),
+				// a crash while appending leaves a truncated last line
+				Err(err) => log::warn!("skip malformed journal record: {}", err),
+			}
+		}
+		Ok(records)
+	}
+
+	/// Rewrite the journal with pending entries only
+	pub async fn compact(&self) -> anyhow::Result<()> {
+		let _guard = self.lock.lock().await;
+		let pending = self.pending().await?;
+		let mut content = vec![];
+		for entry in pending {
+			content.extend(serde_json::to_vec(&Record::Accept { entry })?);
+			content.push(b'\n');
+		}
+		let tmp = self.path.with_extension("tmp");
+		tokio::fs::write(&tmp, content).await?;
+		tokio::fs::rename(&tmp, &self.path).await?;
+		self.written.store(0, Ordering::SeqCst);
+		Ok(())
+	}
+}
+
+#[async_trait::async_trait]
+impl CommitJournal for FileJournal {
+	async fn append(&self, entry: &JournalEntry) -> anyhow::Result<()> {
+		self.write(&Record::Accept {
+			entry: entry.clone(),
+		})
+		.await
+	}
+
+	async fn complete(&self, cid: &str) -> anyhow::Result<()> {
+		self.write(&Record::Complete {
+			cid: cid.to_string(),
+		})
+		.await?;
+		// completed commits are only dropped from the file when compacting
+		if self.written.load(Ordering::SeqCst) >= self.compact_every {
+			self.compact().await?;
+		}
+		Ok(())
+	}
+
+	async fn pending(&self) -> anyhow::Result<Vec<JournalEntry>> {
+		let records = self.records().await?;
+		let completed: HashSet<String> = records
+			.iter()
+			.filter_map(|record| match record {
+				Record::Complete { cid } => Some(cid.clone()),
+				_ => None,
+			})
+			.collect();
+		let mut seen = HashSet::new();
+		let mut pending = vec![];
+		for record in records {
+			if let Record::Accept { entry } = record {
+				let cid = entry.event.cid.to_string();
+				if !completed.contains(&cid) && seen.insert(cid) {
+					pending.push(entry);
+				}
+			}
+		}
+		Ok(pending)
+	}
+}
+
+/// Upload pending commits of the journal, returns the number replayed
+pub async fn replay<U>(journal: &dyn CommitJournal, uploader: &U) -> anyhow::Result<usize>
+where
+	U: EventsUploader + Send + Sync + ?Sized,
+{
+	let mut count = 0;
+	for entry in journal.pending().await? {
+		let cid = entry.event.cid.to_string();
+		match uploader
+			.upload_event(&entry.ceramic, &entry.stream_id, entry.event)
+			.await
+		{
+			Ok(_) => {
+				journal.complete(&cid).await?;
+				count += 1;
+			}
+			Err(err) => log::error!("failed to replay commit {}: {}", cid, err),
+		}
+	}
+	Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+	use dataverse_ceramic::network::Network;
+
+	use super::*;
+
+	#[tokio::test]
+	async fn test_file_journal() -> anyhow::Result<()> {
+		let path = std::env::temp_dir().join(format!("journal-{}.jsonl", uuid::Uuid::new_v4()));
+		let journal = FileJournal::new(&path);
+		let genesis = dataverse_ceramic::commit::example::genesis();
+		let entry = JournalEntry {
+			ceramic: Ceramic {
+				endpoint: "http://localhost:7007".to_string(),
+				network: Network::InMemory,
+				api: Default::default(),
+			},
+			stream_id: genesis.stream_id()?,
+			event: genesis.genesis.try_into()?,
+			accepted_at: Utc::now(),
+		};
+
+		journal.append(&entry).await?;
+		journal.append(&entry).await?;
+		assert_eq!(journal.pending().await?.len(), 1);
+
+		journal.complete(&entry.event.cid.to_string()).await?;
+		assert!(journal.pending().await?.is_empty());
+
+		journal.append(&entry).await?;
+		journal.compact().await?;
+		assert!(journal.pending().await?.is_empty());
+
+		tokio::fs::remove_file(&path).await?;
+		Ok(())
+	}
+
+	async fn lines(path: &std::path::Path) -> anyhow::Result<usize> {
+		Ok(tokio::fs::read_to_string(path).await?.lines().count())
+	}
+
+	#[tokio::test]
+	async fn test_compact_every() -> anyhow::Result<()> {
+		let path = std::env::temp_dir().join(format!("journal-{}.jsonl", uuid::Uuid::new_v4()));
+		let journal = FileJournal::new(&path).with_compact_every(4);
+		let events = dataverse_ceramic::commit::example::events(2);
+		let entry = |event: &Event| JournalEntry {
+			ceramic: Ceramic {
+				endpoint: "http://localhost:7007".to_string(),
+				network: Network::InMemory,
+				api: Default::default(),
+			},
+			stream_id: dataverse_ceramic::commit::example::genesis()
+				.stream_id()
+				.unwrap(),
+			event: event.clone(),
+			accepted_at: Utc::now(),
+		};
+
+		journal.append(&entry(&events[0])).await?;
+		journal.complete(&events[0].cid.to_string()).await?;
+		journal.append(&entry(&events[1])).await?;
+		assert_eq!(lines(&path).await?, 3);
+		// the fourth record compacts the file down to the pending commit
+		journal.complete(&events[0].cid.to_string()).await?;
+		assert_eq!(lines(&path).await?, 1);
+		assert_eq!(journal.pending().await?[0].event.cid, events[1].cid);
+
+		tokio::fs::remove_file(&path).await?;
+		Ok(())
+	}
+}
@@ -1,3 +1,12 @@
+pub mod bloom;
+pub mod computa;
+pub mod journal;
+pub mod lock;
+pub mod mirror;
+pub mod notifier;
+pub mod server;
 pub mod store;
 pub mod stream;
 pub mod task;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Weak};
+
+use ceramic_core::StreamId;
+use once_cell::sync::Lazy;
+use tokio::sync::OwnedMutexGuard;
+
+/// Process wide locks serializing local mutations of a stream
+pub static STREAM_LOCKS: Lazy<StreamLocks> = Lazy::new(StreamLocks::default);
+
+/// Async lock per stream, entries are dropped once no guard or waiter holds them
+#[derive(Debug, Default)]
+pub struct StreamLocks {
+	locks: Mutex<HashMap<String, Weak<tokio::sync::Mutex<()>>>>,
+}
+
+impl StreamLocks {
+	/// Wait until no other task holds the lock of the stream
+	pub async fn lock(&self, stream_id: &StreamId) -> OwnedMutexGuard<()> {
+		let lock = {
+			let mut locks = self.locks.lock().expect("stream locks poisoned");
+			locks.retain(|_, lock| lock.strong_count() > 0);
+			match locks.get(&stream_id.to_string()).and_then(Weak::upgrade) {
+				Some(lock) => lock,
+				None => {
+					let lock = Arc::new(tokio::sync::Mutex::new(()));
+					locks.insert(stream_id.to_string(), Arc::downgrade(&lock));
+					lock
+				}
+			}
+		};
+		lock.lock_owned().await
+	}
+
+	/// Number of streams currently locked or waited on
+	pub fn len(&self) -> usize {
+		let locks = self.locks.lock().expect("stream locks poisoned");
+		locks
+			.values()
+			.filter(|lock| lock.strong_count() > 0)
+			.count()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::*;
+
+	#[tokio::test]
+	async fn test_stream_lock() {
+		let locks = Arc::new(StreamLocks::default());
+		let stream_id: StreamId = "kjzl6kcym7w8y8syiams0kvm3qwfnutk2szi0wlhvf6rr9lalzpibxed0qvotuy"
+			.parse()
+			.unwrap();
+		let other: StreamId = "kjzl6kcym7w8y9s94kcardbh5u0ao76bci07xnnxjw1ew3i4eackykj76uagqfk"
+			.parse()
+			.unwrap();
+
+		let guard = locks.lock(&stream_id).await;
+		// other streams are not blocked
+		drop(locks.lock(&other).await);
+
+		let task = {
+			let locks = locks.clone();
+			let stream_id = stream_id.clone();
+			tokio::spawn(async move {
+				let _guard = locks.lock(&stream_id).await;
+			})
+		};
+		tokio::time::sleep(Duration::from_millis(20)).await;
+		assert!(!task.is_finished());
+
+		drop(guard);
+		task.await.unwrap();
+		assert!(locks.is_empty());
+	}
+}
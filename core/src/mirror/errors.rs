@@ -0,0 +1,24 @@
+use ceramic_core::StreamId;
+
+#[derive(Debug)]
+pub enum MirrorError {
+	ReadOnly,
+	NoModelsConfigured,
+	DuplicateModel(StreamId),
+	InvalidInterval,
+}
+
+impl std::fmt::Display for MirrorError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::ReadOnly => write!(f, "mirror is read-only, writes are rejected"),
+			Self::NoModelsConfigured => write!(f, "mirror has no models configured"),
+			Self::DuplicateModel(model_id) => {
+				write!(f, "model `{}` configured more than once", model_id)
+			}
+			Self::InvalidInterval => write!(f, "mirror backfill interval must be positive"),
+		}
+	}
+}
+
+impl std::error::Error for MirrorError {}
@@ -0,0 +1,271 @@
+mod errors;
+
+pub use errors::MirrorError;
+
+use std::ops::Deref;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use ceramic_core::{Cid, StreamId};
+use dataverse_ceramic::event::{Event, EventsUploader};
+use dataverse_ceramic::interests::{InterestChange, Interests};
+use dataverse_ceramic::{Ceramic, StreamState, StreamsLoader};
+
+use crate::store::dapp::{self, Lookup};
+use crate::stream::{Stream, StreamStore};
+
+/// Models initially served by a read-only mirror and how often they are
+/// backfilled
+#[derive(Debug, Clone)]
+pub struct MirrorConfig {
+	pub models: Vec<StreamId>,
+	pub interval: Duration,
+}
+
+impl MirrorConfig {
+	pub fn new(models: Vec<StreamId>) -> Self {
+		Self {
+			models,
+			interval: Duration::from_secs(60),
+		}
+	}
+
+	pub fn validate(&self) -> anyhow::Result<()> {
+		if self.models.is_empty() {
+			anyhow::bail!(MirrorError::NoModelsConfigured);
+		}
+		if self.interval.is_zero() {
+			anyhow::bail!(MirrorError::InvalidInterval);
+		}
+		for (idx, model) in self.models.iter().enumerate() {
+			if self.models[..idx].contains(model) {
+				anyhow::bail!(MirrorError::DuplicateModel(model.clone()));
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Wrapper rejecting every write with `MirrorError::ReadOnly`
+pub struct ReadOnly<T>(pub T);
+
+#[async_trait::async_trait]
+impl<T> StreamStore for ReadOnly<T>
+where
+	T: Deref + Send + Sync,
+	T::Target: StreamStore,
+{
+	async fn save_stream(&self, _stream: &Stream) -> anyhow::Result<()> {
+		anyhow::bail!(MirrorError::ReadOnly)
+	}
+
+	async fn load_stream(&self, stream_id: &StreamId) -> anyhow::Result<Option<Stream>> {
+		self.0.load_stream(stream_id).await
+	}
+
+	async fn list_all_streams(&self) -> anyhow::Result<Vec<Stream>> {
+		self.0.list_all_streams().await
+	}
+
+	async fn list_model_streams(&self, model_id: &StreamId) -> anyhow::Result<Vec<Stream>> {
+		self.0.list_model_streams(model_id).await
+	}
+
+	async fn list_stream_ids(&self) -> anyhow::Result<Vec<StreamId>> {
+		self.0.list_stream_ids().await
+	}
+
+	async fn remove_model_streams(&self, _model_id: &StreamId) -> anyhow::Result<usize> {
+		anyhow::bail!(MirrorError::ReadOnly)
+	}
+
+	async fn remove_stream(&self, _stream_id: &StreamId) -> anyhow::Result<bool> {
+		anyhow::bail!(MirrorError::ReadOnly)
+	}
+
+	async fn add_stream_label(&self, _stream_id: &StreamId, _label: &str) -> anyhow::Result<()> {
+		anyhow::bail!(MirrorError::ReadOnly)
+	}
+
+	async fn remove_stream_label(
+		&self,
+		_stream_id: &StreamId,
+		_label: &str,
+	) -> anyhow::Result<bool> {
+		anyhow::bail!(MirrorError::ReadOnly)
+	}
+
+	async fn stream_labels(&self, stream_id: &StreamId) -> anyhow::Result<Vec<String>> {
+		self.0.stream_labels(stream_id).await
+	}
+
+	async fn streams_with_label(&self, label: &str) -> anyhow::Result<Vec<StreamId>> {
+		self.0.streams_with_label(label).await
+	}
+
+	async fn filter_labeled(
+		&self,
+		label: &str,
+		stream_ids: &[StreamId],
+	) -> anyhow::Result<Vec<StreamId>> {
+		self.0.filter_labeled(label, stream_ids).await
+	}
+}
+
+#[async_trait::async_trait]
+impl<T: Sync + Send> EventsUploader for ReadOnly<T> {
+	async fn upload_event(
+		&self,
+		_ceramic: &Ceramic,
+		_stream_id: &StreamId,
+		_event: Event,
+	) -> anyhow::Result<()> {
+		anyhow::bail!(MirrorError::ReadOnly)
+	}
+}
+
+pub struct Mirror {
+	pub config: MirrorConfig,
+	pub loader: Arc<dyn StreamsLoader>,
+	store: Arc<dyn StreamStore>,
+	interests: Arc<Interests>,
+}
+
+impl Mirror {
+	pub fn new(
+		config: MirrorConfig,
+		loader: Arc<dyn StreamsLoader>,
+		store: Arc<dyn StreamStore>,
+	) -> anyhow::Result<Self> {
+		config.validate()?;
+		let interests = Arc::new(Interests::new(config.models.clone()));
+		Ok(Self {
+			config,
+			loader,
+			store,
+			interests,
+		})
+	}
+
+	/// Models replicated by the mirror, changed at runtime to backfill or
+	/// prune models
+	pub fn interests(&self) -> Arc<Interests> {
+		self.interests.clone()
+	}
+
+	/// Replicate the models of a dapp, returns the number of added models.
+	/// The models are fetched, a dapp added to the mirror is usually not
+	/// cached yet
+	pub async fn add_dapp(&self, dapp_id: &uuid::Uuid) -> anyhow::Result<usize> {
+		let models = dapp::get_models(dapp_id, Lookup::Fetch).await?;
+		Ok(models
+			.into_iter()
+			.filter(|model| self.interests.add(model.id.clone()))
+			.count())
+	}
+
+	/// Store to serve reads from, writes are rejected
+	pub fn store(&self) -> ReadOnly<Arc<dyn StreamStore>> {
+		ReadOnly(self.store.clone())
+	}
+
+	pub async fn backfill_model(&self, model_id: &StreamId) -> anyhow::Result<usize> {
+		let model = dapp::get_model(model_id).await?;
+		let ceramic = model.ceramic().await?;
+		let states = self
+			.loader
+			.load_stream_states(&ceramic, None, model_id)
+			.await?;
+		let mut count = 0;
+		for state in states {
+			let stream = stream_from_state(&model.dapp_id, &state)?;
+			self.store.save_stream(&stream).await?;
+			count += 1;
+		}
+		Ok(count)
+	}
+
+	pub async fn prune_model(&self, model_id: &StreamId) -> anyhow::Result<usize> {
+		self.store.remove_model_streams(model_id).await
+	}
+
+	pub async fn apply(&self, change: &InterestChange) -> anyhow::Result<usize> {
+		match change {
+			InterestChange::Added(model_id) => self.backfill_model(model_id).await,
+			InterestChange::Removed(model_id) => self.prune_model(model_id).await,
+		}
+	}
+
+	pub async fn backfill(&self) -> anyhow::Result<usize> {
+		let mut count = 0;
+		for model_id in &self.interests.models() {
+			match self.backfill_model(model_id).await {
+				Ok(n) => count += n,
+				Err(err) => log::warn!("failed to backfill model {}: {}", model_id, err),
+			}
+		}
+		Ok(count)
+	}
+
+	/// Backfill the replicated models forever, backfilling added models and
+	/// pruning removed ones as they change
+	pub async fn run(&self) {
+		let mut interval = tokio::time::interval(self.config.interval);
+		let mut changes = self.interests.subscribe();
+		loop {
+			tokio::select! {
+				_ = interval.tick() => {
+					if let Ok(count) = self.backfill().await {
+						log::info!("mirror backfilled {} streams", count);
+					}
+				}
+				Ok(change) = changes.recv() => match self.apply(&change).await {
+					Ok(count) => log::info!("mirror applied {:?} to {} streams", change, count),
+					Err(err) => log::warn!("failed to apply {:?}: {}", change, err),
+				},
+			}
+		}
+	}
+}
+
+fn stream_from_state(dapp_id: &uuid::Uuid, state: &StreamState) -> anyhow::Result<Stream> {
+	let stream_id = state.stream_id()?;
+	let tip = match state.log.last() {
+		Some(log) => Cid::from_str(log.cid.as_ref())?,
+		None => stream_id.cid,
+	};
+	Ok(Stream {
+		r#type: state.r#type,
+		dapp_id: *dapp_id,
+		genesis: stream_id.cid,
+		tip,
+		account: state.controllers().first().cloned(),
+		model: state.model()?,
+		content: state.content.clone(),
+		published: Default::default(),
+		anchored_at: state.anchored_at(),
+		forked: false,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn validate_mirror_config() {
+		let model =
+			StreamId::from_str("kjzl6kcym7w8y8syiams0kvm3qwfnutk2szi0wlhvf6rr9lalzpibxed0qvotuy")
+				.unwrap();
+		assert!(MirrorConfig::new(vec![]).validate().is_err());
+		assert!(MirrorConfig::new(vec![model.clone()]).validate().is_ok());
+
+		let config = MirrorConfig::new(vec![model.clone(), model]);
+		let err = config.validate().unwrap_err();
+		assert!(matches!(
+			err.downcast_ref::<MirrorError>(),
+			Some(MirrorError::DuplicateModel(_))
+		));
+	}
+}
@@ -0,0 +1,210 @@
+use std::sync::Arc;
+
+use ceramic_core::{Cid, StreamId};
+use dataverse_ceramic::kubo;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitNotification {
+	pub stream_id: StreamId,
+	pub tip: Cid,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub model: Option<StreamId>,
+	/// The commit started another branch of the stream, the tip moved to it
+	#[serde(default, skip_serializing_if = "std::ops::Not::not")]
+	pub forked: bool,
+	/// The node changed the quarantine of the stream, the tip did not move
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub quarantine: Option<QuarantineAction>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum QuarantineAction {
+	Quarantined,
+	Released,
+	Purged,
+}
+
+impl CommitNotification {
+	pub fn quarantine(
+		stream_id: StreamId,
+		tip: Cid,
+		model: Option<StreamId>,
+		action: QuarantineAction,
+	) -> Self {
+		Self {
+			stream_id,
+			tip,
+			model,
+			forked: false,
+			quarantine: Some(action),
+		}
+	}
+
+	/// Frame of the notification for a server-sent events response
+	pub fn to_sse(&self) -> anyhow::Result<String> {
+		let event = match (self.quarantine, self.forked) {
+			(Some(QuarantineAction::Quarantined), _) => "quarantine",
+			(Some(QuarantineAction::Released), _) => "release",
+			(Some(QuarantineAction::Purged), _) => "purge",
+			(None, true) => "fork",
+			(None, false) => "commit",
+		};
+		Ok(format!(
+			"event: {}\nid: {}\ndata: {}\n\n",
+			event,
+			self.tip,
+			serde_json::to_string(self)?
+		))
+	}
+}
+
+/// Fan out of commit notifications to subscribers, e.g. the
+/// `GET /streams/:id/events` endpoint
+#[derive(Debug, Clone)]
+pub struct Notifier {
+	sender: broadcast::Sender<CommitNotification>,
+}
+
+impl Default for Notifier {
+	fn default() -> Self {
+		Self::new(1024)
+	}
+}
+
+impl Notifier {
+	pub fn new(capacity: usize) -> Self {
+		let (sender, _) = broadcast::channel(capacity);
+		Self { sender }
+	}
+
+	pub fn notify(&self, notification: CommitNotification) {
+		// no receiver is not an error, nobody is listening yet
+		let _ = self.sender.send(notification);
+	}
+
+	pub fn subscribe(&self, stream_id: Option<StreamId>) -> Subscription {
+		Subscription {
+			stream_id,
+			receiver: self.sender.subscribe(),
+		}
+	}
+}
+
+pub struct Subscription {
+	stream_id: Option<StreamId>,
+	receiver: broadcast::Receiver<CommitNotification>,
+}
+
+impl Subscription {
+	/// Next notification of the subscribed stream, None once the notifier is
+	/// dropped. Lagging subscribers skip the missed notifications.
+	pub async fn next(&mut self) -> Option<CommitNotification> {
+		loop {
+			match self.receiver.recv().await {
+				Ok(notification) => {
+					if self
+						.stream_id
+						.as_ref()
+						.map_or(true, |id| *id == notification.stream_id)
+					{
+						return Some(notification);
+					}
+				}
+				Err(broadcast::error::RecvError::Lagged(skipped)) => {
+					log::warn!("notification subscriber lagged, skipped {}", skipped);
+				}
+				Err(broadcast::error::RecvError::Closed) => return None,
+			}
+		}
+	}
+}
+
+/// kubo store notifying tips received from pubsub
+pub struct NotifyingStore {
+	pub store: Arc<dyn kubo::TipStore>,
+	pub notifier: Arc<Notifier>,
+}
+
+#[async_trait::async_trait]
+impl kubo::TipStore for NotifyingStore {
+	async fn get_tip(&self, stream_id: &StreamId) -> anyhow::Result<Option<Cid>> {
+		self.store.get_tip(stream_id).await
+	}
+
+	async fn set_tip(&self, stream_id: &StreamId, tip: Cid) -> anyhow::Result<()> {
+		self.store.set_tip(stream_id, tip).await?;
+		self.notifier.notify(CommitNotification {
+			stream_id: stream_id.clone(),
+			tip,
+			model: None,
+			forked: false,
+			quarantine: None,
+		});
+		Ok(())
+	}
+
+	async fn has_query(&self, id: &str) -> anyhow::Result<bool> {
+		self.store.has_query(id).await
+	}
+
+	async fn stream_ids(&self) -> anyhow::Result<Vec<StreamId>> {
+		self.store.stream_ids().await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::str::FromStr;
+
+	use super::*;
+
+	#[tokio::test]
+	async fn subscribe_stream() {
+		let stream_a =
+			StreamId::from_str("kjzl6kcym7w8y8syiams0kvm3qwfnutk2szi0wlhvf6rr9lalzpibxed0qvotuy")
+				.unwrap();
+		let stream_b =
+			StreamId::from_str("kjzl6kcym7w8y9s94kcardbh5u0ao76bci07xnnxjw1ew3i4eackykj76uagqfk")
+				.unwrap();
+		let tip =
+			Cid::from_str("bafyreih3skzznsyro5i4bsfsyquhcewyqmsra6cx47nj2eb6aerm2nihuu").unwrap();
+
+		let notifier = Notifier::default();
+		let mut subscription = notifier.subscribe(Some(stream_b.clone()));
+		for stream_id in [stream_a, stream_b.clone()] {
+			notifier.notify(CommitNotification {
+				stream_id,
+				tip,
+				model: None,
+				forked: false,
+				quarantine: None,
+			});
+		}
+
+		let notification = subscription.next().await.unwrap();
+		assert_eq!(notification.stream_id, stream_b);
+		let frame = notification.to_sse().unwrap();
+		assert!(frame.starts_with("event: commit\nid: bafyrei"));
+		assert!(frame.ends_with("\n\n"));
+
+		let fork = CommitNotification {
+			forked: true,
+			..notification
+		};
+		assert!(fork
+			.to_sse()
+			.unwrap()
+			.starts_with("event: fork\nid: bafyrei"));
+
+		let quarantine =
+			CommitNotification::quarantine(stream_b, tip, None, QuarantineAction::Quarantined);
+		assert!(quarantine
+			.to_sse()
+			.unwrap()
+			.starts_with("event: quarantine\nid: bafyrei"));
+	}
+}
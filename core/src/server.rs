@@ -0,0 +1,155 @@
+use dataverse_ceramic::event::commit::CommitLimits;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug)]
+pub enum ServerConfigError {
+	EmptyOrigin,
+	InvalidOrigin(String),
+	ZeroLimit(String),
+	BlockExceedsBody(usize, usize),
+}
+
+impl std::fmt::Display for ServerConfigError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::EmptyOrigin => write!(f, "cors origin must not be empty"),
+			Self::InvalidOrigin(origin) => write!(f, "invalid cors origin: {}", origin),
+			Self::ZeroLimit(name) => write!(f, "{} must be greater than zero", name),
+			Self::BlockExceedsBody(block, body) => {
+				write!(f, "block limit {} exceeds body limit {}", block, body)
+			}
+		}
+	}
+}
+
+impl std::error::Error for ServerConfigError {}
+
+/// Origins allowed to call the api from a browser, `*` allows any origin
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CorsConfig {
+	pub allowed_origins: Vec<String>,
+	pub max_age_secs: u64,
+}
+
+impl Default for CorsConfig {
+	fn default() -> Self {
+		Self {
+			allowed_origins: vec!["*".to_string()],
+			max_age_secs: 3600,
+		}
+	}
+}
+
+impl CorsConfig {
+	pub fn allows_any(&self) -> bool {
+		self.allowed_origins.iter().any(|x| x == "*")
+	}
+
+	pub fn is_allowed(&self, origin: &str) -> bool {
+		let origin = origin.trim_end_matches('/');
+		self.allows_any()
+			|| self
+				.allowed_origins
+				.iter()
+				.any(|x| x.trim_end_matches('/').eq_ignore_ascii_case(origin))
+	}
+
+	/// value of the `Access-Control-Allow-Origin` header for a request origin
+	pub fn allow_origin_header(&self, origin: Option<&str>) -> Option<String> {
+		if self.allows_any() {
+			return Some("*".to_string());
+		}
+		origin.filter(|x| self.is_allowed(x)).map(|x| x.to_string())
+	}
+
+	pub fn validate(&self) -> anyhow::Result<()> {
+		for origin in &self.allowed_origins {
+			if origin.is_empty() {
+				anyhow::bail!(ServerConfigError::EmptyOrigin);
+			}
+			if origin != "*" && !(origin.starts_with("http://") || origin.starts_with("https://")) {
+				anyhow::bail!(ServerConfigError::InvalidOrigin(origin.clone()));
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Per-deployment middleware settings of the http facade
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerConfig {
+	#[serde(default)]
+	pub cors: CorsConfig,
+	#[serde(default)]
+	pub limits: CommitLimits,
+}
+
+impl ServerConfig {
+	pub fn validate(&self) -> anyhow::Result<()> {
+		self.cors.validate()?;
+		if self.limits.max_body_bytes == 0 {
+			anyhow::bail!(ServerConfigError::ZeroLimit("maxBodyBytes".to_string()));
+		}
+		if self.limits.max_block_bytes == 0 {
+			anyhow::bail!(ServerConfigError::ZeroLimit("maxBlockBytes".to_string()));
+		}
+		if self.limits.max_block_bytes > self.limits.max_body_bytes {
+			anyhow::bail!(ServerConfigError::BlockExceedsBody(
+				self.limits.max_block_bytes,
+				self.limits.max_body_bytes
+			));
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use dataverse_ceramic::event::commit::Data;
+	use dataverse_ceramic::event::errors::CommitError;
+
+	use super::*;
+
+	#[test]
+	fn test_default_config() {
+		let config = ServerConfig::default();
+		assert!(config.validate().is_ok());
+		assert!(config.cors.is_allowed("https://example.com"));
+		assert_eq!(config.cors.allow_origin_header(None), Some("*".to_string()));
+	}
+
+	#[test]
+	fn test_cors_origins() {
+		let cors = CorsConfig {
+			allowed_origins: vec!["https://app.dataverse.art/".to_string()],
+			..Default::default()
+		};
+		assert!(cors.validate().is_ok());
+		assert!(cors.is_allowed("https://app.dataverse.art"));
+		assert!(!cors.is_allowed("https://evil.example"));
+		assert_eq!(cors.allow_origin_header(Some("https://evil.example")), None);
+
+		let cors = CorsConfig {
+			allowed_origins: vec!["app.dataverse.art".to_string()],
+			..Default::default()
+		};
+		assert!(cors.validate().is_err());
+	}
+
+	#[test]
+	fn test_oversized_commit() {
+		let body = serde_json::to_vec(&dataverse_ceramic::commit::example::data()).unwrap();
+		let limits = CommitLimits {
+			max_body_bytes: 16,
+			..Default::default()
+		};
+		let err = Data::from_slice_limited(&body, &limits).unwrap_err();
+		assert!(matches!(
+			err.downcast_ref::<CommitError>(),
+			Some(CommitError::BodyTooLarge(_, 16))
+		));
+		assert!(Data::from_slice_limited(&body, &CommitLimits::default()).is_ok());
+	}
+}
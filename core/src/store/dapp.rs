@@ -1,13 +1,17 @@
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use anyhow::Context;
 use ceramic_core::StreamId;
-use dataverse_ceramic::Ceramic;
+use dataverse_ceramic::{Ceramic, LogSelector};
 use once_cell::sync::Lazy;
-use tokio::sync::Mutex;
+use tokio::sync::RwLock;
 
 use crate::store::errors::ModelStoreError;
 
+/// Time models and dapps are served from cache before being looked up again
+pub const DEFAULT_TTL: Duration = Duration::from_secs(300);
+
 #[derive(Debug, Clone)]
 pub struct Model {
 	pub id: StreamId,
@@ -16,6 +20,8 @@ pub struct Model {
 	pub encryptable: Vec<String>,
 	pub version: i32,
 	pub latest: bool,
+	/// registered as one of the dapp's builtin (file system) models
+	pub internal: bool,
 }
 
 impl Model {
@@ -24,157 +30,240 @@ impl Model {
 	}
 }
 
-static MODEL_STORE: Lazy<Mutex<ModelStore>> = Lazy::new(|| Mutex::new(ModelStore::new()));
-
-pub struct ModelStore {
-	client: dapp_table_client::Client,
-	models: HashMap<String, Model>,
-	ceramic: HashMap<String, Ceramic>,
-	dapp_ceramic: HashMap<uuid::Uuid, String>,
+/// Where the models of a dapp are looked up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lookup {
+	/// only the models cached and not expired, no request to the dapp table
+	Cached,
+	/// the models registered in the dapp table, refreshing the cache
+	Fetch,
 }
 
-pub async fn get_dapp_ceramic(dapp_id: &uuid::Uuid) -> anyhow::Result<Ceramic> {
-	let mut store = MODEL_STORE.lock().await;
-	store.get_dapp_ceramic(dapp_id, true).await
+/// Lookup of dapps and their models registered in the dapp table
+#[async_trait::async_trait]
+pub trait DappRegistry: Send + Sync {
+	async fn get_dapp_ceramic(&self, dapp_id: &uuid::Uuid) -> anyhow::Result<Ceramic>;
+	async fn get_ceramic(&self, endpoint: &str) -> anyhow::Result<Ceramic>;
+	async fn get_model_by_name(
+		&self,
+		dapp_id: &uuid::Uuid,
+		model_name: &str,
+	) -> anyhow::Result<Model>;
+	async fn get_model(&self, model_id: &StreamId) -> anyhow::Result<Model>;
+	async fn get_models(&self, dapp_id: &uuid::Uuid, lookup: Lookup) -> anyhow::Result<Vec<Model>>;
+	/// drop the cached model, the next lookup reloads its dapp
+	async fn invalidate_model(&self, _model_id: &StreamId) {}
+	async fn invalidate_dapp(&self, _dapp_id: &uuid::Uuid) {}
+	/// policy picking the branch of forked streams of the dapp
+	async fn get_log_selector(&self, _dapp_id: &uuid::Uuid) -> anyhow::Result<LogSelector> {
+		Ok(LogSelector::default())
+	}
+	/// bytes the content of an instance of the model may take after a patch,
+	/// `None` for no limit
+	async fn get_max_content_size(&self, _model_id: &StreamId) -> anyhow::Result<Option<usize>> {
+		Ok(None)
+	}
 }
 
-pub async fn get_ceramic(ceramic_str: &String) -> anyhow::Result<Ceramic> {
-	MODEL_STORE.lock().await.get_ceramic(ceramic_str).await
+struct Cached<T> {
+	value: T,
+	expires_at: Instant,
 }
 
-pub async fn get_model_by_name(dapp_id: &uuid::Uuid, model_name: &str) -> anyhow::Result<Model> {
-	let mut store = MODEL_STORE.lock().await;
-	store.get_model_by_name(dapp_id, model_name, true).await
+impl<T: Clone> Cached<T> {
+	fn fresh(&self) -> Option<T> {
+		(Instant::now() < self.expires_at).then(|| self.value.clone())
+	}
 }
 
-pub async fn get_model(model_id: &StreamId) -> anyhow::Result<Model> {
-	MODEL_STORE.lock().await.get_model(model_id).await
+#[derive(Default)]
+struct Cache {
+	models: HashMap<StreamId, Cached<Model>>,
+	ceramic: HashMap<String, Ceramic>,
+	dapp_ceramic: HashMap<uuid::Uuid, Cached<String>>,
 }
 
-pub async fn get_models(dapp_id: &uuid::Uuid, offline: bool) -> anyhow::Result<Vec<Model>> {
-	MODEL_STORE.lock().await.get_models(dapp_id, offline).await
+/// Dapp table client caching lookups for a ttl. Locks are not held across
+/// requests, concurrent misses of a dapp may look it up more than once.
+pub struct CachedDappRegistry {
+	client: dapp_table_client::Client,
+	ttl: Duration,
+	cache: RwLock<Cache>,
+	log_selectors: HashMap<uuid::Uuid, LogSelector>,
+	max_content_sizes: HashMap<StreamId, usize>,
 }
 
-impl ModelStore {
-	fn new() -> Self {
-		let backend = std::env::var("DAPP_TABLE_BACKEND").ok();
-		ModelStore {
-			models: Default::default(),
-			dapp_ceramic: Default::default(),
-			ceramic: Default::default(),
+impl CachedDappRegistry {
+	pub fn new(backend: Option<String>) -> Self {
+		Self {
 			client: dapp_table_client::Client::new(backend),
+			ttl: DEFAULT_TTL,
+			cache: Default::default(),
+			log_selectors: HashMap::new(),
+			max_content_sizes: HashMap::new(),
 		}
 	}
 
-	async fn get_dapp_ceramic(
-		&mut self,
-		dapp_id: &uuid::Uuid,
-		online: bool,
-	) -> anyhow::Result<Ceramic> {
-		if let Some(ceramic) = self.dapp_ceramic.get(dapp_id) {
-			return self.get_ceramic(&ceramic.clone()).await;
-		}
-		if online {
-			match self.load_dapp(dapp_id).await {
-				Ok((ceramic, _)) => return Ok(ceramic),
-				Err(err) => log::warn!("load dapp error: {}", err),
-			};
-		}
-
-		anyhow::bail!(ModelStoreError::DappNotFound(*dapp_id))
+	/// backend of `DAPP_TABLE_BACKEND` or the default dapp table
+	pub fn from_env() -> Self {
+		Self::new(std::env::var("DAPP_TABLE_BACKEND").ok())
 	}
 
-	async fn get_ceramic(&mut self, ceramic_str: &String) -> anyhow::Result<Ceramic> {
-		if let Some(ceramic) = self.ceramic.get(ceramic_str) {
-			return Ok(ceramic.clone());
-		}
+	pub fn with_ttl(self, ttl: Duration) -> Self {
+		Self { ttl, ..self }
+	}
 
-		let chains = dataverse_ceramic::http::Client::chains(ceramic_str).await?;
-		let ceramic = Ceramic {
-			endpoint: ceramic_str.clone(),
-			network: chains.first().context(ModelStoreError::CeramicNotInNetworks)?.network(),
-		};
-		self.ceramic.insert(ceramic_str.clone(), ceramic.clone());
-		Ok(ceramic)
+	/// Conflict policy of the dapp, dapps without one follow ceramic
+	pub fn with_log_selector(mut self, dapp_id: uuid::Uuid, selector: LogSelector) -> Self {
+		self.log_selectors.insert(dapp_id, selector);
+		self
 	}
 
-	async fn get_models(
-		&mut self,
-		dapp_id: &uuid::Uuid,
-		online: bool,
-	) -> anyhow::Result<Vec<Model>> {
-		if !online {
-			let models = self
-				.models.values().filter(|&x| x.dapp_id == *dapp_id).cloned()
-				.collect();
-			return Ok(models);
-		}
-		let (_, models) = self.load_dapp(dapp_id).await?;
-		Ok(models)
+	/// Limit of the content of the instances of the model, in bytes of its json
+	pub fn with_max_content_size(mut self, model_id: StreamId, bytes: usize) -> Self {
+		self.max_content_sizes.insert(model_id, bytes);
+		self
 	}
 
-	async fn load_dapp(&mut self, dapp_id: &uuid::Uuid) -> anyhow::Result<(Ceramic, Vec<Model>)> {
+	async fn load_dapp(&self, dapp_id: &uuid::Uuid) -> anyhow::Result<(Ceramic, Vec<Model>)> {
 		log::info!("lookup dapp with dapp_id: {}", dapp_id);
 		let dapp = self
 			.client
 			.lookup_dapp_by_dapp_id(&dapp_id.to_string())
 			.await?;
-		self.dapp_ceramic
-			.insert(*dapp_id, dapp.ceramic.clone());
+		self.cache.write().await.dapp_ceramic.insert(
+			*dapp_id,
+			Cached {
+				value: dapp.ceramic.clone(),
+				expires_at: Instant::now() + self.ttl,
+			},
+		);
 		let ceramic = self.get_ceramic(&dapp.ceramic).await?;
-		let models = self.store_dapp_models(dapp)?;
+		let models = self.store_dapp_models(dapp).await?;
 		Ok((ceramic, models))
 	}
 
-	fn store_dapp_models(
-		&mut self,
+	async fn store_dapp_models(
+		&self,
 		dapp: dapp_table_client::get_dapp::GetDappGetDapp,
 	) -> anyhow::Result<Vec<Model>> {
 		let mut result = vec![];
 		for model in dapp.models {
 			for (idx, ele) in model.streams.iter().enumerate() {
-				let model = Model {
+				result.push(Model {
 					id: ele.model_id.parse()?,
 					dapp_id: dapp.id.parse()?,
 					encryptable: ele.encryptable.clone(),
 					name: model.model_name.clone(),
 					version: idx as i32,
 					latest: ele.latest,
-				};
-				self.models.insert(model.id.to_string(), model.clone());
-				result.push(model)
+					internal: model.internal,
+				});
 			}
 		}
+
+		let expires_at = Instant::now() + self.ttl;
+		let mut cache = self.cache.write().await;
+		for model in &result {
+			cache.models.insert(
+				model.id.clone(),
+				Cached {
+					value: model.clone(),
+					expires_at,
+				},
+			);
+		}
 		Ok(result)
 	}
+}
+
+#[async_trait::async_trait]
+impl DappRegistry for CachedDappRegistry {
+	async fn get_dapp_ceramic(&self, dapp_id: &uuid::Uuid) -> anyhow::Result<Ceramic> {
+		let endpoint = self
+			.cache
+			.read()
+			.await
+			.dapp_ceramic
+			.get(dapp_id)
+			.and_then(Cached::fresh);
+		if let Some(endpoint) = endpoint {
+			return self.get_ceramic(&endpoint).await;
+		}
+		match self.load_dapp(dapp_id).await {
+			Ok((ceramic, _)) => Ok(ceramic),
+			Err(err) => {
+				log::warn!("load dapp error: {}", err);
+				anyhow::bail!(ModelStoreError::DappNotFound(*dapp_id))
+			}
+		}
+	}
+
+	async fn get_ceramic(&self, endpoint: &str) -> anyhow::Result<Ceramic> {
+		if let Some(ceramic) = self.cache.read().await.ceramic.get(endpoint) {
+			return Ok(ceramic.clone());
+		}
+
+		let ceramic = Ceramic::new(endpoint).await?;
+		self.cache
+			.write()
+			.await
+			.ceramic
+			.insert(endpoint.to_string(), ceramic.clone());
+		Ok(ceramic)
+	}
+
+	async fn get_models(&self, dapp_id: &uuid::Uuid, lookup: Lookup) -> anyhow::Result<Vec<Model>> {
+		if lookup == Lookup::Cached {
+			let cache = self.cache.read().await;
+			let models = cache
+				.models
+				.values()
+				.filter_map(Cached::fresh)
+				.filter(|x| x.dapp_id == *dapp_id)
+				.collect();
+			return Ok(models);
+		}
+		let (_, models) = self.load_dapp(dapp_id).await?;
+		Ok(models)
+	}
 
 	async fn get_model_by_name(
-		&mut self,
+		&self,
 		dapp_id: &uuid::Uuid,
 		model_name: &str,
-		online: bool,
 	) -> anyhow::Result<Model> {
-		for model in self.models.values() {
-			if model.name == model_name && model.dapp_id == *dapp_id && model.latest {
-				return Ok(model.clone());
-			}
+		let matches =
+			|model: &Model| model.name == model_name && model.dapp_id == *dapp_id && model.latest;
+		let cached = self
+			.cache
+			.read()
+			.await
+			.models
+			.values()
+			.filter_map(Cached::fresh)
+			.find(|model| matches(model));
+		if let Some(model) = cached {
+			return Ok(model);
 		}
 
-		if online {
-			let (_, models) = self.load_dapp(dapp_id).await?;
-			for model in models {
-				if model.name == model_name && model.dapp_id == *dapp_id && model.latest {
-					return Ok(model.clone());
-				}
-			}
+		let (_, models) = self.load_dapp(dapp_id).await?;
+		match models.into_iter().find(|model| matches(model)) {
+			Some(model) => Ok(model),
+			None => anyhow::bail!(ModelStoreError::ModelNotInDapp(model_name.into(), *dapp_id)),
 		}
-
-		anyhow::bail!(ModelStoreError::ModelNotInDapp(model_name.into(), *dapp_id))
 	}
 
-	pub async fn get_model(&mut self, model_id: &StreamId) -> anyhow::Result<Model> {
-		if let Some(model) = self.models.get(&model_id.to_string()) {
-			return Ok(model.clone());
+	async fn get_model(&self, model_id: &StreamId) -> anyhow::Result<Model> {
+		let cached = self
+			.cache
+			.read()
+			.await
+			.models
+			.get(model_id)
+			.and_then(Cached::fresh);
+		if let Some(model) = cached {
+			return Ok(model);
 		}
 
 		let variables = dapp_table_client::get_dapp::Variables {
@@ -184,12 +273,166 @@ impl ModelStore {
 		log::info!("lookup dapp with model_id: {}", model_id);
 		let dapp = self.client.lookup_dapp(variables).await?;
 
-		let models = self.store_dapp_models(dapp)?;
-		for model in models {
-			if model.id == *model_id {
-				return Ok(model);
+		let models = self.store_dapp_models(dapp).await?;
+		match models.into_iter().find(|model| model.id == *model_id) {
+			Some(model) => Ok(model),
+			None => anyhow::bail!(ModelStoreError::ModelIDNotInDapp(model_id.clone())),
+		}
+	}
+
+	async fn invalidate_model(&self, model_id: &StreamId) {
+		self.cache.write().await.models.remove(model_id);
+	}
+
+	async fn invalidate_dapp(&self, dapp_id: &uuid::Uuid) {
+		let mut cache = self.cache.write().await;
+		cache.dapp_ceramic.remove(dapp_id);
+		cache.models.retain(|_, x| x.value.dapp_id != *dapp_id);
+	}
+
+	async fn get_log_selector(&self, dapp_id: &uuid::Uuid) -> anyhow::Result<LogSelector> {
+		Ok(self.log_selectors.get(dapp_id).copied().unwrap_or_default())
+	}
+
+	async fn get_max_content_size(&self, model_id: &StreamId) -> anyhow::Result<Option<usize>> {
+		Ok(self.max_content_sizes.get(model_id).copied())
+	}
+}
+
+static REGISTRY: Lazy<Arc<CachedDappRegistry>> =
+	Lazy::new(|| Arc::new(CachedDappRegistry::from_env()));
+
+/// Process wide registry backing the functions below, for callers without an
+/// injected registry
+pub fn global() -> Arc<CachedDappRegistry> {
+	REGISTRY.clone()
+}
+
+pub async fn get_dapp_ceramic(dapp_id: &uuid::Uuid) -> anyhow::Result<Ceramic> {
+	REGISTRY.get_dapp_ceramic(dapp_id).await
+}
+
+pub async fn get_ceramic(ceramic_str: &String) -> anyhow::Result<Ceramic> {
+	REGISTRY.get_ceramic(ceramic_str).await
+}
+
+pub async fn get_model_by_name(dapp_id: &uuid::Uuid, model_name: &str) -> anyhow::Result<Model> {
+	REGISTRY.get_model_by_name(dapp_id, model_name).await
+}
+
+pub async fn get_model(model_id: &StreamId) -> anyhow::Result<Model> {
+	REGISTRY.get_model(model_id).await
+}
+
+pub async fn get_models(dapp_id: &uuid::Uuid, lookup: Lookup) -> anyhow::Result<Vec<Model>> {
+	REGISTRY.get_models(dapp_id, lookup).await
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn model(id: &str, dapp_id: uuid::Uuid) -> Model {
+		Model {
+			id: id.parse().unwrap(),
+			name: "post".to_string(),
+			dapp_id,
+			encryptable: vec![],
+			version: 0,
+			latest: true,
+			internal: false,
+		}
+	}
+
+	#[tokio::test]
+	async fn test_cache_invalidation() -> anyhow::Result<()> {
+		let dapp_id = uuid::Uuid::new_v4();
+		let registry = CachedDappRegistry::new(None);
+		let post = model(
+			"kjzl6kcym7w8y8syiams0kvm3qwfnutk2szi0wlhvf6rr9lalzpibxed0qvotuy",
+			dapp_id,
+		);
+		let profile = model(
+			"kjzl6kcym7w8y9s94kcardbh5u0ao76bci07xnnxjw1ew3i4eackykj76uagqfk",
+			dapp_id,
+		);
+		{
+			let mut cache = registry.cache.write().await;
+			for model in [&post, &profile] {
+				cache.models.insert(
+					model.id.clone(),
+					Cached {
+						value: model.clone(),
+						expires_at: Instant::now() + DEFAULT_TTL,
+					},
+				);
 			}
 		}
-		anyhow::bail!(ModelStoreError::ModelIDNotInDapp(model_id.clone()))
+		assert_eq!(registry.get_model(&post.id).await?.name, "post");
+		assert_eq!(
+			registry.get_models(&dapp_id, Lookup::Cached).await?.len(),
+			2
+		);
+
+		registry.invalidate_model(&post.id).await;
+		assert_eq!(
+			registry.get_models(&dapp_id, Lookup::Cached).await?.len(),
+			1
+		);
+		registry.invalidate_dapp(&dapp_id).await;
+		assert!(registry
+			.get_models(&dapp_id, Lookup::Cached)
+			.await?
+			.is_empty());
+
+		// expired models are not served from cache
+		registry.cache.write().await.models.insert(
+			profile.id.clone(),
+			Cached {
+				value: profile.clone(),
+				expires_at: Instant::now(),
+			},
+		);
+		assert!(registry
+			.get_models(&dapp_id, Lookup::Cached)
+			.await?
+			.is_empty());
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_log_selector() -> anyhow::Result<()> {
+		let dapp_id = uuid::Uuid::new_v4();
+		let registry =
+			CachedDappRegistry::new(None).with_log_selector(dapp_id, LogSelector::LongestLog);
+		assert_eq!(
+			registry.get_log_selector(&dapp_id).await?,
+			LogSelector::LongestLog
+		);
+		assert_eq!(
+			registry.get_log_selector(&uuid::Uuid::new_v4()).await?,
+			LogSelector::Ceramic
+		);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_max_content_size() -> anyhow::Result<()> {
+		let model_id: StreamId =
+			"kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju".parse()?;
+		let registry = CachedDappRegistry::new(None).with_max_content_size(model_id.clone(), 1024);
+		assert_eq!(registry.get_max_content_size(&model_id).await?, Some(1024));
+		let other = dataverse_ceramic::stream::single::single_stream_id(&model_id, "did:key:z6Mk")?;
+		assert_eq!(registry.get_max_content_size(&other).await?, None);
+		Ok(())
+	}
+
+	#[test]
+	fn test_cached_expiry() {
+		let cached = Cached {
+			value: 1,
+			expires_at: Instant::now(),
+		};
+		assert_eq!(cached.fresh(), None);
 	}
 }
@@ -1,6 +1,9 @@
+use std::collections::HashMap;
+
 use ceramic_core::{Cid, StreamId};
+use chrono::{DateTime, Utc};
 use dataverse_ceramic::event::Event;
-use dataverse_ceramic::StreamState;
+use dataverse_ceramic::{SortBy, StreamState};
 use int_enum::IntEnum;
 use serde::{Deserialize, Serialize};
 
@@ -15,6 +18,13 @@ pub struct Stream {
 	pub model: Option<StreamId>,
 	#[serde(default = "content_default")]
 	pub content: serde_json::Value,
+	#[serde(default)]
+	pub published: PublishState,
+	#[serde(default)]
+	pub anchored_at: Option<DateTime<Utc>>,
+	/// Another branch of the stream was seen besides the one of the tip
+	#[serde(default)]
+	pub forked: bool,
 }
 
 fn content_default() -> serde_json::Value {
@@ -36,6 +46,9 @@ impl Stream {
 			model,
 			account: None,
 			content: serde_json::Value::Null,
+			published: Default::default(),
+			anchored_at: None,
+			forked: false,
 		})
 	}
 
@@ -46,14 +59,232 @@ impl Stream {
 		})
 	}
 
+	/// Same time as `StreamState::sort_time`, from the stored stream
+	pub fn sort_time(&self, sort_by: SortBy) -> Option<DateTime<Utc>> {
+		let updated_at = || self.content.get("updatedAt")?.as_str()?.parse().ok();
+		match sort_by {
+			SortBy::AnchoredAt => self.anchored_at.or_else(updated_at),
+			SortBy::UpdatedAt => updated_at(),
+		}
+	}
+
 	pub async fn state(&self, commits: Vec<Event>) -> anyhow::Result<StreamState> {
 		StreamState::make(self.r#type, commits).await
 	}
 }
 
+/// Publication progress of a stream tip towards its target endpoints
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PublishState {
+	/// Tip the statuses below refer to
+	pub tip: Option<Cid>,
+	pub endpoints: Vec<String>,
+	pub status: HashMap<String, PublishStatus>,
+	pub last_attempt_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "state", content = "error")]
+pub enum PublishStatus {
+	Pending,
+	Published,
+	Failed(String),
+}
+
+impl PublishState {
+	/// Move the manifest to a new tip, resetting every endpoint to pending.
+	/// Calling it again with the same tip keeps the recorded statuses.
+	pub fn target(&mut self, tip: Cid, endpoints: &[String]) {
+		for endpoint in endpoints {
+			if !self.endpoints.contains(endpoint) {
+				self.endpoints.push(endpoint.clone());
+			}
+		}
+		if self.tip != Some(tip) {
+			self.tip = Some(tip);
+			self.status.clear();
+		}
+		for endpoint in &self.endpoints {
+			self.status
+				.entry(endpoint.clone())
+				.or_insert(PublishStatus::Pending);
+		}
+	}
+
+	pub fn record(&mut self, endpoint: &str, tip: Cid, result: &anyhow::Result<()>) {
+		self.target(tip, &[endpoint.to_string()]);
+		let status = match result {
+			Ok(_) => PublishStatus::Published,
+			Err(err) => PublishStatus::Failed(err.to_string()),
+		};
+		self.status.insert(endpoint.to_string(), status);
+		self.last_attempt_at = Some(Utc::now());
+	}
+
+	pub fn is_published(&self, endpoint: &str, tip: &Cid) -> bool {
+		self.tip.as_ref() == Some(tip)
+			&& self.status.get(endpoint) == Some(&PublishStatus::Published)
+	}
+
+	/// Endpoints which still need the current tip
+	pub fn pending_endpoints(&self) -> Vec<String> {
+		self.endpoints
+			.iter()
+			.filter(|endpoint| self.status.get(*endpoint) != Some(&PublishStatus::Published))
+			.cloned()
+			.collect()
+	}
+}
+
+/// Label of streams the node keeps but no longer serves
+pub const QUARANTINED: &str = "quarantined";
+/// Label left on streams purged from the node, which are not stored or
+/// served again
+pub const PURGED: &str = "purged";
+
 #[async_trait::async_trait]
 pub trait StreamStore: Sync + Send {
 	async fn save_stream(&self, stream: &Stream) -> anyhow::Result<()>;
 	async fn load_stream(&self, stream_id: &StreamId) -> anyhow::Result<Option<Stream>>;
 	async fn list_all_streams(&self) -> anyhow::Result<Vec<Stream>>;
+	async fn list_model_streams(&self, model_id: &StreamId) -> anyhow::Result<Vec<Stream>> {
+		let mut streams = self.list_all_streams().await?;
+		streams.retain(|stream| stream.model.as_ref() == Some(model_id));
+		Ok(streams)
+	}
+	/// Ids of the stored streams, stores override it to skip loading states
+	async fn list_stream_ids(&self) -> anyhow::Result<Vec<StreamId>> {
+		let streams = self.list_all_streams().await?;
+		streams.iter().map(Stream::stream_id).collect()
+	}
+	/// Remove the streams of a model no longer replicated and their events,
+	/// returns the number of removed streams
+	async fn remove_model_streams(&self, model_id: &StreamId) -> anyhow::Result<usize> {
+		anyhow::bail!("store cannot remove streams of model {}", model_id)
+	}
+	/// Remove the stream and its events, false if it was not stored
+	async fn remove_stream(&self, stream_id: &StreamId) -> anyhow::Result<bool> {
+		anyhow::bail!("store cannot remove stream {}", stream_id)
+	}
+	/// Remove the stream and its events, leaving the `PURGED` label so they
+	/// are not stored again when synced, false if it was not stored
+	async fn purge_stream(&self, stream_id: &StreamId) -> anyhow::Result<bool> {
+		let removed = self.remove_stream(stream_id).await?;
+		self.add_stream_label(stream_id, PURGED).await?;
+		Ok(removed)
+	}
+	/// Tag the stream with a node-local label, e.g. `QUARANTINED`, leaving its
+	/// ceramic state untouched
+	async fn add_stream_label(&self, stream_id: &StreamId, label: &str) -> anyhow::Result<()> {
+		anyhow::bail!("store cannot label stream {} as {}", stream_id, label)
+	}
+	/// false if the stream did not have the label
+	async fn remove_stream_label(&self, stream_id: &StreamId, label: &str) -> anyhow::Result<bool> {
+		anyhow::bail!("store cannot unlabel stream {} as {}", stream_id, label)
+	}
+	/// Labels of the stream, none for stores without labels
+	async fn stream_labels(&self, _stream_id: &StreamId) -> anyhow::Result<Vec<String>> {
+		Ok(vec![])
+	}
+	/// Streams with the label
+	async fn streams_with_label(&self, _label: &str) -> anyhow::Result<Vec<StreamId>> {
+		Ok(vec![])
+	}
+	/// Streams among `stream_ids` with the label, to check a page of streams
+	/// at once
+	async fn filter_labeled(
+		&self,
+		label: &str,
+		stream_ids: &[StreamId],
+	) -> anyhow::Result<Vec<StreamId>> {
+		let mut labeled = vec![];
+		for stream_id in stream_ids {
+			if self
+				.stream_labels(stream_id)
+				.await?
+				.iter()
+				.any(|x| x == label)
+			{
+				labeled.push(stream_id.clone());
+			}
+		}
+		Ok(labeled)
+	}
+	async fn is_quarantined(&self, stream_id: &StreamId) -> anyhow::Result<bool> {
+		Ok(self
+			.stream_labels(stream_id)
+			.await?
+			.iter()
+			.any(|label| label == QUARANTINED))
+	}
+	async fn is_purged(&self, stream_id: &StreamId) -> anyhow::Result<bool> {
+		Ok(self
+			.stream_labels(stream_id)
+			.await?
+			.iter()
+			.any(|label| label == PURGED))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::str::FromStr;
+
+	use super::*;
+	use crate::testing::MemoryStreams;
+
+	#[tokio::test]
+	async fn test_quarantined_label() -> anyhow::Result<()> {
+		let stream_id =
+			StreamId::from_str("kjzl6kcym7w8y8syiams0kvm3qwfnutk2szi0wlhvf6rr9lalzpibxed0qvotuy")?;
+		let store = MemoryStreams::default();
+		store.add_stream_label(&stream_id, "migrated").await?;
+		assert!(!store.is_quarantined(&stream_id).await?);
+		store.add_stream_label(&stream_id, QUARANTINED).await?;
+		assert!(store.is_quarantined(&stream_id).await?);
+		let other =
+			StreamId::from_str("kjzl6kcym7w8y9s94kcardbh5u0ao76bci07xnnxjw1ew3i4eackykj76uagqfk")?;
+		let page = [stream_id.clone(), other];
+		assert_eq!(
+			store.filter_labeled(QUARANTINED, &page).await?,
+			vec![stream_id.clone()]
+		);
+		assert!(!store.is_purged(&stream_id).await?);
+		store.add_stream_label(&stream_id, PURGED).await?;
+		assert!(store.is_purged(&stream_id).await?);
+		Ok(())
+	}
+
+	#[test]
+	fn publish_state_manifest() {
+		let tip_a =
+			Cid::from_str("bagcqceraeeto3737ppwcmowjns25bilelzipyxrb4ehjmxz2a3dzbk4llfaq").unwrap();
+		let tip_b =
+			Cid::from_str("bafyreih3skzznsyro5i4bsfsyquhcewyqmsra6cx47nj2eb6aerm2nihuu").unwrap();
+		let endpoints = vec!["mainnet".to_string(), "clay".to_string()];
+
+		let mut state = PublishState::default();
+		state.target(tip_a, &endpoints);
+		assert_eq!(state.pending_endpoints(), endpoints);
+
+		state.record("mainnet", tip_a, &Ok(()));
+		assert!(state.is_published("mainnet", &tip_a));
+		assert_eq!(state.pending_endpoints(), vec!["clay".to_string()]);
+
+		// targeting the same tip again is a no-op
+		state.target(tip_a, &endpoints);
+		assert!(state.is_published("mainnet", &tip_a));
+
+		state.record("clay", tip_b, &Err(anyhow::anyhow!("timeout")));
+		assert!(!state.is_published("mainnet", &tip_b));
+		assert_eq!(state.pending_endpoints(), endpoints);
+		assert!(state.last_attempt_at.is_some());
+
+		let value = serde_json::to_value(&state).unwrap();
+		let decoded: PublishState = serde_json::from_value(value).unwrap();
+		assert_eq!(decoded, state);
+		let empty: PublishState = serde_json::from_value(serde_json::json!({})).unwrap();
+		assert_eq!(empty, PublishState::default());
+	}
 }
@@ -1,14 +1,21 @@
-use dataverse_ceramic::event::{Event, EventsUploader};
-use dataverse_ceramic::{http, Ceramic};
+use std::sync::Arc;
+use std::time::Duration;
+
+use dataverse_ceramic::event::{Event, EventsLoader, EventsUploader};
+use dataverse_ceramic::{Ceramic, StreamOperator};
 use fang::async_trait;
 use fang::asynk::async_queue::AsyncQueueable;
 use fang::serde::{Deserialize, Serialize};
 use fang::typetag;
 use fang::AsyncRunnable;
 use fang::FangError;
+use serde::de::DeserializeOwned;
 
-use crate::stream::Stream;
+use crate::lock::STREAM_LOCKS;
+use crate::stream::{Stream, StreamStore};
 
+/// Queued upload of the events of a stream to a ceramic node, run by a
+/// `ContextWorker<SyncStream>` holding a [`SyncContext`]
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "fang::serde")]
 pub struct SyncStream {
@@ -17,11 +24,23 @@ pub struct SyncStream {
 	pub events: Vec<Event>,
 }
 
+/// Stores and clients [`SyncStream`] tasks run with
+pub struct SyncContext {
+	pub stream_store: Arc<dyn StreamStore>,
+	pub operator: Arc<dyn StreamOperator>,
+}
+
 #[async_trait]
-#[typetag::serde]
-impl AsyncRunnable for SyncStream {
-	async fn run(&self, _queue: &mut dyn AsyncQueueable) -> Result<(), FangError> {
-		let client = http::Client::new();
+impl ContextTask for SyncStream {
+	type Context = SyncContext;
+
+	const TASK_TYPE: &'static str = "sync_stream";
+	const MAX_RETRIES: i32 = 5;
+
+	/// Upload the stream as stored when the task runs, which may have moved
+	/// past the queued tip, and record the outcome in its publish state. A
+	/// failed upload is retried by the worker
+	async fn run_with(&self, context: &SyncContext) -> anyhow::Result<()> {
 		let stream_id = match self.stream.stream_id() {
 			Ok(stream_id) => stream_id,
 			Err(err) => {
@@ -29,12 +48,257 @@ impl AsyncRunnable for SyncStream {
 				return Ok(());
 			}
 		};
-		let res = client
-			.upload_events(&self.ceramic, &stream_id, self.events.to_vec())
+		let _guard = STREAM_LOCKS.lock(&stream_id).await;
+		let mut stream = match context.stream_store.load_stream(&stream_id).await? {
+			Some(stream) => stream,
+			None => {
+				log::warn!("stream {} to sync is no longer stored", stream_id);
+				return Ok(());
+			}
+		};
+		if stream
+			.published
+			.is_published(&self.ceramic.endpoint, &stream.tip)
+		{
+			return Ok(());
+		}
+		let events = match stream.tip == self.stream.tip {
+			true => self.events.clone(),
+			false => {
+				context
+					.operator
+					.load_events(&self.ceramic, &stream_id, Some(stream.tip))
+					.await?
+			}
+		};
+		let uploaded = context
+			.operator
+			.upload_events(&self.ceramic, &stream_id, events)
 			.await;
-		if let Err(err) = res {
+		stream
+			.published
+			.record(&self.ceramic.endpoint, stream.tip, &uploaded);
+		context.stream_store.save_stream(&stream).await?;
+		uploaded.map_err(|err| {
 			log::error!("failed to upload events: {}", err);
+			err
+		})
+	}
+}
+
+#[async_trait]
+#[typetag::serde]
+impl AsyncRunnable for SyncStream {
+	async fn run(&self, _queue: &mut dyn AsyncQueueable) -> Result<(), FangError> {
+		Err(missing_context(Self::TASK_TYPE))
+	}
+
+	fn task_type(&self) -> String {
+		Self::TASK_TYPE.to_string()
+	}
+}
+
+/// Queued task run with state of the node, e.g. its stores, handed to the
+/// [`ContextWorker`] of the task type instead of looked up from a global.
+/// The task type keeps the workers of the pool from fetching it
+#[async_trait]
+pub trait ContextTask: DeserializeOwned + Send + Sync {
+	type Context: Send + Sync;
+
+	/// fang task type of the tasks
+	const TASK_TYPE: &'static str;
+
+	/// failed runs retried before the task is marked as failed
+	const MAX_RETRIES: i32;
+
+	async fn run_with(&self, context: &Self::Context) -> anyhow::Result<()>;
+}
+
+/// Fetches the queued tasks of one type and runs them with its context
+pub struct ContextWorker<T: ContextTask> {
+	context: Arc<T::Context>,
+	/// pause once no task is queued, or after failing to fetch one
+	pub interval: Duration,
+}
+
+impl<T: ContextTask> ContextWorker<T> {
+	pub fn new(context: Arc<T::Context>) -> Self {
+		Self {
+			context,
+			interval: Duration::from_secs(1),
 		}
+	}
+
+	/// Run the next queued task, false if none was queued
+	pub async fn run_next(&self, queue: &mut dyn AsyncQueueable) -> anyhow::Result<bool> {
+		let task = match queue
+			.fetch_and_touch_task(Some(T::TASK_TYPE.to_string()))
+			.await?
+		{
+			Some(task) => task,
+			None => return Ok(false),
+		};
+		// metadata of a queued task is tagged with its type name
+		let handler = task
+			.metadata
+			.as_object()
+			.and_then(|tagged| tagged.values().next())
+			.cloned()
+			.unwrap_or_else(|| task.metadata.clone());
+		let result = match serde_json::from_value::<T>(handler) {
+			Ok(handler) => handler.run_with(&self.context).await,
+			Err(err) => Err(err.into()),
+		};
+		match result {
+			Ok(_) => {
+				queue.remove_task(&task.id).await?;
+			}
+			Err(err) if task.retries < T::MAX_RETRIES => {
+				let backoff = 2u32.saturating_pow(task.retries as u32);
+				queue
+					.schedule_retry(&task, backoff, &format!("{:#}", err))
+					.await?;
+			}
+			Err(err) => {
+				log::warn!("{} task failed: {:#}", T::TASK_TYPE, err);
+				queue.fail_task(task, &format!("{:#}", err)).await?;
+			}
+		}
+		Ok(true)
+	}
+
+	/// Run the queued tasks until the worker is dropped
+	pub async fn run(&self, queue: &mut dyn AsyncQueueable) {
+		loop {
+			match self.run_next(queue).await {
+				Ok(true) => continue,
+				Ok(false) => {}
+				Err(err) => log::warn!("failed to run {} task: {:#}", T::TASK_TYPE, err),
+			}
+			tokio::time::sleep(self.interval).await;
+		}
+	}
+
+	/// Run the queued tasks on a task of their own, beside the worker pool
+	pub fn spawn<Q>(self, mut queue: Q) -> tokio::task::JoinHandle<()>
+	where
+		T: 'static,
+		Q: AsyncQueueable + 'static,
+	{
+		tokio::spawn(async move { self.run(&mut queue).await })
+	}
+}
+
+/// Error of a context task fetched by a worker of the pool, which has no
+/// context to run it with
+pub fn missing_context(task_type: &str) -> FangError {
+	log::error!("{} task fetched without its context", task_type);
+	FangError {
+		description: format!("{} tasks are run by their context worker", task_type),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::atomic::{AtomicUsize, Ordering};
+	use std::sync::Mutex;
+
+	use ceramic_core::{Cid, StreamId};
+	use dataverse_ceramic::commit::example;
+	use dataverse_ceramic::network::Network;
+	use dataverse_ceramic::StreamLoader;
+	use int_enum::IntEnum;
+
+	use super::*;
+	use crate::stream::PublishStatus;
+	use crate::testing::MemoryStreams;
+
+	/// Serves the example log, failing the first `failures` uploads
+	#[derive(Default)]
+	struct FlakyNode {
+		failures: usize,
+		calls: AtomicUsize,
+		uploaded: Mutex<Vec<Cid>>,
+	}
+
+	#[async_trait]
+	impl EventsLoader for FlakyNode {
+		async fn load_events(
+			&self,
+			_ceramic: &Ceramic,
+			_stream_id: &StreamId,
+			_tip: Option<Cid>,
+		) -> anyhow::Result<Vec<Event>> {
+			Ok(example::events(2))
+		}
+	}
+
+	impl StreamLoader for FlakyNode {}
+
+	#[async_trait]
+	impl EventsUploader for FlakyNode {
+		async fn upload_event(
+			&self,
+			_ceramic: &Ceramic,
+			_stream_id: &StreamId,
+			event: Event,
+		) -> anyhow::Result<()> {
+			if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+				anyhow::bail!("node unavailable");
+			}
+			self.uploaded.lock().unwrap().push(event.cid);
+			Ok(())
+		}
+	}
+
+	#[tokio::test]
+	async fn test_sync_stored_tip() -> anyhow::Result<()> {
+		let events = example::events(2);
+		let stream_id = example::genesis().stream_id()?;
+		let queued = Stream::new(
+			&uuid::Uuid::nil(),
+			stream_id.r#type.int_value(),
+			&events[0],
+			None,
+		)?;
+		// updated after the task was queued
+		let stored = Stream {
+			tip: events[1].cid,
+			..queued.clone()
+		};
+		let store = Arc::new(MemoryStreams::new(vec![stored]));
+		let node = Arc::new(FlakyNode {
+			failures: 1,
+			..Default::default()
+		});
+		let context = SyncContext {
+			stream_store: store.clone(),
+			operator: node.clone(),
+		};
+		let task = SyncStream {
+			ceramic: Ceramic {
+				endpoint: "http://localhost:7007".to_string(),
+				network: Network::InMemory,
+				api: Default::default(),
+			},
+			stream: queued,
+			events: vec![events[0].clone()],
+		};
+
+		assert!(task.run_with(&context).await.is_err());
+		let stream = store.load_stream(&stream_id).await?.unwrap();
+		assert!(matches!(
+			stream.published.status.get(&task.ceramic.endpoint),
+			Some(PublishStatus::Failed(_))
+		));
+
+		task.run_with(&context).await?;
+		let stream = store.load_stream(&stream_id).await?.unwrap();
+		assert!(stream
+			.published
+			.is_published(&task.ceramic.endpoint, &events[1].cid));
+		let uploaded: Vec<Cid> = events.iter().map(|event| event.cid).collect();
+		assert_eq!(*node.uploaded.lock().unwrap(), uploaded);
 		Ok(())
 	}
 }
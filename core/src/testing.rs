@@ -0,0 +1,144 @@
+//! In-memory stores shared by the tests of the crates of the workspace,
+//! enabled by the `testing` feature
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use ceramic_core::StreamId;
+use dataverse_ceramic::network::Network;
+use dataverse_ceramic::Ceramic;
+
+use crate::store::dapp::{DappRegistry, Lookup, Model};
+use crate::stream::{Stream, StreamStore};
+
+/// Stream store keeping streams and labels in memory
+#[derive(Default)]
+pub struct MemoryStreams {
+	streams: Mutex<Vec<Stream>>,
+	labels: Mutex<HashMap<String, Vec<String>>>,
+}
+
+impl MemoryStreams {
+	pub fn new(streams: Vec<Stream>) -> Self {
+		Self {
+			streams: Mutex::new(streams),
+			..Default::default()
+		}
+	}
+}
+
+#[async_trait::async_trait]
+impl StreamStore for MemoryStreams {
+	async fn save_stream(&self, stream: &Stream) -> anyhow::Result<()> {
+		let mut streams = self.streams.lock().unwrap();
+		streams.retain(|x| x.genesis != stream.genesis);
+		streams.push(stream.clone());
+		Ok(())
+	}
+
+	async fn load_stream(&self, stream_id: &StreamId) -> anyhow::Result<Option<Stream>> {
+		let streams = self.streams.lock().unwrap();
+		Ok(streams
+			.iter()
+			.find(|x| x.stream_id().ok().as_ref() == Some(stream_id))
+			.cloned())
+	}
+
+	async fn list_all_streams(&self) -> anyhow::Result<Vec<Stream>> {
+		Ok(self.streams.lock().unwrap().clone())
+	}
+
+	async fn remove_stream(&self, stream_id: &StreamId) -> anyhow::Result<bool> {
+		let mut streams = self.streams.lock().unwrap();
+		let len = streams.len();
+		streams.retain(|x| x.stream_id().ok().as_ref() != Some(stream_id));
+		Ok(streams.len() < len)
+	}
+
+	async fn add_stream_label(&self, stream_id: &StreamId, label: &str) -> anyhow::Result<()> {
+		let mut labels = self.labels.lock().unwrap();
+		let labels = labels.entry(stream_id.to_string()).or_default();
+		if !labels.iter().any(|x| x == label) {
+			labels.push(label.to_string());
+		}
+		Ok(())
+	}
+
+	async fn remove_stream_label(&self, stream_id: &StreamId, label: &str) -> anyhow::Result<bool> {
+		let mut labels = self.labels.lock().unwrap();
+		let labels = labels.entry(stream_id.to_string()).or_default();
+		let len = labels.len();
+		labels.retain(|x| x != label);
+		Ok(labels.len() < len)
+	}
+
+	async fn stream_labels(&self, stream_id: &StreamId) -> anyhow::Result<Vec<String>> {
+		let labels = self.labels.lock().unwrap();
+		Ok(labels
+			.get(&stream_id.to_string())
+			.cloned()
+			.unwrap_or_default())
+	}
+}
+
+/// Registry of a fixed set of models, every dapp on an in-memory node
+#[derive(Default)]
+pub struct MemoryRegistry {
+	pub models: Vec<Model>,
+}
+
+impl MemoryRegistry {
+	pub fn new(models: Vec<Model>) -> Self {
+		Self { models }
+	}
+}
+
+#[async_trait::async_trait]
+impl DappRegistry for MemoryRegistry {
+	async fn get_dapp_ceramic(&self, _dapp_id: &uuid::Uuid) -> anyhow::Result<Ceramic> {
+		Ok(Ceramic {
+			endpoint: "http://localhost:7007".to_string(),
+			network: Network::InMemory,
+			api: Default::default(),
+		})
+	}
+
+	async fn get_ceramic(&self, endpoint: &str) -> anyhow::Result<Ceramic> {
+		anyhow::bail!("no ceramic at {}", endpoint)
+	}
+
+	async fn get_model_by_name(
+		&self,
+		dapp_id: &uuid::Uuid,
+		model_name: &str,
+	) -> anyhow::Result<Model> {
+		match self
+			.models
+			.iter()
+			.find(|model| model.dapp_id == *dapp_id && model.name == model_name)
+		{
+			Some(model) => Ok(model.clone()),
+			None => anyhow::bail!("no model {}", model_name),
+		}
+	}
+
+	async fn get_model(&self, model_id: &StreamId) -> anyhow::Result<Model> {
+		match self.models.iter().find(|model| model.id == *model_id) {
+			Some(model) => Ok(model.clone()),
+			None => anyhow::bail!("no model {}", model_id),
+		}
+	}
+
+	async fn get_models(
+		&self,
+		dapp_id: &uuid::Uuid,
+		_lookup: Lookup,
+	) -> anyhow::Result<Vec<Model>> {
+		Ok(self
+			.models
+			.iter()
+			.filter(|model| model.dapp_id == *dapp_id)
+			.cloned()
+			.collect())
+	}
+}
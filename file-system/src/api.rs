@@ -0,0 +1,208 @@
+//! Endpoints of the node api, mounted by the http server facade on the routes
+//! they are documented at. The `/openapi.json` document of the `openapi`
+//! feature describes every endpoint.
+
+use anyhow::Result;
+use ceramic_core::StreamId;
+use dataverse_ceramic::event::commit::{CommitLimits, DecodedData, DecodedGenesis};
+use dataverse_ceramic::event::Event;
+use dataverse_ceramic::StreamState;
+use dataverse_core::notifier::Notifier;
+use futures::Stream;
+
+use crate::file::dapp_query::{DappFilesPage, DappQuery};
+use crate::file::folder_delta::FolderDelta;
+use crate::file::folder_stats::FolderStats;
+use crate::file::{Client, StreamEventSaver, StreamFile, StreamFileTrait};
+
+/// Route of the OpenAPI document of the api
+pub const OPENAPI_JSON: &str = "/openapi.json";
+
+/// Route of the server-sent events of a stream, `{stream_id}` is the path
+/// parameter
+pub const STREAM_EVENTS: &str = "/streams/{stream_id}/events";
+
+/// Content type of server-sent events responses
+pub const EVENT_STREAM: &str = "text/event-stream";
+
+#[cfg_attr(feature = "openapi", utoipa::path(
+	get,
+	path = "/dapp/{dapp_id}/streams/{stream_id}",
+	params(
+		("dapp_id" = String, Path, description = "Uuid of the dapp"),
+		("stream_id" = String, Path, description = "Stream id of the file")
+	),
+	responses((status = 200, description = "File of the stream", body = StreamFile))
+))]
+pub async fn get_file(
+	client: &Client,
+	dapp_id: &uuid::Uuid,
+	stream_id: &StreamId,
+) -> Result<StreamFile> {
+	client.load_file(dapp_id, stream_id).await
+}
+
+/// Create a stream from a genesis body, its blocks decoded once while parsed
+#[cfg_attr(feature = "openapi", utoipa::path(
+	post,
+	path = "/dapp/{dapp_id}/streams",
+	params(("dapp_id" = String, Path, description = "Uuid of the dapp")),
+	request_body(
+		content = String,
+		content_type = "application/json",
+		description = "Genesis commit with base64 encoded blocks"
+	),
+	responses((status = 200, description = "State of the created stream"))
+))]
+pub async fn create_stream(
+	client: &Client,
+	dapp_id: &uuid::Uuid,
+	body: &[u8],
+	limits: &CommitLimits,
+) -> Result<StreamState> {
+	let genesis = DecodedGenesis::from_slice_limited(body, limits)?;
+	let stream_id = genesis.stream_id()?;
+	let event: Event = genesis.genesis.try_into()?;
+	client.save_event(dapp_id, &stream_id, &event).await
+}
+
+/// Apply a data commit body to its stream, its blocks decoded once while
+/// parsed
+#[cfg_attr(feature = "openapi", utoipa::path(
+	post,
+	path = "/dapp/{dapp_id}/commits",
+	params(("dapp_id" = String, Path, description = "Uuid of the dapp")),
+	request_body(
+		content = String,
+		content_type = "application/json",
+		description = "Data commit with base64 encoded blocks"
+	),
+	responses((status = 200, description = "State of the updated stream"))
+))]
+pub async fn update_stream(
+	client: &Client,
+	dapp_id: &uuid::Uuid,
+	body: &[u8],
+	limits: &CommitLimits,
+) -> Result<StreamState> {
+	let data = DecodedData::from_slice_limited(body, limits)?;
+	let event: Event = data.commit.try_into()?;
+	client.save_event(dapp_id, &data.stream_id, &event).await
+}
+
+#[cfg_attr(feature = "openapi", utoipa::path(
+	get,
+	path = "/dapp/{dapp_id}/files",
+	params(
+		("dapp_id" = String, Path, description = "Uuid of the dapp"),
+		("account" = Option<String>, Query, description = "Controller of the files"),
+		("offset" = Option<usize>, Query, description = "Files skipped"),
+		("limit" = Option<usize>, Query, description = "Files of the page, 50 if not set")
+	),
+	responses((status = 200, description = "Page of the files of the dapp", body = DappFilesPage))
+))]
+pub async fn query_dapp_files(
+	client: &Client,
+	dapp_id: &uuid::Uuid,
+	account: Option<String>,
+	query: DappQuery,
+) -> Result<DappFilesPage> {
+	client.query_dapp(dapp_id, account, query).await
+}
+
+#[cfg_attr(feature = "openapi", utoipa::path(
+	get,
+	path = "/dapp/{dapp_id}/folders/{folder_id}/stats",
+	params(
+		("dapp_id" = String, Path, description = "Uuid of the dapp"),
+		("folder_id" = String, Path, description = "Stream id of the folder")
+	),
+	responses((status = 200, description = "Counts of the folder", body = FolderStats))
+))]
+pub async fn get_folder_stats(
+	client: &Client,
+	dapp_id: &uuid::Uuid,
+	folder_id: &StreamId,
+) -> Result<FolderStats> {
+	client.folder_stats(dapp_id, folder_id).await
+}
+
+#[cfg_attr(feature = "openapi", utoipa::path(
+	get,
+	path = "/folders/{folder_id}/changes",
+	params(
+		("folder_id" = String, Path, description = "Stream id of the folder"),
+		("since" = i64, Query, description = "Sequence of the last synced change")
+	),
+	responses((status = 200, description = "Changes of the folder since the sequence", body = FolderDelta))
+))]
+pub async fn get_folder_delta(
+	client: &Client,
+	folder_id: &StreamId,
+	since: i64,
+) -> Result<FolderDelta> {
+	client.folder_delta(folder_id, since).await
+}
+
+/// Frames of the commit notifications of the stream, until the notifier is
+/// dropped. Subscribed when called, so commits saved before polling the
+/// frames are not missed
+#[cfg_attr(feature = "openapi", utoipa::path(
+	get,
+	path = "/streams/{stream_id}/events",
+	params(("stream_id" = String, Path, description = "Stream id to follow")),
+	responses((
+		status = 200,
+		description = "Server-sent events of the commits of the stream",
+		content_type = "text/event-stream",
+		body = String
+	))
+))]
+pub fn stream_events(
+	notifier: &Notifier,
+	stream_id: StreamId,
+) -> impl Stream<Item = Result<String>> {
+	futures::stream::unfold(
+		notifier.subscribe(Some(stream_id)),
+		|mut subscription| async move {
+			let notification = subscription.next().await?;
+			Some((notification.to_sse(), subscription))
+		},
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::str::FromStr;
+
+	use async_std::task;
+	use ceramic_core::Cid;
+	use dataverse_core::notifier::CommitNotification;
+	use futures::StreamExt;
+
+	use super::*;
+
+	#[test]
+	fn test_stream_events() {
+		let stream_id =
+			StreamId::from_str("kjzl6kcym7w8y9s94kcardbh5u0ao76bci07xnnxjw1ew3i4eackykj76uagqfk")
+				.unwrap();
+		let tip =
+			Cid::from_str("bafyreih3skzznsyro5i4bsfsyquhcewyqmsra6cx47nj2eb6aerm2nihuu").unwrap();
+
+		let notifier = Notifier::default();
+		let events = stream_events(&notifier, stream_id.clone());
+		notifier.notify(CommitNotification {
+			stream_id,
+			tip,
+			model: None,
+			forked: false,
+			quarantine: None,
+		});
+		drop(notifier);
+
+		let frames: Vec<String> = task::block_on(events.map(Result::unwrap).collect());
+		assert_eq!(frames.len(), 1);
+		assert!(frames[0].starts_with("event: commit\nid: bafyrei"));
+	}
+}
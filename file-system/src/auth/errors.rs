@@ -0,0 +1,48 @@
+use dataverse_ceramic::StreamId;
+
+#[derive(Debug)]
+pub enum AuthError {
+	MissingBearerToken,
+	NotController(String, StreamId),
+	UnknownController(StreamId),
+	/// cacao of a stream without model not granting every model
+	CapabilityNotGranted,
+}
+
+impl std::fmt::Display for AuthError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::MissingBearerToken => write!(f, "missing bearer token"),
+			Self::NotController(did, stream_id) => {
+				write!(f, "{} is not controller of stream {}", did, stream_id)
+			}
+			Self::UnknownController(stream_id) => {
+				write!(f, "controller of stream {} unknown", stream_id)
+			}
+			Self::CapabilityNotGranted => write!(f, "capability does not grant every model"),
+		}
+	}
+}
+
+impl std::error::Error for AuthError {}
+
+#[derive(Debug)]
+pub enum TokenError {
+	InvalidScope(String),
+	UnknownToken,
+	Revoked(uuid::Uuid),
+	MissingScope(String),
+}
+
+impl std::fmt::Display for TokenError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::InvalidScope(scope) => write!(f, "invalid scope `{}`", scope),
+			Self::UnknownToken => write!(f, "unknown service token"),
+			Self::Revoked(id) => write!(f, "service token {} revoked", id),
+			Self::MissingScope(scope) => write!(f, "service token missing scope `{}`", scope),
+		}
+	}
+}
+
+impl std::error::Error for TokenError {}
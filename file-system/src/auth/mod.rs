@@ -0,0 +1,250 @@
+mod errors;
+pub mod token;
+
+pub use errors::{AuthError, TokenError};
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use dataverse_ceramic::did::verify_did_jwt;
+use dataverse_ceramic::event::verify::DEFAULT_CLOCK_SKEW;
+use dataverse_ceramic::event::{Event, EventValue, VerifyOption};
+use dataverse_ceramic::{StreamId, StreamState};
+
+use crate::file::{SaveOpts, StreamEventSaver, StreamFileTrait};
+
+/// Authenticated caller of the api
+#[derive(Debug, Clone, PartialEq)]
+pub struct Caller {
+	pub did: String,
+}
+
+impl Caller {
+	/// Account used for file-system calls made on behalf of the caller
+	pub fn account(&self) -> Option<String> {
+		Some(self.did.clone())
+	}
+
+	/// Caller acting for the event of a stream of the model. Tokens are signed
+	/// by the `did:key` of a session, streams are controlled by the `did:pkh`
+	/// of an account: when the event is signed by the caller's key under a
+	/// cacao delegating to that key, the caller acts as the issuer of the
+	/// cacao. Events whose jws or cacao signature does not verify, or whose
+	/// cacao is not valid now or does not grant the model, are rejected.
+	pub fn for_event(&self, event: &Event, model: Option<&StreamId>) -> anyhow::Result<Caller> {
+		self.for_event_at(event, model, Utc::now())
+	}
+
+	/// `for_event` with the cacao checked at the time
+	pub fn for_event_at(
+		&self,
+		event: &Event,
+		model: Option<&StreamId>,
+		at: DateTime<Utc>,
+	) -> anyhow::Result<Caller> {
+		let signed = match &event.value {
+			EventValue::Signed(signed) => signed,
+			EventValue::Anchor(_) => return Ok(self.clone()),
+		};
+		let mut opts = vec![VerifyOption::ExpirationTimeBefore(at)];
+		opts.extend(model.cloned().map(VerifyOption::ResourceModelsContain));
+		event.verify_signature(opts)?;
+
+		if let Some(cacao) = signed.cacao()? {
+			if model.is_none() && !cacao.grants_all_models() {
+				anyhow::bail!(AuthError::CapabilityNotGranted);
+			}
+			if signed.signer()? == self.did && cacao.p.aud == self.did {
+				return Ok(Caller {
+					did: cacao.p.iss.clone(),
+				});
+			}
+		}
+		Ok(self.clone())
+	}
+}
+
+#[async_trait::async_trait]
+pub trait TokenVerifier: Send + Sync {
+	async fn verify(&self, token: &str) -> anyhow::Result<Caller>;
+
+	async fn authenticate(&self, authorization: Option<&str>) -> anyhow::Result<Caller> {
+		let token = authorization
+			.and_then(|header| header.strip_prefix("Bearer "))
+			.map(str::trim)
+			.filter(|token| !token.is_empty());
+		match token {
+			Some(token) => self.verify(token).await,
+			None => anyhow::bail!(AuthError::MissingBearerToken),
+		}
+	}
+}
+
+/// Verifier of DID-JWTs signed by a `did:key`, SIWE sessions can be plugged in
+/// through another `TokenVerifier`
+pub struct DidJwtVerifier {
+	/// `aud` the tokens must be issued for, the url of the service
+	pub audience: String,
+	/// tolerance of the time claims of the tokens
+	pub clock_skew: Duration,
+}
+
+impl DidJwtVerifier {
+	pub fn new(audience: &str) -> Self {
+		Self {
+			audience: audience.to_string(),
+			clock_skew: DEFAULT_CLOCK_SKEW,
+		}
+	}
+}
+
+#[async_trait::async_trait]
+impl TokenVerifier for DidJwtVerifier {
+	async fn verify(&self, token: &str) -> anyhow::Result<Caller> {
+		let jwt = verify_did_jwt(token, &self.audience, Utc::now(), self.clock_skew)?;
+		Ok(Caller { did: jwt.did })
+	}
+}
+
+pub fn ensure_controller(
+	caller: &Caller,
+	stream_id: &StreamId,
+	controllers: &[String],
+) -> anyhow::Result<()> {
+	if controllers.is_empty() {
+		anyhow::bail!(AuthError::UnknownController(stream_id.clone()));
+	}
+	if !controllers.contains(&caller.did) {
+		anyhow::bail!(AuthError::NotController(
+			caller.did.clone(),
+			stream_id.clone()
+		));
+	}
+	Ok(())
+}
+
+/// Save events only when the caller controls the stream
+#[async_trait::async_trait]
+pub trait AuthorizedEventSaver: StreamEventSaver + StreamFileTrait + Sync {
+	async fn save_event_as(
+		&self,
+		caller: &Caller,
+		dapp_id: &uuid::Uuid,
+		stream_id: &StreamId,
+		event: &Event,
+	) -> anyhow::Result<StreamState> {
+		self.save_event_as_with(caller, dapp_id, stream_id, event, SaveOpts::default())
+			.await
+	}
+
+	async fn save_event_as_with(
+		&self,
+		caller: &Caller,
+		dapp_id: &uuid::Uuid,
+		stream_id: &StreamId,
+		event: &Event,
+		opts: SaveOpts,
+	) -> anyhow::Result<StreamState> {
+		let (controllers, model) = match &event.value {
+			EventValue::Signed(signed) if signed.is_gensis() => match signed.payload()?.header {
+				Some(header) => (header.controllers, Some(header.model)),
+				None => (vec![], None),
+			},
+			_ => {
+				let state = self.load_stream(dapp_id, stream_id).await?;
+				(state.controllers(), state.model()?)
+			}
+		};
+		let caller = caller.for_event(event, model.as_ref())?;
+		ensure_controller(&caller, stream_id, &controllers)?;
+		self.save_event_with(dapp_id, stream_id, event, opts).await
+	}
+}
+
+impl<T: StreamEventSaver + StreamFileTrait + Sync> AuthorizedEventSaver for T {}
+
+#[cfg(test)]
+mod tests {
+	use std::str::FromStr;
+
+	use async_std::task;
+
+	use super::*;
+
+	struct Static;
+
+	#[async_trait::async_trait]
+	impl TokenVerifier for Static {
+		async fn verify(&self, token: &str) -> anyhow::Result<Caller> {
+			Ok(Caller {
+				did: token.to_string(),
+			})
+		}
+	}
+
+	#[test]
+	fn authenticate_bearer() {
+		task::block_on(async {
+			let caller = Static.authenticate(Some("Bearer did:key:z6Mk")).await;
+			assert_eq!(caller.unwrap().did, "did:key:z6Mk");
+			assert!(Static.authenticate(Some("Basic abc")).await.is_err());
+			assert!(Static.authenticate(None).await.is_err());
+		});
+	}
+
+	#[test]
+	fn check_controller() {
+		let stream_id =
+			StreamId::from_str("kjzl6kcym7w8y8syiams0kvm3qwfnutk2szi0wlhvf6rr9lalzpibxed0qvotuy")
+				.unwrap();
+		let caller = Caller {
+			did: "did:pkh:eip155:1:0xabc".to_string(),
+		};
+		assert!(ensure_controller(&caller, &stream_id, &[caller.did.clone()]).is_ok());
+		assert!(ensure_controller(&caller, &stream_id, &["did:key:z6Mk".to_string()]).is_err());
+		assert!(ensure_controller(&caller, &stream_id, &[]).is_err());
+	}
+
+	#[test]
+	fn delegated_caller() -> anyhow::Result<()> {
+		let account = "did:pkh:eip155:1:0x312eA852726E3A9f633A0377c0ea882086d66666";
+		let genesis: Event = dataverse_ceramic::commit::example::genesis()
+			.genesis
+			.try_into()?;
+		let session = match &genesis.value {
+			EventValue::Signed(signed) => signed.signer()?,
+			EventValue::Anchor(_) => unreachable!(),
+		};
+		let stream_id =
+			StreamId::from_str("kjzl6kcym7w8y8syiams0kvm3qwfnutk2szi0wlhvf6rr9lalzpibxed0qvotuy")?;
+		let controllers = [account.to_string()];
+
+		let model = match &genesis.value {
+			EventValue::Signed(signed) => signed.payload()?.header.map(|header| header.model),
+			EventValue::Anchor(_) => unreachable!(),
+		};
+		let model = model.as_ref();
+		let at: DateTime<Utc> = "2023-11-09T00:00:00Z".parse()?;
+
+		// the session key the cacao delegates to acts as the account
+		let caller = Caller { did: session };
+		assert!(ensure_controller(&caller, &stream_id, &controllers).is_err());
+		let delegated = caller.for_event_at(&genesis, model, at)?;
+		assert_eq!(delegated.did, account);
+		assert!(ensure_controller(&delegated, &stream_id, &controllers).is_ok());
+
+		// another key does not
+		let other = Caller {
+			did: "did:key:z6MkuBcU2NW8Yfd1pJKA8HeFxeojzujcNyhmTNkuhDEfpqKT".to_string(),
+		};
+		assert_eq!(other.for_event_at(&genesis, model, at)?, other);
+
+		// the cacao must be valid at the time and grant the model
+		assert!(caller.for_event(&genesis, model).is_err());
+		let ungranted =
+			StreamId::from_str("kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju")?;
+		assert!(caller.for_event_at(&genesis, Some(&ungranted), at).is_err());
+		assert!(caller.for_event_at(&genesis, None, at).is_err());
+		Ok(())
+	}
+}
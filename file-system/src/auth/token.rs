@@ -0,0 +1,198 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use openssl::rand::rand_bytes;
+use openssl::sha::sha256;
+
+use super::errors::TokenError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+	Read,
+	Write,
+	Admin,
+}
+
+/// Capability of a service token, e.g. `read:model:<id>`, `write:dapp:<id>`
+/// or `admin:pins`. `*` as id grants every resource of the kind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scope {
+	pub action: Action,
+	pub resource: String,
+	pub id: Option<String>,
+}
+
+impl Scope {
+	pub fn new(action: Action, resource: &str, id: Option<&str>) -> Self {
+		Self {
+			action,
+			resource: resource.to_string(),
+			id: id.map(ToString::to_string),
+		}
+	}
+
+	/// Whether holding this scope permits the required one, write implies read
+	pub fn grants(&self, required: &Scope) -> bool {
+		let action = self.action == required.action
+			|| (self.action == Action::Write && required.action == Action::Read);
+		let id = match (&self.id, &required.id) {
+			(Some(id), _) if id == "*" => true,
+			(id, required) => id == required,
+		};
+		action && self.resource == required.resource && id
+	}
+}
+
+impl FromStr for Scope {
+	type Err = anyhow::Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut parts = s.splitn(3, ':');
+		let action = match parts.next() {
+			Some("read") => Action::Read,
+			Some("write") => Action::Write,
+			Some("admin") => Action::Admin,
+			_ => anyhow::bail!(TokenError::InvalidScope(s.to_string())),
+		};
+		let resource = match parts.next() {
+			Some(resource) if !resource.is_empty() => resource,
+			_ => anyhow::bail!(TokenError::InvalidScope(s.to_string())),
+		};
+		Ok(Scope::new(action, resource, parts.next()))
+	}
+}
+
+impl Display for Scope {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let action = match self.action {
+			Action::Read => "read",
+			Action::Write => "write",
+			Action::Admin => "admin",
+		};
+		match &self.id {
+			Some(id) => write!(f, "{}:{}:{}", action, self.resource, id),
+			None => write!(f, "{}:{}", action, self.resource),
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct ServiceToken {
+	pub id: uuid::Uuid,
+	pub name: String,
+	pub scopes: Vec<Scope>,
+	pub created_at: DateTime<Utc>,
+	pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl ServiceToken {
+	pub fn authorize(&self, required: &Scope) -> anyhow::Result<()> {
+		if self.revoked_at.is_some() {
+			anyhow::bail!(TokenError::Revoked(self.id));
+		}
+		if !self.scopes.iter().any(|scope| scope.grants(required)) {
+			anyhow::bail!(TokenError::MissingScope(required.to_string()));
+		}
+		Ok(())
+	}
+}
+
+/// Generate the secret handed out once to the integration
+pub fn generate_secret() -> anyhow::Result<String> {
+	let mut buf = [0; 32];
+	rand_bytes(&mut buf)?;
+	Ok(format!("dvt_{}", hex_encode(&buf)))
+}
+
+/// Only the hash of a secret is stored
+pub fn hash_secret(secret: &str) -> String {
+	hex_encode(&sha256(secret.as_bytes()))
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+	bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[async_trait::async_trait]
+pub trait ServiceTokenStore: Send + Sync {
+	async fn save_token(&self, token: &ServiceToken, token_hash: &str) -> anyhow::Result<()>;
+	async fn lookup_token(&self, token_hash: &str) -> anyhow::Result<Option<ServiceToken>>;
+	async fn revoke_token(&self, id: &uuid::Uuid) -> anyhow::Result<()>;
+
+	/// Issue a token, returning it with its secret
+	async fn issue_token(
+		&self,
+		name: &str,
+		scopes: Vec<Scope>,
+	) -> anyhow::Result<(ServiceToken, String)> {
+		let secret = generate_secret()?;
+		let token = ServiceToken {
+			id: uuid::Uuid::new_v4(),
+			name: name.to_string(),
+			scopes,
+			created_at: Utc::now(),
+			revoked_at: None,
+		};
+		self.save_token(&token, &hash_secret(&secret)).await?;
+		Ok((token, secret))
+	}
+
+	/// Check the secret grants the scope before calling into stores
+	async fn authorize(&self, secret: &str, required: &Scope) -> anyhow::Result<ServiceToken> {
+		let token = self
+			.lookup_token(&hash_secret(secret))
+			.await?
+			.ok_or(TokenError::UnknownToken)?;
+		token.authorize(required)?;
+		Ok(token)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn scope_grants() {
+		let read: Scope = "read:model:kjzl6".parse().unwrap();
+		let write: Scope = "write:model:kjzl6".parse().unwrap();
+		let any_dapp: Scope = "write:dapp:*".parse().unwrap();
+		let pins: Scope = "admin:pins".parse().unwrap();
+
+		assert!(write.grants(&read));
+		assert!(!read.grants(&write));
+		assert!(any_dapp.grants(&"read:dapp:c3a1".parse().unwrap()));
+		assert!(!any_dapp.grants(&read));
+		assert!(pins.grants(&Scope::new(Action::Admin, "pins", None)));
+		assert_eq!(pins.to_string(), "admin:pins");
+		assert!("delete:pins".parse::<Scope>().is_err());
+	}
+
+	#[test]
+	fn authorize_token() {
+		let mut token = ServiceToken {
+			id: uuid::Uuid::new_v4(),
+			name: "indexer".to_string(),
+			scopes: vec!["read:model:*".parse().unwrap()],
+			created_at: Utc::now(),
+			revoked_at: None,
+		};
+		let required = "read:model:kjzl6".parse().unwrap();
+		assert!(token.authorize(&required).is_ok());
+		assert!(token
+			.authorize(&"write:model:kjzl6".parse().unwrap())
+			.is_err());
+
+		token.revoked_at = Some(Utc::now());
+		assert!(token.authorize(&required).is_err());
+	}
+
+	#[test]
+	fn secret_hash() {
+		let secret = generate_secret().unwrap();
+		assert!(secret.starts_with("dvt_"));
+		assert_eq!(hash_secret(&secret), hash_secret(&secret));
+		assert_eq!(hash_secret(&secret).len(), 64);
+	}
+}
@@ -0,0 +1,293 @@
+//! Envelope encryption of file contents, in the format of the js sdk: fields
+//! are encrypted with a random symmetric key, the key itself is encrypted by
+//! Lit against the decryption conditions of the file.
+
+use std::collections::BTreeMap;
+
+use base64::Engine;
+use openssl::rand::rand_bytes;
+use openssl::sha::sha256;
+use openssl::symm::{decrypt, encrypt, Cipher};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+const KEY_LEN: usize = 32;
+const IV_LEN: usize = 16;
+/// field of the content listing which fields are encrypted, as a json string
+/// e.g. `{"text":true,"images":false}`
+const ENCRYPTED_FIELD: &str = "encrypted";
+
+#[derive(Debug)]
+pub enum CryptoError {
+	InvalidKeyLength(usize),
+	CiphertextTooShort,
+	NotAnObject,
+	InvalidEncryptedField(String),
+	/// the symmetric key is not the one the encrypted key was created for
+	KeyMismatch,
+}
+
+impl std::fmt::Display for CryptoError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::InvalidKeyLength(len) => {
+				write!(f, "symmetric key of {} bytes, expected {}", len, KEY_LEN)
+			}
+			Self::CiphertextTooShort => write!(f, "ciphertext shorter than its iv"),
+			Self::NotAnObject => write!(f, "content is not an object"),
+			Self::InvalidEncryptedField(err) => {
+				write!(f, "invalid `{}` field: {}", ENCRYPTED_FIELD, err)
+			}
+			Self::KeyMismatch => write!(f, "symmetric key does not match encrypted key"),
+		}
+	}
+}
+
+impl std::error::Error for CryptoError {}
+
+/// AES-256 key the content fields are encrypted with
+#[derive(Clone, PartialEq, Eq)]
+pub struct SymmetricKey([u8; KEY_LEN]);
+
+impl std::fmt::Debug for SymmetricKey {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str("SymmetricKey(..)")
+	}
+}
+
+impl SymmetricKey {
+	pub fn generate() -> anyhow::Result<Self> {
+		let mut key = [0; KEY_LEN];
+		rand_bytes(&mut key)?;
+		Ok(Self(key))
+	}
+
+	pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+		match bytes.try_into() {
+			Ok(key) => Ok(Self(key)),
+			Err(_) => anyhow::bail!(CryptoError::InvalidKeyLength(bytes.len())),
+		}
+	}
+
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.0
+	}
+
+	/// AES-256-CBC with a random iv, prepended to the ciphertext as Lit does
+	pub fn encrypt(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+		let mut iv = [0; IV_LEN];
+		rand_bytes(&mut iv)?;
+		let mut encrypted = iv.to_vec();
+		encrypted.extend(encrypt(Cipher::aes_256_cbc(), &self.0, Some(&iv), data)?);
+		Ok(encrypted)
+	}
+
+	pub fn decrypt(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+		if data.len() < IV_LEN {
+			anyhow::bail!(CryptoError::CiphertextTooShort);
+		}
+		let (iv, data) = data.split_at(IV_LEN);
+		Ok(decrypt(Cipher::aes_256_cbc(), &self.0, Some(iv), data)?)
+	}
+
+	pub fn encrypt_string(&self, text: &str) -> anyhow::Result<String> {
+		let encrypted = self.encrypt(text.as_bytes())?;
+		Ok(base64::engine::general_purpose::STANDARD.encode(encrypted))
+	}
+
+	pub fn decrypt_string(&self, encrypted: &str) -> anyhow::Result<String> {
+		let encrypted = base64::engine::general_purpose::STANDARD.decode(encrypted)?;
+		Ok(String::from_utf8(self.decrypt(&encrypted)?)?)
+	}
+
+	/// Encrypt the fields of the content and record them in its `encrypted`
+	/// field. Strings are encrypted as is, other values as their json text.
+	pub fn encrypt_fields(&self, content: &mut Value, fields: &[&str]) -> anyhow::Result<()> {
+		let mut encrypted = encrypted_fields(content)?;
+		let object = content.as_object_mut().ok_or(CryptoError::NotAnObject)?;
+		for field in fields {
+			let text = match object.get(*field) {
+				None | Some(Value::Null) => continue,
+				Some(Value::String(text)) => text.clone(),
+				Some(value) => serde_json::to_string(value)?,
+			};
+			object.insert(field.to_string(), self.encrypt_string(&text)?.into());
+			encrypted.insert(field.to_string(), true);
+		}
+		object.insert(
+			ENCRYPTED_FIELD.to_string(),
+			serde_json::to_string(&encrypted)?.into(),
+		);
+		Ok(())
+	}
+
+	/// Decrypt the fields the content lists as encrypted. Decrypted arrays and
+	/// objects are restored, other values are left as their text.
+	pub fn decrypt_fields(&self, content: &mut Value) -> anyhow::Result<()> {
+		let mut encrypted = encrypted_fields(content)?;
+		let object = content.as_object_mut().ok_or(CryptoError::NotAnObject)?;
+		for (field, is_encrypted) in encrypted.iter_mut() {
+			let text = match object.get(field) {
+				Some(Value::String(text)) if *is_encrypted => self.decrypt_string(text)?,
+				_ => continue,
+			};
+			let value = match serde_json::from_str(&text) {
+				Ok(value @ (Value::Array(_) | Value::Object(_))) => value,
+				_ => Value::String(text),
+			};
+			object.insert(field.clone(), value);
+			*is_encrypted = false;
+		}
+		if object.contains_key(ENCRYPTED_FIELD) {
+			object.insert(
+				ENCRYPTED_FIELD.to_string(),
+				serde_json::to_string(&encrypted)?.into(),
+			);
+		}
+		Ok(())
+	}
+}
+
+fn encrypted_fields(content: &Value) -> anyhow::Result<BTreeMap<String, bool>> {
+	match content.get(ENCRYPTED_FIELD) {
+		None | Some(Value::Null) => Ok(BTreeMap::new()),
+		Some(Value::String(encrypted)) => serde_json::from_str(encrypted)
+			.map_err(|err| CryptoError::InvalidEncryptedField(err.to_string()).into()),
+		Some(_) => anyhow::bail!(CryptoError::InvalidEncryptedField(
+			"not a string".to_string()
+		)),
+	}
+}
+
+/// Symmetric key encrypted by Lit, the `encryptedSymmetricKey` of the
+/// encryption provider of an access control is its json hex encoded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptedSymmetricKey {
+	/// base64 ciphertext returned by Lit
+	pub ciphertext: String,
+	/// hex sha256 of the symmetric key
+	pub data_to_encrypt_hash: String,
+}
+
+impl EncryptedSymmetricKey {
+	/// Key to be encrypted by Lit, the ciphertext is left empty until
+	/// `with_ciphertext` is called with the one Lit returns
+	pub fn placeholder(key: &SymmetricKey) -> Self {
+		Self {
+			ciphertext: String::new(),
+			data_to_encrypt_hash: hex::encode(sha256(key.as_bytes())),
+		}
+	}
+
+	pub fn with_ciphertext(self, ciphertext: &str) -> Self {
+		Self {
+			ciphertext: ciphertext.to_string(),
+			..self
+		}
+	}
+
+	pub fn is_placeholder(&self) -> bool {
+		self.ciphertext.is_empty()
+	}
+
+	/// Check the key decrypted by Lit is the one this was created for
+	pub fn verify(&self, key: &SymmetricKey) -> anyhow::Result<()> {
+		if hex::encode(sha256(key.as_bytes())) != self.data_to_encrypt_hash {
+			anyhow::bail!(CryptoError::KeyMismatch);
+		}
+		Ok(())
+	}
+
+	pub fn encode(&self) -> anyhow::Result<String> {
+		Ok(hex::encode(serde_json::to_vec(self)?))
+	}
+
+	pub fn decode(encoded: &str) -> anyhow::Result<Self> {
+		Ok(serde_json::from_slice(&hex::decode(encoded)?)?)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn test_fields_roundtrip() -> anyhow::Result<()> {
+		let key = SymmetricKey::generate()?;
+		let original = json!({
+			"text": "hello",
+			"images": ["ipfs://a", "ipfs://b"],
+			"videos": [],
+			"encrypted": "{\"text\":false,\"images\":false,\"videos\":false}",
+		});
+		let mut content = original.clone();
+		key.encrypt_fields(&mut content, &["text", "images"])?;
+		assert_ne!(content["text"], original["text"]);
+		assert!(content["images"].is_string());
+		assert_eq!(content["videos"], original["videos"]);
+		assert_eq!(
+			content["encrypted"],
+			"{\"images\":true,\"text\":true,\"videos\":false}"
+		);
+
+		let other = SymmetricKey::generate()?;
+		assert!(other.decrypt_fields(&mut content.clone()).is_err());
+
+		key.decrypt_fields(&mut content)?;
+		assert_eq!(content["text"], original["text"]);
+		assert_eq!(content["images"], original["images"]);
+		assert_eq!(
+			content["encrypted"],
+			"{\"images\":false,\"text\":false,\"videos\":false}"
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_decrypt_webcrypto_vector() -> anyhow::Result<()> {
+		// AES-256-CBC ciphertexts with the iv prepended, produced with the
+		// WebCrypto api of node: key bytes 0..32, iv bytes 0xf0..0x100
+		let key: Vec<u8> = (0..32).collect();
+		let key = SymmetricKey::from_bytes(&key)?;
+		let mut content = json!({
+			"text": "8PHy8/T19vf4+fr7/P3+/94r5qqOmiaVajQvT4NHkqs=",
+			"images": "8PHy8/T19vf4+fr7/P3+/7JxRhUcT4j89KY5rppj6p8=",
+			"encrypted": "{\"text\":true,\"images\":true}",
+		});
+		key.decrypt_fields(&mut content)?;
+		assert_eq!(content["text"], "hello");
+		assert_eq!(content["images"], json!(["ipfs://a"]));
+
+		// strings are encrypted as is
+		let mut content = json!({ "text": "hello" });
+		key.encrypt_fields(&mut content, &["text"])?;
+		let text = content["text"].as_str().unwrap();
+		assert_eq!(key.decrypt_string(text)?, "hello");
+		Ok(())
+	}
+
+	#[test]
+	fn test_encrypted_key_format() -> anyhow::Result<()> {
+		let key = SymmetricKey::generate()?;
+		let placeholder = EncryptedSymmetricKey::placeholder(&key);
+		assert!(placeholder.is_placeholder());
+		placeholder.verify(&key)?;
+		assert!(placeholder.verify(&SymmetricKey::generate()?).is_err());
+
+		let encrypted = placeholder.with_ciphertext("kCf0dQBi886MHFJeH0w4GQ");
+		let decoded = EncryptedSymmetricKey::decode(&encrypted.encode()?)?;
+		assert_eq!(decoded, encrypted);
+
+		// encryptedSymmetricKey in the hex encoded json of the js sdk, the
+		// ciphertext is not a real Lit ciphertext
+		let encoded = hex::encode(
+			r#"{"ciphertext":"kCf0dQBi886MHFJeH0w4GQ","dataToEncryptHash":"d45cfb7ffcf84deefce7e92498fbb3e35593870210ea62323164a8145f85f61f"}"#,
+		);
+		let decoded = EncryptedSymmetricKey::decode(&encoded)?;
+		assert_eq!(decoded.ciphertext, "kCf0dQBi886MHFJeH0w4GQ");
+		assert_eq!(decoded.encode()?, encoded);
+		Ok(())
+	}
+}
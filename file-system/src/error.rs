@@ -1,6 +1,13 @@
+use dataverse_ceramic::event::errors::CommitError;
+use serde::{Deserialize, Serialize};
+
+use crate::auth::AuthError;
+use crate::file::errors::FileClientError;
+
 #[derive(Debug)]
 pub enum FilePolicyError {
 	AttemptToModifyProtectedFields,
+	AttemptToModifyImmutableFields,
 	PatchValidationFailed
 }
 
@@ -9,12 +16,85 @@ impl std::fmt::Display for FilePolicyError {
 		match self {
 			Self::PatchValidationFailed => write!(f, "validate patch field"),
 			Self::AttemptToModifyProtectedFields => write!(f, "attempt to modify protected fields"),
+			Self::AttemptToModifyImmutableFields => write!(f, "attempt to modify immutable fields"),
 		}
 	}
 }
 
 impl std::error::Error for FilePolicyError {}
 
+/// Structured reason returned to the submitter of a rejected commit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitRejection {
+	pub code: String,
+	/// path of the offending field, e.g. `accessControl.decryptionConditions[3]`
+	pub field_path: Option<String>,
+	/// name of the policy which rejected the commit
+	pub policy: Option<String>,
+	pub message: String,
+}
+
+impl CommitRejection {
+	pub fn new(code: &str, message: impl ToString) -> Self {
+		Self {
+			code: code.to_string(),
+			field_path: None,
+			policy: None,
+			message: message.to_string(),
+		}
+	}
+
+	pub fn with_field(mut self, field_path: impl ToString) -> Self {
+		self.field_path = Some(field_path.to_string());
+		self
+	}
+
+	pub fn with_policy(mut self, policy: &str) -> Self {
+		self.policy = Some(policy.to_string());
+		self
+	}
+}
+
+impl std::fmt::Display for CommitRejection {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match &self.field_path {
+			Some(field_path) => write!(f, "{}: {}", field_path, self.message),
+			None => write!(f, "{}", self.message),
+		}
+	}
+}
+
+impl std::error::Error for CommitRejection {}
+
+impl From<&anyhow::Error> for CommitRejection {
+	fn from(err: &anyhow::Error) -> Self {
+		if let Some(rejection) = err.downcast_ref::<CommitRejection>() {
+			return rejection.clone();
+		}
+		if let Some(e) = err.downcast_ref::<FilePolicyError>() {
+			return Self::new("PATCH_VALIDATION_FAILED", e);
+		}
+		if let Some(e @ FileClientError::PayloadTooLarge(..)) =
+			err.downcast_ref::<FileClientError>()
+		{
+			return Self::new("PAYLOAD_TOO_LARGE", e).with_field("content");
+		}
+		if let Some(e) = err.downcast_ref::<AuthError>() {
+			return Self::new("UNAUTHORIZED", e);
+		}
+		if let Some(e) = err.downcast_ref::<CommitError>() {
+			return match e {
+				CommitError::BodyTooLarge(..) => Self::new("BODY_TOO_LARGE", e),
+				CommitError::BlockTooLarge(name, ..) => {
+					Self::new("BLOCK_TOO_LARGE", e).with_field(name)
+				}
+			};
+		}
+		Self::new("INVALID_COMMIT", err)
+	}
+}
+
 pub struct IllegalError {
 	pub code: i64,
 	pub message: String,
@@ -205,3 +285,40 @@ error!(ERR_54, 0x1034, "Failed to retreive from IPFS");
 error!(ERR_55, 0x1035, "Unmatched system call used");
 error!(ERR_56, 0x1036, "The file is not a bare file");
 error!(ERR_57, 0x1037, "Not unlock");
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_commit_rejection_from_error() {
+		let rejection =
+			CommitRejection::new("LINKED_MODEL_NOT_IN_APP", "linked model not in same app")
+				.with_field("accessControl.decryptionConditions[3]")
+				.with_policy("indexFile");
+		let err = anyhow::anyhow!(rejection.clone());
+		assert_eq!(CommitRejection::from(&err), rejection);
+		assert_eq!(
+			serde_json::to_value(&rejection).unwrap()["fieldPath"],
+			"accessControl.decryptionConditions[3]"
+		);
+
+		let err = anyhow::anyhow!(CommitError::BlockTooLarge("cacaoBlock".to_string(), 2, 1));
+		let rejection = CommitRejection::from(&err);
+		assert_eq!(rejection.code, "BLOCK_TOO_LARGE");
+		assert_eq!(rejection.field_path, Some("cacaoBlock".to_string()));
+
+		let model_id = "kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju";
+		let err = anyhow::anyhow!(FileClientError::PayloadTooLarge(
+			model_id.parse().unwrap(),
+			2048,
+			1024
+		));
+		let rejection = CommitRejection::from(&err);
+		assert_eq!(rejection.code, "PAYLOAD_TOO_LARGE");
+		assert_eq!(rejection.field_path, Some("content".to_string()));
+
+		let err = anyhow::anyhow!("boom");
+		assert_eq!(CommitRejection::from(&err).code, "INVALID_COMMIT");
+	}
+}
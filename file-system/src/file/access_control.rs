@@ -31,9 +31,18 @@ pub struct EncryptionProvider {
 
 impl EncryptionProvider {
 	pub fn linked_ceramic_models(&self) -> anyhow::Result<Vec<StreamId>> {
+		Ok(self
+			.indexed_linked_ceramic_models()?
+			.into_iter()
+			.map(|(_, model_id)| model_id)
+			.collect())
+	}
+
+	/// linked models with index of the decryption condition referencing them
+	pub fn indexed_linked_ceramic_models(&self) -> anyhow::Result<Vec<(usize, StreamId)>> {
 		let mut models = vec![];
 		if let Some(conditions) = &self.decryption_conditions {
-			for ele in conditions {
+			for (idx, ele) in conditions.iter().enumerate() {
 				if let DecryptionCondition::AccessControl(ele) = ele {
 					let model_id: StreamId = ele
 						.return_value_test
@@ -42,7 +51,7 @@ impl EncryptionProvider {
 						.last()
 						.expect("failed to parse returnValueParse.value as ceramic streamId")
 						.parse()?;
-					models.push(model_id);
+					models.push((idx, model_id));
 				}
 			}
 		}
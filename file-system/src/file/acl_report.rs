@@ -0,0 +1,347 @@
+use chrono::{DateTime, Utc};
+use dataverse_ceramic::{StreamId, StreamState};
+use int_enum::IntEnum;
+use serde::{Deserialize, Serialize};
+
+use super::access_control::{
+	AccessControl, DecryptionCondition, ReturnValueTest, UnifiedAccessControlConditions,
+};
+use super::{Client, FileModel, IndexFile, IndexFileType};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AclReportFormat {
+	Csv,
+	Json,
+}
+
+/// Access rules of one private or payable file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AclEntry {
+	pub file_id: StreamId,
+	pub controller: String,
+	pub file_type: String,
+	/// readable form of the decryption conditions, e.g.
+	/// `evmBasic@ethereum :userAddress = 0x31 or evmContract@mumbai isCollected = true`
+	pub conditions: String,
+	pub chains: Vec<String>,
+	pub linked_models: Vec<StreamId>,
+	/// contracts of the monetized data asset and its dependencies
+	pub contracts: Vec<String>,
+	pub chain_ids: Vec<u64>,
+	/// access control present but not parsable, the rules are unknown
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AclReport {
+	pub dapp_id: uuid::Uuid,
+	pub generated_at: DateTime<Utc>,
+	pub entries: Vec<AclEntry>,
+}
+
+const CSV_HEADER: [&str; 9] = [
+	"fileId",
+	"controller",
+	"fileType",
+	"conditions",
+	"chains",
+	"linkedModels",
+	"contracts",
+	"chainIds",
+	"error",
+];
+
+fn csv_field(value: &str) -> String {
+	match value.contains([',', '"', '\n']) {
+		true => format!("\"{}\"", value.replace('"', "\"\"")),
+		false => value.to_string(),
+	}
+}
+
+fn join<T: ToString>(values: &[T]) -> String {
+	values
+		.iter()
+		.map(ToString::to_string)
+		.collect::<Vec<_>>()
+		.join(";")
+}
+
+impl AclReport {
+	pub fn render(&self, format: AclReportFormat) -> anyhow::Result<String> {
+		match format {
+			AclReportFormat::Json => Ok(serde_json::to_string_pretty(self)?),
+			AclReportFormat::Csv => Ok(self.to_csv()),
+		}
+	}
+
+	/// One row per file, list columns joined by `;`
+	pub fn to_csv(&self) -> String {
+		let mut csv = CSV_HEADER.join(",");
+		csv.push('\n');
+		for entry in &self.entries {
+			let row = [
+				entry.file_id.to_string(),
+				entry.controller.clone(),
+				entry.file_type.clone(),
+				entry.conditions.clone(),
+				join(&entry.chains),
+				join(&entry.linked_models),
+				join(&entry.contracts),
+				join(&entry.chain_ids),
+				entry.error.clone().unwrap_or_default(),
+			];
+			let row: Vec<String> = row.iter().map(|x| csv_field(x)).collect();
+			csv.push_str(&row.join(","));
+			csv.push('\n');
+		}
+		csv
+	}
+}
+
+fn describe_test(test: &ReturnValueTest) -> String {
+	match test.key.as_deref() {
+		Some(key) if !key.is_empty() => format!("{} {} {}", key, test.comparator, test.value),
+		_ => format!("{} {}", test.comparator, test.value),
+	}
+}
+
+fn describe_condition(condition: &DecryptionCondition, chains: &mut Vec<String>) -> String {
+	let mut push_chain = |chain: &str| {
+		if !chains.iter().any(|x| x == chain) {
+			chains.push(chain.to_string());
+		}
+	};
+	match condition {
+		DecryptionCondition::AccessControl(x) => {
+			push_chain(&x.chain);
+			format!(
+				"{}@{} {} {}",
+				x.condition_type,
+				x.chain,
+				x.parameters.join(" "),
+				describe_test(&x.return_value_test)
+			)
+		}
+		DecryptionCondition::Boolean(x) => x.operator.clone(),
+		DecryptionCondition::UnifiedAccessControl(conditions) => {
+			let parts: Vec<String> = conditions
+				.iter()
+				.map(|condition| match condition {
+					UnifiedAccessControlConditions::UnifiedAccessControl(x) => {
+						push_chain(&x.chain);
+						let call = x
+							.function_name
+							.clone()
+							.or(x.method.clone())
+							.unwrap_or_default();
+						format!(
+							"{}@{} {} {}",
+							x.condition_type,
+							x.chain,
+							call,
+							describe_test(&x.return_value_test)
+						)
+					}
+					UnifiedAccessControlConditions::Boolean(x) => x.operator.clone(),
+				})
+				.collect();
+			format!("({})", parts.join(" "))
+		}
+		DecryptionCondition::Any(value) => value.to_string(),
+	}
+}
+
+impl AclEntry {
+	/// Entry of a private or payable index file, None for public and deleted
+	/// files
+	pub fn from_index_file(
+		file_id: StreamId,
+		controller: String,
+		file: &IndexFile,
+	) -> Option<Self> {
+		let file_type = match IndexFileType::from_int(file.file_type) {
+			Ok(IndexFileType::Public) => return None,
+			Ok(file_type) => format!("{:?}", file_type).to_lowercase(),
+			Err(_) => file.file_type.to_string(),
+		};
+		if file.deleted == Some(true) {
+			return None;
+		}
+		let mut entry = Self {
+			file_id,
+			controller,
+			file_type,
+			conditions: String::new(),
+			chains: vec![],
+			linked_models: vec![],
+			contracts: vec![],
+			chain_ids: vec![],
+			error: None,
+		};
+		match file.access_control() {
+			Ok(Some(acl)) => {
+				if let Err(err) = entry.write_acl(&acl) {
+					entry.error = Some(err.to_string());
+				}
+			}
+			Ok(None) => entry.error = Some("access control is missing".to_string()),
+			Err(err) => entry.error = Some(err.to_string()),
+		}
+		Some(entry)
+	}
+
+	fn write_acl(&mut self, acl: &AccessControl) -> anyhow::Result<()> {
+		if let Some(monetization) = &acl.monetization_provider {
+			let assets = monetization.data_asset.iter().chain(
+				monetization
+					.dependencies
+					.iter()
+					.flatten()
+					.map(|x| &x.linked_asset),
+			);
+			for asset in assets {
+				if !self.contracts.contains(&asset.asset_contract) {
+					self.contracts.push(asset.asset_contract.clone());
+				}
+				if !self.chain_ids.contains(&asset.chain_id) {
+					self.chain_ids.push(asset.chain_id);
+				}
+			}
+		}
+		if let Some(encryption) = &acl.encryption_provider {
+			let conditions: Vec<String> = encryption
+				.decryption_conditions
+				.iter()
+				.flatten()
+				.map(|x| describe_condition(x, &mut self.chains))
+				.collect();
+			self.conditions = conditions.join(" ");
+			self.linked_models = encryption.linked_ceramic_models()?;
+		}
+		Ok(())
+	}
+}
+
+fn acl_entry(state: &StreamState) -> anyhow::Result<Option<AclEntry>> {
+	let file: IndexFile = serde_json::from_value(state.content.clone())?;
+	let controller = state.controllers().first().cloned().unwrap_or_default();
+	Ok(AclEntry::from_index_file(
+		state.stream_id()?,
+		controller,
+		&file,
+	))
+}
+
+impl Client {
+	/// Access rules of every private and payable file of the dapp, for dapp
+	/// owners auditing what is live
+	pub async fn export_acl_report(&self, dapp_id: &uuid::Uuid) -> anyhow::Result<AclReport> {
+		let model = self.get_file_model(dapp_id, FileModel::IndexFile).await?;
+		let ceramic = self.registry.get_dapp_ceramic(dapp_id).await?;
+		let states = self
+			.operator
+			.load_stream_states(&ceramic, None, &model.id)
+			.await?;
+
+		let mut entries = vec![];
+		for state in states {
+			match acl_entry(&state) {
+				Ok(Some(entry)) => entries.push(entry),
+				Ok(None) => {}
+				Err(err) => tracing::warn!(
+					dapp_id = dapp_id.to_string(),
+					"skipping index file in acl report: {}",
+					err
+				),
+			}
+		}
+		Ok(AclReport {
+			dapp_id: *dapp_id,
+			generated_at: Utc::now(),
+			entries,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use base64::Engine;
+
+	use super::*;
+
+	fn index_file(file_type: IndexFileType, acl: serde_json::Value) -> IndexFile {
+		let acl = base64::engine::general_purpose::STANDARD.encode(acl.to_string());
+		IndexFile {
+			file_type: file_type.int_value(),
+			access_control: Some(acl),
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn test_acl_entry() -> anyhow::Result<()> {
+		let file_id: StreamId =
+			"kjzl6kcym7w8y8syiams0kvm3qwfnutk2szi0wlhvf6rr9lalzpibxed0qvotuy".parse()?;
+		let acl = serde_json::json!({
+			"monetizationProvider": {
+				"dataAsset": {
+					"assetId": "0x11a8",
+					"assetContract": "0x67804E153F9675E2173142B76f9fe949b2b20bDE",
+					"chainId": 80001
+				}
+			},
+			"encryptionProvider": {
+				"protocol": "Lit",
+				"decryptionConditions": [
+					{
+						"conditionType": "evmBasic",
+						"contractAddress": "",
+						"standardContractType": "SIWE",
+						"chain": "ethereum",
+						"method": "",
+						"parameters": [":resources"],
+						"returnValueTest": {
+							"comparator": "contains",
+							"value": "ceramic://*?model=kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju"
+						}
+					}
+				]
+			}
+		});
+
+		let public = index_file(IndexFileType::Public, acl.clone());
+		assert_eq!(
+			AclEntry::from_index_file(file_id.clone(), "did:pkh".into(), &public),
+			None
+		);
+
+		let payable = index_file(IndexFileType::Payable, acl);
+		let entry = AclEntry::from_index_file(file_id, "did:pkh".into(), &payable).unwrap();
+		assert_eq!(entry.file_type, "payable");
+		assert_eq!(entry.chains, vec!["ethereum".to_string()]);
+		assert_eq!(entry.chain_ids, vec![80001]);
+		assert_eq!(entry.linked_models.len(), 1);
+		assert_eq!(entry.error, None);
+
+		let report = AclReport {
+			dapp_id: uuid::Uuid::nil(),
+			generated_at: Utc::now(),
+			entries: vec![entry],
+		};
+		let csv = report.render(AclReportFormat::Csv)?;
+		assert_eq!(csv.lines().count(), 2);
+		assert!(csv.lines().nth(1).unwrap().contains(",payable,"));
+		Ok(())
+	}
+
+	#[test]
+	fn test_csv_field() {
+		assert_eq!(csv_field("a,b"), "\"a,b\"");
+		assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+		assert_eq!(csv_field("plain"), "plain");
+	}
+}
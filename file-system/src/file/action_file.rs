@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use ceramic_core::StreamId;
 use chrono::{DateTime, Utc};
 use dataverse_core::store::dapp;
@@ -6,6 +8,8 @@ use serde::{Deserialize, Serialize};
 use crate::policy::Policy;
 
 use super::common::decode_base64;
+use super::model_names::ModelNames;
+use super::FileModel;
 
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -51,18 +55,26 @@ pub enum ActionType {
 	Receive,
 }
 
-struct ActionFileProcessor {}
+struct ActionFileProcessor {
+	names: Arc<ModelNames>,
+}
 
 #[async_trait::async_trait]
 impl Policy for ActionFileProcessor {
+	fn name(&self) -> &str {
+		FileModel::ActionFile.default_name()
+	}
+
 	async fn effect_at(
 		&self,
 		state: &dataverse_ceramic::stream::StreamState,
 	) -> anyhow::Result<bool> {
-		// check model_name is indexfile
+		// check model_name is actionFile
 		let model_id = state.must_model()?;
 		let model = dapp::get_model(&model_id).await?;
-		Ok(model.name == "indexFile")
+		self.names
+			.matches_in(&model.dapp_id, FileModel::ActionFile, &model.name)
+			.await
 	}
 }
 
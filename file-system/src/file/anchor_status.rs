@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use dataverse_ceramic::{AnchorStatus, Ceramic, StreamId, StreamLoader, StreamState};
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+
+use super::Client;
+
+/// Anchor status of a stream as shown on dashboards
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamAnchor {
+	pub anchor_status: AnchorStatus,
+	pub anchored_at: Option<DateTime<Utc>>,
+	/// the status was answered by the node instead of the stored log
+	pub refreshed: bool,
+}
+
+impl From<&StreamState> for StreamAnchor {
+	fn from(state: &StreamState) -> Self {
+		Self {
+			anchor_status: state.logged_anchor_status(),
+			anchored_at: state.anchored_at(),
+			refreshed: false,
+		}
+	}
+}
+
+/// Streams whose anchor status is loaded at once
+const ANCHOR_STATUS_CONCURRENCY: usize = 16;
+
+/// Anchor statuses of a batch of streams, a stream failing to load is
+/// reported apart instead of failing the batch
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnchorStatuses {
+	pub statuses: HashMap<String, StreamAnchor>,
+	/// error of each stream whose status could not be loaded
+	pub errors: HashMap<String, String>,
+}
+
+impl Client {
+	/// Anchor status of the streams keyed by stream id, answered from the
+	/// stored logs. Streams whose tip is not anchored yet are stale, with
+	/// `remote` they are asked to the node instead, keeping the stored
+	/// answer if the node fails.
+	pub async fn anchor_statuses(
+		&self,
+		dapp_id: &uuid::Uuid,
+		stream_ids: &[StreamId],
+		remote: Option<&dyn StreamLoader>,
+	) -> Result<AnchorStatuses> {
+		let ceramic = &self.registry.get_dapp_ceramic(dapp_id).await?;
+		let mut loaded = stream::iter(stream_ids)
+			.map(|stream_id| async move {
+				let anchor = self.anchor_status(ceramic, stream_id, remote).await;
+				(stream_id.to_string(), anchor)
+			})
+			.buffer_unordered(ANCHOR_STATUS_CONCURRENCY);
+
+		let mut statuses = AnchorStatuses::default();
+		while let Some((stream_id, anchor)) = loaded.next().await {
+			match anchor {
+				Ok(anchor) => {
+					statuses.statuses.insert(stream_id, anchor);
+				}
+				Err(err) => {
+					tracing::warn!(stream_id, "failed to load anchor status: {}", err);
+					statuses.errors.insert(stream_id, err.to_string());
+				}
+			}
+		}
+		Ok(statuses)
+	}
+
+	async fn anchor_status(
+		&self,
+		ceramic: &Ceramic,
+		stream_id: &StreamId,
+		remote: Option<&dyn StreamLoader>,
+	) -> Result<StreamAnchor> {
+		let state = self
+			.operator
+			.load_stream_state(ceramic, stream_id, None)
+			.await?;
+		let anchor = StreamAnchor::from(&state);
+		let remote = match (anchor.anchor_status, remote) {
+			(AnchorStatus::Pending, Some(remote)) => remote,
+			_ => return Ok(anchor),
+		};
+		match remote.load_stream_state(ceramic, stream_id, None).await {
+			Ok(state) => Ok(StreamAnchor {
+				anchor_status: state.anchor_status,
+				anchored_at: state.anchored_at().or(anchor.anchored_at),
+				refreshed: true,
+			}),
+			Err(err) => {
+				tracing::warn!(
+					stream_id = stream_id.to_string(),
+					"failed to refresh anchor status: {}",
+					err
+				);
+				Ok(anchor)
+			}
+		}
+	}
+}
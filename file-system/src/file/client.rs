@@ -1,31 +1,153 @@
-use std::{collections::HashMap, sync::Arc};
+use std::time::Duration;
+use std::{
+	collections::{HashMap, HashSet},
+	sync::Arc,
+};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use chrono::Utc;
-use dataverse_ceramic::event::{Event, EventValue, VerifyOption};
-use dataverse_ceramic::{StreamId, StreamState};
-use dataverse_core::store::dapp;
-use dataverse_core::stream::{Stream, StreamStore};
+use dataverse_ceramic::did::ensure_did_key_controllers;
+use dataverse_ceramic::event::metadata::controller_update_event;
+use dataverse_ceramic::event::errors::EventError;
+use dataverse_ceramic::event::{Event, EventValue, VerifyOption, DEFAULT_CLOCK_SKEW};
+use dataverse_ceramic::stream::registry::{is_invalid_instance, SharedSchemaRegistry};
+use dataverse_ceramic::{CommitDag, StatesPage, StreamId, StreamState};
+use dataverse_core::journal::{replay, CommitJournal, JournalEntry};
+use dataverse_core::lock::STREAM_LOCKS;
+use dataverse_core::notifier::{CommitNotification, Notifier};
+use dataverse_core::store::dapp::{DappRegistry, Model};
+use dataverse_core::stream::{Stream, StreamStore, PURGED, QUARANTINED};
 use int_enum::IntEnum;
 
+use crate::error::CommitRejection;
 use crate::file::errors::FileClientError;
 use crate::file::status::Status;
+use crate::policy::{validate_event, ModelFieldsPolicies, Policy};
 
+use super::computed::ComputedFields;
+use super::content_ref::ContentRef;
+use super::folder_delta::FolderChangeStore;
+use super::folder_stats::FolderStatsStore;
 use super::index_file::IndexFile;
 use super::index_folder::IndexFolder;
-use super::FileModel;
+use super::model_names::ModelNames;
+use super::name_filter::NameFilter;
+use super::revalidate::RevalidationReportStore;
+use super::{FileModel, SortBy};
 use super::{operator::StreamFileLoader, StreamFile};
 
 pub struct Client {
 	pub operator: Arc<dyn StreamFileLoader>,
 	pub stream_store: Arc<dyn StreamStore>,
+	pub notifier: Option<Arc<Notifier>>,
+	pub journal: Option<Arc<dyn CommitJournal>>,
+	pub folder_stats: Option<Arc<dyn FolderStatsStore>>,
+	pub folder_changes: Option<Arc<dyn FolderChangeStore>>,
+	pub revalidation_reports: Option<Arc<dyn RevalidationReportStore>>,
+	pub registry: Arc<dyn DappRegistry>,
+	/// names the dapps registered for the file models
+	pub model_names: Arc<ModelNames>,
+	/// fields computed for the files of a model when they are loaded
+	pub computed_fields: Arc<ComputedFields>,
+	/// policies every saved commit of a stream they take effect at must pass
+	pub policies: Vec<Arc<dyn Policy>>,
+	/// immutable and read only fields of the models of saved commits
+	pub model_fields: Arc<ModelFieldsPolicies>,
+	/// schemas saved commits must match, inferred from the model streams of
+	/// unknown models
+	pub schemas: Option<SharedSchemaRegistry>,
+	/// tolerance of the time checks of the cacao of saved commits
+	pub clock_skew: Duration,
 }
 
 impl Client {
-	pub fn new(operator: Arc<dyn StreamFileLoader>, stream_store: Arc<dyn StreamStore>) -> Self {
+	pub fn new(
+		operator: Arc<dyn StreamFileLoader>,
+		stream_store: Arc<dyn StreamStore>,
+		registry: Arc<dyn DappRegistry>,
+	) -> Self {
 		Self {
 			operator,
 			stream_store,
+			notifier: None,
+			journal: None,
+			folder_stats: None,
+			folder_changes: None,
+			revalidation_reports: None,
+			model_names: Arc::new(ModelNames::new(registry.clone())),
+			computed_fields: Arc::new(ComputedFields::with_defaults()),
+			registry,
+			policies: vec![],
+			model_fields: Default::default(),
+			schemas: None,
+			clock_skew: DEFAULT_CLOCK_SKEW,
+		}
+	}
+
+	pub fn with_notifier(self, notifier: Arc<Notifier>) -> Self {
+		Self {
+			notifier: Some(notifier),
+			..self
+		}
+	}
+
+	pub fn with_journal(self, journal: Arc<dyn CommitJournal>) -> Self {
+		Self {
+			journal: Some(journal),
+			..self
+		}
+	}
+
+	pub fn with_policy(mut self, policy: Arc<dyn Policy>) -> Self {
+		self.policies.push(policy);
+		self
+	}
+
+	pub fn with_clock_skew(self, clock_skew: Duration) -> Self {
+		Self { clock_skew, ..self }
+	}
+
+	pub fn with_schemas(self, schemas: SharedSchemaRegistry) -> Self {
+		Self {
+			schemas: Some(schemas),
+			..self
+		}
+	}
+
+	/// Ids of the streams the files are made of with the label, looked up
+	/// once for the whole page
+	async fn labeled_in(&self, label: &str, files: &[StreamFile]) -> Result<HashSet<String>> {
+		let stream_ids: Vec<StreamId> = files
+			.iter()
+			.flat_map(file_stream_ids)
+			.filter_map(|stream_id| stream_id.parse().ok())
+			.collect();
+		let labeled = self.stream_store.filter_labeled(label, &stream_ids).await?;
+		Ok(labeled.iter().map(ToString::to_string).collect())
+	}
+
+	/// Only the metadata of a quarantined stream is served
+	async fn redact_state(&self, stream_id: &StreamId, state: &mut StreamState) -> Result<()> {
+		if self.stream_store.is_quarantined(stream_id).await? {
+			state.content = serde_json::Value::Null;
+		}
+		Ok(())
+	}
+
+	/// Purged streams are neither stored nor served again, even if the
+	/// network still has them
+	async fn ensure_not_purged(&self, stream_id: &StreamId) -> Result<()> {
+		if self.stream_store.is_purged(stream_id).await? {
+			anyhow::bail!(FileClientError::StreamPurged(stream_id.clone()));
+		}
+		Ok(())
+	}
+
+	/// Upload commits accepted before a crash, to be called on startup
+	pub async fn replay_journal(&self) -> Result<usize> {
+		match &self.journal {
+			Some(journal) => replay(journal.as_ref(), self.operator.as_ref()).await,
+			None => Ok(0),
 		}
 	}
 }
@@ -35,8 +157,9 @@ impl Client {
 		&self,
 		app_id: &uuid::Uuid,
 		model: FileModel,
-	) -> anyhow::Result<dataverse_core::store::dapp::Model> {
-		dapp::get_model_by_name(app_id, &model.to_string()).await
+	) -> anyhow::Result<Model> {
+		let model_name = self.model_names.name_in(app_id, model).await?;
+		self.registry.get_model_by_name(app_id, &model_name).await
 	}
 
 	pub async fn load_stream_by_app_id(
@@ -44,11 +167,60 @@ impl Client {
 		app_id: &uuid::Uuid,
 		stream_id: &StreamId,
 	) -> anyhow::Result<StreamState> {
-		let ceramic = dapp::get_dapp_ceramic(app_id).await?;
+		self.ensure_not_purged(stream_id).await?;
+		let ceramic = self.registry.get_dapp_ceramic(app_id).await?;
 
-		self.operator
+		let mut state = self
+			.operator
 			.load_stream_state(&ceramic, stream_id, None)
-			.await
+			.await?;
+		self.redact_state(stream_id, &mut state).await?;
+		Ok(state)
+	}
+
+	/// Load the content a file loaded with `LoadFilesOption::LazyContent`
+	/// references, a quarantined content is redacted instead
+	pub async fn resolve_content(
+		&self,
+		app_id: &uuid::Uuid,
+		file: &mut StreamFile,
+	) -> anyhow::Result<()> {
+		let quarantined = self
+			.labeled_in(QUARANTINED, std::slice::from_ref(file))
+			.await?;
+		if redact_quarantined(file, &quarantined) {
+			return Ok(());
+		}
+		let ceramic = self.registry.get_dapp_ceramic(app_id).await?;
+		file.resolve_content(self.operator.as_ref(), &ceramic).await
+	}
+
+	/// Rotate the controller of a stored stream with a data commit signed by the
+	/// current `did:key` controller, the store's account follows the new controller.
+	/// Streams controlled by other did methods are rejected
+	pub async fn transfer_controller(
+		&self,
+		dapp_id: &uuid::Uuid,
+		stream_id: &StreamId,
+		pk: &str,
+		new_controller: &str,
+		opts: SaveOpts,
+	) -> Result<StreamState> {
+		let ceramic = self.registry.get_dapp_ceramic(dapp_id).await?;
+		let stream = self.stream_store.load_stream(stream_id).await?.context(
+			FileClientError::CommitStreamIdNotFoundOnStore(stream_id.clone()),
+		)?;
+		let events = self
+			.operator
+			.load_events(&ceramic, stream_id, Some(stream.tip))
+			.await?;
+		let genesis = events
+			.first()
+			.context(FileClientError::NoPrevCommitFound)?
+			.cid;
+		ensure_did_key_controllers(&stream.state(events).await?.controllers())?;
+		let event = controller_update_event(pk, genesis, stream.tip, new_controller)?;
+		self.save_event_with(dapp_id, stream_id, &event, opts).await
 	}
 
 	pub async fn load_streams_auto_model(
@@ -56,14 +228,69 @@ impl Client {
 		account: Option<String>,
 		model_id: &StreamId,
 	) -> anyhow::Result<Vec<StreamState>> {
-		let model = dapp::get_model(model_id).await?;
-		let ceramic = model.ceramic().await?;
-		self.operator
+		let model = self.registry.get_model(model_id).await?;
+		let ceramic = self.registry.get_dapp_ceramic(&model.dapp_id).await?;
+		let states = self
+			.operator
 			.load_stream_states(&ceramic, account, model_id)
-			.await
+			.await?;
+		let stream_ids = states
+			.iter()
+			.map(StreamState::stream_id)
+			.collect::<Result<Vec<_>>>()?;
+		let purged = self
+			.stream_store
+			.filter_labeled(PURGED, &stream_ids)
+			.await?;
+		let quarantined = self
+			.stream_store
+			.filter_labeled(QUARANTINED, &stream_ids)
+			.await?;
+		Ok(states
+			.into_iter()
+			.zip(stream_ids)
+			.filter(|(_, stream_id)| !purged.contains(stream_id))
+			.map(|(mut state, stream_id)| {
+				if quarantined.contains(&stream_id) {
+					state.content = serde_json::Value::Null;
+				}
+				state
+			})
+			.collect())
 	}
 }
 
+/// Streams a file is made of, the index file and its content
+fn file_stream_ids(file: &StreamFile) -> impl Iterator<Item = String> + '_ {
+	file.file_id
+		.iter()
+		.map(ToString::to_string)
+		.chain(file.content_id.clone())
+}
+
+fn file_in(file: &StreamFile, stream_ids: &HashSet<String>) -> bool {
+	file_stream_ids(file).any(|stream_id| stream_ids.contains(&stream_id))
+}
+
+/// Only the metadata of a file whose index file or content is quarantined
+/// is served, false if the file is left as is
+fn redact_quarantined(file: &mut StreamFile, quarantined: &HashSet<String>) -> bool {
+	let stream_id = file_stream_ids(file).find(|stream_id| quarantined.contains(stream_id));
+	let stream_id = match stream_id {
+		Some(stream_id) => stream_id,
+		None => return false,
+	};
+	file.file = None;
+	file.content = None;
+	file.content_ref = None;
+	file.computed = None;
+	file.write_status(
+		Status::Quarantined,
+		format!("stream {} is quarantined", stream_id),
+	);
+	true
+}
+
 #[async_trait::async_trait]
 pub trait StreamFileTrait {
 	async fn load_file(&self, dapp_id: &uuid::Uuid, stream_id: &StreamId) -> Result<StreamFile>;
@@ -80,24 +307,37 @@ pub trait StreamFileTrait {
 
 pub enum LoadFilesOption {
 	Signal(serde_json::Value),
+	FileName(NameFilter),
+	/// newest first
+	SortBy(SortBy),
+	/// reference the content streams of index files as `content_ref` instead
+	/// of loading them
+	LazyContent,
+	/// only files whose index file or content has the node-local label
+	Label(String),
+	/// page of the files in the order of `SortBy`, updatedAt if not set.
+	/// Paged by the store query unless another option filters the files
+	Page { offset: usize, limit: usize },
 	None,
 }
 
 #[async_trait::async_trait]
 impl StreamFileTrait for Client {
 	async fn load_file(&self, dapp_id: &uuid::Uuid, stream_id: &StreamId) -> Result<StreamFile> {
-		let ceramic = dapp::get_dapp_ceramic(dapp_id).await?;
+		self.ensure_not_purged(stream_id).await?;
+		let ceramic = self.registry.get_dapp_ceramic(dapp_id).await?;
 		let stream_state = self
 			.operator
 			.load_stream_state(&ceramic, stream_id, None)
 			.await?;
 		let model_id = &stream_state.must_model()?;
-		let model = dapp::get_model(model_id).await?;
+		let model = self.registry.get_model(model_id).await?;
 		if model.dapp_id != *dapp_id {
 			anyhow::bail!(FileClientError::StreamWithModelNotInDapp(stream_id.clone(), model_id.clone(), *dapp_id));
 		}
-		match model.name.as_str() {
-			"indexFile" => {
+		let file_model = self.model_names.resolve(dapp_id, &model.name).await?;
+		let file = match file_model {
+			Some(FileModel::IndexFile) => {
 				let index_file = serde_json::from_value::<IndexFile>(stream_state.content.clone())?;
 				let mut file = StreamFile::new_with_file(stream_state)?;
 				if let Ok(content_id) = &index_file.content_id.parse() {
@@ -109,9 +349,11 @@ impl StreamFileTrait for Client {
 				}
 				Ok(file)
 			}
-			"actionFile" => StreamFile::new_with_file(stream_state),
-			"indexFolder" | "contentFolder" => StreamFile::new_with_content(stream_state),
-			_ => {
+			Some(FileModel::ActionFile) => StreamFile::new_with_file(stream_state),
+			Some(FileModel::IndexFolder) | Some(FileModel::ContentFolder) => {
+				StreamFile::new_with_content(stream_state)
+			}
+			None => {
 				let mut file = StreamFile::new_with_content(stream_state)?;
 				let index_file_model_id = self
 					.get_file_model(dapp_id, FileModel::IndexFile)
@@ -144,7 +386,18 @@ impl StreamFileTrait for Client {
 				}
 				Ok(file)
 			}
-		}
+		};
+		let computed_model = file_model.map_or(model.name.clone(), |x| x.to_string());
+		let mut file = file?.with_computed_fields(&self.computed_fields, &computed_model);
+		file.forked = matches!(
+			self.stream_store.load_stream(stream_id).await,
+			Ok(Some(stream)) if stream.forked
+		);
+		let quarantined = self
+			.labeled_in(QUARANTINED, std::slice::from_ref(&file))
+			.await?;
+		redact_quarantined(&mut file, &quarantined);
+		Ok(file)
 	}
 
 	async fn load_stream(
@@ -152,10 +405,14 @@ impl StreamFileTrait for Client {
 		dapp_id: &uuid::Uuid,
 		stream_id: &StreamId,
 	) -> anyhow::Result<StreamState> {
-		let ceramic = dapp::get_dapp_ceramic(dapp_id).await?;
-		self.operator
+		self.ensure_not_purged(stream_id).await?;
+		let ceramic = self.registry.get_dapp_ceramic(dapp_id).await?;
+		let mut state = self
+			.operator
 			.load_stream_state(&ceramic, stream_id, None)
-			.await
+			.await?;
+		self.redact_state(stream_id, &mut state).await?;
+		Ok(state)
 	}
 
 	async fn load_files(
@@ -164,27 +421,76 @@ impl StreamFileTrait for Client {
 		model_id: &StreamId,
 		options: Vec<LoadFilesOption>,
 	) -> Result<Vec<StreamFile>> {
-		let model = dapp::get_model(model_id).await?;
+		let model = self.registry.get_model(model_id).await?;
 		let app_id = model.dapp_id;
-		let ceramic = model.ceramic().await?;
-
-		let stream_states = self
-			.operator
-			.load_stream_states(&ceramic, account.clone(), model_id)
-			.await?;
+		let ceramic = self.registry.get_dapp_ceramic(&model.dapp_id).await?;
+
+		let name_filter = options.iter().find_map(|option| match option {
+			LoadFilesOption::FileName(filter) => Some(filter.clone()),
+			_ => None,
+		});
+		let sort_by = options.iter().find_map(|option| match option {
+			LoadFilesOption::SortBy(sort_by) => Some(*sort_by),
+			_ => None,
+		});
+		let label = options.iter().find_map(|option| match option {
+			LoadFilesOption::Label(label) => Some(label),
+			_ => None,
+		});
+		let page = options.iter().find_map(|option| match option {
+			LoadFilesOption::Page { offset, limit } => Some(StatesPage {
+				sort_by: sort_by.unwrap_or(SortBy::UpdatedAt),
+				offset: *offset,
+				limit: *limit,
+			}),
+			_ => None,
+		});
+		// files dropped after the query would leave the page short
+		let filtered = name_filter.is_some()
+			|| label.is_some()
+			|| options
+				.iter()
+				.any(|option| matches!(option, LoadFilesOption::Signal(_)));
+		let store_page = page.filter(|_| !filtered);
+		let file_model = self.model_names.resolve(&app_id, &model.name).await?;
+		let stream_states = match (file_model, &name_filter, store_page) {
+			(Some(FileModel::IndexFile), Some(filter), _) => {
+				self.operator
+					.load_index_files_by_name(&ceramic, model_id, account.clone(), filter)
+					.await?
+			}
+			(_, _, Some(page)) => {
+				self.operator
+					.load_stream_states_page(&ceramic, account.clone(), model_id, page)
+					.await?
+			}
+			_ => {
+				self.operator
+					.load_stream_states(&ceramic, account.clone(), model_id)
+					.await?
+			}
+		};
 
-		match model.name.as_str() {
-			"indexFile" => {
+		let lazy_content = options
+			.iter()
+			.any(|option| matches!(option, LoadFilesOption::LazyContent));
+		let files: Result<Vec<StreamFile>> = match file_model {
+			Some(FileModel::IndexFile) => {
 				let mut files: Vec<StreamFile> = vec![];
 				for state in stream_states {
 					let index_file: IndexFile = serde_json::from_value(state.content.clone())?;
 					let mut file = StreamFile::new_with_file(state)?;
 					file.content_id = Some(index_file.content_id.clone());
 
-					if let Ok(stream_id) = &index_file.content_id.parse() {
+					if let Ok(stream_id) = index_file.content_id.parse() {
+						if lazy_content {
+							file.content_ref = Some(ContentRef::new(stream_id));
+							files.push(file);
+							continue;
+						}
 						let content_state = self
 							.operator
-							.load_stream_state(&ceramic, stream_id, None)
+							.load_stream_state(&ceramic, &stream_id, None)
 							.await?;
 						if let Err(err) = file.write_content(content_state) {
 							let desc = format!("failed load content file model {}", err);
@@ -196,11 +502,11 @@ impl StreamFileTrait for Client {
 
 				Ok(files)
 			}
-			"actionFile" => stream_states
+			Some(FileModel::ActionFile) => stream_states
 				.into_iter()
 				.map(StreamFile::new_with_file)
 				.collect(),
-			"indexFolder" => {
+			Some(FileModel::IndexFolder) => {
 				let files = stream_states
 					.into_iter()
 					.filter_map(|state| {
@@ -260,17 +566,52 @@ impl StreamFileTrait for Client {
 					.collect();
 				Ok(files)
 			}
-			"contentFolder" => stream_states
+			Some(FileModel::ContentFolder) => stream_states
 				.into_iter()
 				.map(StreamFile::new_with_content)
 				.collect(),
-			_ => {
+			None if store_page.is_some() => {
+				// index files of the page only, in the order of the query
+				let model_index_file = self.get_file_model(&app_id, FileModel::IndexFile).await?;
+				let mut files = vec![];
+				for state in stream_states {
+					let content_id = state.stream_id()?.to_string();
+					let mut file = StreamFile::new_with_content(state)?;
+					match self
+						.operator
+						.load_index_file_by_content_id(&ceramic, &model_index_file.id, &content_id)
+						.await
+					{
+						Ok((file_state, _)) => file.write_file(file_state)?,
+						Err(_) => {
+							let desc = format!("file_id is None, content_id: {}", content_id);
+							file.write_status(Status::NakedStream, desc);
+						}
+					}
+					files.push(file);
+				}
+				Ok(files)
+			}
+			None => {
 				let model_index_file = self.get_file_model(&app_id, FileModel::IndexFile).await?;
 
-				let file_query_edges = self
-					.operator
-					.load_stream_states(&ceramic, account, &model_index_file.id)
-					.await?;
+				let file_query_edges = match &name_filter {
+					Some(filter) => {
+						self.operator
+							.load_index_files_by_name(
+								&ceramic,
+								&model_index_file.id,
+								account,
+								filter,
+							)
+							.await?
+					}
+					None => {
+						self.operator
+							.load_stream_states(&ceramic, account, &model_index_file.id)
+							.await?
+					}
+				};
 
 				let mut file_map: HashMap<String, StreamFile> = HashMap::new();
 				for state in stream_states {
@@ -289,6 +630,9 @@ impl StreamFileTrait for Client {
 						}
 					}
 				}
+				if name_filter.is_some() {
+					file_map.retain(|_, file| file.file_id.is_some());
+				}
 
 				// set verified_status to -1 if file_id is None (illegal file)
 				let files = file_map.into_values().map(|mut file| {
@@ -304,7 +648,52 @@ impl StreamFileTrait for Client {
 
 				Ok(files)
 			}
+		};
+		let files = files?;
+		// labels of the streams of this page only, quarantined files are
+		// redacted as by `load_file`
+		let purged = self.labeled_in(PURGED, &files).await?;
+		let quarantined = self.labeled_in(QUARANTINED, &files).await?;
+		let labeled = match label {
+			Some(label) => Some(self.labeled_in(label, &files).await?),
+			None => None,
+		};
+		let computed_model = file_model.map_or(model.name.clone(), |x| x.to_string());
+		let mut files: Vec<StreamFile> = files
+			.into_iter()
+			.filter(|file| !file_in(file, &purged))
+			.filter(|file| labeled.as_ref().map_or(true, |ids| file_in(file, ids)))
+			.map(|file| {
+				let mut file = file.with_computed_fields(&self.computed_fields, &computed_model);
+				redact_quarantined(&mut file, &quarantined);
+				file
+			})
+			.collect();
+		if let Some(sort_by) = page.map(|page| page.sort_by).or(sort_by) {
+			files.sort_by_key(|file| std::cmp::Reverse(file.sort_time(sort_by)));
+		}
+		if let (Some(page), None) = (page, store_page) {
+			files = files
+				.into_iter()
+				.skip(page.offset)
+				.take(page.limit)
+				.collect();
 		}
+		Ok(files)
+	}
+}
+
+/// Options of a commit submission
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SaveOpts {
+	/// Run every check of the commit and return the state it would result in,
+	/// without storing or publishing it
+	pub dry_run: bool,
+}
+
+impl SaveOpts {
+	pub fn dry_run() -> Self {
+		Self { dry_run: true }
 	}
 }
 
@@ -316,6 +705,20 @@ pub trait StreamEventSaver {
 		stream_id: &StreamId,
 		event: &Event,
 	) -> Result<StreamState>;
+
+	/// Savers without a dry run only take the default options
+	async fn save_event_with(
+		&self,
+		dapp_id: &uuid::Uuid,
+		stream_id: &StreamId,
+		event: &Event,
+		opts: SaveOpts,
+	) -> Result<StreamState> {
+		if opts.dry_run {
+			anyhow::bail!(FileClientError::DryRunUnsupported);
+		}
+		self.save_event(dapp_id, stream_id, event).await
+	}
 }
 
 #[async_trait::async_trait]
@@ -326,10 +729,23 @@ impl StreamEventSaver for Client {
 		stream_id: &StreamId,
 		event: &Event,
 	) -> Result<StreamState> {
-		let ceramic = dapp::get_dapp_ceramic(dapp_id).await?;
+		self.save_event_with(dapp_id, stream_id, event, SaveOpts::default())
+			.await
+	}
+
+	async fn save_event_with(
+		&self,
+		dapp_id: &uuid::Uuid,
+		stream_id: &StreamId,
+		event: &Event,
+		opts: SaveOpts,
+	) -> Result<StreamState> {
+		self.ensure_not_purged(stream_id).await?;
+		let ceramic = self.registry.get_dapp_ceramic(dapp_id).await?;
+		let _guard = STREAM_LOCKS.lock(stream_id).await;
 		match &event.value {
 			EventValue::Signed(signed) => {
-				let (mut stream, mut commits) = {
+				let (mut stream, commits) = {
 					let stream = self.stream_store.load_stream(stream_id).await;
 					match stream.ok().flatten() {
 						Some(stream) => (
@@ -354,33 +770,141 @@ impl StreamEventSaver for Client {
 					return stream.state(commits).await;
 				}
 
+				// a prev before the tip starts another branch and marks the stream as
+				// forked, the conflict policy of the dapp picks the tip
+				let mut dag = CommitDag::new(commits)?;
 				if let Some(prev) = event.prev()? {
-					if commits.iter().all(|ele| ele.cid != prev) {
+					if !dag.contains(&prev) {
 						anyhow::bail!(FileClientError::NoPrevCommitFound);
 					}
 				}
-				commits.push(event.clone());
-				let state = stream.state(commits).await?;
+				dag.insert(event.clone())?;
+				let forked = dag.is_forked();
+				let tip = match forked {
+					true => {
+						let selector = self.registry.get_log_selector(dapp_id).await?;
+						let tip = dag.select(selector)?.unwrap_or(event.cid);
+						tracing::warn!(
+							stream_id = stream_id.to_string(),
+							cid = event.cid.to_string(),
+							tip = tip.to_string(),
+							?selector,
+							"commit forks the stream"
+						);
+						tip
+					}
+					false => event.cid,
+				};
+				let state = stream.state(dag.log(tip)?).await?;
 
 				let model = state.must_model()?;
-				let opts = vec![
+				let model_fields = self
+					.model_fields
+					.get(self.operator.as_ref(), &ceramic, &model)
+					.await?;
+				// state the event applies to, only rebuilt when it may differ from
+				// the state at the tip in more than the content
+				let rebuild = forked
+					|| signed.controllers_update()?.is_some()
+					|| !self.policies.is_empty()
+					|| model_fields.is_some();
+				let prev_state = match event.prev()? {
+					Some(prev) if rebuild => Some(stream.state(dag.log(prev)?).await?),
+					_ => None,
+				};
+				let prev_state = prev_state.as_ref().unwrap_or(&state);
+
+				let verify_opts = vec![
 					VerifyOption::ResourceModelsContain(model.clone()),
 					VerifyOption::ExpirationTimeBefore(Utc::now()),
+					VerifyOption::IssuerIn(prev_state.controllers()),
+					VerifyOption::ClockSkew(self.clock_skew),
 				];
-				event.verify_signature(opts)?;
+				event.verify_signature(verify_opts).map_err(|err| {
+					let code = match err.downcast_ref::<EventError>() {
+						Some(EventError::ControllerMismatch(..)) => "CONTROLLER_MISMATCH",
+						_ => "INVALID_SIGNATURE",
+					};
+					CommitRejection::new(code, err).with_field("jws")
+				})?;
+				for policy in &self.policies {
+					if policy.effect_at(&state).await? {
+						validate_event(policy.as_ref(), prev_state, event).await?;
+					}
+				}
+				if let Some(policy) = model_fields {
+					validate_event(policy.as_ref(), prev_state, event).await?;
+				}
+				if let Some(schemas) = &self.schemas {
+					let validated = schemas
+						.validate(self.operator.as_ref(), &ceramic, &state)
+						.await;
+					if let Err(err) = validated {
+						match is_invalid_instance(&err) {
+							true => anyhow::bail!(CommitRejection::new("SCHEMA_VIOLATION", err)),
+							false => return Err(err),
+						}
+					}
+				}
+				if let Some(limit) = self.registry.get_max_content_size(&model).await? {
+					let size = serde_json::to_vec(&state.content)?.len();
+					if size > limit {
+						anyhow::bail!(FileClientError::PayloadTooLarge(model, size, limit));
+					}
+				}
+				if opts.dry_run {
+					return Ok(state);
+				}
 
 				stream = Stream {
 					model: Some(model),
 					account: state.controllers().first().map(Clone::clone),
-					tip: event.cid,
+					tip,
 					content: state.content.clone(),
+					anchored_at: state.anchored_at().or(stream.anchored_at),
+					forked: stream.forked || forked,
 					..stream
 				};
 
-				self.stream_store.save_stream(&stream).await?;
-				self.operator
+				if let Some(journal) = &self.journal {
+					let entry = JournalEntry {
+						ceramic: ceramic.clone(),
+						stream_id: stream_id.clone(),
+						event: event.clone(),
+						accepted_at: Utc::now(),
+					};
+					journal.append(&entry).await?;
+				}
+				// the stream is only stored once the node has the commit, a
+				// failed upload leaves the store as it was
+				let uploaded = self
+					.operator
 					.upload_event(&ceramic, stream_id, event.clone())
-					.await?;
+					.await;
+				if let Err(err) = uploaded {
+					// the commit failed for the caller, it is not replayed
+					if let Some(journal) = &self.journal {
+						journal.complete(&event.cid.to_string()).await?;
+					}
+					return Err(err);
+				}
+				stream
+					.published
+					.record(&ceramic.endpoint, event.cid, &uploaded);
+				self.stream_store.save_stream(&stream).await?;
+				if let Some(journal) = &self.journal {
+					journal.complete(&event.cid.to_string()).await?;
+				}
+
+				if let Some(notifier) = &self.notifier {
+					notifier.notify(CommitNotification {
+						stream_id: stream_id.clone(),
+						tip: stream.tip,
+						model: stream.model.clone(),
+						forked,
+						quarantine: None,
+					});
+				}
 
 				Ok(state)
 			}
@@ -390,3 +914,25 @@ impl StreamEventSaver for Client {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn redact_quarantined_content() {
+		let content_id = "kjzl6kcym7w8y8syiams0kvm3qwfnutk2szi0wlhvf6rr9lalzpibxed0qvotuy";
+		let mut file = StreamFile {
+			content_id: Some(content_id.to_string()),
+			content: Some(serde_json::json!({ "text": "hello" })),
+			..Default::default()
+		};
+		assert!(!redact_quarantined(&mut file, &HashSet::new()));
+		assert!(file.content.is_some());
+
+		let quarantined = HashSet::from([content_id.to_string()]);
+		assert!(redact_quarantined(&mut file, &quarantined));
+		assert!(file.content.is_none());
+		assert_eq!(file.verified_status, Status::Quarantined);
+	}
+}
@@ -0,0 +1,162 @@
+use std::sync::{Arc, RwLock};
+
+use serde_json::{json, Map, Value};
+
+use super::{FileModel, IndexFile, StreamFile};
+
+pub type ComputeFn = Arc<dyn Fn(&StreamFile) -> Option<Value> + Send + Sync>;
+
+struct ComputedField {
+	model: String,
+	name: String,
+	compute: ComputeFn,
+}
+
+/// Fields computed for the files of a model at load time, held by the client
+#[derive(Default)]
+pub struct ComputedFields {
+	fields: RwLock<Vec<ComputedField>>,
+}
+
+impl ComputedFields {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// The builtin computed fields of indexFile
+	pub fn with_defaults() -> Self {
+		let fields = Self::new();
+		fields.register_defaults();
+		fields
+	}
+
+	/// Register a field computed for every file of the model (by model name)
+	/// at load time, replacing a previous registration with the same name
+	pub fn register(&self, model: &str, name: &str, compute: ComputeFn) {
+		let mut fields = self.fields.write().expect("computed fields poisoned");
+		fields.retain(|field| !(field.model == model && field.name == name));
+		fields.push(ComputedField {
+			model: model.to_string(),
+			name: name.to_string(),
+			compute,
+		});
+	}
+
+	fn register_defaults(&self) {
+		self.register(
+			FileModel::IndexFile.default_name(),
+			"contentType",
+			Arc::new(|file| {
+				let content_type = index_file(file)?.content_type().ok()?;
+				serde_json::to_value(content_type).ok()
+			}),
+		);
+		self.register(
+			FileModel::IndexFile.default_name(),
+			"accessControl",
+			Arc::new(|file| {
+				let acl = match index_file(file)?.access_control().ok()? {
+					Some(acl) => acl,
+					None => return Some(json!({ "encrypted": false, "monetized": false })),
+				};
+				let linked_models = acl
+					.encryption_provider
+					.as_ref()
+					.and_then(|provider| provider.linked_ceramic_models().ok())
+					.unwrap_or_default();
+				Some(json!({
+					"encrypted": acl.encryption_provider.is_some(),
+					"monetized": acl
+						.monetization_provider
+						.as_ref()
+						.map_or(false, |provider| provider.data_asset.is_some()),
+					"linkedModels": linked_models
+						.iter()
+						.map(ToString::to_string)
+						.collect::<Vec<_>>(),
+				}))
+			}),
+		);
+		self.register(
+			FileModel::IndexFile.default_name(),
+			"size",
+			Arc::new(|file| {
+				let size = file.content.as_ref()?.get("size")?.as_u64()?;
+				Some(Value::String(human_readable_size(size)))
+			}),
+		);
+	}
+}
+
+fn index_file(file: &StreamFile) -> Option<IndexFile> {
+	serde_json::from_value(file.file.clone()?).ok()
+}
+
+pub fn human_readable_size(size: u64) -> String {
+	const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+	let mut value = size as f64;
+	let mut unit = 0;
+	while value >= 1024.0 && unit < UNITS.len() - 1 {
+		value /= 1024.0;
+		unit += 1;
+	}
+	match unit {
+		0 => format!("{} {}", size, UNITS[0]),
+		_ => format!("{:.1} {}", value, UNITS[unit]),
+	}
+}
+
+impl StreamFile {
+	pub fn with_computed_fields(mut self, fields: &ComputedFields, model: &str) -> Self {
+		let fields = fields.fields.read().expect("computed fields poisoned");
+		let mut computed = Map::new();
+		for field in fields.iter().filter(|field| field.model == model) {
+			if let Some(value) = (field.compute)(&self) {
+				computed.insert(field.name.clone(), value);
+			}
+		}
+		if !computed.is_empty() {
+			self.computed = Some(computed);
+		}
+		self
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn compute_fields() {
+		let fields = ComputedFields::new();
+		fields.register(
+			"testModel",
+			"title",
+			Arc::new(|file| {
+				let name = file.content.as_ref()?.get("name")?.as_str()?;
+				Some(Value::String(name.to_uppercase()))
+			}),
+		);
+		let file = StreamFile {
+			content: Some(json!({ "name": "hello" })),
+			..Default::default()
+		};
+		let file = file.with_computed_fields(&fields, "testModel");
+		assert_eq!(
+			file.computed,
+			Some(Map::from_iter([("title".into(), json!("HELLO"))]))
+		);
+
+		let file = StreamFile::default().with_computed_fields(&fields, "otherModel");
+		assert!(file.computed.is_none());
+		let file = StreamFile::default().with_computed_fields(&ComputedFields::new(), "testModel");
+		assert!(file.computed.is_none());
+	}
+
+	#[test]
+	fn readable_size() {
+		assert_eq!(human_readable_size(512), "512 B");
+		assert_eq!(human_readable_size(2048), "2.0 KB");
+		assert_eq!(human_readable_size(5 * 1024 * 1024 + 1024 * 512), "5.5 MB");
+	}
+}
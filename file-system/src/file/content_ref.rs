@@ -0,0 +1,53 @@
+use ceramic_core::{Cid, StreamId};
+use dataverse_ceramic::{Ceramic, StreamLoader, StreamState};
+use serde::{Deserialize, Serialize};
+
+/// Content stream of a file left unloaded, resolved through a loader once the
+/// content is needed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ContentRef {
+	#[cfg_attr(feature = "openapi", schema(value_type = String))]
+	pub stream_id: StreamId,
+	/// the latest tip if unset
+	#[serde(skip_serializing_if = "Option::is_none")]
+	#[cfg_attr(feature = "openapi", schema(value_type = Option<String>))]
+	pub tip: Option<Cid>,
+}
+
+impl ContentRef {
+	pub fn new(stream_id: StreamId) -> Self {
+		Self {
+			stream_id,
+			tip: None,
+		}
+	}
+
+	pub async fn resolve<L: StreamLoader + ?Sized>(
+		&self,
+		loader: &L,
+		ceramic: &Ceramic,
+	) -> anyhow::Result<StreamState> {
+		loader
+			.load_stream_state(ceramic, &self.stream_id, self.tip)
+			.await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_content_ref() -> anyhow::Result<()> {
+		let stream_id: StreamId =
+			"kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju".parse()?;
+		let content_ref = ContentRef::new(stream_id.clone());
+		assert_eq!(
+			serde_json::to_value(&content_ref)?,
+			serde_json::json!({ "streamId": stream_id.to_string() })
+		);
+		Ok(())
+	}
+}
@@ -0,0 +1,187 @@
+use std::collections::HashSet;
+
+use ceramic_core::StreamId;
+use dataverse_core::store::dapp::Lookup;
+use serde::{Deserialize, Serialize};
+
+use super::name_filter::NameFilter;
+use super::{Client, LoadFilesOption, SortBy, StreamFile, StreamFileTrait};
+
+pub const DEFAULT_PAGE_LIMIT: usize = 50;
+
+/// Filter and page of a query across all models of a dapp
+#[derive(Debug, Clone)]
+pub struct DappQuery {
+	pub file_name: Option<NameFilter>,
+	/// only files with the node-local label
+	pub label: Option<String>,
+	/// newest first, updatedAt if not set
+	pub sort_by: Option<SortBy>,
+	pub offset: usize,
+	pub limit: usize,
+}
+
+impl Default for DappQuery {
+	fn default() -> Self {
+		Self {
+			file_name: None,
+			label: None,
+			sort_by: None,
+			offset: 0,
+			limit: DEFAULT_PAGE_LIMIT,
+		}
+	}
+}
+
+/// File loaded by a dapp query with the model it was queried from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DappFile {
+	#[cfg_attr(feature = "openapi", schema(value_type = String))]
+	pub source_model_id: StreamId,
+	pub source_model_name: String,
+	#[serde(flatten)]
+	pub file: StreamFile,
+}
+
+impl DappFile {
+	/// index files are also loaded through the models of their contents
+	fn key(&self) -> String {
+		match (&self.file.file_id, &self.file.content_id) {
+			(Some(file_id), _) => file_id.to_string(),
+			(None, Some(content_id)) => content_id.clone(),
+			(None, None) => self.source_model_id.to_string(),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DappFilesPage {
+	pub files: Vec<DappFile>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub next_offset: Option<usize>,
+}
+
+/// Deduplicate, order and page files merged from several models, each model
+/// giving at most its first `offset + limit + 1` files
+fn paginate(files: Vec<DappFile>, query: &DappQuery) -> DappFilesPage {
+	let mut seen = HashSet::new();
+	let mut files: Vec<DappFile> = files
+		.into_iter()
+		.filter(|file| seen.insert(file.key()))
+		.collect();
+
+	let sort_by = query.sort_by.unwrap_or(SortBy::UpdatedAt);
+	files.sort_by(|a, b| {
+		b.file
+			.sort_time(sort_by)
+			.cmp(&a.file.sort_time(sort_by))
+			.then_with(|| a.key().cmp(&b.key()))
+	});
+
+	let end = query.offset.saturating_add(query.limit);
+	let next_offset = (end < files.len()).then_some(end);
+	let files = files
+		.into_iter()
+		.skip(query.offset)
+		.take(query.limit)
+		.collect();
+	DappFilesPage { files, next_offset }
+}
+
+impl Client {
+	/// Files of all latest models registered for the dapp, merged into one
+	/// ordered list and paged. Each model is paged by its store query up to
+	/// the end of the requested page.
+	pub async fn query_dapp(
+		&self,
+		dapp_id: &uuid::Uuid,
+		account: Option<String>,
+		query: DappQuery,
+	) -> anyhow::Result<DappFilesPage> {
+		let models = self.registry.get_models(dapp_id, Lookup::Fetch).await?;
+		let mut files = vec![];
+		for model in models.into_iter().filter(|model| model.latest) {
+			let mut options = vec![
+				LoadFilesOption::SortBy(query.sort_by.unwrap_or(SortBy::UpdatedAt)),
+				LoadFilesOption::Page {
+					offset: 0,
+					limit: query.offset.saturating_add(query.limit).saturating_add(1),
+				},
+			];
+			if let Some(filter) = &query.file_name {
+				options.push(LoadFilesOption::FileName(filter.clone()));
+			}
+			if let Some(label) = &query.label {
+				options.push(LoadFilesOption::Label(label.clone()));
+			}
+			let loaded = match self.load_files(account.clone(), &model.id, options).await {
+				Ok(loaded) => loaded,
+				Err(err) => {
+					tracing::warn!(
+						dapp_id = dapp_id.to_string(),
+						model_id = model.id.to_string(),
+						"failed to load files of model: {}",
+						err
+					);
+					continue;
+				}
+			};
+			files.extend(loaded.into_iter().map(|file| DappFile {
+				source_model_id: model.id.clone(),
+				source_model_name: model.name.clone(),
+				file,
+			}));
+		}
+		Ok(paginate(files, &query))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn dapp_file(file_id: &str, updated_at: &str) -> DappFile {
+		DappFile {
+			source_model_id: "kjzl6kcym7w8y8syiams0kvm3qwfnutk2szi0wlhvf6rr9lalzpibxed0qvotuy"
+				.parse()
+				.unwrap(),
+			source_model_name: "post".to_string(),
+			file: StreamFile {
+				content_id: Some(file_id.to_string()),
+				content: Some(serde_json::json!({ "updatedAt": updated_at })),
+				..Default::default()
+			},
+		}
+	}
+
+	#[test]
+	fn test_paginate() {
+		let files = vec![
+			dapp_file("a", "2024-01-01T00:00:00Z"),
+			dapp_file("b", "2024-01-03T00:00:00Z"),
+			dapp_file("a", "2024-01-01T00:00:00Z"),
+			dapp_file("c", "2024-01-02T00:00:00Z"),
+		];
+		let query = DappQuery {
+			limit: 2,
+			..Default::default()
+		};
+		let page = paginate(files.clone(), &query);
+		assert_eq!(page.next_offset, Some(2));
+		let keys: Vec<String> = page.files.iter().map(DappFile::key).collect();
+		assert_eq!(keys, vec!["b", "c"]);
+
+		let query = DappQuery {
+			offset: 2,
+			limit: 2,
+			..Default::default()
+		};
+		let page = paginate(files, &query);
+		assert_eq!(page.next_offset, None);
+		assert_eq!(page.files[0].key(), "a");
+	}
+}
@@ -26,7 +26,14 @@ pub enum FileClientError {
 	StreamWithModelNotInDapp(StreamId,StreamId,Uuid),
 	AnchorCommitUnsupported,
 	NoPrevCommitFound,
-	CommitStreamIdNotFoundOnStore(StreamId)
+	CommitStreamIdNotFoundOnStore(StreamId),
+	/// content of an instance of the model over its limit, size and limit in bytes
+	PayloadTooLarge(StreamId, usize, usize),
+	FolderChangesUnavailable,
+	StreamNotQuarantined(StreamId),
+	StreamPurged(StreamId),
+	ModelNotSingle(StreamId),
+	DryRunUnsupported,
 }
 
 impl std::fmt::Display for FileClientError {
@@ -35,6 +42,14 @@ impl std::fmt::Display for FileClientError {
 			Self::AnchorCommitUnsupported => write!(f, "anchor commit not supported"),
 			Self::NoPrevCommitFound => write!(f,"donot have previous commit"),
 			Self::CommitStreamIdNotFoundOnStore(stream_id) => write!(f, "publishing commit with stream_id {} not found in store", stream_id),
+			Self::FolderChangesUnavailable => write!(f, "no change log of folders configured"),
+			Self::StreamNotQuarantined(stream_id) => {
+				write!(f, "stream {} is not quarantined", stream_id)
+			}
+			Self::StreamPurged(stream_id) => write!(f, "stream {} is purged from the node", stream_id),
+			Self::ModelNotSingle(model_id) => write!(f, "model {} has no single account relation", model_id),
+			Self::DryRunUnsupported => write!(f, "dry run of commits not supported"),
+			Self::PayloadTooLarge(model_id, size, limit) => write!(f, "content of {} bytes exceeds the limit of {} bytes of model {}", size, limit, model_id),
 			Self::StreamWithModelNotInDapp(stream_id, model_id, dapp_id) => write!(f,"stream_id {} with model_id {} not belong to dapp {}", stream_id, model_id, dapp_id),
 		}
 	}
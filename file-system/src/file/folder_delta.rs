@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use dataverse_ceramic::StreamId;
+use serde::{Deserialize, Serialize};
+
+use super::errors::FileClientError;
+use super::Client;
+
+/// Write of a stream affecting an index folder, the folder itself, one of its
+/// content folders or a file they mirror
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FolderChange {
+	/// position in the change log, increasing across all folders
+	pub seq: i64,
+	pub stream_id: String,
+	pub tip: String,
+	pub changed_at: DateTime<Utc>,
+	/// the stream left the folder, e.g. a file its content folder no longer
+	/// mirrors, `tip` is the one of the write removing it
+	#[serde(default)]
+	pub removed: bool,
+}
+
+/// Streams of a folder written after `since_seq`, each with its latest write
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FolderDelta {
+	pub folder_id: String,
+	pub since_seq: i64,
+	/// sequence to sync from next time, `since_seq` if nothing changed
+	pub latest_seq: i64,
+	pub changes: Vec<FolderChange>,
+}
+
+impl FolderDelta {
+	/// Keep the latest change of every stream, ordered by sequence
+	pub fn new(folder_id: &StreamId, since_seq: i64, changes: Vec<FolderChange>) -> Self {
+		let mut latest: HashMap<String, FolderChange> = HashMap::new();
+		for change in changes.into_iter().filter(|x| x.seq > since_seq) {
+			match latest.get(&change.stream_id) {
+				Some(prev) if prev.seq >= change.seq => {}
+				_ => {
+					latest.insert(change.stream_id.clone(), change);
+				}
+			}
+		}
+		let mut changes: Vec<FolderChange> = latest.into_values().collect();
+		changes.sort_by_key(|x| x.seq);
+		Self {
+			folder_id: folder_id.to_string(),
+			since_seq,
+			latest_seq: changes.last().map_or(since_seq, |x| x.seq),
+			changes,
+		}
+	}
+}
+
+/// Change log of the folders, appended by the store whenever folders or files
+/// are written
+#[async_trait::async_trait]
+pub trait FolderChangeStore: Send + Sync {
+	async fn folder_changes(
+		&self,
+		folder_id: &StreamId,
+		since_seq: i64,
+	) -> Result<Vec<FolderChange>>;
+}
+
+impl Client {
+	pub fn with_folder_changes(self, folder_changes: Arc<dyn FolderChangeStore>) -> Self {
+		Self {
+			folder_changes: Some(folder_changes),
+			..self
+		}
+	}
+
+	/// What changed in the folder since the last sync of a client, so it
+	/// reloads only those streams instead of the whole tree
+	pub async fn folder_delta(&self, folder_id: &StreamId, since_seq: i64) -> Result<FolderDelta> {
+		let store = match &self.folder_changes {
+			Some(store) => store,
+			None => anyhow::bail!(FileClientError::FolderChangesUnavailable),
+		};
+		let changes = store.folder_changes(folder_id, since_seq).await?;
+		Ok(FolderDelta::new(folder_id, since_seq, changes))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn change(seq: i64, stream_id: &str, tip: &str) -> FolderChange {
+		FolderChange {
+			seq,
+			stream_id: stream_id.to_string(),
+			tip: tip.to_string(),
+			changed_at: Utc::now(),
+			removed: false,
+		}
+	}
+
+	#[test]
+	fn test_folder_delta() -> anyhow::Result<()> {
+		let folder_id: StreamId =
+			"kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju".parse()?;
+		let changes = vec![
+			change(3, "file", "a"),
+			change(5, "folder", "b"),
+			change(7, "file", "c"),
+			change(2, "old", "d"),
+		];
+		let delta = FolderDelta::new(&folder_id, 2, changes);
+		assert_eq!(delta.latest_seq, 7);
+		let tips: Vec<&str> = delta.changes.iter().map(|x| x.tip.as_str()).collect();
+		assert_eq!(tips, vec!["b", "c"]);
+
+		// a file removed after its last write is reported as removed
+		let removal = FolderChange {
+			removed: true,
+			..change(9, "file", "e")
+		};
+		let delta = FolderDelta::new(&folder_id, 2, vec![change(7, "file", "c"), removal]);
+		assert_eq!(delta.changes.len(), 1);
+		assert!(delta.changes[0].removed);
+
+		let empty = FolderDelta::new(&folder_id, 7, vec![]);
+		assert_eq!(empty.latest_seq, 7);
+		assert!(empty.changes.is_empty());
+		Ok(())
+	}
+}
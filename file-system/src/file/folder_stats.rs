@@ -0,0 +1,111 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use dataverse_ceramic::{Ceramic, StreamId};
+use serde::{Deserialize, Serialize};
+
+use super::content_folder::ContentFolder;
+use super::index_folder::IndexFolder;
+use super::{Client, StreamFileLoader};
+
+/// Item counts of an index folder
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FolderStats {
+	pub folder_id: String,
+	/// size of contentFolderIds
+	pub content_folder_count: i64,
+	/// files mirrored by the content folders and not deleted
+	pub file_count: i64,
+	pub updated_at: DateTime<Utc>,
+}
+
+/// Counters maintained by the store whenever folders or files are written
+#[async_trait::async_trait]
+pub trait FolderStatsStore: Send + Sync {
+	async fn folder_stats(&self, folder_id: &StreamId) -> Result<Option<FolderStats>>;
+}
+
+impl Client {
+	pub fn with_folder_stats(self, folder_stats: Arc<dyn FolderStatsStore>) -> Self {
+		Self {
+			folder_stats: Some(folder_stats),
+			..self
+		}
+	}
+
+	/// Counters of the folder, computed from the streams if the store does not
+	/// maintain them
+	pub async fn folder_stats(
+		&self,
+		dapp_id: &uuid::Uuid,
+		folder_id: &StreamId,
+	) -> Result<FolderStats> {
+		if let Some(store) = &self.folder_stats {
+			if let Some(stats) = store.folder_stats(folder_id).await? {
+				return Ok(stats);
+			}
+		}
+		let ceramic = self.registry.get_dapp_ceramic(dapp_id).await?;
+		compute_folder_stats(self.operator.as_ref(), &ceramic, folder_id).await
+	}
+}
+
+/// index files are soft deleted by setting `deleted`
+fn is_live_file(content: &serde_json::Value) -> bool {
+	!content
+		.get("deleted")
+		.and_then(serde_json::Value::as_bool)
+		.unwrap_or(false)
+}
+
+async fn compute_folder_stats(
+	operator: &dyn StreamFileLoader,
+	ceramic: &Ceramic,
+	folder_id: &StreamId,
+) -> Result<FolderStats> {
+	let state = operator.load_stream_state(ceramic, folder_id, None).await?;
+	let index_folder: IndexFolder = serde_json::from_value(state.content)?;
+
+	let mut file_ids = vec![];
+	for content_folder_id in &index_folder.content_folder_ids {
+		let state = operator
+			.load_stream_state(ceramic, &content_folder_id.parse()?, None)
+			.await?;
+		let content_folder: ContentFolder = serde_json::from_value(state.content)?;
+		file_ids.extend(content_folder.mirror_file_ids);
+	}
+	file_ids.sort();
+	file_ids.dedup();
+
+	let mut file_count = 0;
+	for file_id in file_ids {
+		let state = operator
+			.load_stream_state(ceramic, &file_id.parse()?, None)
+			.await?;
+		if is_live_file(&state.content) {
+			file_count += 1;
+		}
+	}
+
+	Ok(FolderStats {
+		folder_id: folder_id.to_string(),
+		content_folder_count: index_folder.content_folder_ids.len() as i64,
+		file_count,
+		updated_at: Utc::now(),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_is_live_file() {
+		assert!(is_live_file(&serde_json::json!({ "fileName": "a" })));
+		assert!(is_live_file(&serde_json::json!({ "deleted": false })));
+		assert!(!is_live_file(&serde_json::json!({ "deleted": true })));
+	}
+}
@@ -1,4 +1,5 @@
 use std::str::FromStr;
+use std::sync::Arc;
 
 use anyhow::Result;
 use async_std::task;
@@ -10,16 +11,20 @@ use int_enum::IntEnum;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::error::CommitRejection;
 use crate::{file::errors::IndexFileError, policy::Policy};
 
 use super::{
 	access_control::AccessControl,
 	common::decode_base64,
 	content_type::{ContentType, ContentTypeResourceType},
+	model_names::ModelNames,
+	FileModel,
 };
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct IndexFile {
 	/// file name, encrypted when payable type
 	pub file_name: String,
@@ -61,6 +66,7 @@ pub enum IndexFileType {
 
 struct IndexFileProcessor {
 	pub state: ModelState,
+	names: Arc<ModelNames>,
 }
 
 struct ModelState {
@@ -69,11 +75,17 @@ struct ModelState {
 
 #[async_trait::async_trait]
 impl Policy for IndexFileProcessor {
+	fn name(&self) -> &str {
+		FileModel::IndexFile.default_name()
+	}
+
 	async fn effect_at(&self, state: &ceramic::StreamState) -> Result<bool> {
 		// check model_name is indexfile
 		let model_id = state.must_model()?;
 		let model = dapp::get_model(&model_id).await?;
-		Ok(model.name == "indexFile")
+		self.names
+			.matches_in(&model.dapp_id, FileModel::IndexFile, &model.name)
+			.await
 	}
 
 	async fn validate_data(
@@ -123,7 +135,12 @@ impl IndexFileProcessor {
 	) -> anyhow::Result<()> {
 		let index_file: IndexFile = serde_json::from_value(data.clone())?;
 		if index_file.file_type == IndexFileType::Payable as u64 {
-			anyhow::bail!(IndexFileError::FileTypeUnchangeable);
+			anyhow::bail!(CommitRejection::new(
+				"FILE_TYPE_UNCHANGEABLE",
+				IndexFileError::FileTypeUnchangeable
+			)
+			.with_field("fileType")
+			.with_policy(FileModel::IndexFile.default_name()));
 		}
 		Ok(())
 	}
@@ -171,11 +188,16 @@ impl IndexFileProcessor {
 
 	pub async fn validate_acl(&self, acl: &AccessControl) -> Result<()> {
 		if let Some(p) = &acl.encryption_provider {
-			let linked_ceramic_models = p.linked_ceramic_models()?;
-			for ele in linked_ceramic_models {
+			let linked_ceramic_models = p.indexed_linked_ceramic_models()?;
+			for (idx, ele) in linked_ceramic_models {
 				let model = dapp::get_model(&ele).await?;
 				if model.dapp_id != self.state.dapp_id {
-					anyhow::bail!(IndexFileError::LinkedModelNotInApp);
+					anyhow::bail!(CommitRejection::new(
+						"LINKED_MODEL_NOT_IN_APP",
+						format!("{}: {}", IndexFileError::LinkedModelNotInApp, ele),
+					)
+					.with_field(format!("accessControl.decryptionConditions[{}]", idx))
+					.with_policy(FileModel::IndexFile.default_name()));
 				}
 			}
 		}
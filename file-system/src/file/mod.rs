@@ -1,9 +1,21 @@
+pub mod anchor_status;
 pub mod client;
 pub mod common;
+pub mod computed;
+pub mod content_ref;
+pub mod dapp_query;
+pub mod folder_delta;
+pub mod folder_stats;
+pub mod model_names;
+pub mod name_filter;
 pub mod operator;
+pub mod quarantine;
+pub mod revalidate;
+pub mod singleton;
 pub mod status;
 
 pub mod access_control;
+pub mod acl_report;
 pub mod action_file;
 pub mod content_folder;
 pub mod content_type;
@@ -17,8 +29,10 @@ pub use index_file::*;
 use std::fmt::Display;
 
 use anyhow::Context;
+use chrono::{DateTime, Utc};
 pub use client::*;
-use dataverse_ceramic::StreamState;
+use dataverse_ceramic::{Ceramic, StreamLoader, StreamState};
+pub use dataverse_ceramic::SortBy;
 pub use operator::*;
 
 use ceramic_core::StreamId;
@@ -26,30 +40,50 @@ use errors::StreamFileError;
 use serde::{Deserialize, Serialize};
 use serde_json::value::Value;
 
+use self::content_ref::ContentRef;
 use self::status::Status;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[derive(Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct StreamFile {
 	#[serde(skip_serializing_if = "Option::is_none")]
+	#[cfg_attr(feature = "openapi", schema(value_type = Option<String>))]
 	pub file_id: Option<StreamId>,
 	#[serde(skip_serializing_if = "Option::is_none")]
+	#[cfg_attr(feature = "openapi", schema(value_type = Option<String>))]
 	pub file_model_id: Option<StreamId>,
 	#[serde(skip_serializing_if = "Option::is_none")]
+	#[cfg_attr(feature = "openapi", schema(value_type = Option<Object>))]
 	pub file: Option<Value>,
 
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub content_id: Option<String>,
 	#[serde(skip_serializing_if = "Option::is_none")]
+	#[cfg_attr(feature = "openapi", schema(value_type = Option<String>))]
 	pub model_id: Option<StreamId>,
 	#[serde(skip_serializing_if = "Option::is_none")]
+	#[cfg_attr(feature = "openapi", schema(value_type = Option<Object>))]
 	pub content: Option<Value>,
+	/// Content stream not loaded yet, set instead of `content`
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub content_ref: Option<ContentRef>,
 
 	pub controller: String,
+	#[cfg_attr(feature = "openapi", schema(value_type = i32))]
 	pub verified_status: Status,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub verified_status_desc: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	#[cfg_attr(feature = "openapi", schema(value_type = Option<Object>))]
+	pub computed: Option<serde_json::Map<String, Value>>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub anchored_at: Option<DateTime<Utc>>,
+	/// Another branch of the stream was seen, the file shows the branch of
+	/// the stored tip
+	#[serde(default, skip_serializing_if = "std::ops::Not::not")]
+	pub forked: bool,
 }
 
 
@@ -65,6 +99,7 @@ impl StreamFile {
 		self.file = Some(state.content.clone());
 		self.file_id = Some(state.stream_id()?);
 		self.file_model_id = Some(state.must_model()?);
+		self.anchored_at = state.anchored_at().or(self.anchored_at);
 		self.controller = state
 			.controllers()
 			.first()
@@ -83,6 +118,7 @@ impl StreamFile {
 		self.content = Some(state.content.clone());
 		self.content_id = Some(state.stream_id()?.to_string());
 		self.model_id = Some(state.must_model()?);
+		self.anchored_at = self.anchored_at.or(state.anchored_at());
 		self.controller = state
 			.controllers()
 			.first()
@@ -91,14 +127,46 @@ impl StreamFile {
 		Ok(())
 	}
 
+	/// Load the referenced content stream into the file, a file without
+	/// reference is left as is
+	pub async fn resolve_content<L: StreamLoader + ?Sized>(
+		&mut self,
+		loader: &L,
+		ceramic: &Ceramic,
+	) -> anyhow::Result<()> {
+		if let Some(content_ref) = &self.content_ref {
+			let state = content_ref.resolve(loader, ceramic).await?;
+			self.write_content(state)?;
+			self.content_ref = None;
+		}
+		Ok(())
+	}
+
 	pub fn write_status(&mut self, status: Status, desc: String) {
 		self.verified_status = status;
 		self.verified_status_desc = Some(format!("{:?}: {}", status, desc));
 	}
+
+	pub fn updated_at(&self) -> Option<DateTime<Utc>> {
+		[&self.file, &self.content]
+			.into_iter()
+			.flatten()
+			.find_map(|value| value.get("updatedAt")?.as_str()?.parse().ok())
+	}
+
+	/// Time used to order files, anchored time falls back to updatedAt for
+	/// files not anchored yet
+	pub fn sort_time(&self, sort_by: SortBy) -> Option<DateTime<Utc>> {
+		match sort_by {
+			SortBy::AnchoredAt => self.anchored_at.or_else(|| self.updated_at()),
+			SortBy::UpdatedAt => self.updated_at(),
+		}
+	}
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub enum FileModel {
 	IndexFile,
 	ActionFile,
@@ -108,13 +176,7 @@ pub enum FileModel {
 
 impl Display for FileModel {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-		let str = match self {
-			FileModel::IndexFile => "indexFile",
-			FileModel::ActionFile => "actionFile",
-			FileModel::IndexFolder => "indexFolder",
-			FileModel::ContentFolder => "contentFolder",
-		};
-		write!(f, "{}", str)
+		write!(f, "{}", self.default_name())
 	}
 }
 
@@ -127,4 +189,19 @@ mod tests {
 		assert_eq!(FileModel::IndexFile.to_string(), "indexFile".to_string());
 		Ok(())
 	}
+
+	#[test]
+	fn sort_time() {
+		let updated_at: DateTime<Utc> = "2023-04-04T07:25:14.877Z".parse().unwrap();
+		let mut file = StreamFile {
+			file: Some(serde_json::json!({ "updatedAt": "2023-04-04T07:25:14.877Z" })),
+			..Default::default()
+		};
+		assert_eq!(file.sort_time(SortBy::AnchoredAt), Some(updated_at));
+
+		let anchored_at: DateTime<Utc> = "2023-04-05T00:00:00Z".parse().unwrap();
+		file.anchored_at = Some(anchored_at);
+		assert_eq!(file.sort_time(SortBy::AnchoredAt), Some(anchored_at));
+		assert_eq!(file.sort_time(SortBy::UpdatedAt), Some(updated_at));
+	}
 }
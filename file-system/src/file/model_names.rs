@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use dataverse_core::store::dapp::{DappRegistry, Lookup};
+
+use super::FileModel;
+
+impl FileModel {
+	pub const ALL: [FileModel; 4] = [
+		FileModel::IndexFile,
+		FileModel::ActionFile,
+		FileModel::IndexFolder,
+		FileModel::ContentFolder,
+	];
+
+	pub fn default_name(&self) -> &'static str {
+		match self {
+			FileModel::IndexFile => "indexFile",
+			FileModel::ActionFile => "actionFile",
+			FileModel::IndexFolder => "indexFolder",
+			FileModel::ContentFolder => "contentFolder",
+		}
+	}
+
+	pub fn matches(&self, model_name: &str) -> bool {
+		self.default_name() == model_name
+	}
+
+	/// file model of a custom registry name, ignoring case and `_`/`-`
+	/// separators, e.g. `Index_File` or `dataverse-index-file-v2`
+	fn from_alias(model_name: &str) -> Option<FileModel> {
+		let normalize = |name: &str| {
+			name.chars()
+				.filter(|c| *c != '_' && *c != '-')
+				.collect::<String>()
+				.to_lowercase()
+		};
+		let model_name = normalize(model_name);
+		Self::ALL
+			.into_iter()
+			.find(|model| model_name.contains(&normalize(model.default_name())))
+	}
+}
+
+/// Custom names of the file models of dapps, loaded from the registry the
+/// first time a dapp is looked up
+pub struct ModelNames {
+	registry: Arc<dyn DappRegistry>,
+	/// overridden names by dapp, dapps loaded without overrides map to none
+	names: RwLock<HashMap<uuid::Uuid, HashMap<FileModel, String>>>,
+}
+
+impl ModelNames {
+	pub fn new(registry: Arc<dyn DappRegistry>) -> Self {
+		Self {
+			registry,
+			names: Default::default(),
+		}
+	}
+
+	/// Load the names of the internal models registered by the dapp,
+	/// replacing the ones loaded before
+	pub async fn load(&self, dapp_id: &uuid::Uuid) -> anyhow::Result<()> {
+		let models = self.registry.get_models(dapp_id, Lookup::Fetch).await?;
+		let mut names = HashMap::new();
+		for model in models.iter().filter(|model| model.internal && model.latest) {
+			if let Some(file_model) = FileModel::from_alias(&model.name) {
+				if !file_model.matches(&model.name) {
+					names.insert(file_model, model.name.clone());
+				}
+			}
+		}
+		self.names
+			.write()
+			.expect("model names poisoned")
+			.insert(*dapp_id, names);
+		Ok(())
+	}
+
+	/// Override the model name of a file model for a dapp
+	pub fn register(&self, dapp_id: &uuid::Uuid, model: FileModel, name: &str) {
+		let mut names = self.names.write().expect("model names poisoned");
+		let names = names.entry(*dapp_id).or_default();
+		match model.matches(name) {
+			true => names.remove(&model),
+			false => names.insert(model, name.to_string()),
+		};
+	}
+
+	async fn names_of(&self, dapp_id: &uuid::Uuid) -> anyhow::Result<HashMap<FileModel, String>> {
+		let loaded = self
+			.names
+			.read()
+			.expect("model names poisoned")
+			.get(dapp_id)
+			.cloned();
+		if let Some(names) = loaded {
+			return Ok(names);
+		}
+		self.load(dapp_id).await?;
+		let names = self.names.read().expect("model names poisoned");
+		Ok(names.get(dapp_id).cloned().unwrap_or_default())
+	}
+
+	/// model name used by the dapp, the default name if not overridden
+	pub async fn name_in(&self, dapp_id: &uuid::Uuid, model: FileModel) -> anyhow::Result<String> {
+		let names = self.names_of(dapp_id).await?;
+		Ok(names
+			.get(&model)
+			.cloned()
+			.unwrap_or_else(|| model.default_name().to_string()))
+	}
+
+	pub async fn matches_in(
+		&self,
+		dapp_id: &uuid::Uuid,
+		model: FileModel,
+		model_name: &str,
+	) -> anyhow::Result<bool> {
+		Ok(self.name_in(dapp_id, model).await? == model_name)
+	}
+
+	/// file model of a model name registered by the dapp
+	pub async fn resolve(
+		&self,
+		dapp_id: &uuid::Uuid,
+		model_name: &str,
+	) -> anyhow::Result<Option<FileModel>> {
+		let names = self.names_of(dapp_id).await?;
+		Ok(FileModel::ALL.into_iter().find(|model| {
+			names
+				.get(model)
+				.map_or(model.default_name(), String::as_str)
+				== model_name
+		}))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use async_std::task;
+	use dataverse_core::store::dapp::Model;
+	use dataverse_core::testing::MemoryRegistry;
+
+	use super::*;
+
+	fn model(dapp_id: uuid::Uuid, name: &str) -> Model {
+		Model {
+			id: "kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju"
+				.parse()
+				.unwrap(),
+			name: name.to_string(),
+			dapp_id,
+			encryptable: vec![],
+			version: 0,
+			latest: true,
+			internal: true,
+		}
+	}
+
+	#[test]
+	fn test_model_name_override() -> anyhow::Result<()> {
+		task::block_on(async {
+			let dapp_id = uuid::Uuid::new_v4();
+			let names = ModelNames::new(Arc::new(MemoryRegistry::new(vec![
+				model(dapp_id, "Index_File"),
+				model(dapp_id, "contentFolder"),
+			])));
+			assert!(FileModel::IndexFile.matches("indexFile"));
+
+			// loaded from the registry on the first lookup of the dapp
+			assert_eq!(
+				names.name_in(&dapp_id, FileModel::IndexFile).await?,
+				"Index_File"
+			);
+			assert_eq!(
+				names.resolve(&dapp_id, "Index_File").await?,
+				Some(FileModel::IndexFile)
+			);
+			assert_eq!(names.resolve(&dapp_id, "indexFile").await?, None);
+			assert_eq!(
+				names.resolve(&dapp_id, "contentFolder").await?,
+				Some(FileModel::ContentFolder)
+			);
+
+			names.register(&dapp_id, FileModel::IndexFolder, "folders");
+			assert!(
+				names
+					.matches_in(&dapp_id, FileModel::IndexFolder, "folders")
+					.await?
+			);
+			assert_eq!(
+				names.resolve(&uuid::Uuid::new_v4(), "indexFile").await?,
+				Some(FileModel::IndexFile)
+			);
+			Ok(())
+		})
+	}
+
+	#[test]
+	fn test_model_from_alias() {
+		assert_eq!(
+			FileModel::from_alias("Index_File"),
+			Some(FileModel::IndexFile)
+		);
+		assert_eq!(
+			FileModel::from_alias("content-folder"),
+			Some(FileModel::ContentFolder)
+		);
+		assert_eq!(
+			FileModel::from_alias("dataverse_action_file_v2"),
+			Some(FileModel::ActionFile)
+		);
+		assert_eq!(FileModel::from_alias("post"), None);
+	}
+}
@@ -0,0 +1,98 @@
+use serde_json::Value;
+
+/// Filter on the `fileName` of index files
+#[derive(Debug, Clone, PartialEq)]
+pub enum NameFilter {
+	Prefix(String),
+	Suffix(String),
+	/// glob pattern, `*` matches any sequence and `?` a single character
+	Wildcard(String),
+}
+
+impl NameFilter {
+	pub fn matches(&self, name: &str) -> bool {
+		match self {
+			NameFilter::Prefix(prefix) => name.starts_with(prefix.as_str()),
+			NameFilter::Suffix(suffix) => name.ends_with(suffix.as_str()),
+			NameFilter::Wildcard(pattern) => {
+				let pattern: Vec<char> = pattern.chars().collect();
+				let name: Vec<char> = name.chars().collect();
+				glob_match(&pattern, &name)
+			}
+		}
+	}
+
+	pub fn matches_content(&self, content: &Value) -> bool {
+		content
+			.get("fileName")
+			.and_then(Value::as_str)
+			.map_or(false, |name| self.matches(name))
+	}
+
+	/// Pattern for sql LIKE, suffix filters are matched against the reversed
+	/// name so that a prefix index can be used
+	pub fn like_pattern(&self) -> String {
+		match self {
+			NameFilter::Prefix(prefix) => format!("{}%", escape_like(prefix)),
+			NameFilter::Suffix(suffix) => {
+				let reversed: String = suffix.chars().rev().collect();
+				format!("{}%", escape_like(&reversed))
+			}
+			NameFilter::Wildcard(pattern) => escape_like(pattern)
+				.chars()
+				.map(|c| match c {
+					'*' => '%',
+					'?' => '_',
+					c => c,
+				})
+				.collect(),
+		}
+	}
+}
+
+fn escape_like(value: &str) -> String {
+	let mut result = String::with_capacity(value.len());
+	for c in value.chars() {
+		if matches!(c, '\\' | '%' | '_') {
+			result.push('\\');
+		}
+		result.push(c);
+	}
+	result
+}
+
+fn glob_match(pattern: &[char], name: &[char]) -> bool {
+	match (pattern.first(), name.first()) {
+		(None, None) => true,
+		(Some('*'), _) => {
+			glob_match(&pattern[1..], name) || (!name.is_empty() && glob_match(pattern, &name[1..]))
+		}
+		(Some('?'), Some(_)) => glob_match(&pattern[1..], &name[1..]),
+		(Some(p), Some(n)) if p == n => glob_match(&pattern[1..], &name[1..]),
+		_ => false,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn match_file_name() {
+		assert!(NameFilter::Prefix("report".into()).matches("report-2024.pdf"));
+		assert!(!NameFilter::Prefix("report".into()).matches("old-report.pdf"));
+		assert!(NameFilter::Suffix(".pdf".into()).matches("report-2024.pdf"));
+		assert!(NameFilter::Wildcard("report-*.p?f".into()).matches("report-2024.pdf"));
+		assert!(!NameFilter::Wildcard("report-*.txt".into()).matches("report-2024.pdf"));
+	}
+
+	#[test]
+	fn like_pattern() {
+		assert_eq!(NameFilter::Prefix("a_b".into()).like_pattern(), "a\\_b%");
+		assert_eq!(NameFilter::Suffix(".pdf".into()).like_pattern(), "fdp.%");
+		assert_eq!(
+			NameFilter::Wildcard("*100%?".into()).like_pattern(),
+			"%100\\%_"
+		);
+	}
+}
@@ -1,11 +1,10 @@
-use std::collections::HashMap;
-
-use ceramic_http_client::{FilterQuery, OperationFilter};
+use dataverse_ceramic::http::filter::Filter;
 use dataverse_ceramic::{event::EventsUploader, Ceramic, StreamId, StreamState, StreamsLoader};
 
 use crate::file::errors::StreamFileError;
 
 use super::index_file::IndexFile;
+use super::name_filter::NameFilter;
 
 #[async_trait::async_trait]
 pub trait StreamFileLoader: StreamsLoader + EventsUploader + Send + Sync {
@@ -38,6 +37,22 @@ pub trait StreamFileLoader: StreamsLoader + EventsUploader + Send + Sync {
 		}
 		anyhow::bail!(StreamFileError::IndexFileWithIdNotFound(content_id.clone()))
 	}
+
+	async fn load_index_files_by_name(
+		&self,
+		ceramic: &Ceramic,
+		index_file_model_id: &StreamId,
+		account: Option<String>,
+		filter: &NameFilter,
+	) -> anyhow::Result<Vec<StreamState>> {
+		let stream_states = self
+			.load_stream_states(ceramic, account, index_file_model_id)
+			.await?;
+		Ok(stream_states
+			.into_iter()
+			.filter(|state| filter.matches_content(&state.content))
+			.collect())
+	}
 }
 
 #[async_trait::async_trait]
@@ -48,14 +63,8 @@ impl StreamFileLoader for dataverse_ceramic::http::Client {
 		model_id: &StreamId,
 		content_id: &String,
 	) -> anyhow::Result<(StreamState, IndexFile)> {
-		let mut where_filter = HashMap::new();
-		where_filter.insert(
-			"contentId".to_string(),
-			OperationFilter::EqualTo(content_id.clone().into()),
-		);
-
-		let query = Some(FilterQuery::Where(where_filter));
-		let streams = self.query_model(ceramic, None, model_id, query).await?;
+		let filter = Filter::field("contentId").eq(content_id.as_str());
+		let streams = self.query_filtered(ceramic, model_id, filter).await?;
 		if streams.len() != 1 {
 			anyhow::bail!(StreamFileError::IndexFileNotFound)
 		}
@@ -0,0 +1,136 @@
+use anyhow::{Context, Result};
+use dataverse_ceramic::{StreamId, StreamState};
+use dataverse_core::notifier::{CommitNotification, QuarantineAction};
+use dataverse_core::stream::{Stream, QUARANTINED};
+use int_enum::IntEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::error::CommitRejection;
+use crate::file::errors::FileClientError;
+use crate::policy::validate_event;
+
+use super::Client;
+
+/// Outcome of the review of a quarantined stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum QuarantineDecision {
+	/// serve the stream again
+	Release,
+	/// drop the stream and its commits from the node, which no longer stores
+	/// or serves it
+	Purge,
+}
+
+impl Client {
+	/// Replay the stored commits of the stream through the policies of the
+	/// client, the rejection of the first commit violating them
+	pub async fn check_policies(
+		&self,
+		dapp_id: &uuid::Uuid,
+		stream_id: &StreamId,
+	) -> Result<Option<CommitRejection>> {
+		let stream = self.stored_stream(stream_id).await?;
+		let ceramic = self.registry.get_dapp_ceramic(dapp_id).await?;
+		let events = self
+			.operator
+			.load_events(&ceramic, stream_id, Some(stream.tip))
+			.await?;
+		let mut state = StreamState {
+			r#type: stream_id.r#type.int_value(),
+			..Default::default()
+		};
+		for event in events {
+			let mut next = state.clone();
+			event.apply_to(&mut next).await?;
+			for policy in &self.policies {
+				if !policy.effect_at(&next).await? {
+					continue;
+				}
+				if let Err(err) = validate_event(policy.as_ref(), &state, &event).await {
+					return Ok(Some(CommitRejection::from(&err)));
+				}
+			}
+			state = next;
+		}
+		Ok(None)
+	}
+
+	/// Quarantine the stream if it violates the policies accepting its
+	/// commits back then, e.g. an acl referencing models of another dapp
+	pub async fn enforce_policies(
+		&self,
+		dapp_id: &uuid::Uuid,
+		stream_id: &StreamId,
+	) -> Result<Option<CommitRejection>> {
+		let rejection = self.check_policies(dapp_id, stream_id).await?;
+		if let Some(rejection) = &rejection {
+			self.quarantine(stream_id, &rejection.to_string()).await?;
+		}
+		Ok(rejection)
+	}
+
+	/// Keep the stream on the node but serve only its metadata until it is
+	/// reviewed
+	pub async fn quarantine(&self, stream_id: &StreamId, reason: &str) -> Result<()> {
+		let stream = self.stored_stream(stream_id).await?;
+		self.stream_store
+			.add_stream_label(stream_id, QUARANTINED)
+			.await?;
+		tracing::warn!(
+			stream_id = stream_id.to_string(),
+			reason,
+			"stream quarantined"
+		);
+		self.notify_quarantine(stream_id, &stream, QuarantineAction::Quarantined);
+		Ok(())
+	}
+
+	pub async fn review_quarantine(
+		&self,
+		stream_id: &StreamId,
+		decision: QuarantineDecision,
+	) -> Result<()> {
+		if !self.stream_store.is_quarantined(stream_id).await? {
+			anyhow::bail!(FileClientError::StreamNotQuarantined(stream_id.clone()));
+		}
+		let stream = self.stored_stream(stream_id).await?;
+		let action = match decision {
+			QuarantineDecision::Release => {
+				self.stream_store
+					.remove_stream_label(stream_id, QUARANTINED)
+					.await?;
+				QuarantineAction::Released
+			}
+			QuarantineDecision::Purge => {
+				self.stream_store.purge_stream(stream_id).await?;
+				QuarantineAction::Purged
+			}
+		};
+		tracing::info!(
+			stream_id = stream_id.to_string(),
+			?decision,
+			"quarantine reviewed"
+		);
+		self.notify_quarantine(stream_id, &stream, action);
+		Ok(())
+	}
+
+	async fn stored_stream(&self, stream_id: &StreamId) -> Result<Stream> {
+		self.stream_store.load_stream(stream_id).await?.context(
+			FileClientError::CommitStreamIdNotFoundOnStore(stream_id.clone()),
+		)
+	}
+
+	fn notify_quarantine(&self, stream_id: &StreamId, stream: &Stream, action: QuarantineAction) {
+		if let Some(notifier) = &self.notifier {
+			notifier.notify(CommitNotification::quarantine(
+				stream_id.clone(),
+				stream.tip,
+				stream.model.clone(),
+				action,
+			));
+		}
+	}
+}
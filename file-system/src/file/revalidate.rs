@@ -0,0 +1,414 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use dataverse_ceramic::StreamId;
+use dataverse_core::task::{missing_context, ContextTask};
+use fang::async_trait;
+use fang::asynk::async_queue::AsyncQueueable;
+use fang::typetag;
+use fang::AsyncRunnable;
+use fang::FangError;
+use serde::{Deserialize, Serialize};
+
+use crate::error::CommitRejection;
+
+use super::Client;
+
+/// Stored stream the policies reject now
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PolicyViolation {
+	pub stream_id: String,
+	#[cfg_attr(feature = "openapi", schema(value_type = Object))]
+	pub rejection: CommitRejection,
+	/// quarantined by this run or a previous one
+	pub quarantined: bool,
+}
+
+/// Outcome of running the policies again over the stored streams of a model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RevalidationReport {
+	pub model_id: String,
+	pub checked: usize,
+	pub violations: Vec<PolicyViolation>,
+	/// errors of the streams whose commits could not be replayed
+	pub failed: HashMap<String, String>,
+	pub started_at: DateTime<Utc>,
+	pub finished_at: DateTime<Utc>,
+}
+
+/// Latest revalidation report of every model
+#[async_trait]
+pub trait RevalidationReportStore: Send + Sync {
+	async fn save_report(&self, report: &RevalidationReport) -> Result<()>;
+
+	async fn load_report(&self, model_id: &StreamId) -> Result<Option<RevalidationReport>>;
+}
+
+impl Client {
+	pub fn with_revalidation_reports(self, reports: Arc<dyn RevalidationReportStore>) -> Self {
+		Self {
+			revalidation_reports: Some(reports),
+			..self
+		}
+	}
+
+	/// Run the policies over every stored stream of the model, to be called
+	/// once policies or the configuration of the dapp changed. Violating
+	/// streams are quarantined if asked, the report is saved if a report
+	/// store is configured.
+	pub async fn revalidate(
+		&self,
+		model_id: &StreamId,
+		quarantine: bool,
+	) -> Result<RevalidationReport> {
+		let started_at = Utc::now();
+		let model = self.registry.get_model(model_id).await?;
+		let streams = self.stream_store.list_model_streams(model_id).await?;
+		let mut report = RevalidationReport {
+			model_id: model_id.to_string(),
+			checked: 0,
+			violations: vec![],
+			failed: HashMap::new(),
+			started_at,
+			finished_at: started_at,
+		};
+		for stream in streams {
+			report.checked += 1;
+			let stream_id = match stream.stream_id() {
+				Ok(stream_id) => stream_id,
+				Err(err) => {
+					report
+						.failed
+						.insert(stream.genesis.to_string(), err.to_string());
+					continue;
+				}
+			};
+			let rejection = match self.check_policies(&model.dapp_id, &stream_id).await {
+				Ok(Some(rejection)) => rejection,
+				Ok(None) => continue,
+				Err(err) => {
+					report.failed.insert(stream_id.to_string(), err.to_string());
+					continue;
+				}
+			};
+			let quarantined = match self
+				.quarantine_violation(&stream_id, &rejection, quarantine)
+				.await
+			{
+				Ok(quarantined) => quarantined,
+				Err(err) => {
+					report.failed.insert(stream_id.to_string(), err.to_string());
+					false
+				}
+			};
+			report.violations.push(PolicyViolation {
+				stream_id: stream_id.to_string(),
+				rejection,
+				quarantined,
+			});
+		}
+		report.finished_at = Utc::now();
+		tracing::info!(
+			model_id = model_id.to_string(),
+			checked = report.checked,
+			violations = report.violations.len(),
+			failed = report.failed.len(),
+			"model revalidated"
+		);
+		if let Some(reports) = &self.revalidation_reports {
+			reports.save_report(&report).await?;
+		}
+		Ok(report)
+	}
+
+	/// Whether the violating stream is quarantined, quarantining it first if
+	/// asked
+	async fn quarantine_violation(
+		&self,
+		stream_id: &StreamId,
+		rejection: &CommitRejection,
+		quarantine: bool,
+	) -> Result<bool> {
+		let quarantined = self.stream_store.is_quarantined(stream_id).await?;
+		if quarantine && !quarantined {
+			self.quarantine(stream_id, &rejection.to_string()).await?;
+			return Ok(true);
+		}
+		Ok(quarantined)
+	}
+
+	/// Queue the revalidation of the model, run by a
+	/// `ContextWorker<RevalidateHandler>` holding the client
+	pub async fn submit_revalidation(
+		&self,
+		queue: &mut dyn AsyncQueueable,
+		model_id: &StreamId,
+		quarantine: bool,
+	) -> Result<()> {
+		queue
+			.insert_task(&RevalidateHandler {
+				model_id: model_id.to_string(),
+				quarantine,
+			})
+			.await?;
+		Ok(())
+	}
+
+	pub async fn revalidation_report(
+		&self,
+		model_id: &StreamId,
+	) -> Result<Option<RevalidationReport>> {
+		match &self.revalidation_reports {
+			Some(reports) => reports.load_report(model_id).await,
+			None => Ok(None),
+		}
+	}
+}
+
+#[derive(fang::serde::Serialize, fang::serde::Deserialize)]
+#[serde(crate = "fang::serde")]
+pub struct RevalidateHandler {
+	pub model_id: String,
+	pub quarantine: bool,
+}
+
+#[async_trait]
+impl ContextTask for RevalidateHandler {
+	type Context = Client;
+
+	const TASK_TYPE: &'static str = "revalidate";
+	const MAX_RETRIES: i32 = 3;
+
+	async fn run_with(&self, client: &Client) -> Result<()> {
+		let result = match self.model_id.parse() {
+			Ok(model_id) => client.revalidate(&model_id, self.quarantine).await,
+			Err(err) => Err(anyhow::Error::from(err)),
+		};
+		if let Err(err) = &result {
+			tracing::warn!(
+				model_id = self.model_id.as_str(),
+				"revalidation failed: {:#}",
+				err
+			);
+		}
+		result.map(|_| ())
+	}
+}
+
+#[async_trait]
+#[typetag::serde]
+impl AsyncRunnable for RevalidateHandler {
+	async fn run(&self, _queue: &mut dyn AsyncQueueable) -> Result<(), FangError> {
+		Err(missing_context(Self::TASK_TYPE))
+	}
+
+	fn task_type(&self) -> String {
+		Self::TASK_TYPE.to_string()
+	}
+
+	fn uniq(&self) -> bool {
+		true
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use async_std::task;
+	use dataverse_ceramic::commit::example;
+	use dataverse_ceramic::event::{Event, EventsLoader, EventsUploader};
+	use dataverse_ceramic::{Ceramic, StreamLoader, StreamState, StreamsLoader};
+	use dataverse_core::store::dapp::Model;
+	use dataverse_core::stream::{Stream, StreamStore, QUARANTINED};
+	use dataverse_core::testing::{MemoryRegistry, MemoryStreams};
+	use int_enum::IntEnum;
+	use serde_json::Value;
+
+	use super::*;
+	use crate::file::StreamFileLoader;
+	use crate::policy::Policy;
+
+	/// Operator serving the example log for every stream
+	struct Events;
+
+	#[async_trait]
+	impl EventsLoader for Events {
+		async fn load_events(
+			&self,
+			_ceramic: &Ceramic,
+			_stream_id: &StreamId,
+			_tip: Option<ceramic_core::Cid>,
+		) -> Result<Vec<Event>> {
+			Ok(example::events(1))
+		}
+	}
+
+	impl StreamLoader for Events {}
+
+	#[async_trait]
+	impl StreamsLoader for Events {
+		async fn load_stream_states(
+			&self,
+			_ceramic: &Ceramic,
+			_account: Option<String>,
+			_model_id: &StreamId,
+		) -> Result<Vec<StreamState>> {
+			Ok(vec![])
+		}
+	}
+
+	#[async_trait]
+	impl EventsUploader for Events {
+		async fn upload_event(
+			&self,
+			_ceramic: &Ceramic,
+			_stream_id: &StreamId,
+			_event: Event,
+		) -> Result<()> {
+			Ok(())
+		}
+	}
+
+	impl StreamFileLoader for Events {}
+
+	/// Store failing to label streams
+	struct NoLabels(MemoryStreams);
+
+	#[async_trait]
+	impl StreamStore for NoLabels {
+		async fn save_stream(&self, stream: &Stream) -> Result<()> {
+			self.0.save_stream(stream).await
+		}
+
+		async fn load_stream(&self, stream_id: &StreamId) -> Result<Option<Stream>> {
+			self.0.load_stream(stream_id).await
+		}
+
+		async fn list_all_streams(&self) -> Result<Vec<Stream>> {
+			self.0.list_all_streams().await
+		}
+	}
+
+	/// Rejects every content with a text
+	struct NoText;
+
+	#[async_trait]
+	impl Policy for NoText {
+		fn name(&self) -> &str {
+			"noText"
+		}
+
+		async fn effect_at(&self, _state: &StreamState) -> Result<bool> {
+			Ok(true)
+		}
+
+		async fn validate_data(&self, _state: &StreamState, data: Value) -> Result<()> {
+			if data.get("text").is_some() {
+				anyhow::bail!(CommitRejection::new("TEXT_NOT_ALLOWED", "text not allowed"));
+			}
+			Ok(())
+		}
+	}
+
+	/// Stored stream of the example log and the registry of its model
+	fn example_stream() -> Result<(Stream, MemoryRegistry)> {
+		let dapp_id = uuid::Uuid::new_v4();
+		let genesis = example::genesis();
+		let model_id = genesis.model_id()?;
+		let event: Event = genesis.genesis.try_into()?;
+		let stream = Stream::new(
+			&dapp_id,
+			genesis.stream_id()?.r#type.int_value(),
+			&event,
+			Some(model_id.clone()),
+		)?;
+		let registry = MemoryRegistry::new(vec![Model {
+			id: model_id,
+			name: "post".to_string(),
+			dapp_id,
+			encryptable: vec![],
+			version: 0,
+			latest: true,
+			internal: false,
+		}]);
+		Ok((stream, registry))
+	}
+
+	#[test]
+	fn test_revalidate_quarantines_violations() -> anyhow::Result<()> {
+		task::block_on(async {
+			let (stream, registry) = example_stream()?;
+			let model_id = stream.model.clone().unwrap();
+			let stream_id = stream.stream_id()?;
+			let store = Arc::new(MemoryStreams::new(vec![stream]));
+			let client = Client::new(Arc::new(Events), store.clone(), Arc::new(registry))
+				.with_policy(Arc::new(NoText));
+
+			let report = client.revalidate(&model_id, false).await?;
+			assert_eq!(report.checked, 1);
+			assert_eq!(report.violations.len(), 1);
+			assert!(!report.violations[0].quarantined);
+			assert!(!store.is_quarantined(&stream_id).await?);
+
+			let report = client.revalidate(&model_id, true).await?;
+			let violation = &report.violations[0];
+			assert_eq!(violation.stream_id, stream_id.to_string());
+			assert_eq!(violation.rejection.code, "TEXT_NOT_ALLOWED");
+			assert_eq!(violation.rejection.policy.as_deref(), Some("noText"));
+			assert!(violation.quarantined);
+			assert_eq!(store.stream_labels(&stream_id).await?, vec![QUARANTINED]);
+			Ok(())
+		})
+	}
+
+	#[test]
+	fn test_revalidate_reports_failed_quarantine() -> anyhow::Result<()> {
+		task::block_on(async {
+			let (stream, registry) = example_stream()?;
+			let model_id = stream.model.clone().unwrap();
+			let stream_id = stream.stream_id()?;
+			let store = Arc::new(NoLabels(MemoryStreams::new(vec![stream])));
+			let client = Client::new(Arc::new(Events), store, Arc::new(registry))
+				.with_policy(Arc::new(NoText));
+
+			let report = client.revalidate(&model_id, true).await?;
+			assert_eq!(report.checked, 1);
+			assert_eq!(report.violations.len(), 1);
+			assert!(!report.violations[0].quarantined);
+			assert!(report.failed.contains_key(&stream_id.to_string()));
+			Ok(())
+		})
+	}
+
+	#[test]
+	fn test_report_roundtrip() -> anyhow::Result<()> {
+		let stream_id = "kjzl6kcym7w8y8syiams0kvm3qwfnutk2szi0wlhvf6rr9lalzpibxed0qvotuy";
+		let now = Utc::now();
+		let report = RevalidationReport {
+			model_id: "kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju".to_string(),
+			checked: 2,
+			violations: vec![PolicyViolation {
+				stream_id: stream_id.to_string(),
+				rejection: CommitRejection::new(
+					"LINKED_MODEL_NOT_IN_APP",
+					"linked model not in same app",
+				)
+				.with_policy("indexFile"),
+				quarantined: true,
+			}],
+			failed: HashMap::new(),
+			started_at: now,
+			finished_at: now,
+		};
+		let value = serde_json::to_value(&report)?;
+		assert_eq!(value["violations"][0]["streamId"], stream_id);
+		assert_eq!(value["violations"][0]["rejection"]["policy"], "indexFile");
+		assert_eq!(serde_json::from_value::<RevalidationReport>(value)?, report);
+		Ok(())
+	}
+}
@@ -0,0 +1,65 @@
+use anyhow::Result;
+use dataverse_ceramic::model::ModelAccountRelation;
+use dataverse_ceramic::stream::single::{
+	load_single_instance, single_genesis_state, single_stream_id,
+};
+use dataverse_ceramic::StreamId;
+
+use super::errors::FileClientError;
+use super::{Client, StreamFile};
+
+impl Client {
+	/// File of the single instance of `model_id` controlled by `account`, e.g. a
+	/// user's settings file.
+	///
+	/// The stream id is derived from the model and account, so a missing
+	/// instance is returned from its genesis without content and is created by
+	/// the first commit of the account. The genesis is only used when the
+	/// stream is unknown to the node and ceramic answers that it does not
+	/// exist, failing to load it otherwise would fork the instance.
+	pub async fn get_or_create_singleton(
+		&self,
+		dapp_id: &uuid::Uuid,
+		model_id: &StreamId,
+		account: &str,
+	) -> Result<StreamFile> {
+		let stream_id = single_stream_id(model_id, account)?;
+		let model = self.registry.get_model(model_id).await?;
+		if model.dapp_id != *dapp_id {
+			anyhow::bail!(FileClientError::StreamWithModelNotInDapp(
+				stream_id,
+				model_id.clone(),
+				*dapp_id
+			));
+		}
+
+		let ceramic = self.registry.get_dapp_ceramic(dapp_id).await?;
+		let definition = self
+			.operator
+			.load_stream_state(&ceramic, model_id, None)
+			.await?;
+		let relation = definition.content.get("accountRelation").cloned();
+		let relation = relation.map(serde_json::from_value::<ModelAccountRelation>);
+		if !matches!(relation, Some(Ok(ModelAccountRelation::Single))) {
+			anyhow::bail!(FileClientError::ModelNotSingle(model_id.clone()));
+		}
+
+		let state = match self.stream_store.load_stream(&stream_id).await? {
+			Some(stream) => {
+				self.operator
+					.load_stream_state(&ceramic, &stream_id, Some(stream.tip))
+					.await?
+			}
+			None => {
+				let state =
+					load_single_instance(self.operator.as_ref(), &ceramic, model_id, account)
+						.await?;
+				match state {
+					Some(state) => state,
+					None => single_genesis_state(model_id, account)?,
+				}
+			}
+		};
+		StreamFile::new_with_content(state)
+	}
+}
@@ -11,6 +11,7 @@ pub enum Status {
 	CACAOExpired = -2,
 	BrokenContent = -3,
 	BrokenFolder = -4,
+	Quarantined = -5,
 }
 
 impl Default for Status {
@@ -1,5 +1,11 @@
+pub mod api;
+pub mod auth;
+#[cfg(feature = "crypto")]
+pub mod crypto;
 pub mod error;
 pub mod file;
+#[cfg(feature = "openapi")]
+pub mod openapi;
 pub mod policy;
 pub mod schema;
 pub mod task;
@@ -0,0 +1,66 @@
+use utoipa::OpenApi;
+
+use crate::api;
+use crate::file::dapp_query::{DappFile, DappFilesPage};
+use crate::file::folder_delta::{FolderChange, FolderDelta};
+use crate::file::folder_stats::FolderStats;
+use crate::file::quarantine::QuarantineDecision;
+use crate::file::revalidate::{PolicyViolation, RevalidationReport};
+use crate::file::{FileModel, IndexFile, StreamFile};
+
+/// Routes and schemas of the node api, served as `/openapi.json`
+#[derive(OpenApi)]
+#[openapi(
+	info(title = "Dataverse Node", description = "Dataverse file system api"),
+	paths(
+		openapi_json,
+		api::get_file,
+		api::create_stream,
+		api::update_stream,
+		api::query_dapp_files,
+		api::get_folder_stats,
+		api::get_folder_delta,
+		api::stream_events
+	),
+	components(schemas(
+		StreamFile,
+		IndexFile,
+		FileModel,
+		DappFile,
+		DappFilesPage,
+		FolderStats,
+		FolderChange,
+		FolderDelta,
+		QuarantineDecision,
+		PolicyViolation,
+		RevalidationReport
+	))
+)]
+pub struct ApiDoc;
+
+#[utoipa::path(
+	get,
+	path = "/openapi.json",
+	responses((status = 200, description = "OpenAPI document of the api", content_type = "application/json", body = String))
+)]
+pub fn openapi_json() -> anyhow::Result<String> {
+	Ok(ApiDoc::openapi().to_pretty_json()?)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn generate_openapi() {
+		let json = openapi_json();
+		assert!(json.is_ok());
+		let json: serde_json::Value = serde_json::from_str(&json.unwrap()).unwrap();
+		assert!(json["components"]["schemas"]["StreamFile"].is_object());
+		assert!(json["paths"][api::OPENAPI_JSON]["get"].is_object());
+		let events = &json["paths"][api::STREAM_EVENTS]["get"];
+		assert!(events["responses"]["200"]["content"][api::EVENT_STREAM].is_object());
+		assert!(json["paths"]["/dapp/{dapp_id}/streams/{stream_id}"]["get"].is_object());
+		assert!(json["paths"]["/dapp/{dapp_id}/streams"]["post"].is_object());
+	}
+}
@@ -1,16 +1,23 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
 use async_trait::async_trait;
 use ceramic::event::EventsLoader;
 use ceramic::Ceramic;
 use dataverse_ceramic as ceramic;
-use dataverse_ceramic::{event::EventValue, StreamId, StreamState};
+use dataverse_ceramic::model::{schema_at, validate_value_at, ModelDefinition, SchemaPathError};
+use dataverse_ceramic::{event::EventValue, Event, StreamId, StreamLoader, StreamState};
 use int_enum::IntEnum;
 use json_patch::{Patch, PatchOperation};
 use serde_json::Value;
 
-use crate::error::FilePolicyError;
+use crate::error::{CommitRejection, FilePolicyError};
 
 #[async_trait::async_trait]
 pub trait Policy: Send + Sync {
+	fn name(&self) -> &str {
+		"policy"
+	}
 	async fn effect_at(&self, _state: &ceramic::StreamState) -> anyhow::Result<bool> {
 		Ok(false)
 	}
@@ -62,27 +69,42 @@ impl<T: EventsLoader + Sync> PolicyStreamLoader for T {
 			..Default::default()
 		};
 
+		// whether a policy takes effect is decided on the state with the event
+		// applied, the genesis sets the model policies are matched by
 		for event in events {
+			let mut next = stream_state.clone();
+			event.apply_to(&mut next).await?;
 			for ele in &policies {
-				if ele.effect_at(&stream_state).await? {
-					if let EventValue::Signed(signed) = &event.value {
-						match signed.is_gensis() {
-							true => ele.validate_data(&stream_state, signed.data()?).await?,
-							false => {
-								ele.validate_patch(&stream_state.content, signed.patch()?)
-									.await?
-							}
-						};
-					}
+				if ele.effect_at(&next).await? {
+					validate_event(ele.as_ref(), &stream_state, &event).await?;
 				}
 			}
-			event.apply_to(&mut stream_state).await?;
+			stream_state = next;
 		}
 
 		Ok(stream_state)
 	}
 }
 
+/// Validate the data of a genesis or the patch of a data event against the
+/// policy, `state` being the state the event applies to
+pub(crate) async fn validate_event(
+	policy: &dyn Policy,
+	state: &StreamState,
+	event: &Event,
+) -> anyhow::Result<()> {
+	if let EventValue::Signed(signed) = &event.value {
+		let result = match signed.is_gensis() {
+			true => policy.validate_data(state, signed.data()?).await,
+			false => policy.validate_patch(&state.content, signed.patch()?).await,
+		};
+		if let Err(err) = result {
+			anyhow::bail!(rejection_of(policy.name(), &err));
+		}
+	}
+	Ok(())
+}
+
 static mut POLICIES: Vec<Box<dyn Policy>> = vec![];
 
 #[async_trait::async_trait]
@@ -105,7 +127,12 @@ impl PolicyProcessor for dyn Policy {
 			// check if modify the protected fields
 			for ele in patch.path() {
 				if self.protected_fields().contains(&ele) {
-					anyhow::bail!(FilePolicyError::PatchValidationFailed);
+					anyhow::bail!(CommitRejection::new(
+						"PROTECTED_FIELD",
+						FilePolicyError::AttemptToModifyProtectedFields
+					)
+					.with_field(field_path(&ele))
+					.with_policy(self.name()));
 				};
 			}
 
@@ -119,14 +146,48 @@ impl PolicyProcessor for dyn Policy {
 				}
 				_ => Ok(()),
 			};
-			if result.is_err() {
-				anyhow::bail!(FilePolicyError::PatchValidationFailed);
+			if let Err(err) = result {
+				let rejection = match err.downcast::<CommitRejection>() {
+					Ok(rejection) => rejection,
+					Err(err) => CommitRejection::new(
+						"PATCH_VALIDATION_FAILED",
+						format!("{}: {}", FilePolicyError::PatchValidationFailed, err),
+					)
+					.with_field(field_path(&patch.path()[0]))
+					.with_policy(self.name()),
+				};
+				anyhow::bail!(rejection);
 			}
 		}
 		Ok(())
 	}
 }
 
+fn rejection_of(policy: &str, err: &anyhow::Error) -> CommitRejection {
+	let rejection = CommitRejection::from(err);
+	match rejection.policy {
+		Some(_) => rejection,
+		None => rejection.with_policy(policy),
+	}
+}
+
+/// converts json pointer `/a/0/b` into field path `a[0].b`
+pub fn field_path(pointer: &str) -> String {
+	let mut path = String::new();
+	for token in pointer.split('/').skip(1) {
+		let token = token.replace("~1", "/").replace("~0", "~");
+		if token.parse::<usize>().is_ok() {
+			path.push_str(&format!("[{}]", token));
+		} else {
+			if !path.is_empty() {
+				path.push('.');
+			}
+			path.push_str(&token);
+		}
+	}
+	path
+}
+
 trait PatchOperationTrait {
 	fn path(&self) -> Vec<String>;
 	fn value(&self) -> Option<Value>;
@@ -153,3 +214,342 @@ impl PatchOperationTrait for PatchOperation {
 		}
 	}
 }
+
+/// Rejects data commits patching the fields the model definition declares
+/// immutable or read only
+#[derive(Debug, Clone)]
+pub struct ModelFieldsPolicy {
+	model_id: StreamId,
+	fields: Vec<String>,
+}
+
+impl ModelFieldsPolicy {
+	pub fn new(model_id: StreamId, definition: &ModelDefinition) -> Self {
+		Self {
+			model_id,
+			fields: definition.locked_fields(),
+		}
+	}
+
+	pub fn fields(&self) -> &[String] {
+		&self.fields
+	}
+
+	fn rejection(&self, path: String) -> CommitRejection {
+		CommitRejection::new(
+			"IMMUTABLE_FIELD",
+			FilePolicyError::AttemptToModifyImmutableFields,
+		)
+		.with_field(path)
+		.with_policy(self.name())
+	}
+
+	/// Field path of the pointer if it falls under a locked field
+	fn locked(&self, pointer: &str) -> Option<String> {
+		let path = field_path(pointer);
+		let field = path.split(['.', '[']).next().unwrap_or_default();
+		self.fields.iter().any(|x| x == field).then_some(path)
+	}
+}
+
+#[async_trait::async_trait]
+impl Policy for ModelFieldsPolicy {
+	fn name(&self) -> &str {
+		"modelFields"
+	}
+
+	async fn effect_at(&self, state: &ceramic::StreamState) -> anyhow::Result<bool> {
+		Ok(state.model()?.as_ref() == Some(&self.model_id))
+	}
+
+	async fn validate_patches(&self, patch: &PatchOperation) -> anyhow::Result<()> {
+		// only the paths the operation writes, a test or the source of a copy
+		// leave the fields as they are. Add and replace are checked with their
+		// value below
+		let targets = match patch {
+			PatchOperation::Remove(op) => vec![&op.path],
+			PatchOperation::Move(op) => vec![&op.path, &op.from],
+			PatchOperation::Copy(op) => vec![&op.path],
+			_ => vec![],
+		};
+		for pointer in targets {
+			// the whole content is replaced by a value not known here
+			if pointer.is_empty() {
+				if let Some(field) = self.fields.first() {
+					anyhow::bail!(self.rejection(field.clone()));
+				}
+			}
+			if let Some(path) = self.locked(pointer) {
+				anyhow::bail!(self.rejection(path));
+			}
+		}
+		Ok(())
+	}
+
+	async fn validate_patch_add_or_replace(
+		&self,
+		data: &Value,
+		path: &str,
+		value: &Value,
+	) -> anyhow::Result<()> {
+		// writing the root keeps the locked fields only if their values do not
+		// change
+		if path.is_empty() {
+			for field in &self.fields {
+				if data.get(field) != value.get(field) {
+					anyhow::bail!(self.rejection(field.clone()));
+				}
+			}
+			return Ok(());
+		}
+		if let Some(path) = self.locked(path) {
+			anyhow::bail!(self.rejection(path));
+		}
+		Ok(())
+	}
+}
+
+/// Field policies of the models saved commits belong to, built from the
+/// definitions of the model streams. Models cannot be updated so the
+/// policies are kept once loaded
+#[derive(Default)]
+pub struct ModelFieldsPolicies {
+	policies: RwLock<HashMap<StreamId, Option<Arc<ModelFieldsPolicy>>>>,
+}
+
+impl ModelFieldsPolicies {
+	/// Policy of the model, `None` for models without locked fields
+	pub async fn get<L: StreamLoader + ?Sized>(
+		&self,
+		loader: &L,
+		ceramic: &Ceramic,
+		model_id: &StreamId,
+	) -> anyhow::Result<Option<Arc<ModelFieldsPolicy>>> {
+		if let Some(policy) = self
+			.policies
+			.read()
+			.expect("model fields poisoned")
+			.get(model_id)
+		{
+			return Ok(policy.clone());
+		}
+		// loaded outside the lock, a concurrent load of the model yields the
+		// same policy
+		let model = loader.load_stream_state(ceramic, model_id, None).await?;
+		let policy = match serde_json::from_value::<ModelDefinition>(model.content) {
+			Ok(definition) => Some(ModelFieldsPolicy::new(model_id.clone(), &definition)),
+			Err(err) => {
+				tracing::warn!(
+					model_id = model_id.to_string(),
+					?err,
+					"model content is not a model definition"
+				);
+				None
+			}
+		};
+		let policy = policy
+			.filter(|policy| !policy.fields.is_empty())
+			.map(Arc::new);
+		self.policies
+			.write()
+			.expect("model fields poisoned")
+			.insert(model_id.clone(), policy.clone());
+		Ok(policy)
+	}
+}
+
+/// Rejects patches writing paths or values the json schema of the model does
+/// not allow, before ceramic rejects the commit later on
+#[derive(Debug, Clone)]
+pub struct SchemaPolicy {
+	model_id: StreamId,
+	schema: Value,
+}
+
+impl SchemaPolicy {
+	pub fn new(model_id: StreamId, schema: Value) -> Self {
+		Self { model_id, schema }
+	}
+
+	fn rejection(&self, path: &str, err: SchemaPathError) -> CommitRejection {
+		CommitRejection::new("SCHEMA_VIOLATION", err)
+			.with_field(field_path(path))
+			.with_policy(self.name())
+	}
+}
+
+#[async_trait::async_trait]
+impl Policy for SchemaPolicy {
+	fn name(&self) -> &str {
+		"modelSchema"
+	}
+
+	async fn effect_at(&self, state: &ceramic::StreamState) -> anyhow::Result<bool> {
+		Ok(state.model()?.as_ref() == Some(&self.model_id))
+	}
+
+	async fn validate_patches(&self, patch: &PatchOperation) -> anyhow::Result<()> {
+		// values of add and replace are checked with their path below
+		let path = match patch {
+			PatchOperation::Move(op) => &op.path,
+			PatchOperation::Copy(op) => &op.path,
+			_ => return Ok(()),
+		};
+		if let Err(err) = schema_at(&self.schema, path) {
+			anyhow::bail!(self.rejection(path, err));
+		}
+		Ok(())
+	}
+
+	async fn validate_patch_add_or_replace(
+		&self,
+		_data: &Value,
+		path: &str,
+		value: &Value,
+	) -> anyhow::Result<()> {
+		if let Err(err) = validate_value_at(&self.schema, path, value) {
+			anyhow::bail!(self.rejection(path, err));
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_field_path() {
+		assert_eq!(field_path("/accessControl"), "accessControl");
+		assert_eq!(
+			field_path("/decryptionConditions/3/returnValueTest"),
+			"decryptionConditions[3].returnValueTest"
+		);
+		assert_eq!(field_path("/a~1b"), "a/b");
+		assert_eq!(field_path(""), "");
+	}
+
+	struct ExampleLoader;
+
+	#[async_trait]
+	impl EventsLoader for ExampleLoader {
+		async fn load_events(
+			&self,
+			_ceramic: &Ceramic,
+			_stream_id: &StreamId,
+			_tip: Option<ceramic_core::Cid>,
+		) -> anyhow::Result<Vec<Event>> {
+			Ok(ceramic::commit::example::events(2))
+		}
+	}
+
+	/// Rejects the data of every genesis of a stream with a model
+	struct RejectGenesis;
+
+	#[async_trait]
+	impl Policy for RejectGenesis {
+		async fn effect_at(&self, state: &StreamState) -> anyhow::Result<bool> {
+			Ok(state.model()?.is_some())
+		}
+
+		async fn validate_data(&self, _state: &StreamState, _data: Value) -> anyhow::Result<()> {
+			anyhow::bail!("genesis rejected")
+		}
+	}
+
+	#[test]
+	fn test_replay_effect_at_genesis() -> anyhow::Result<()> {
+		let ceramic = Ceramic {
+			endpoint: "http://localhost:7007".to_string(),
+			network: ceramic::network::Network::InMemory,
+			api: Default::default(),
+		};
+		let stream_id = ceramic::commit::example::genesis().stream_id()?;
+		let loaded = async_std::task::block_on(ExampleLoader.load_stream_with_policies(
+			&ceramic,
+			&stream_id,
+			vec![Box::new(RejectGenesis)],
+		));
+		assert!(loaded.is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_model_fields_policy() -> anyhow::Result<()> {
+		let model_id: StreamId =
+			"kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju".parse()?;
+		let definition: ModelDefinition = serde_json::from_value(serde_json::json!({
+			"version": "2.0",
+			"name": "post",
+			"schema": {
+				"type": "object",
+				"properties": {
+					"text": { "type": "string" },
+					"author": { "type": "string" },
+					"createdAt": { "type": "string", "readOnly": true },
+				},
+			},
+			"accountRelation": { "type": "list" },
+			"immutableFields": ["author"],
+		}))?;
+		let policy: Box<dyn Policy> = Box::new(ModelFieldsPolicy::new(model_id, &definition));
+
+		let patch: Patch = serde_json::from_value(serde_json::json!([
+			{ "op": "replace", "path": "/text", "value": "edited" },
+		]))?;
+		async_std::task::block_on(policy.validate_patch(&Value::Null, patch))?;
+
+		for path in ["/author", "/createdAt"] {
+			let patch: Patch = serde_json::from_value(serde_json::json!([
+				{ "op": "replace", "path": path, "value": "changed" },
+			]))?;
+			let err =
+				async_std::task::block_on(policy.validate_patch(&Value::Null, patch)).unwrap_err();
+			let rejection = CommitRejection::from(&err);
+			assert_eq!(rejection.code, "IMMUTABLE_FIELD");
+			assert_eq!(rejection.field_path, Some(path[1..].to_string()));
+		}
+
+		// reading a locked field is fine, writing the root only if it keeps it
+		let data = serde_json::json!({ "text": "hello", "author": "alice" });
+		let patch: Patch = serde_json::from_value(serde_json::json!([
+			{ "op": "test", "path": "/author", "value": "alice" },
+			{ "op": "copy", "from": "/author", "path": "/text" },
+			{ "op": "replace", "path": "", "value": { "text": "edited", "author": "alice" } },
+		]))?;
+		async_std::task::block_on(policy.validate_patch(&data, patch))?;
+		for patch in [
+			serde_json::json!([{ "op": "replace", "path": "", "value": { "text": "edited" } }]),
+			serde_json::json!([{ "op": "move", "from": "/author", "path": "/text" }]),
+		] {
+			let patch: Patch = serde_json::from_value(patch)?;
+			let err = async_std::task::block_on(policy.validate_patch(&data, patch)).unwrap_err();
+			let rejection = CommitRejection::from(&err);
+			assert_eq!(rejection.code, "IMMUTABLE_FIELD");
+			assert_eq!(rejection.field_path, Some("author".to_string()));
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn test_schema_policy() -> anyhow::Result<()> {
+		let model_id: StreamId =
+			"kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju".parse()?;
+		let schema = serde_json::json!({
+			"type": "object",
+			"additionalProperties": false,
+			"properties": { "foo": { "type": "object", "additionalProperties": false } },
+		});
+		let policy: Box<dyn Policy> = Box::new(SchemaPolicy::new(model_id, schema));
+
+		let patch: Patch = serde_json::from_value(serde_json::json!([
+			{ "op": "add", "path": "/foo/bar/baz", "value": 1 },
+		]))?;
+		let err =
+			async_std::task::block_on(policy.validate_patch(&Value::Null, patch)).unwrap_err();
+		let rejection = CommitRejection::from(&err);
+		assert_eq!(rejection.code, "SCHEMA_VIOLATION");
+		assert_eq!(rejection.field_path, Some("foo.bar.baz".to_string()));
+		Ok(())
+	}
+}
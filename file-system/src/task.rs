@@ -1,7 +1,16 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use dataverse_ceramic::redact::redact_dsn;
+use dataverse_core::computa::{QueryJobHandler, QueryJobRunner};
+use dataverse_core::task::{ContextWorker, SyncContext, SyncStream};
 use fang::{AsyncQueue, AsyncWorkerPool};
 use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
 use postgres_openssl::MakeTlsConnector;
 
+use crate::file::revalidate::RevalidateHandler;
+use crate::file::Client;
+
 pub type Queue = AsyncQueue<MakeTlsConnector>;
 
 pub async fn new_queue(dsn: &str, max_pool_size: u32) -> anyhow::Result<Queue> {
@@ -14,7 +23,10 @@ pub async fn new_queue(dsn: &str, max_pool_size: u32) -> anyhow::Result<Queue> {
 	let mut builder = SslConnector::builder(SslMethod::tls())?;
 	builder.set_verify(SslVerifyMode::NONE);
 	let connector = MakeTlsConnector::new(builder.build());
-	queue.connect(connector).await?;
+	queue
+		.connect(connector)
+		.await
+		.with_context(|| format!("failed to connect task queue to {}", redact_dsn(dsn)))?;
 	Ok(queue)
 }
 
@@ -24,3 +36,33 @@ pub fn build_pool(queue: Queue, num: u32) -> AsyncWorkerPool<AsyncQueue<MakeTlsC
 		.queue(queue)
 		.build()
 }
+
+/// Contexts of the tasks the pool workers cannot run, each set one gets a
+/// `ContextWorker` of its own
+#[derive(Default)]
+pub struct TaskContexts {
+	pub sync: Option<Arc<SyncContext>>,
+	pub revalidate: Option<Arc<Client>>,
+	pub query_jobs: Option<Arc<QueryJobRunner>>,
+}
+
+/// Start the worker pool and the context workers, tasks queued without their
+/// context set fail with `missing_context`
+pub async fn start_workers(
+	queue: Queue,
+	num: u32,
+	contexts: TaskContexts,
+) -> AsyncWorkerPool<AsyncQueue<MakeTlsConnector>> {
+	if let Some(context) = contexts.sync {
+		ContextWorker::<SyncStream>::new(context).spawn(queue.clone());
+	}
+	if let Some(client) = contexts.revalidate {
+		ContextWorker::<RevalidateHandler>::new(client).spawn(queue.clone());
+	}
+	if let Some(runner) = contexts.query_jobs {
+		ContextWorker::<QueryJobHandler>::new(runner).spawn(queue.clone());
+	}
+	let mut pool = build_pool(queue, num);
+	pool.start().await;
+	pool
+}
@@ -0,0 +1,146 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use futures::TryStreamExt;
+use iroh_sync::{Author, AuthorId};
+use tokio::sync::RwLock;
+
+use crate::errors::IrohClientError;
+use crate::Client;
+
+/// Authors writing the entries of dapps, persisted as json next to the docs
+/// store. Dapps without an author write as the node author.
+pub struct DappAuthors {
+	path: PathBuf,
+	authors: RwLock<HashMap<uuid::Uuid, AuthorId>>,
+}
+
+impl DappAuthors {
+	pub async fn load(path: PathBuf) -> anyhow::Result<Self> {
+		let authors = match tokio::fs::read(&path).await {
+			Ok(content) => {
+				let entries: BTreeMap<String, String> = serde_json::from_slice(&content)?;
+				entries
+					.iter()
+					.map(|(dapp_id, author)| Ok((dapp_id.parse()?, AuthorId::from_str(author)?)))
+					.collect::<anyhow::Result<_>>()?
+			}
+			Err(err) if err.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+			Err(err) => return Err(err.into()),
+		};
+		Ok(Self {
+			path,
+			authors: RwLock::new(authors),
+		})
+	}
+
+	pub async fn get(&self, dapp_id: &uuid::Uuid) -> Option<AuthorId> {
+		self.authors.read().await.get(dapp_id).copied()
+	}
+
+	pub async fn list(&self) -> Vec<(uuid::Uuid, AuthorId)> {
+		let authors = self.authors.read().await;
+		authors.iter().map(|(k, v)| (*k, *v)).collect()
+	}
+
+	async fn set(
+		&self,
+		dapp_id: &uuid::Uuid,
+		author: Option<AuthorId>,
+	) -> anyhow::Result<Option<AuthorId>> {
+		let mut authors = self.authors.write().await;
+		let previous = match author {
+			Some(author) => authors.insert(*dapp_id, author),
+			None => authors.remove(dapp_id),
+		};
+		let entries: BTreeMap<String, String> = authors
+			.iter()
+			.map(|(k, v)| (k.to_string(), v.to_string()))
+			.collect();
+		let tmp = self.path.with_extension("tmp");
+		tokio::fs::write(&tmp, serde_json::to_vec_pretty(&entries)?).await?;
+		tokio::fs::rename(&tmp, &self.path).await?;
+		Ok(previous)
+	}
+}
+
+impl Client {
+	/// Create an author on the node, to be assigned to a dapp
+	pub async fn create_author(&self) -> anyhow::Result<AuthorId> {
+		self.iroh.authors.create().await
+	}
+
+	/// Import an author from its secret, e.g. moved over from another node
+	pub async fn import_author(&self, secret: &str) -> anyhow::Result<AuthorId> {
+		let author = Author::from_str(secret)?;
+		self.iroh.authors.import(author.clone()).await?;
+		Ok(author.id())
+	}
+
+	/// Authors the node holds secrets of, and thus can write as
+	pub async fn list_authors(&self) -> anyhow::Result<Vec<AuthorId>> {
+		self.iroh.authors.list().await?.try_collect().await
+	}
+
+	/// Write the streams of the dapp as `author` from now on, entries written
+	/// before keep their author
+	pub async fn assign_author(
+		&self,
+		dapp_id: &uuid::Uuid,
+		author: AuthorId,
+	) -> anyhow::Result<()> {
+		if !self.list_authors().await?.contains(&author) {
+			anyhow::bail!(IrohClientError::AuthorNotFound(author));
+		}
+		self.authors.set(dapp_id, Some(author)).await?;
+		tracing::info!(
+			dapp_id = dapp_id.to_string(),
+			author = author.to_string(),
+			"assigned author"
+		);
+		Ok(())
+	}
+
+	/// Stop writing as the author of the dapp, falling back to the node
+	/// author. Docs and the entries the author wrote are kept.
+	pub async fn revoke_author(&self, dapp_id: &uuid::Uuid) -> anyhow::Result<Option<AuthorId>> {
+		let revoked = self.authors.set(dapp_id, None).await?;
+		if let Some(author) = revoked {
+			tracing::info!(
+				dapp_id = dapp_id.to_string(),
+				author = author.to_string(),
+				"revoked author"
+			);
+		}
+		Ok(revoked)
+	}
+
+	/// Author writing the entries of the dapp
+	pub async fn author_of(&self, dapp_id: &uuid::Uuid) -> AuthorId {
+		self.authors.get(dapp_id).await.unwrap_or(self.author)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn test_dapp_authors_persisted() -> anyhow::Result<()> {
+		let temp = tempfile::tempdir()?;
+		let path = temp.path().join("authors.json");
+		let dapp_id = uuid::Uuid::new_v4();
+		let author = Author::from_str("q7eqbabgzwhu6be7xiy67jkajevrawb32cauytinv6aw4szlozka")?;
+
+		let authors = DappAuthors::load(path.clone()).await?;
+		assert_eq!(authors.get(&dapp_id).await, None);
+		authors.set(&dapp_id, Some(author.id())).await?;
+
+		let authors = DappAuthors::load(path.clone()).await?;
+		assert_eq!(authors.get(&dapp_id).await, Some(author.id()));
+		assert_eq!(authors.set(&dapp_id, None).await?, Some(author.id()));
+		assert!(DappAuthors::load(path).await?.list().await.is_empty());
+		Ok(())
+	}
+}
@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+
+use futures::TryStreamExt;
+use iroh::client::Entry;
+use iroh_sync::store::Query;
+
+use crate::Client;
+
+/// Entries of the docs before a compaction, and the ones holding content
+/// after it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompactionStats {
+	pub docs: usize,
+	pub entries_before: usize,
+	pub entries_after: usize,
+}
+
+impl CompactionStats {
+	pub fn reclaimed(&self) -> usize {
+		self.entries_before - self.entries_after
+	}
+}
+
+/// Entries holding content superseded by a later entry of their key, e.g.
+/// by another author or a deletion marker
+fn superseded(entries: &[Entry]) -> Vec<&Entry> {
+	let mut latest: HashMap<&[u8], &Entry> = HashMap::new();
+	for entry in entries {
+		match latest.get(entry.key()) {
+			Some(current) if current.timestamp() >= entry.timestamp() => {}
+			_ => {
+				latest.insert(entry.key(), entry);
+			}
+		}
+	}
+	entries
+		.iter()
+		.filter(|entry| entry.content_len() > 0)
+		.filter(|entry| latest[entry.key()].author() != entry.author())
+		.collect()
+}
+
+impl Client {
+	/// Compact the doc of every model in place, deleting the superseded
+	/// entries written by authors of this node.
+	///
+	/// The doc keeps its namespace so peers keep syncing it through the same
+	/// ticket, and receive the deletions as they sync. Entries of remote
+	/// authors are kept since the node cannot write as them. The streams and
+	/// model index docs are shared namespaces and kept as is, blobs of the
+	/// deleted entries are reclaimed by the garbage collection of the bao
+	/// store, enabled by creating the client with `Client::new_with_gc`.
+	pub async fn compact(&self) -> anyhow::Result<CompactionStats> {
+		let mut stats = CompactionStats::default();
+		let authors = self.list_authors().await?;
+		for model_id in self.list_models().await? {
+			let doc = self.lookup_model_doc(&model_id).await?;
+			let entries: Vec<Entry> = doc.get_many(Query::all()).await?.try_collect().await?;
+			let deletable: Vec<&Entry> = superseded(&entries)
+				.into_iter()
+				.filter(|entry| authors.contains(&entry.author()))
+				.collect();
+			let with_content = entries.iter().filter(|x| x.content_len() > 0).count();
+			stats.docs += 1;
+			stats.entries_before += entries.len();
+			stats.entries_after += with_content - deletable.len();
+			if deletable.is_empty() {
+				continue;
+			}
+
+			for entry in &deletable {
+				// keys are stream ids, no other key of the author starts with it
+				doc.del(entry.author(), entry.key().to_vec()).await?;
+			}
+			tracing::info!(
+				model_id = model_id.to_string(),
+				before = entries.len(),
+				deleted = deletable.len(),
+				"compacted model doc"
+			);
+		}
+		Ok(stats)
+	}
+}
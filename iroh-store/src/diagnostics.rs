@@ -0,0 +1,40 @@
+use dataverse_ceramic::diagnostics::Probe;
+
+use crate::Client;
+
+/// Writes an entry into a fresh doc, reads it back and drops the doc
+#[async_trait::async_trait]
+impl Probe for Client {
+	fn name(&self) -> &str {
+		"iroh"
+	}
+
+	async fn probe(&self) -> anyhow::Result<String> {
+		let doc = self.iroh.docs.create().await?;
+		let namespace_id = doc.id();
+		let written = async {
+			let key = b"smoke-test".to_vec();
+			let value = std::time::SystemTime::now()
+				.duration_since(std::time::UNIX_EPOCH)?
+				.as_nanos()
+				.to_string()
+				.into_bytes();
+			doc.set_bytes(self.author, key.clone(), value.clone())
+				.await?;
+			let content = match doc.get_exact(self.author, key, false).await? {
+				Some(entry) => entry.content_bytes(&self.iroh).await?,
+				None => anyhow::bail!("entry missing from doc {}", namespace_id),
+			};
+			if content.as_ref() != value.as_slice() {
+				anyhow::bail!("entry of doc {} differs from written", namespace_id);
+			}
+			Ok(())
+		}
+		.await;
+		// the doc is dropped whether the probe passed or not
+		let dropped = self.iroh.docs.drop_doc(namespace_id).await;
+		written?;
+		dropped?;
+		Ok(format!("created and read doc {}", namespace_id))
+	}
+}
@@ -1,4 +1,5 @@
 use ceramic_core::StreamId;
+use iroh_sync::AuthorId;
 use std::path::PathBuf;
 
 #[derive(Debug)]
@@ -7,6 +8,7 @@ pub enum IrohClientError {
 	StreamNotInModel(StreamId, StreamId),
 	TaskLoadingFailed(PathBuf),
 	StreamNotFound(StreamId),
+	AuthorNotFound(AuthorId),
 }
 
 impl std::fmt::Display for IrohClientError {
@@ -24,6 +26,7 @@ impl std::fmt::Display for IrohClientError {
 				data_path.display()
 			),
 			Self::StreamNotFound(stream_id) => write!(f, "stream not found: {}", stream_id),
+			Self::AuthorNotFound(author) => write!(f, "author {} not found on node", author),
 		}
 	}
 }
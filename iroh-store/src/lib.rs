@@ -1,52 +1,68 @@
+pub mod author;
+pub mod compact;
+pub mod diagnostics;
 mod errors;
 pub mod file;
+pub mod task;
 
+use std::collections::BTreeMap;
 use std::sync::Arc;
+use std::time::Duration;
 use std::{path::PathBuf, str::FromStr};
 
 use anyhow::Context;
 use ceramic_core::{Cid, StreamId};
+use dataverse_ceramic::redact::Secret;
+use dataverse_ceramic::stream::operator::Reservoir;
 use dataverse_ceramic::stream::StreamState;
-use dataverse_ceramic::{kubo, Ceramic, StreamLoader, StreamOperator, StreamsLoader};
+use dataverse_ceramic::{kubo, Ceramic, StatesPage, StreamLoader, StreamOperator, StreamsLoader};
 use dataverse_core::stream::{Stream, StreamStore};
 use futures::TryStreamExt;
 use iroh::client::mem::{Doc, Iroh};
+use iroh::client::Entry;
 pub use iroh::net::key::SecretKey;
-use iroh::node::Node;
+use iroh::node::{GcPolicy, Node};
 use iroh::rpc_protocol::DocTicket;
 use iroh_bytes::store::flat::Store as BaoFileStore;
 use iroh_sync::store::{Query, Store};
 use iroh_sync::{Author, AuthorId, NamespaceId, NamespacePublicKey, NamespaceSecret};
 
+use crate::author::DappAuthors;
 use crate::errors::IrohClientError;
 
 pub struct Client {
 	pub iroh: Iroh,
 	pub operator: Arc<dyn StreamOperator>,
+	/// node author, writing the entries of dapps without an assigned author
 	pub author: AuthorId,
+	pub authors: DappAuthors,
 	pub streams: Doc,
 	pub model: Doc,
 }
 
+/// Secret key of the node author and write secrets of the namespaces
 pub struct KeySet {
-	pub author: String,
+	pub author: Secret<String>,
 
-	pub model: String,
-	pub streams: String,
+	pub model: Secret<String>,
+	pub streams: Secret<String>,
 }
 
 impl KeySet {
 	pub fn new(author: &str, model: &str, streams: &str) -> Self {
 		Self {
-			author: author.to_string(),
-			model: model.to_string(),
-			streams: streams.to_string(),
+			author: Secret::new(author.to_string()),
+			model: Secret::new(model.to_string()),
+			streams: Secret::new(streams.to_string()),
 		}
 	}
 }
 
 pub const DEFAULT_RPC_PORT: u16 = 0x1337;
 
+/// Interval of reclaiming blobs no longer referenced by docs, e.g. after `compact`
+pub const GC_INTERVAL: Duration = Duration::from_secs(600);
+
 impl Client {
 	pub async fn new(
 		data_path: PathBuf,
@@ -54,7 +70,20 @@ impl Client {
 		key_set: KeySet,
 		operator: Arc<dyn StreamOperator>,
 	) -> anyhow::Result<Self> {
+		Self::new_with_gc(data_path, key, key_set, operator, GcPolicy::Disabled).await
+	}
 
+	/// Client whose node reclaims unreferenced blobs by the policy, e.g.
+	/// `GcPolicy::Interval(GC_INTERVAL)` for a node running `compact`. Blobs
+	/// are kept by default, as other users of the bao store may still need
+	/// blobs no doc references
+	pub async fn new_with_gc(
+		data_path: PathBuf,
+		key: SecretKey,
+		key_set: KeySet,
+		operator: Arc<dyn StreamOperator>,
+		gc_policy: GcPolicy,
+	) -> anyhow::Result<Self> {
 		let bao_path = data_path.join("iroh/bao");
 		let bao_store = BaoFileStore::load(&bao_path)
 			.await
@@ -63,19 +92,21 @@ impl Client {
 		let path = data_path.join("iroh/docs.redb");
 		let doc_store = iroh_sync::store::fs::Store::new(path)?;
 
-		let author: Author = Author::from_str(&key_set.author)?;
+		let author: Author = Author::from_str(key_set.author.expose())?;
 		doc_store.import_author(author.clone())?;
 
 		let node = Node::builder(bao_store, doc_store)
 			.secret_key(key)
+			.gc_policy(gc_policy)
 			.spawn()
 			.await?;
 		let client: Iroh = node.client();
 
 		Ok(Self {
 			author: author.id(),
-			streams: Client::init_store(&client, &key_set.streams).await?,
-			model: Client::init_store(&client, &key_set.model).await?,
+			authors: DappAuthors::load(data_path.join("iroh/authors.json")).await?,
+			streams: Client::init_store(&client, key_set.streams.expose()).await?,
+			model: Client::init_store(&client, key_set.model.expose()).await?,
 			iroh: client,
 			operator,
 		})
@@ -114,15 +145,24 @@ impl Client {
 		model_id: &StreamId,
 	) -> anyhow::Result<Option<NamespaceId>> {
 		let mut stream = self.streams.get_many(Query::all()).await?;
+		let mut latest: Option<iroh::client::Entry> = None;
 		while let Some(entry) = stream.try_next().await? {
 			if entry.key() == model_id.to_string().as_bytes().to_vec() {
-
+				// the latest entry wins if several authors set the model
+				match &latest {
+					Some(x) if x.timestamp() >= entry.timestamp() => {}
+					_ => latest = Some(entry),
+				}
+			}
+		}
+		match latest {
+			Some(entry) => {
 				let content = entry.content_bytes(&self.iroh).await?;
 				let key = NamespacePublicKey::from_bytes(content.as_ref().try_into()?)?;
-				return Ok(Some(NamespaceId::from(key)));
+				Ok(Some(NamespaceId::from(key)))
 			}
+			None => Ok(None),
 		}
-		Ok(None)
 	}
 
 	async fn lookup_model_doc(&self, model_id: &StreamId) -> anyhow::Result<Doc> {
@@ -167,8 +207,12 @@ impl Client {
 	) -> anyhow::Result<Stream> {
 		let doc = &self.lookup_model_doc(model_id).await?;
 		let key = stream_id.to_vec()?;
-		let mut stream = doc.get_many(Query::key_exact(key)).await?;
-		if let Some(entry) = stream.try_next().await? {
+		let entries: Vec<Entry> = doc
+			.get_many(Query::key_exact(key))
+			.await?
+			.try_collect()
+			.await?;
+		if let Some(entry) = latest_per_key(entries).first() {
 			let content = entry.content_bytes(&self.iroh).await?;
 			let content: Stream = serde_json::from_slice(&content)?;
 			return Ok(content);
@@ -181,9 +225,9 @@ impl Client {
 
 	async fn list_stream_in_model(&self, model_id: &StreamId) -> anyhow::Result<Vec<Stream>> {
 		let doc: Doc = self.lookup_model_doc(model_id).await?;
-		let mut stream = doc.get_many(Query::all()).await?;
+		let entries: Vec<Entry> = doc.get_many(Query::all()).await?.try_collect().await?;
 		let mut result = Vec::new();
-		while let Some(entry) = stream.try_next().await? {
+		for entry in latest_per_key(entries) {
 			let content = entry.content_bytes(&self.iroh).await?;
 			result.push(serde_json::from_slice(&content)?);
 		}
@@ -191,6 +235,25 @@ impl Client {
 	}
 }
 
+/// Latest entry of every key, in the order of the keys. Authors of several
+/// dapps may write the same stream, the older entries of the key are
+/// superseded and empty entries mark a deleted key
+fn latest_per_key(entries: Vec<Entry>) -> Vec<Entry> {
+	let mut latest: BTreeMap<Vec<u8>, Entry> = BTreeMap::new();
+	for entry in entries {
+		match latest.get(entry.key()) {
+			Some(current) if current.timestamp() >= entry.timestamp() => {}
+			_ => {
+				latest.insert(entry.key().to_vec(), entry);
+			}
+		}
+	}
+	latest
+		.into_values()
+		.filter(|entry| entry.content_len() > 0)
+		.collect()
+}
+
 #[async_trait::async_trait]
 impl StreamStore for Client {
 	async fn list_all_streams(&self) -> anyhow::Result<Vec<Stream>> {
@@ -213,9 +276,10 @@ impl StreamStore for Client {
 		match &stream.model {
 			Some(model) => {
 				self.set_model_of_stream(&stream_id, model).await?;
+				let author = self.author_of(&stream.dapp_id).await;
 				self.lookup_model_doc(model)
 					.await?
-					.set_bytes(self.author, key, value)
+					.set_bytes(author, key, value)
 					.await?;
 			}
 			_ => todo!("save stream without model"),
@@ -247,8 +311,61 @@ impl StreamsLoader for Client {
 		account: Option<String>,
 		model_id: &StreamId,
 	) -> anyhow::Result<Vec<StreamState>> {
-		let mut result = Vec::new();
 		let streams = self.list_stream_in_model(model_id).await?;
+		let mut result = self.load_states_of_streams(ceramic, streams).await?;
+		if let Some(account) = account {
+			result.retain(|state| state.controllers().contains(&account));
+		}
+		Ok(result)
+	}
+
+	/// Ordered and paged by the stored streams, only the states of the page
+	/// are loaded
+	async fn load_stream_states_page(
+		&self,
+		ceramic: &Ceramic,
+		account: Option<String>,
+		model_id: &StreamId,
+		page: StatesPage,
+	) -> anyhow::Result<Vec<StreamState>> {
+		let mut streams = self.list_stream_in_model(model_id).await?;
+		if let Some(account) = account {
+			streams.retain(|stream| stream.account.as_ref() == Some(&account));
+		}
+		streams.sort_by_key(|stream| std::cmp::Reverse(stream.sort_time(page.sort_by)));
+		let streams = streams
+			.into_iter()
+			.skip(page.offset)
+			.take(page.limit)
+			.collect();
+		self.load_states_of_streams(ceramic, streams).await
+	}
+
+	/// Reservoir sample of the stored streams, only the states of the sample
+	/// are loaded
+	async fn sample(
+		&self,
+		ceramic: &Ceramic,
+		model_id: &StreamId,
+		n: usize,
+		seed: u64,
+	) -> anyhow::Result<Vec<StreamState>> {
+		let mut reservoir = Reservoir::new(n, seed);
+		for stream in self.list_stream_in_model(model_id).await? {
+			reservoir.push(stream);
+		}
+		self.load_states_of_streams(ceramic, reservoir.into_items())
+			.await
+	}
+}
+
+impl Client {
+	async fn load_states_of_streams(
+		&self,
+		ceramic: &Ceramic,
+		streams: Vec<Stream>,
+	) -> anyhow::Result<Vec<StreamState>> {
+		let mut result = Vec::new();
 		for stream in streams {
 			let (stream_id, tip) = (stream.stream_id()?, Some(stream.tip));
 			let state = self
@@ -257,9 +374,6 @@ impl StreamsLoader for Client {
 				.await?;
 			result.push(state);
 		}
-		if let Some(account) = account {
-			result.retain(|state| state.controllers().contains(&account));
-		}
 		Ok(result)
 	}
 }
@@ -289,41 +403,31 @@ impl StreamLoader for Client {
 }
 
 #[async_trait::async_trait]
-impl kubo::Store for Client {
-	async fn get(
-		&self,
-		_id: Option<String>,
-		stream_id: Option<StreamId>,
-	) -> anyhow::Result<Option<Cid>> {
-		if let Some(stream_id) = &stream_id {
-			if let Ok(model_id) = self.get_model_of_stream(stream_id).await {
-				let stream = self.load_stream_with_model(&model_id, stream_id).await?;
-				return Ok(Some(stream.tip));
-			}
-
-			return self
-				.load_stream(stream_id)
-				.await
-				.map(|stream| stream.map(|s| s.tip));
+impl kubo::TipStore for Client {
+	async fn get_tip(&self, stream_id: &StreamId) -> anyhow::Result<Option<Cid>> {
+		if let Ok(model_id) = self.get_model_of_stream(stream_id).await {
+			let stream = self.load_stream_with_model(&model_id, stream_id).await?;
+			return Ok(Some(stream.tip));
 		}
-		Ok(None)
+
+		self.load_stream(stream_id)
+			.await
+			.map(|stream| stream.map(|s| s.tip))
 	}
 
-	async fn push(
-		&self,
-		_id: Option<String>,
-		stream_id: Option<StreamId>,
-		tip: Cid,
-	) -> anyhow::Result<()> {
-		if let Some(stream_id) = &stream_id {
-			if let Some(mut stream) = self.load_stream(stream_id).await? {
-				//TODO: load events and check if the input tip is older than the current tip
-				stream.tip = tip;
-				return self.save_stream(&stream).await;
-			}
+	async fn set_tip(&self, stream_id: &StreamId, tip: Cid) -> anyhow::Result<()> {
+		if let Some(mut stream) = self.load_stream(stream_id).await? {
+			//TODO: load events and check if the input tip is older than the current tip
+			stream.tip = tip;
+			return self.save_stream(&stream).await;
 		}
 		Ok(())
 	}
+
+	async fn stream_ids(&self) -> anyhow::Result<Vec<StreamId>> {
+		let streams = self.list_all_streams().await?;
+		streams.iter().map(|stream| stream.stream_id()).collect()
+	}
 }
 
 #[cfg(test)]
@@ -336,11 +440,11 @@ mod tests {
 		let key = SecretKey::from_str("vprfpdhssy5erwum2ql2sijgpr4rpeq4zacwcjrbxfmxmwtgeo3a")?;
 		let temp = tempfile::tempdir()?;
 		println!("temp dir: {:?}", temp);
-		let key_set = KeySet {
-			author: "q7eqbabgzwhu6be7xiy67jkajevrawb32cauytinv6aw4szlozka".to_string(),
-			model: "lmnjsx6pmazhkr5ixhhtaw365pcengpawe36yhczcw6qrz2xxqzq".to_string(),
-			streams: "ckuuo72r7skny5qy6njecmbgbix6ifn5wxg5sakqfvsamjsiohqq".to_string(),
-		};
+		let key_set = KeySet::new(
+			"q7eqbabgzwhu6be7xiy67jkajevrawb32cauytinv6aw4szlozka",
+			"lmnjsx6pmazhkr5ixhhtaw365pcengpawe36yhczcw6qrz2xxqzq",
+			"ckuuo72r7skny5qy6njecmbgbix6ifn5wxg5sakqfvsamjsiohqq",
+		);
 		let kubo_path = "http://localhost:5001";
 		let kubo = kubo::new(kubo_path);
 		let kubo = Arc::new(kubo);
@@ -403,4 +507,119 @@ mod tests {
 		assert_eq!(streams.unwrap().len(), 1);
 		Ok(())
 	}
+
+	#[tokio::test]
+	async fn latest_stream_across_authors() -> anyhow::Result<()> {
+		let client = init_client().await?;
+
+		let genesis = dataverse_ceramic::commit::example::genesis();
+		let commit: Event = genesis.genesis.try_into()?;
+		let state = StreamState::make(genesis.r#type, vec![commit.clone()]).await?;
+		let stream = Stream::new(
+			&uuid::Uuid::new_v4(),
+			genesis.r#type,
+			&commit,
+			state.must_model().ok(),
+		)?;
+		client.save_stream(&stream).await?;
+
+		// the same stream written later by the author of another dapp
+		let dapp_id = uuid::Uuid::new_v4();
+		let author = client.create_author().await?;
+		client.assign_author(&dapp_id, author).await?;
+		let data = dataverse_ceramic::commit::example::data();
+		let commit: Event = data.commit.try_into()?;
+		let updated = Stream {
+			dapp_id,
+			tip: commit.cid,
+			..stream.clone()
+		};
+		client.save_stream(&updated).await?;
+
+		let stream_id = stream.stream_id()?;
+		let loaded = client.load_stream(&stream_id).await?;
+		assert_eq!(loaded.map(|x| x.tip), Some(updated.tip));
+		let streams = client.list_stream_in_model(&state.must_model()?).await?;
+		assert_eq!(streams.len(), 1);
+		assert_eq!(streams[0].tip, updated.tip);
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn compact_docs() -> anyhow::Result<()> {
+		let client = init_client().await?;
+
+		let genesis = dataverse_ceramic::commit::example::genesis();
+		let commit: Event = genesis.genesis.try_into()?;
+		let state = StreamState::make(genesis.r#type, vec![commit.clone()]).await?;
+		let mut stream = Stream::new(
+			&uuid::Uuid::new_v4(),
+			genesis.r#type,
+			&commit,
+			state.must_model().ok(),
+		)?;
+		client.save_stream(&stream).await?;
+
+		let data = dataverse_ceramic::commit::example::data();
+		let commit: Event = data.commit.try_into()?;
+		stream.tip = commit.cid;
+		client.save_stream(&stream).await?;
+
+		let model_id = state.must_model()?;
+		let doc_id = client.lookup_model_doc(&model_id).await?.id();
+		let stats = client.compact().await?;
+		assert_eq!(stats.docs, 1);
+		assert_eq!(stats.entries_after, 1);
+		// compacted in place, peers keep syncing the same namespace
+		assert_eq!(client.lookup_model_doc(&model_id).await?.id(), doc_id);
+
+		let stream_id = stream.stream_id()?;
+		let loaded = client.load_stream(&stream_id).await?;
+		assert_eq!(loaded.map(|x| x.tip), Some(stream.tip));
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn compact_docs_across_authors() -> anyhow::Result<()> {
+		let client = init_client().await?;
+
+		let genesis = dataverse_ceramic::commit::example::genesis();
+		let commit: Event = genesis.genesis.try_into()?;
+		let state = StreamState::make(genesis.r#type, vec![commit.clone()]).await?;
+		let stream = Stream::new(
+			&uuid::Uuid::new_v4(),
+			genesis.r#type,
+			&commit,
+			state.must_model().ok(),
+		)?;
+		client.save_stream(&stream).await?;
+
+		// the entry of the node author is superseded by the one of a dapp author
+		let dapp_id = uuid::Uuid::new_v4();
+		let author = client.create_author().await?;
+		client.assign_author(&dapp_id, author).await?;
+		let data = dataverse_ceramic::commit::example::data();
+		let commit: Event = data.commit.try_into()?;
+		let updated = Stream {
+			dapp_id,
+			tip: commit.cid,
+			..stream.clone()
+		};
+		client.save_stream(&updated).await?;
+
+		let stats = client.compact().await?;
+		assert_eq!(stats.docs, 1);
+		assert_eq!(stats.entries_before, 2);
+		assert_eq!(stats.entries_after, 1);
+		// nothing left to compact
+		assert_eq!(client.compact().await?.reclaimed(), 0);
+
+		let stream_id = stream.stream_id()?;
+		let loaded = client.load_stream(&stream_id).await?;
+		assert_eq!(loaded.map(|x| x.tip), Some(updated.tip));
+		let streams = client.list_stream_in_model(&state.must_model()?).await?;
+		assert_eq!(streams.len(), 1);
+		assert_eq!(streams[0].dapp_id, dapp_id);
+		Ok(())
+	}
 }
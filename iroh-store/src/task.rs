@@ -0,0 +1,66 @@
+use std::sync::{Arc, OnceLock};
+
+use fang::async_trait;
+use fang::asynk::async_queue::AsyncQueueable;
+use fang::serde::{Deserialize, Serialize};
+use fang::typetag;
+use fang::AsyncRunnable;
+use fang::FangError;
+use fang::Scheduled;
+
+use crate::Client;
+
+static IROH: OnceLock<Arc<Client>> = OnceLock::new();
+
+pub fn init_iroh(client: Arc<Client>) {
+	IROH.get_or_init(|| client);
+}
+
+async fn get_iroh() -> Result<&'static Arc<Client>, FangError> {
+	match IROH.get() {
+		Some(iroh) => Ok(iroh),
+		None => {
+			tracing::error!("Iroh client not initialized");
+			Err(FangError {
+				description: "Iroh client not initialized".to_string(),
+			})
+		}
+	}
+}
+
+/// Compact the docs of the iroh store, repeated by the cron pattern if set,
+/// e.g. `0 0 3 * * *` to run daily
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "fang::serde")]
+pub struct CompactHandler {
+	pub cron_pattern: Option<String>,
+}
+
+#[async_trait]
+#[typetag::serde]
+impl AsyncRunnable for CompactHandler {
+	async fn run(&self, _queue: &mut dyn AsyncQueueable) -> Result<(), FangError> {
+		let iroh = get_iroh().await?;
+
+		match iroh.compact().await {
+			Ok(stats) => {
+				tracing::info!(?stats, "compacting iroh docs");
+				Ok(())
+			}
+			Err(err) => {
+				tracing::warn!(?err, "compacting iroh docs");
+				Err(FangError {
+					description: format!("Failed to compact iroh docs: {:?}", err),
+				})
+			}
+		}
+	}
+
+	fn cron(&self) -> Option<Scheduled> {
+		self.cron_pattern.clone().map(Scheduled::CronPattern)
+	}
+
+	fn uniq(&self) -> bool {
+		true
+	}
+}
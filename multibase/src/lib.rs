@@ -0,0 +1,213 @@
+//! Multibase strings of cids and stream ids shared by the dataverse crates.
+//!
+//! Each type holds a string in a single base, checked when parsed, so ids
+//! can be passed around in their encoded form and decoded when needed.
+
+use std::fmt;
+use std::str::FromStr;
+
+use ceramic_core::{Cid, StreamId};
+use multibase::Base;
+use serde::{Deserialize, Serialize};
+
+/// Lowercase base32, the string form of cids v1, as already used by the
+/// ceramic types
+pub use ceramic_core::MultiBase32String;
+
+#[derive(Debug)]
+pub enum Error {
+	Decode(multibase::Error),
+	UnexpectedBase { expected: Base, found: Base },
+	InvalidCid(String),
+	InvalidStreamId(String),
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Error::Decode(err) => write!(f, "invalid multibase: {}", err),
+			Error::UnexpectedBase { expected, found } => {
+				write!(f, "expected {:?} multibase, found {:?}", expected, found)
+			}
+			Error::InvalidCid(err) => write!(f, "invalid cid: {}", err),
+			Error::InvalidStreamId(err) => write!(f, "invalid stream id: {}", err),
+		}
+	}
+}
+
+impl std::error::Error for Error {}
+
+impl From<multibase::Error> for Error {
+	fn from(err: multibase::Error) -> Self {
+		Error::Decode(err)
+	}
+}
+
+macro_rules! multibase_string {
+	($(#[$meta:meta])* $name:ident, $base:expr) => {
+		$(#[$meta])*
+		#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+		#[serde(try_from = "String", into = "String")]
+		pub struct $name(String);
+
+		impl $name {
+			pub const BASE: Base = $base;
+
+			pub fn encode<T: AsRef<[u8]>>(bytes: T) -> Self {
+				Self(multibase::encode(Self::BASE, bytes))
+			}
+
+			pub fn decode(&self) -> Result<Vec<u8>, Error> {
+				let (_, bytes) = multibase::decode(&self.0)?;
+				Ok(bytes)
+			}
+
+			pub fn to_cid(&self) -> Result<Cid, Error> {
+				Cid::try_from(self.decode()?.as_slice())
+					.map_err(|err| Error::InvalidCid(err.to_string()))
+			}
+
+			pub fn to_stream_id(&self) -> Result<StreamId, Error> {
+				StreamId::try_from(self.decode()?.as_slice())
+					.map_err(|err| Error::InvalidStreamId(err.to_string()))
+			}
+		}
+
+		impl FromStr for $name {
+			type Err = Error;
+
+			fn from_str(s: &str) -> Result<Self, Self::Err> {
+				let (found, _) = multibase::decode(s)?;
+				if found != Self::BASE {
+					return Err(Error::UnexpectedBase {
+						expected: Self::BASE,
+						found,
+					});
+				}
+				Ok(Self(s.to_string()))
+			}
+		}
+
+		impl TryFrom<String> for $name {
+			type Error = Error;
+
+			fn try_from(value: String) -> Result<Self, Self::Error> {
+				value.parse()
+			}
+		}
+
+		impl From<$name> for String {
+			fn from(value: $name) -> Self {
+				value.0
+			}
+		}
+
+		impl AsRef<str> for $name {
+			fn as_ref(&self) -> &str {
+				&self.0
+			}
+		}
+
+		impl fmt::Display for $name {
+			fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+				write!(f, "{}", self.0)
+			}
+		}
+
+		impl From<&Cid> for $name {
+			fn from(value: &Cid) -> Self {
+				Self::encode(value.to_bytes())
+			}
+		}
+
+		impl From<Cid> for $name {
+			fn from(value: Cid) -> Self {
+				Self::from(&value)
+			}
+		}
+
+		impl TryFrom<&StreamId> for $name {
+			type Error = Error;
+
+			fn try_from(value: &StreamId) -> Result<Self, Self::Error> {
+				let bytes = value
+					.to_vec()
+					.map_err(|err| Error::InvalidStreamId(err.to_string()))?;
+				Ok(Self::encode(bytes))
+			}
+		}
+
+		impl TryFrom<StreamId> for $name {
+			type Error = Error;
+
+			fn try_from(value: StreamId) -> Result<Self, Self::Error> {
+				Self::try_from(&value)
+			}
+		}
+	};
+}
+
+multibase_string!(
+	/// Lowercase base36, the string form of stream ids and commit ids
+	MultiBase36String,
+	Base::Base36Lower
+);
+
+multibase_string!(
+	/// Unpadded base64url, e.g. kubo pubsub topics
+	MultiBase64UrlString,
+	Base::Base64Url
+);
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const STREAM_ID: &str = "kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju";
+	const CID: &str = "bagcqcerage6hnesjqdkhis6b52bb25rbex2wenp7zh5nvepl5cwundctinmq";
+
+	#[test]
+	fn test_stream_id() -> anyhow::Result<()> {
+		let stream_id = StreamId::from_str(STREAM_ID)?;
+		let encoded = MultiBase36String::try_from(&stream_id)?;
+		assert_eq!(encoded.as_ref(), STREAM_ID);
+		assert_eq!(encoded.to_stream_id()?, stream_id);
+		assert_eq!(STREAM_ID.parse::<MultiBase36String>()?, encoded);
+		Ok(())
+	}
+
+	#[test]
+	fn test_cid() -> anyhow::Result<()> {
+		let cid = Cid::from_str(CID)?;
+		let encoded = MultiBase32String::try_from(&cid)?;
+		assert_eq!(encoded.as_ref(), CID);
+
+		let encoded = MultiBase64UrlString::from(cid);
+		assert!(encoded.as_ref().starts_with('u'));
+		assert_eq!(encoded.to_cid()?, cid);
+		Ok(())
+	}
+
+	#[test]
+	fn test_unexpected_base() {
+		assert!(matches!(
+			CID.parse::<MultiBase36String>(),
+			Err(Error::UnexpectedBase {
+				expected: Base::Base36Lower,
+				found: Base::Base32Lower,
+			})
+		));
+		assert!("not multibase".parse::<MultiBase36String>().is_err());
+	}
+
+	#[test]
+	fn test_serde() -> anyhow::Result<()> {
+		let encoded: MultiBase36String = serde_json::from_str(&format!("\"{}\"", STREAM_ID))?;
+		assert_eq!(
+			serde_json::to_string(&encoded)?,
+			format!("\"{}\"", STREAM_ID)
+		);
+		assert!(serde_json::from_str::<MultiBase36String>(&format!("\"{}\"", CID)).is_err());
+		Ok(())
+	}
+}
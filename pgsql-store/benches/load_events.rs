@@ -0,0 +1,46 @@
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use dataverse_ceramic::commit::example;
+use dataverse_ceramic::{http, LogSelector};
+use dataverse_pgsql_store::Client;
+
+const LOG_LENGTHS: [usize; 3] = [10, 100, 1000];
+
+/// Requires a freshly migrated database at `DATABASE_URL`, skipped if not set.
+/// Synthetic logs share the example genesis, so lengths are benched in
+/// ascending order each extending the stored log.
+fn load_events_from_db(c: &mut Criterion) {
+	let dsn = match std::env::var("DATABASE_URL") {
+		Ok(dsn) => dsn,
+		Err(_) => {
+			eprintln!("DATABASE_URL not set, skip pgsql benches");
+			return;
+		}
+	};
+	let runtime = tokio::runtime::Runtime::new().unwrap();
+	let client = Client::new(Arc::new(http::Client::new()), &dsn, None).unwrap();
+	let stream_id = example::genesis().stream_id().unwrap();
+
+	let mut group = c.benchmark_group("load_events_from_db");
+	for len in LOG_LENGTHS {
+		let events = example::events(len);
+		let tip = events.last().map(|event| event.cid);
+		runtime
+			.block_on(client.save_events_to_db(&stream_id, events))
+			.unwrap();
+
+		group.bench_with_input(BenchmarkId::from_parameter(len), &tip, |b, tip| {
+			b.to_async(&runtime).iter(|| async {
+				client
+					.load_events_from_db(&stream_id, *tip, LogSelector::default())
+					.await
+					.unwrap()
+			})
+		});
+	}
+	group.finish();
+}
+
+criterion_group!(benches, load_events_from_db);
+criterion_main!(benches);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+alter table events
+    drop column created_at;
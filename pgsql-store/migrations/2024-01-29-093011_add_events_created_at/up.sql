@@ -0,0 +1,3 @@
+-- Your SQL goes here
+alter table events
+    add created_at timestamptz not null default now();
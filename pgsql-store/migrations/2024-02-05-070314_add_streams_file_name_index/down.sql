@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+drop index streams_file_name_idx;
+drop index streams_file_name_reverse_idx;
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+create index streams_file_name_idx
+    on streams ((content ->> 'fileName') text_pattern_ops);
+
+create index streams_file_name_reverse_idx
+    on streams (reverse(content ->> 'fileName') text_pattern_ops);
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+drop index streams_anchored_at_idx;
+
+alter table streams
+    drop column anchored_at;
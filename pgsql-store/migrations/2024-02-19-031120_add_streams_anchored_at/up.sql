@@ -0,0 +1,6 @@
+-- Your SQL goes here
+alter table streams
+    add anchored_at timestamptz;
+
+create index streams_anchored_at_idx
+    on streams (model_id, anchored_at);
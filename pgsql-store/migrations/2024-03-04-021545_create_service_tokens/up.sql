@@ -0,0 +1,13 @@
+-- Your SQL goes here
+create table service_tokens (
+    id uuid not null
+        constraint service_tokens_pk
+            primary key,
+    name varchar(100) not null,
+    token_hash char(64) not null
+        constraint service_tokens_hash_uk
+            unique,
+    scopes text[] not null,
+    created_at timestamptz not null default now(),
+    revoked_at timestamptz
+);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX streams_mirror_file_ids_idx;
+DROP TABLE folder_stats;
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+create table folder_stats (
+    folder_id varchar(70) not null
+        constraint folder_stats_pk
+            primary key,
+    content_folder_count int8 not null default 0,
+    file_count int8 not null default 0,
+    updated_at timestamptz not null default now()
+);
+
+create index streams_mirror_file_ids_idx on streams using gin ((content -> 'mirrorFileIds'));
@@ -0,0 +1,17 @@
+-- Your SQL goes here
+create table attestations (
+    tx_hash char(66) not null
+        constraint attestations_pk
+            primary key,
+    chain_id bigint not null,
+    contract char(42) not null,
+    stream_id varchar(70) not null,
+    commit_id varchar(70) not null,
+    status varchar(20) not null,
+    block_number bigint,
+    created_at timestamptz not null default now(),
+    updated_at timestamptz not null default now()
+);
+
+create index attestations_stream_id_commit_id_index
+    on attestations (stream_id, commit_id);
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+create table query_jobs (
+    query_id varchar(100) not null
+        constraint query_jobs_pk
+            primary key,
+    data bytea not null,
+    stream_ids text[] not null,
+    state varchar(20) not null,
+    tips text[] not null,
+    payload bytea,
+    journal bytea,
+    seal bytea,
+    tx_hash char(66),
+    attempts integer not null default 0,
+    error text,
+    created_at timestamptz not null default now(),
+    updated_at timestamptz not null default now()
+);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+drop index streams_model_id_account_idx;
+
+drop index events_genesis_idx;
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+create index events_genesis_idx
+    on events (genesis);
+
+create index streams_model_id_account_idx
+    on streams (model_id, account);
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+drop index streams_updated_at_idx;
+
+alter table streams
+    drop column created_at,
+    drop column updated_at;
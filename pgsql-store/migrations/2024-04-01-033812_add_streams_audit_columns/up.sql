@@ -0,0 +1,7 @@
+-- Your SQL goes here
+alter table streams
+    add created_at timestamptz not null default now(),
+    add updated_at timestamptz not null default now();
+
+create index streams_updated_at_idx
+    on streams (updated_at);
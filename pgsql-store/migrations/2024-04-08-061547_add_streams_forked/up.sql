@@ -0,0 +1,3 @@
+-- Your SQL goes here
+alter table streams
+    add forked boolean not null default false;
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+create table model_activity (
+    model_id varchar(70) not null,
+    hour timestamptz not null,
+    commits int8 not null default 0,
+    constraint model_activity_pk
+        primary key (model_id, hour)
+);
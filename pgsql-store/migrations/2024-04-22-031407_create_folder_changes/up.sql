@@ -0,0 +1,12 @@
+-- Your SQL goes here
+create table folder_changes (
+    seq bigserial not null
+        constraint folder_changes_pk
+            primary key,
+    folder_id varchar(70) not null,
+    stream_id varchar(70) not null,
+    tip varchar(70) not null,
+    created_at timestamptz not null default now()
+);
+
+create index folder_changes_folder_id_seq_idx on folder_changes (folder_id, seq);
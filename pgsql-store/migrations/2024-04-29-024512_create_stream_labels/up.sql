@@ -0,0 +1,10 @@
+-- Your SQL goes here
+create table stream_labels (
+    stream_id varchar(70) not null,
+    label varchar(64) not null,
+    created_at timestamptz not null default now(),
+    constraint stream_labels_pk
+        primary key (stream_id, label)
+);
+
+create index stream_labels_label_idx on stream_labels (label);
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+create table revalidation_reports (
+    model_id varchar(70) not null
+        constraint revalidation_reports_pk
+            primary key,
+    report jsonb not null,
+    updated_at timestamptz not null default now()
+);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX folder_changes_folder_id_stream_id_idx;
+ALTER TABLE folder_changes DROP COLUMN removed;
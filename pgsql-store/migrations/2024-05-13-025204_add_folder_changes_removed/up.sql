@@ -0,0 +1,5 @@
+-- Your SQL goes here
+alter table folder_changes
+    add removed boolean not null default false;
+
+create index folder_changes_folder_id_stream_id_idx on folder_changes (folder_id, stream_id, seq);
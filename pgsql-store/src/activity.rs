@@ -0,0 +1,145 @@
+use std::fmt::Write;
+
+use ceramic_core::StreamId;
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Text, Timestamptz};
+
+use crate::Client;
+
+/// Commits of the models bucketed by hour, one row per model and hour
+const RECORD_ACTIVITY: &str = "\
+	INSERT INTO model_activity (model_id, hour, commits) \
+	VALUES ($1, date_trunc('hour', now()), 1) \
+	ON CONFLICT (model_id, hour) DO UPDATE SET \
+	 commits = model_activity.commits + 1";
+
+const MODEL_ACTIVITY: &str = "\
+	SELECT model_id, COALESCE(SUM(commits), 0)::int8 AS commits FROM model_activity \
+	WHERE model_id = $1 AND hour >= date_trunc('hour', $2) GROUP BY model_id";
+
+const ALL_ACTIVITY: &str = "\
+	SELECT model_id, SUM(commits)::int8 AS commits FROM model_activity \
+	WHERE hour >= date_trunc('hour', $1) GROUP BY model_id ORDER BY model_id";
+
+const PRUNE_ACTIVITY: &str = "DELETE FROM model_activity WHERE hour < $1";
+
+#[derive(Debug, QueryableByName)]
+struct ActivityRow {
+	#[diesel(sql_type = Text)]
+	model_id: String,
+	#[diesel(sql_type = BigInt)]
+	commits: i64,
+}
+
+/// Commits of a model over a window, to spot runaway writers or abandoned
+/// models
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelActivity {
+	pub model_id: String,
+	pub window: Duration,
+	pub commits: i64,
+}
+
+impl ModelActivity {
+	/// Windows shorter than an hour count as an hour, the resolution of the
+	/// rollup
+	pub fn commits_per_hour(&self) -> f64 {
+		self.commits as f64 / self.window.num_hours().max(1) as f64
+	}
+}
+
+/// Commits per hour of every model in the prometheus text format
+pub fn render_prometheus(activities: &[ModelActivity]) -> String {
+	let mut out = String::new();
+	out.push_str("# HELP dataverse_model_commits_per_hour Commits per hour of the model\n");
+	out.push_str("# TYPE dataverse_model_commits_per_hour gauge\n");
+	for activity in activities {
+		let _ = writeln!(
+			out,
+			"dataverse_model_commits_per_hour{{model_id=\"{}\"}} {}",
+			activity.model_id,
+			activity.commits_per_hour()
+		);
+	}
+	out
+}
+
+impl Client {
+	/// Count a commit of the model in the bucket of the current hour
+	pub fn record_model_activity(&self, model_id: &str) -> anyhow::Result<()> {
+		let conn = &mut self.pool.get()?;
+		diesel::sql_query(RECORD_ACTIVITY)
+			.bind::<Text, _>(model_id)
+			.execute(conn)?;
+		Ok(())
+	}
+
+	pub fn model_activity(
+		&self,
+		model_id: &StreamId,
+		window: Duration,
+	) -> anyhow::Result<ModelActivity> {
+		let conn = &mut self.read_pool.get()?;
+		let row: Option<ActivityRow> = diesel::sql_query(MODEL_ACTIVITY)
+			.bind::<Text, _>(model_id.to_string())
+			.bind::<Timestamptz, _>(Utc::now() - window)
+			.get_result(conn)
+			.optional()?;
+		Ok(ModelActivity {
+			model_id: model_id.to_string(),
+			window,
+			commits: row.map_or(0, |row| row.commits),
+		})
+	}
+
+	/// Activity of every model written within the window
+	pub fn models_activity(&self, window: Duration) -> anyhow::Result<Vec<ModelActivity>> {
+		let conn = &mut self.read_pool.get()?;
+		let rows: Vec<ActivityRow> = diesel::sql_query(ALL_ACTIVITY)
+			.bind::<Timestamptz, _>(Utc::now() - window)
+			.load(conn)?;
+		Ok(rows
+			.into_iter()
+			.map(|row| ModelActivity {
+				model_id: row.model_id,
+				window,
+				commits: row.commits,
+			})
+			.collect())
+	}
+
+	/// Drop the buckets before `before`, returns the number of buckets removed
+	pub fn prune_model_activity(&self, before: DateTime<Utc>) -> anyhow::Result<usize> {
+		let conn = &mut self.pool.get()?;
+		Ok(diesel::sql_query(PRUNE_ACTIVITY)
+			.bind::<Timestamptz, _>(before)
+			.execute(conn)?)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_render_prometheus() {
+		let activity = ModelActivity {
+			model_id: "kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju".to_string(),
+			window: Duration::hours(4),
+			commits: 6,
+		};
+		assert_eq!(activity.commits_per_hour(), 1.5);
+		let short = ModelActivity {
+			window: Duration::minutes(10),
+			..activity.clone()
+		};
+		assert_eq!(short.commits_per_hour(), 6.0);
+
+		let out = render_prometheus(&[activity]);
+		assert!(out.contains("# TYPE dataverse_model_commits_per_hour gauge"));
+		assert!(out.ends_with(
+			"dataverse_model_commits_per_hour{model_id=\"kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju\"} 1.5\n"
+		));
+	}
+}
@@ -0,0 +1,50 @@
+use ceramic_core::{Cid, StreamId};
+use dataverse_ceramic::publisher::{AttestationRecord, AttestationStore};
+use diesel::prelude::*;
+use primitive_types::H256;
+
+use crate::{models, schema, Client};
+
+#[async_trait::async_trait]
+impl AttestationStore for Client {
+	async fn save_attestation(&self, record: &AttestationRecord) -> anyhow::Result<()> {
+		let conn = &mut self.pool.get()?;
+		let attestation = models::Attestation::from(record);
+		diesel::insert_into(schema::attestations::table)
+			.values(&attestation)
+			.on_conflict(schema::attestations::tx_hash)
+			.do_update()
+			.set(&attestation)
+			.execute(conn)?;
+		Ok(())
+	}
+
+	async fn load_attestation(&self, tx_hash: &H256) -> anyhow::Result<Option<AttestationRecord>> {
+		let conn = &mut self.pool.get()?;
+		let attestation: Option<models::Attestation> = schema::attestations::table
+			.find(format!("{:?}", tx_hash))
+			.select(models::Attestation::as_select())
+			.first(conn)
+			.optional()?;
+		attestation.map(TryInto::try_into).transpose()
+	}
+
+	async fn load_attestations(
+		&self,
+		stream_id: &StreamId,
+		commit: Option<&Cid>,
+	) -> anyhow::Result<Vec<AttestationRecord>> {
+		let conn = &mut self.read_pool.get()?;
+		let mut query = schema::attestations::table
+			.filter(schema::attestations::stream_id.eq(stream_id.to_string()))
+			.into_boxed();
+		if let Some(commit) = commit {
+			query = query.filter(schema::attestations::commit_id.eq(commit.to_string()));
+		}
+		let attestations: Vec<models::Attestation> = query
+			.order(schema::attestations::created_at.asc())
+			.select(models::Attestation::as_select())
+			.load(conn)?;
+		attestations.into_iter().map(TryInto::try_into).collect()
+	}
+}
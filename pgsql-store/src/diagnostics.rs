@@ -0,0 +1,36 @@
+use dataverse_ceramic::diagnostics::Probe;
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+
+use crate::Client;
+
+#[derive(Debug, QueryableByName)]
+struct SmokeRow {
+	#[diesel(sql_type = Text)]
+	value: String,
+}
+
+/// Inserts into and selects from a temporary table dropped with the transaction
+#[async_trait::async_trait]
+impl Probe for Client {
+	fn name(&self) -> &str {
+		"postgres"
+	}
+
+	async fn probe(&self) -> anyhow::Result<String> {
+		let conn = &mut self.pool.get()?;
+		let value = format!("smoke-{}", uuid::Uuid::new_v4());
+		let row: SmokeRow = conn.transaction(|conn| {
+			diesel::sql_query("CREATE TEMP TABLE smoke_test (value text) ON COMMIT DROP")
+				.execute(conn)?;
+			diesel::sql_query("INSERT INTO smoke_test (value) VALUES ($1)")
+				.bind::<Text, _>(&value)
+				.execute(conn)?;
+			diesel::sql_query("SELECT value FROM smoke_test").get_result(conn)
+		})?;
+		if row.value != value {
+			anyhow::bail!("selected {} instead of {}", row.value, value);
+		}
+		Ok("inserted and selected a row".to_string())
+	}
+}
@@ -24,6 +24,8 @@ pub enum PgSqlClientError {
 	MissingGenesis,
 	MissingEventForStream(Cid, StreamId),
 	DbExecError,
+	IntegrityConflict(Cid),
+	StreamPurged(StreamId),
 }
 
 impl std::fmt::Display for PgSqlClientError {
@@ -34,6 +36,10 @@ impl std::fmt::Display for PgSqlClientError {
 				write!(f, "missing event {} for stream {}", cid, stream_id)
 			}
 			Self::DbExecError => write!(f, "db exec error"),
+			Self::IntegrityConflict(cid) => {
+				write!(f, "event {} is stored with different content", cid)
+			}
+			Self::StreamPurged(stream_id) => write!(f, "stream {} is purged", stream_id),
 		}
 	}
 }
@@ -43,12 +49,16 @@ impl Error for PgSqlClientError {}
 #[derive(Debug)]
 pub enum PgSqlEventError {
 	UnsupportedCodecError(u64),
+	GenesisMismatch(Cid, StreamId),
 }
 
 impl std::fmt::Display for PgSqlEventError {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		match self {
 			Self::UnsupportedCodecError(codec) => write!(f, "unsupported codec {}", codec),
+			Self::GenesisMismatch(cid, stream_id) => {
+				write!(f, "event {} does not belong to stream {}", cid, stream_id)
+			}
 		}
 	}
 }
@@ -0,0 +1,225 @@
+use ceramic_core::StreamId;
+use chrono::{DateTime, Utc};
+use dataverse_file_system::file::folder_delta::{FolderChange, FolderChangeStore};
+use dataverse_file_system::file::folder_stats::{FolderStats, FolderStatsStore};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Bool, Text, Timestamptz};
+use serde_json::Value;
+
+use crate::{models, schema, Client};
+
+#[derive(Debug, QueryableByName)]
+struct FolderId {
+	#[diesel(sql_type = Text)]
+	folder_id: String,
+}
+
+#[derive(Debug, QueryableByName)]
+struct FolderChangeRow {
+	#[diesel(sql_type = BigInt)]
+	seq: i64,
+	#[diesel(sql_type = Text)]
+	stream_id: String,
+	#[diesel(sql_type = Text)]
+	tip: String,
+	#[diesel(sql_type = Timestamptz)]
+	created_at: DateTime<Utc>,
+	#[diesel(sql_type = Bool)]
+	removed: bool,
+}
+
+/// Index folders whose counters depend on the stream: the folder itself, the
+/// index folder of a content folder, or the index folders mirroring a file
+const AFFECTED_FOLDERS: &str = "\
+	SELECT stream_id AS folder_id FROM streams \
+	 WHERE stream_id = $1 AND content ? 'contentFolderIds' \
+	UNION SELECT content ->> 'indexFolderId' FROM streams \
+	 WHERE stream_id = $1 AND content ? 'indexFolderId' \
+	UNION SELECT content ->> 'indexFolderId' FROM streams \
+	 WHERE content -> 'mirrorFileIds' ? $1 AND content ? 'indexFolderId'";
+
+const REFRESH_FOLDER: &str = "\
+	INSERT INTO folder_stats (folder_id, content_folder_count, file_count, updated_at) \
+	SELECT folder.stream_id, \
+	 COALESCE(jsonb_array_length(folder.content -> 'contentFolderIds'), 0), \
+	 (SELECT COUNT(DISTINCT file.stream_id) FROM streams content_folder \
+	  JOIN streams file ON content_folder.content -> 'mirrorFileIds' ? file.stream_id \
+	  WHERE folder.content -> 'contentFolderIds' ? content_folder.stream_id \
+	  AND COALESCE((file.content ->> 'deleted')::boolean, false) = false), \
+	 now() \
+	FROM streams folder WHERE folder.stream_id = $1 \
+	ON CONFLICT (folder_id) DO UPDATE SET \
+	 content_folder_count = EXCLUDED.content_folder_count, \
+	 file_count = EXCLUDED.file_count, \
+	 updated_at = EXCLUDED.updated_at";
+
+const RECORD_CHANGE: &str = "\
+	INSERT INTO folder_changes (folder_id, stream_id, tip, removed) VALUES ($1, $2, $3, $4)";
+
+/// Serializes the transactions recording changes until they commit. A `seq`
+/// is taken from its sequence when inserted, not when committed: without the
+/// lock a reader could see a later `seq` before an earlier one commits and
+/// move its cursor past it for good.
+const LOCK_CHANGES: &str = "SELECT pg_advisory_xact_lock($1)";
+
+/// Key of the advisory lock of the change log
+const CHANGES_LOCK_KEY: i64 = 0x666f6c646572;
+
+/// Latest change of every stream of the folder after the sequence
+const FOLDER_CHANGES: &str = "\
+	SELECT seq, stream_id, tip, created_at, removed FROM ( \
+	 SELECT DISTINCT ON (stream_id) seq, stream_id, tip, created_at, removed FROM folder_changes \
+	 WHERE folder_id = $1 AND seq > $2 ORDER BY stream_id, seq DESC \
+	) latest ORDER BY seq";
+
+/// Changes older than `$1` superseded by a later change of the stream in the
+/// folder, a delta only returns the latest change of every stream
+const PRUNE_CHANGES: &str = "\
+	DELETE FROM folder_changes old USING folder_changes newer \
+	WHERE old.folder_id = newer.folder_id AND old.stream_id = newer.stream_id \
+	 AND old.seq < newer.seq AND old.created_at < $1";
+
+pub(crate) fn affected_folders(
+	conn: &mut PgConnection,
+	stream_id: &str,
+) -> anyhow::Result<Vec<String>> {
+	let folders: Vec<FolderId> = diesel::sql_query(AFFECTED_FOLDERS)
+		.bind::<Text, _>(stream_id)
+		.load(conn)?;
+	Ok(folders.into_iter().map(|x| x.folder_id).collect())
+}
+
+fn record_change(
+	conn: &mut PgConnection,
+	folder_id: &str,
+	stream_id: &str,
+	tip: &str,
+	removed: bool,
+) -> anyhow::Result<()> {
+	// held until the transaction ends, taking it again does not block
+	diesel::sql_query(LOCK_CHANGES)
+		.bind::<BigInt, _>(CHANGES_LOCK_KEY)
+		.execute(conn)?;
+	diesel::sql_query(RECORD_CHANGE)
+		.bind::<Text, _>(folder_id)
+		.bind::<Text, _>(stream_id)
+		.bind::<Text, _>(tip)
+		.bind::<Bool, _>(removed)
+		.execute(conn)?;
+	Ok(())
+}
+
+fn mirror_file_ids(content: &Value) -> Vec<&str> {
+	content
+		.get("mirrorFileIds")
+		.and_then(Value::as_array)
+		.map_or(vec![], |ids| ids.iter().filter_map(Value::as_str).collect())
+}
+
+/// Append a commit of the stream to the change log of every index folder it
+/// affects, on the connection saving the stream so both are written
+/// together. `folders_before` are the folders the stream affected before the
+/// commit, the ones it no longer affects get a removal, as do the index
+/// folders of the files a content folder stopped mirroring.
+pub(crate) fn record_folder_changes(
+	conn: &mut PgConnection,
+	stream: &models::Stream,
+	previous_content: Option<&Value>,
+	folders_before: &[String],
+) -> anyhow::Result<()> {
+	let folders = affected_folders(conn, &stream.stream_id)?;
+	for folder_id in &folders {
+		record_change(conn, folder_id, &stream.stream_id, &stream.tip, false)?;
+	}
+	for folder_id in folders_before.iter().filter(|x| !folders.contains(x)) {
+		record_change(conn, folder_id, &stream.stream_id, &stream.tip, true)?;
+	}
+
+	let previous_content = match previous_content {
+		Some(content) => content,
+		None => return Ok(()),
+	};
+	let index_folder_id = match previous_content
+		.get("indexFolderId")
+		.and_then(Value::as_str)
+	{
+		Some(index_folder_id) => index_folder_id,
+		None => return Ok(()),
+	};
+	let mirrored = mirror_file_ids(&stream.content);
+	for file_id in mirror_file_ids(previous_content) {
+		if mirrored.contains(&file_id) {
+			continue;
+		}
+		// the file may still be mirrored by another content folder
+		if !affected_folders(conn, file_id)?
+			.iter()
+			.any(|x| x == index_folder_id)
+		{
+			record_change(conn, index_folder_id, file_id, &stream.tip, true)?;
+		}
+	}
+	Ok(())
+}
+
+impl Client {
+	/// Recompute the counters of every index folder affected by a write of the stream
+	pub fn refresh_folder_stats(&self, stream_id: &StreamId) -> anyhow::Result<()> {
+		let conn = &mut self.pool.get()?;
+		for folder_id in affected_folders(conn, &stream_id.to_string())? {
+			diesel::sql_query(REFRESH_FOLDER)
+				.bind::<Text, _>(&folder_id)
+				.execute(conn)?;
+		}
+		Ok(())
+	}
+
+	/// Drop the changes before `before` a later change of the same stream
+	/// supersedes, returns the number of changes removed. The latest change
+	/// of every stream is kept, deltas since any sequence stay complete.
+	pub fn prune_folder_changes(&self, before: DateTime<Utc>) -> anyhow::Result<usize> {
+		let conn = &mut self.pool.get()?;
+		Ok(diesel::sql_query(PRUNE_CHANGES)
+			.bind::<Timestamptz, _>(before)
+			.execute(conn)?)
+	}
+}
+
+#[async_trait::async_trait]
+impl FolderStatsStore for Client {
+	async fn folder_stats(&self, folder_id: &StreamId) -> anyhow::Result<Option<FolderStats>> {
+		let conn = &mut self.read_pool.get()?;
+		let stats: Option<models::FolderStats> = schema::folder_stats::table
+			.find(folder_id.to_string())
+			.select(models::FolderStats::as_select())
+			.first(conn)
+			.optional()?;
+		Ok(stats.map(Into::into))
+	}
+}
+
+#[async_trait::async_trait]
+impl FolderChangeStore for Client {
+	async fn folder_changes(
+		&self,
+		folder_id: &StreamId,
+		since_seq: i64,
+	) -> anyhow::Result<Vec<FolderChange>> {
+		let conn = &mut self.read_pool.get()?;
+		let rows: Vec<FolderChangeRow> = diesel::sql_query(FOLDER_CHANGES)
+			.bind::<Text, _>(folder_id.to_string())
+			.bind::<BigInt, _>(since_seq)
+			.load(conn)?;
+		Ok(rows
+			.into_iter()
+			.map(|row| FolderChange {
+				seq: row.seq,
+				stream_id: row.stream_id,
+				tip: row.tip,
+				changed_at: row.created_at,
+				removed: row.removed,
+			})
+			.collect())
+	}
+}
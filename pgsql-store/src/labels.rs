@@ -0,0 +1,79 @@
+use ceramic_core::StreamId;
+use diesel::prelude::*;
+
+use crate::{schema, Client};
+
+pub(crate) fn has_label(
+	conn: &mut PgConnection,
+	stream_id: &StreamId,
+	label: &str,
+) -> QueryResult<bool> {
+	diesel::select(diesel::dsl::exists(
+		schema::stream_labels::table
+			.filter(schema::stream_labels::stream_id.eq(stream_id.to_string()))
+			.filter(schema::stream_labels::label.eq(label)),
+	))
+	.get_result(conn)
+}
+
+impl Client {
+	/// Labels live apart from the streams table so saving a stream never
+	/// touches them
+	pub fn add_label(&self, stream_id: &StreamId, label: &str) -> anyhow::Result<()> {
+		let conn = &mut self.pool.get()?;
+		diesel::insert_into(schema::stream_labels::table)
+			.values((
+				schema::stream_labels::stream_id.eq(stream_id.to_string()),
+				schema::stream_labels::label.eq(label),
+			))
+			.on_conflict_do_nothing()
+			.execute(conn)?;
+		Ok(())
+	}
+
+	pub fn remove_label(&self, stream_id: &StreamId, label: &str) -> anyhow::Result<bool> {
+		let conn = &mut self.pool.get()?;
+		let removed = diesel::delete(schema::stream_labels::table)
+			.filter(schema::stream_labels::stream_id.eq(stream_id.to_string()))
+			.filter(schema::stream_labels::label.eq(label))
+			.execute(conn)?;
+		Ok(removed > 0)
+	}
+
+	pub fn labels(&self, stream_id: &StreamId) -> anyhow::Result<Vec<String>> {
+		let conn = &mut self.read_pool.get()?;
+		Ok(schema::stream_labels::table
+			.filter(schema::stream_labels::stream_id.eq(stream_id.to_string()))
+			.order(schema::stream_labels::label.asc())
+			.select(schema::stream_labels::label)
+			.load(conn)?)
+	}
+
+	pub fn labeled_streams(&self, label: &str) -> anyhow::Result<Vec<StreamId>> {
+		let conn = &mut self.read_pool.get()?;
+		let stream_ids: Vec<String> = schema::stream_labels::table
+			.filter(schema::stream_labels::label.eq(label))
+			.order(schema::stream_labels::created_at.asc())
+			.select(schema::stream_labels::stream_id)
+			.load(conn)?;
+		stream_ids.iter().map(|x| Ok(x.parse()?)).collect()
+	}
+
+	pub fn labeled_among(
+		&self,
+		label: &str,
+		stream_ids: &[StreamId],
+	) -> anyhow::Result<Vec<StreamId>> {
+		if stream_ids.is_empty() {
+			return Ok(vec![]);
+		}
+		let stream_ids: Vec<String> = stream_ids.iter().map(ToString::to_string).collect();
+		let conn = &mut self.read_pool.get()?;
+		let stream_ids: Vec<String> = schema::stream_labels::table
+			.filter(schema::stream_labels::label.eq(label))
+			.filter(schema::stream_labels::stream_id.eq_any(&stream_ids))
+			.select(schema::stream_labels::stream_id)
+			.load(conn)?;
+		stream_ids.iter().map(|x| Ok(x.parse()?)).collect()
+	}
+}
@@ -1,118 +1,340 @@
+pub mod activity;
+pub mod attestation;
+pub mod diagnostics;
 pub mod errors;
+pub mod folder;
+pub mod labels;
 pub mod models;
+pub mod plan;
+pub mod query_job;
+pub mod retention;
+pub mod revalidation;
 pub mod schema;
+pub mod token;
 
 use anyhow::Context;
+use chrono::{DateTime, Utc};
+use dataverse_file_system::file::name_filter::NameFilter;
 use dataverse_file_system::file::{IndexFile, StreamFileLoader};
 use diesel::dsl::sql;
-use diesel::sql_types::{Bool, Text};
+use diesel::sql_types::{Array, BigInt, Bool, Double, Text};
 use int_enum::IntEnum;
 use std::collections::HashMap;
 use std::sync::Arc;
 
 use ceramic_core::{Cid, StreamId};
-use dataverse_ceramic::{kubo, Ceramic, Event, EventsUploader, StreamState};
-use dataverse_ceramic::{EventsLoader, StreamLoader, StreamOperator, StreamsLoader};
-use dataverse_core::stream::{Stream, StreamStore};
+use dataverse_ceramic::event::CommitSummary;
+use dataverse_ceramic::redact::redact_dsn;
+use dataverse_ceramic::stream::registry::{is_invalid_instance, SharedSchemaRegistry};
+use dataverse_ceramic::{
+	kubo, Ceramic, CommitDag, Event, EventsUploader, LogSelector, StreamState,
+};
+use dataverse_ceramic::{
+	project_fields, EventsLoader, StatesPage, StreamLoader, StreamOperator, StreamsLoader,
+};
+use dataverse_core::store::dapp::DappRegistry;
+use dataverse_core::stream::{Stream, StreamStore, PURGED};
 use diesel::prelude::*;
 use diesel::r2d2::{ConnectionManager, Pool};
 use errors::{ConnectionPoolError, PgSqlClientError};
+use serde_json::{Map, Value};
 
+pub type PgPool = Pool<ConnectionManager<PgConnection>>;
+
+/// Writes, and reads a write depends on, go to `pool`. Listings, projections
+/// and stats go to `read_pool` so heavy queries against a replica do not
+/// starve the write path.
 #[derive(Clone)]
 pub struct Client {
 	pub operator: Arc<dyn StreamOperator>,
-	pub pool: Pool<ConnectionManager<PgConnection>>,
+	pub pool: PgPool,
+	/// same as `pool` without a read dsn
+	pub read_pool: PgPool,
+	/// log the plan of hot queries at debug level, to confirm index usage
+	pub explain: bool,
+	/// conflict policies and ceramic nodes of the dapps, ceramic's policy for
+	/// every stream without it
+	pub registry: Option<Arc<dyn DappRegistry>>,
+	/// schemas synced tips must match, checked only along with `registry`
+	pub schemas: Option<SharedSchemaRegistry>,
 }
 
-impl Client {
-	pub fn new(operator: Arc<dyn StreamOperator>, dsn: &str) -> anyhow::Result<Self> {
-		let manager = ConnectionManager::<PgConnection>::new(dsn);
+fn build_pool(dsn: &str) -> anyhow::Result<PgPool> {
+	let manager = ConnectionManager::<PgConnection>::new(dsn);
+	Pool::builder()
+		.test_on_check_out(true)
+		.build(manager)
+		.map_err(|err| {
+			ConnectionPoolError::PoolInitializationError(format!(
+				"failed build connection pool to {}: {}",
+				redact_dsn(dsn),
+				err
+			))
+			.into()
+		})
+}
 
-		let pool = match Pool::builder().test_on_check_out(true).build(manager) {
-			Ok(it) => it,
-			Err(err) => {
-				anyhow::bail!(ConnectionPoolError::PoolInitializationError(format!(
-					"failed build connection pool: {}",
-					err
-				)));
+/// Compare an event with the stored row of its cid. Only the envelope block
+/// (jws or anchor) is hashed into the cid, the linked, cacao and proof blocks
+/// are optional: blocks present on both sides must be equal, returns the blocks
+/// of the row with the ones it misses filled in, `None` if the row has nothing
+/// to add
+fn check_integrity(
+	stored: &models::Event,
+	event: &models::Event,
+) -> anyhow::Result<Option<Vec<Option<Vec<u8>>>>> {
+	let divergent = event
+		.blocks
+		.iter()
+		.zip(&stored.blocks)
+		.skip(1)
+		.any(|pair| matches!(pair, (Some(block), Some(stored)) if block != stored));
+	if divergent
+		|| stored.blocks.first() != event.blocks.first()
+		|| stored.prev != event.prev
+		|| stored.genesis != event.genesis
+	{
+		tracing::error!(cid = event.cid, "divergent event content for stored cid");
+		anyhow::bail!(PgSqlClientError::IntegrityConflict(Cid::try_from(
+			event.cid.as_str()
+		)?));
+	}
+	let mut blocks = stored.blocks.clone();
+	let mut filled = false;
+	for (idx, block) in event.blocks.iter().enumerate().skip(1) {
+		if block.is_some() && !matches!(blocks.get(idx), Some(Some(_))) {
+			if blocks.len() <= idx {
+				blocks.resize(idx + 1, None);
 			}
+			blocks[idx] = block.clone();
+			filled = true;
+		}
+	}
+	Ok(filled.then_some(blocks))
+}
+
+/// Delete the events, row and labels of the stream, returns the number of
+/// deleted events and whether the stream was stored
+fn delete_stream(conn: &mut PgConnection, stream_id: &StreamId) -> QueryResult<(usize, bool)> {
+	let events = diesel::delete(schema::events::table)
+		.filter(schema::events::genesis.eq(stream_id.cid.to_string()))
+		.execute(conn)?;
+	let streams = diesel::delete(schema::streams::table)
+		.filter(schema::streams::stream_id.eq(stream_id.to_string()))
+		.execute(conn)?;
+	diesel::delete(schema::stream_labels::table)
+		.filter(schema::stream_labels::stream_id.eq(stream_id.to_string()))
+		.execute(conn)?;
+	Ok((events, streams > 0))
+}
+
+impl Client {
+	/// `read_dsn` is usually a replica of `dsn`, reads go to the primary without it
+	pub fn new(
+		operator: Arc<dyn StreamOperator>,
+		dsn: &str,
+		read_dsn: Option<&str>,
+	) -> anyhow::Result<Self> {
+		let pool = build_pool(dsn)?;
+		let read_pool = match read_dsn {
+			Some(read_dsn) => build_pool(read_dsn)?,
+			None => pool.clone(),
 		};
-		Ok(Self { operator, pool })
+		Ok(Self {
+			operator,
+			pool,
+			read_pool,
+			explain: false,
+			registry: None,
+			schemas: None,
+		})
 	}
 
-	async fn load_events_from_db(
+	/// Conflict policy of the dapp of the stored stream
+	pub async fn log_selector(&self, stream_id: &StreamId) -> anyhow::Result<LogSelector> {
+		let registry = match &self.registry {
+			Some(registry) => registry,
+			None => return Ok(LogSelector::default()),
+		};
+		match self.dapp_of(stream_id)? {
+			Some(dapp_id) => registry.get_log_selector(&dapp_id).await,
+			None => Ok(LogSelector::default()),
+		}
+	}
+
+	fn dapp_of(&self, stream_id: &StreamId) -> anyhow::Result<Option<uuid::Uuid>> {
+		let conn = &mut self.pool.get()?;
+		Ok(plan::stream_by_id(stream_id)
+			.select(schema::streams::dapp_id)
+			.first(conn)
+			.optional()?)
+	}
+
+	/// Whether the content at a synced tip matches the schema of its model,
+	/// inferred from the model stream for unknown models
+	async fn matches_schema(
 		&self,
 		stream_id: &StreamId,
-		mut tip: Option<Cid>,
-	) -> anyhow::Result<Vec<Event>> {
+		dag: &CommitDag,
+		tip: Cid,
+	) -> anyhow::Result<bool> {
+		let (schemas, registry) = match (&self.schemas, &self.registry) {
+			(Some(schemas), Some(registry)) => (schemas, registry),
+			_ => return Ok(true),
+		};
+		let dapp_id = match self.dapp_of(stream_id)? {
+			Some(dapp_id) => dapp_id,
+			None => return Ok(true),
+		};
+		let ceramic = registry.get_dapp_ceramic(&dapp_id).await?;
+		let state = StreamState::make(stream_id.r#type.int_value(), dag.log(tip)?).await?;
+		match schemas
+			.validate(self.operator.as_ref(), &ceramic, &state)
+			.await
+		{
+			Ok(()) => Ok(true),
+			Err(err) if is_invalid_instance(&err) => {
+				tracing::warn!(
+					stream_id = stream_id.to_string(),
+					tip = tip.to_string(),
+					"synced tip rejected: {}",
+					err
+				);
+				Ok(false)
+			}
+			Err(err) => Err(err),
+		}
+	}
+
+	/// Stored events of the stream, whatever their branch
+	fn load_dag(&self, stream_id: &StreamId) -> anyhow::Result<CommitDag> {
+		self.debug_plan("load_events", plan::events_by_genesis(stream_id));
 		let conn = &mut self.pool.get()?;
-		let events: Vec<models::Event> = schema::events::table
-			.filter(schema::events::genesis.eq(stream_id.cid.to_string()))
+		let events: Vec<models::Event> = plan::events_by_genesis(stream_id)
 			.select(models::Event::as_select())
 			.load(conn)?;
-
-		let mut map: HashMap<Cid, Event> = HashMap::new();
+		let mut dag = CommitDag::default();
 		for event in events {
-			let event: Event = event.try_into()?;
-			map.insert(event.cid, event);
+			dag.insert(event.try_into()?)?;
 		}
+		Ok(dag)
+	}
 
-		let mut result = Vec::new();
-		if tip.is_none() {
-			while let Some(cid) = tip {
-				let event = match map.get(&cid) {
-					Some(event) => event,
-					None => anyhow::bail!("missing event {} for stream {}", cid, stream_id),
-				};
-				result.push(event.clone());
-				tip = event.prev()?;
-			}
-			result.reverse();
-		} else {
-			let mut prev_map: HashMap<Cid, Cid> = HashMap::new();
-			for (cid, event) in &map {
-				if let Some(prev) = event.prev()? {
-					prev_map.insert(prev, *cid);
-				}
-			}
-			let mut prev = stream_id.cid;
-			let genesis = map.get(&prev).context(PgSqlClientError::MissingGenesis)?;
-			result.push(genesis.clone());
-			while let Some(cid) = prev_map.get(&prev) {
-				let event = match map.get(cid) {
-					Some(event) => event,
-					None => anyhow::bail!(PgSqlClientError::MissingEventForStream(
-						*cid,
-						stream_id.clone()
-					)),
-				};
-				result.push(event.clone());
-				prev = *cid;
-			}
+	/// Events of the stream stored in the database, ordered from genesis to
+	/// tip. Without a tip, `selector` picks the branch of a forked stream
+	pub async fn load_events_from_db(
+		&self,
+		stream_id: &StreamId,
+		tip: Option<Cid>,
+		selector: LogSelector,
+	) -> anyhow::Result<Vec<Event>> {
+		let dag = self.load_dag(stream_id)?;
+		if !dag.contains(&stream_id.cid) {
+			anyhow::bail!(PgSqlClientError::MissingGenesis);
 		}
+		let tip = match tip {
+			Some(tip) => tip,
+			None => dag
+				.select(selector)?
+				.context(PgSqlClientError::MissingGenesis)?,
+		};
+		dag.log(tip)
+	}
 
+	async fn load_states_of_streams(
+		&self,
+		ceramic: &Ceramic,
+		streams: Vec<models::Stream>,
+	) -> anyhow::Result<Vec<StreamState>> {
+		let mut result = Vec::new();
+		for stream in streams {
+			let stream_id = stream.stream_id()?;
+			let tip = Some(Cid::try_from(stream.tip.to_string())?);
+			let commits: Vec<Event> = self.load_events(ceramic, &stream_id, tip).await?;
+			let state = StreamState::make(stream_id.r#type.int_value(), commits).await?;
+			result.push(state);
+		}
 		Ok(result)
 	}
 
-	async fn save_events_to_db(&self, events: Vec<Event>) -> anyhow::Result<()> {
+	/// Insert events of the stream missing from the database, rejecting events
+	/// of other streams. An event already stored is compared with the stored
+	/// row, failing with `PgSqlClientError::IntegrityConflict` if the same cid
+	/// has another envelope, and fills in the optional blocks the row misses.
+	/// Events of purged streams are not stored again
+	pub async fn save_events_to_db(
+		&self,
+		stream_id: &StreamId,
+		events: Vec<Event>,
+	) -> anyhow::Result<()> {
 		let conn = &mut self.pool.get()?;
+		if labels::has_label(conn, stream_id, PURGED)? {
+			anyhow::bail!(PgSqlClientError::StreamPurged(stream_id.clone()));
+		}
 		for event in events {
-			let event: models::Event = event.try_into()?;
-			diesel::insert_into(schema::events::table)
+			let event = models::Event::for_stream(stream_id, event)?;
+			let inserted = diesel::insert_into(schema::events::table)
 				.values(&event)
 				.on_conflict(schema::events::cid)
 				.do_nothing()
 				.execute(conn)?;
+			if inserted == 0 {
+				let stored: models::Event = schema::events::table
+					.find(&event.cid)
+					.select(models::Event::as_select())
+					.first(conn)?;
+				if let Some(blocks) = check_integrity(&stored, &event)? {
+					diesel::update(schema::events::table.find(&event.cid))
+						.set(schema::events::blocks.eq(blocks))
+						.execute(conn)?;
+				}
+			}
 		}
 		Ok(())
 	}
+
+	/// Commits of the stream from the genesis to the tip, stamped with the
+	/// time the store received them, for version histories
+	pub async fn commit_history(&self, stream_id: &StreamId) -> anyhow::Result<Vec<CommitSummary>> {
+		let selector = self.log_selector(stream_id).await?;
+		let events = self.load_events_from_db(stream_id, None, selector).await?;
+		let conn = &mut self.read_pool.get()?;
+		let received_at: HashMap<String, DateTime<Utc>> = schema::events::table
+			.filter(schema::events::genesis.eq(stream_id.cid.to_string()))
+			.select((schema::events::cid, schema::events::created_at))
+			.load::<(String, DateTime<Utc>)>(conn)?
+			.into_iter()
+			.collect();
+		events
+			.iter()
+			.map(|event| {
+				let mut summary = event.summary()?;
+				summary.timestamp = received_at.get(&summary.cid).copied();
+				Ok(summary)
+			})
+			.collect()
+	}
+
+	/// Streams saved at or after `since`, oldest change first, for change
+	/// logs and incremental exports
+	pub fn streams_modified_since(&self, since: DateTime<Utc>) -> anyhow::Result<Vec<Stream>> {
+		let conn = &mut self.read_pool.get()?;
+		let streams: Vec<models::Stream> = schema::streams::table
+			.filter(schema::streams::updated_at.ge(since))
+			.order(schema::streams::updated_at.asc())
+			.select(models::Stream::as_select())
+			.load(conn)?;
+		streams.into_iter().map(TryInto::try_into).collect()
+	}
 }
 
 #[async_trait::async_trait]
 impl StreamStore for Client {
 	async fn list_all_streams(&self) -> anyhow::Result<Vec<Stream>> {
-		let conn = &mut self.pool.get()?;
-		let streams: Vec<models::Stream> = schema::streams::table.load(conn)?;
+		let conn = &mut self.read_pool.get()?;
+		let streams: Vec<models::Stream> = schema::streams::table
+			.select(models::Stream::as_select())
+			.load(conn)?;
 		let mut result = Vec::new();
 		for stream in streams {
 			let stream = stream.try_into()?;
@@ -121,25 +343,77 @@ impl StreamStore for Client {
 		Ok(result)
 	}
 
+	async fn list_model_streams(&self, model_id: &StreamId) -> anyhow::Result<Vec<Stream>> {
+		let conn = &mut self.read_pool.get()?;
+		let streams: Vec<models::Stream> = schema::streams::table
+			.filter(schema::streams::model_id.eq(model_id.to_string()))
+			.select(models::Stream::as_select())
+			.load(conn)?;
+		streams.into_iter().map(TryInto::try_into).collect()
+	}
+
+	async fn list_stream_ids(&self) -> anyhow::Result<Vec<StreamId>> {
+		kubo::TipStore::stream_ids(self).await
+	}
+
 	async fn save_stream(&self, stream: &Stream) -> anyhow::Result<()> {
+		let stream_id = stream.stream_id()?;
 		let stream: models::Stream = stream.try_into()?;
 		let conn = &mut self.pool.get()?;
-		let execute = diesel::insert_into(schema::streams::table)
-			.values(&stream)
-			.on_conflict(schema::streams::stream_id)
-			.do_update()
-			.set(&stream)
-			.execute(conn);
-		if let Err(err) = execute {
-			tracing::error!(?stream, "db exec error: {}", err);
-			anyhow::bail!(PgSqlClientError::DbExecError)
+		if labels::has_label(conn, &stream_id, PURGED)? {
+			anyhow::bail!(PgSqlClientError::StreamPurged(stream_id));
+		}
+		// the stream and the change log of its folders are written together
+		let committed = conn.transaction::<_, anyhow::Error, _>(|conn| {
+			// saving the state again, e.g. on a resync, is not a commit
+			let previous: Option<(String, Value)> = schema::streams::table
+				.find(&stream.stream_id)
+				.select((schema::streams::tip, schema::streams::content))
+				.first(conn)
+				.optional()?;
+			let committed = previous.as_ref().map(|x| &x.0) != Some(&stream.tip);
+			let folders_before = match committed {
+				true => folder::affected_folders(conn, &stream.stream_id)?,
+				false => vec![],
+			};
+			let execute = diesel::insert_into(schema::streams::table)
+				.values(&stream)
+				.on_conflict(schema::streams::stream_id)
+				.do_update()
+				.set((&stream, schema::streams::updated_at.eq(Utc::now())))
+				.execute(conn);
+			if let Err(err) = execute {
+				tracing::error!(?stream, "db exec error: {}", err);
+				anyhow::bail!(PgSqlClientError::DbExecError)
+			}
+			if committed {
+				let previous_content = previous.as_ref().map(|x| &x.1);
+				folder::record_folder_changes(conn, &stream, previous_content, &folders_before)?;
+			}
+			Ok(committed)
+		})?;
+		if !committed {
+			return Ok(());
+		}
+		if let Some(model_id) = &stream.model_id {
+			if let Err(err) = self.record_model_activity(model_id) {
+				tracing::warn!(model_id, "failed to record model activity: {}", err);
+			}
+		}
+		if let Err(err) = self.refresh_folder_stats(&stream_id) {
+			tracing::warn!(
+				stream_id = stream_id.to_string(),
+				"failed to refresh folder stats: {}",
+				err
+			);
 		}
 		Ok(())
 	}
 	async fn load_stream(&self, stream_id: &StreamId) -> anyhow::Result<Option<Stream>> {
+		self.debug_plan("load_stream", plan::stream_by_id(stream_id));
 		let conn = &mut self.pool.get()?;
-		let stream: Option<models::Stream> = schema::streams::table
-			.filter(schema::streams::stream_id.eq(stream_id.to_string()))
+		let stream: Option<models::Stream> = plan::stream_by_id(stream_id)
+			.select(models::Stream::as_select())
 			.first(conn)
 			.optional()?;
 		if let Some(stream) = stream {
@@ -148,52 +422,150 @@ impl StreamStore for Client {
 		}
 		Ok(None)
 	}
+
+	async fn remove_model_streams(&self, model_id: &StreamId) -> anyhow::Result<usize> {
+		let conn = &mut self.pool.get()?;
+		let (events, streams) = conn.transaction::<_, anyhow::Error, _>(|conn| {
+			let stream_ids: Vec<String> = schema::streams::table
+				.filter(schema::streams::model_id.eq(model_id.to_string()))
+				.select(schema::streams::stream_id)
+				.load(conn)?;
+			let genesis = stream_ids
+				.iter()
+				.map(|x| Ok(x.parse::<StreamId>()?.cid.to_string()))
+				.collect::<anyhow::Result<Vec<_>>>()?;
+
+			let events = diesel::delete(schema::events::table)
+				.filter(schema::events::genesis.eq_any(&genesis))
+				.execute(conn)?;
+			let streams = diesel::delete(schema::streams::table)
+				.filter(schema::streams::stream_id.eq_any(&stream_ids))
+				.execute(conn)?;
+			diesel::delete(schema::stream_labels::table)
+				.filter(schema::stream_labels::stream_id.eq_any(&stream_ids))
+				.execute(conn)?;
+			Ok((events, streams))
+		})?;
+		tracing::info!(
+			model_id = model_id.to_string(),
+			streams,
+			events,
+			"removed streams of model"
+		);
+		Ok(streams)
+	}
+
+	async fn remove_stream(&self, stream_id: &StreamId) -> anyhow::Result<bool> {
+		let conn = &mut self.pool.get()?;
+		let (events, removed) = conn.transaction(|conn| delete_stream(conn, stream_id))?;
+		tracing::info!(stream_id = stream_id.to_string(), events, "removed stream");
+		Ok(removed)
+	}
+
+	async fn purge_stream(&self, stream_id: &StreamId) -> anyhow::Result<bool> {
+		let conn = &mut self.pool.get()?;
+		let (events, removed) = conn.transaction(|conn| {
+			let deleted = delete_stream(conn, stream_id)?;
+			diesel::insert_into(schema::stream_labels::table)
+				.values((
+					schema::stream_labels::stream_id.eq(stream_id.to_string()),
+					schema::stream_labels::label.eq(PURGED),
+				))
+				.execute(conn)?;
+			QueryResult::Ok(deleted)
+		})?;
+		tracing::info!(stream_id = stream_id.to_string(), events, "purged stream");
+		Ok(removed)
+	}
+
+	async fn add_stream_label(&self, stream_id: &StreamId, label: &str) -> anyhow::Result<()> {
+		self.add_label(stream_id, label)
+	}
+
+	async fn remove_stream_label(&self, stream_id: &StreamId, label: &str) -> anyhow::Result<bool> {
+		self.remove_label(stream_id, label)
+	}
+
+	async fn stream_labels(&self, stream_id: &StreamId) -> anyhow::Result<Vec<String>> {
+		self.labels(stream_id)
+	}
+
+	async fn streams_with_label(&self, label: &str) -> anyhow::Result<Vec<StreamId>> {
+		self.labeled_streams(label)
+	}
+
+	async fn filter_labeled(
+		&self,
+		label: &str,
+		stream_ids: &[StreamId],
+	) -> anyhow::Result<Vec<StreamId>> {
+		self.labeled_among(label, stream_ids)
+	}
 }
 
 #[async_trait::async_trait]
-impl kubo::Store for Client {
-	async fn get(
-		&self,
-		_id: Option<String>,
-		stream_id: Option<StreamId>,
-	) -> anyhow::Result<Option<Cid>> {
-		if let Some(stream_id) = stream_id {
-			let conn = &mut self.pool.get()?;
-			let stream: Option<models::Stream> = schema::streams::table
-				.filter(schema::streams::stream_id.eq(stream_id.to_string()))
-				.first(conn)
-				.optional()?;
-			if let Some(stream) = stream {
-				return Ok(Some(Cid::try_from(stream.tip.to_string())?));
-			}
+impl kubo::TipStore for Client {
+	async fn get_tip(&self, stream_id: &StreamId) -> anyhow::Result<Option<Cid>> {
+		let conn = &mut self.pool.get()?;
+		let stream: Option<models::Stream> = plan::stream_by_id(stream_id)
+			.select(models::Stream::as_select())
+			.first(conn)
+			.optional()?;
+		match stream {
+			Some(stream) => Ok(Some(Cid::try_from(stream.tip.to_string())?)),
+			None => Ok(None),
 		}
-		Ok(None)
 	}
 
-	async fn push(
-		&self,
-		_id: Option<String>,
-		stream_id: Option<StreamId>,
-		tip: Cid,
-	) -> anyhow::Result<()> {
-		if let Some(stream_id) = stream_id {
-			let conn = &mut self.pool.get()?;
-			let stream: Option<models::Stream> = schema::streams::table
-				.filter(schema::streams::stream_id.eq(stream_id.to_string()))
-				.first(conn)
-				.optional()?;
-			if let Some(mut stream) = stream {
-				stream.tip = tip.to_string();
-				diesel::insert_into(schema::streams::table)
-					.values(&stream)
-					.on_conflict(schema::streams::stream_id)
-					.do_update()
-					.set(&stream)
-					.execute(conn)?;
+	/// A synced tip forking the stored events is replaced by the branch the
+	/// conflict policy of the dapp picks and marks the stream as forked, one
+	/// whose content does not match the schema of the model is ignored
+	async fn set_tip(&self, stream_id: &StreamId, tip: Cid) -> anyhow::Result<()> {
+		let dag = self.load_dag(stream_id)?;
+		let forked = dag.contains(&tip) && dag.is_forked();
+		let tip = match forked {
+			true => {
+				tracing::warn!(
+					stream_id = stream_id.to_string(),
+					forks = ?dag.forks(),
+					"stream is forked"
+				);
+				let selector = self.log_selector(stream_id).await?;
+				dag.select(selector)?.unwrap_or(tip)
 			}
+			false => tip,
+		};
+		if dag.contains(&tip) && !self.matches_schema(stream_id, &dag, tip).await? {
+			return Ok(());
+		}
+		let conn = &mut self.pool.get()?;
+		let stream: Option<models::Stream> = plan::stream_by_id(stream_id)
+			.select(models::Stream::as_select())
+			.first(conn)
+			.optional()?;
+		if let Some(mut stream) = stream {
+			stream.tip = tip.to_string();
+			stream.forked = stream.forked || forked;
+			diesel::insert_into(schema::streams::table)
+				.values(&stream)
+				.on_conflict(schema::streams::stream_id)
+				.do_update()
+				.set((&stream, schema::streams::updated_at.eq(Utc::now())))
+				.execute(conn)?;
 		}
 		Ok(())
 	}
+
+	async fn stream_ids(&self) -> anyhow::Result<Vec<StreamId>> {
+		let conn = &mut self.read_pool.get()?;
+		let stream_ids: Vec<String> = schema::streams::table
+			.select(schema::streams::stream_id)
+			.load(conn)?;
+		stream_ids
+			.iter()
+			.map(|stream_id| Ok(stream_id.parse()?))
+			.collect()
+	}
 }
 
 #[async_trait::async_trait]
@@ -214,6 +586,33 @@ impl StreamLoader for Client {
 		let events = self.load_events(ceramic, stream_id, Some(tip)).await?;
 		StreamState::make(stream_id.r#type.int_value(), events).await
 	}
+
+	async fn load_fields(
+		&self,
+		ceramic: &Ceramic,
+		stream_id: &StreamId,
+		fields: &[&str],
+	) -> anyhow::Result<Map<String, Value>> {
+		let conn = &mut self.read_pool.get()?;
+		let fields: Vec<String> = fields.iter().map(ToString::to_string).collect();
+		let projected: Option<models::ProjectedFields> = diesel::sql_query(
+			"SELECT COALESCE((SELECT jsonb_object_agg(key, value) FROM jsonb_each(content) \
+			 WHERE key = ANY($1)), '{}'::jsonb) AS fields FROM streams WHERE stream_id = $2",
+		)
+		.bind::<Array<Text>, _>(&fields)
+		.bind::<Text, _>(stream_id.to_string())
+		.get_result(conn)
+		.optional()?;
+
+		match projected.map(|x| x.fields) {
+			Some(Value::Object(fields)) => Ok(fields),
+			_ => {
+				let state = self.load_stream_state(ceramic, stream_id, None).await?;
+				let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+				Ok(project_fields(&state.content, &fields))
+			}
+		}
+	}
 }
 
 #[async_trait::async_trait]
@@ -224,7 +623,7 @@ impl StreamsLoader for Client {
 		account: Option<String>,
 		model_id: &StreamId,
 	) -> anyhow::Result<Vec<StreamState>> {
-		let conn = &mut self.pool.get()?;
+		let conn = &mut self.read_pool.get()?;
 		let model_id = model_id.to_string();
 		let mut query = schema::streams::table.into_boxed();
 		query = query.filter(schema::streams::model_id.eq(model_id));
@@ -233,16 +632,62 @@ impl StreamsLoader for Client {
 			query = query.filter(schema::streams::account.eq(account));
 		}
 
-		let streams: Vec<models::Stream> = query.load(conn)?;
-		let mut result = Vec::new();
-		for stream in streams {
-			let stream_id = stream.stream_id()?;
-			let tip = Some(Cid::try_from(stream.tip.to_string())?);
-			let commits: Vec<Event> = self.load_events(_ceramic, &stream_id, tip).await?;
-			let state = StreamState::make(stream_id.r#type.int_value(), commits).await?;
-			result.push(state);
+		let streams: Vec<models::Stream> = query.select(models::Stream::as_select()).load(conn)?;
+		self.load_states_of_streams(_ceramic, streams).await
+	}
+
+	/// Ordered and paged by the query, only the states of the page are loaded
+	async fn load_stream_states_page(
+		&self,
+		ceramic: &Ceramic,
+		account: Option<String>,
+		model_id: &StreamId,
+		page: StatesPage,
+	) -> anyhow::Result<Vec<StreamState>> {
+		let conn = &mut self.read_pool.get()?;
+		let streams: Vec<models::Stream> = plan::model_streams_page(model_id, account, page)
+			.select(models::Stream::as_select())
+			.load(conn)?;
+		self.load_states_of_streams(ceramic, streams).await
+	}
+
+	/// Block sample of the streams table, repeatable with the seed, sized from
+	/// the number of instances so the model is not scanned. Clustered rows
+	/// make it less uniform than a reservoir sample.
+	async fn sample(
+		&self,
+		ceramic: &Ceramic,
+		model_id: &StreamId,
+		n: usize,
+		seed: u64,
+	) -> anyhow::Result<Vec<StreamState>> {
+		let conn = &mut self.read_pool.get()?;
+		let model_id = model_id.to_string();
+		let count: i64 = schema::streams::table
+			.filter(schema::streams::model_id.eq(&model_id))
+			.count()
+			.get_result(conn)?;
+		if count == 0 || n == 0 {
+			return Ok(vec![]);
 		}
-		return Ok(result);
+		// twice the expected rows, the size of a block sample varies
+		let percent = (200.0 * n as f64 / count as f64).min(100.0);
+		let sampled: Vec<models::SampledStream> = diesel::sql_query(
+			"SELECT stream_id FROM streams TABLESAMPLE SYSTEM ($1) REPEATABLE ($2) \
+			 WHERE model_id = $3 LIMIT $4",
+		)
+		.bind::<Double, _>(percent)
+		.bind::<Double, _>(seed as f64)
+		.bind::<Text, _>(&model_id)
+		.bind::<BigInt, _>(n as i64)
+		.load(conn)?;
+
+		let stream_ids: Vec<String> = sampled.into_iter().map(|x| x.stream_id).collect();
+		let streams: Vec<models::Stream> = schema::streams::table
+			.filter(schema::streams::stream_id.eq_any(&stream_ids))
+			.select(models::Stream::as_select())
+			.load(conn)?;
+		self.load_states_of_streams(ceramic, streams).await
 	}
 }
 
@@ -254,7 +699,11 @@ impl EventsLoader for Client {
 		stream_id: &StreamId,
 		tip: Option<Cid>,
 	) -> anyhow::Result<Vec<Event>> {
-		match self.load_events_from_db(stream_id, tip).await {
+		let selector = match tip {
+			Some(_) => LogSelector::default(),
+			None => self.log_selector(stream_id).await?,
+		};
+		match self.load_events_from_db(stream_id, tip, selector).await {
 			Ok(result) => Ok(result),
 			Err(err) => {
 				tracing::warn!(
@@ -264,7 +713,7 @@ impl EventsLoader for Client {
 				);
 
 				let result = self.operator.load_events(ceramic, stream_id, tip).await?;
-				self.save_events_to_db(result.clone()).await?;
+				self.save_events_to_db(stream_id, result.clone()).await?;
 				Ok(result)
 			}
 		}
@@ -279,10 +728,11 @@ impl StreamFileLoader for Client {
 		index_file_model_id: &StreamId,
 		content_id: &String,
 	) -> anyhow::Result<(StreamState, IndexFile)> {
-		let conn = &mut self.pool.get()?;
+		let conn = &mut self.read_pool.get()?;
 		let stream: Result<Option<models::Stream>, _> = schema::streams::table
 			.filter(schema::streams::model_id.eq(index_file_model_id.to_string()))
 			.filter(sql::<Bool>("content->>'contentId' = ").bind::<Text, _>(content_id))
+			.select(models::Stream::as_select())
 			.first(conn)
 			.optional();
 
@@ -309,17 +759,99 @@ impl StreamFileLoader for Client {
 		}
 		anyhow::bail!("index file with content_id {} not found", content_id)
 	}
+
+	async fn load_index_files_by_name(
+		&self,
+		ceramic: &Ceramic,
+		index_file_model_id: &StreamId,
+		account: Option<String>,
+		filter: &NameFilter,
+	) -> anyhow::Result<Vec<StreamState>> {
+		let conn = &mut self.read_pool.get()?;
+		let mut query = schema::streams::table
+			.filter(schema::streams::model_id.eq(index_file_model_id.to_string()))
+			.into_boxed();
+		if let Some(account) = account {
+			query = query.filter(schema::streams::account.eq(account));
+		}
+		// matched with the indexes created in migration add_streams_file_name_index
+		let condition = match filter {
+			NameFilter::Suffix(_) => "reverse(content->>'fileName') LIKE ",
+			_ => "content->>'fileName' LIKE ",
+		};
+		query = query.filter(sql::<Bool>(condition).bind::<Text, _>(filter.like_pattern()));
+
+		let streams: Vec<models::Stream> = query.select(models::Stream::as_select()).load(conn)?;
+		self.load_states_of_streams(ceramic, streams).await
+	}
 }
 
 #[async_trait::async_trait]
 impl EventsUploader for Client {
+	/// Store the event once the node accepted it, a failed upload leaves the
+	/// store as it was
 	async fn upload_event(
 		&self,
 		ceramic: &Ceramic,
 		stream_id: &StreamId,
 		event: Event,
 	) -> anyhow::Result<()> {
-		self.save_events_to_db(vec![event.clone()]).await?;
-		self.operator.upload_event(ceramic, stream_id, event).await
+		self.operator
+			.upload_event(ceramic, stream_id, event.clone())
+			.await?;
+		self.save_events_to_db(stream_id, vec![event]).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::errors::PgSqlEventError;
+
+	#[test]
+	fn test_check_integrity() -> anyhow::Result<()> {
+		let events = dataverse_ceramic::commit::example::events(2);
+		let stored: models::Event = events[1].clone().try_into()?;
+		assert_eq!(check_integrity(&stored, &stored.clone())?, None);
+
+		// optional blocks missing from either side are not a conflict
+		let mut partial = stored.clone();
+		partial.blocks[1] = None;
+		partial.blocks[2] = None;
+		assert_eq!(check_integrity(&stored, &partial)?, None);
+		assert_eq!(
+			check_integrity(&partial, &stored)?,
+			Some(stored.blocks.clone())
+		);
+
+		// envelope, linked and cacao blocks present on both sides must match
+		for idx in [0, 1, 2] {
+			let mut divergent = stored.clone();
+			divergent.blocks[idx] = Some(vec![0]);
+			let err = check_integrity(&stored, &divergent).unwrap_err();
+			assert!(matches!(
+				err.downcast_ref::<PgSqlClientError>(),
+				Some(PgSqlClientError::IntegrityConflict(_))
+			));
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn test_reject_other_stream() -> anyhow::Result<()> {
+		let events = dataverse_ceramic::commit::example::events(2);
+		let stream_id = dataverse_ceramic::commit::example::genesis().stream_id()?;
+		for event in events.clone() {
+			models::Event::for_stream(&stream_id, event)?;
+		}
+
+		let other: StreamId =
+			"kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju".parse()?;
+		let err = models::Event::for_stream(&other, events[1].clone()).unwrap_err();
+		assert!(matches!(
+			err.downcast_ref::<PgSqlEventError>(),
+			Some(PgSqlEventError::GenesisMismatch(..))
+		));
+		Ok(())
 	}
 }
@@ -1,6 +1,8 @@
 use std::str::FromStr;
 
 use ceramic_core::{Cid, StreamId};
+use chrono::{DateTime, Utc};
+use dataverse_ceramic::publisher::AttestationRecord;
 use dataverse_ceramic::{
 	event::{AnchorValue, SignedValue, ToCid},
 	EventValue,
@@ -10,7 +12,11 @@ use int_enum::IntEnum;
 
 use crate::errors::PgSqlEventError;
 
-#[derive(Debug, Queryable, Selectable, Insertable, AsChangeset)]
+/// Row of `events`. `genesis` is the cid of the genesis event of the stream:
+/// the event itself for a genesis, the `id` of the payload for a data event
+/// and the `id` of an anchor event. It is the cid of the stream id, so events
+/// of a stream are looked up by `stream_id.cid`.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Insertable, AsChangeset)]
 #[diesel(table_name = crate::schema::events)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct Event {
@@ -55,8 +61,8 @@ impl TryFrom<dataverse_ceramic::Event> for Event {
 			genesis: cid.to_string(),
 			blocks: match value.value {
 				EventValue::Signed(signed) => {
-					let jws = signed.jws.to_vec()?;
-					vec![Some(jws), signed.linked_block, signed.cacao_block]
+					let (jws, linked_block, cacao_block) = signed.into_parts();
+					vec![Some(jws.to_vec()?), linked_block, cacao_block]
 				}
 				EventValue::Anchor(anchor) => {
 					let block = anchor.to_vec()?;
@@ -68,6 +74,24 @@ impl TryFrom<dataverse_ceramic::Event> for Event {
 	}
 }
 
+impl Event {
+	/// Row of an event of the stream, rejecting events whose genesis is not
+	/// the stream's so events of one stream never pollute another
+	pub fn for_stream(
+		stream_id: &StreamId,
+		event: dataverse_ceramic::Event,
+	) -> anyhow::Result<Self> {
+		let cid = event.cid;
+		let event = Self::try_from(event)?;
+		if event.genesis != stream_id.cid.to_string() {
+			anyhow::bail!(PgSqlEventError::GenesisMismatch(cid, stream_id.clone()));
+		}
+		Ok(event)
+	}
+}
+
+/// Row of `streams` without `created_at` and `updated_at`, which are left to
+/// their defaults on insert and set by the save paths on update
 #[derive(Debug, Clone, Queryable, Selectable, Insertable, AsChangeset)]
 #[diesel(table_name = crate::schema::streams)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
@@ -78,6 +102,9 @@ pub struct Stream {
 	pub account: Option<String>,
 	pub model_id: Option<String>,
 	pub content: serde_json::Value,
+	pub published: serde_json::Value,
+	pub anchored_at: Option<DateTime<Utc>>,
+	pub forked: bool,
 }
 
 impl Stream {
@@ -97,6 +124,9 @@ impl TryFrom<&dataverse_core::stream::Stream> for Stream {
 			account: value.account.clone(),
 			model_id: value.model.clone().map(|x| x.to_string()),
 			content: value.content.clone(),
+			published: serde_json::to_value(&value.published)?,
+			anchored_at: value.anchored_at,
+			forked: value.forked,
 		})
 	}
 }
@@ -118,6 +148,204 @@ impl TryInto<dataverse_core::stream::Stream> for Stream {
 			account: self.account,
 			model,
 			content: self.content,
+			published: serde_json::from_value(self.published)?,
+			anchored_at: self.anchored_at,
+			forked: self.forked,
+		})
+	}
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct ProjectedFields {
+	#[diesel(sql_type = diesel::sql_types::Jsonb)]
+	pub fields: serde_json::Value,
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct SampledStream {
+	#[diesel(sql_type = diesel::sql_types::Text)]
+	pub stream_id: String,
+}
+
+#[derive(Debug, Clone, Queryable, Selectable, Insertable)]
+#[diesel(table_name = crate::schema::service_tokens)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ServiceToken {
+	pub id: uuid::Uuid,
+	pub name: String,
+	pub token_hash: String,
+	pub scopes: Vec<String>,
+	pub created_at: DateTime<Utc>,
+	pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl ServiceToken {
+	pub fn new(token: &dataverse_file_system::auth::token::ServiceToken, token_hash: &str) -> Self {
+		Self {
+			id: token.id,
+			name: token.name.clone(),
+			token_hash: token_hash.to_string(),
+			scopes: token.scopes.iter().map(ToString::to_string).collect(),
+			created_at: token.created_at,
+			revoked_at: token.revoked_at,
+		}
+	}
+}
+
+impl TryInto<dataverse_file_system::auth::token::ServiceToken> for ServiceToken {
+	type Error = anyhow::Error;
+
+	fn try_into(self) -> Result<dataverse_file_system::auth::token::ServiceToken, Self::Error> {
+		Ok(dataverse_file_system::auth::token::ServiceToken {
+			id: self.id,
+			name: self.name,
+			scopes: self
+				.scopes
+				.iter()
+				.map(|scope| scope.parse())
+				.collect::<anyhow::Result<_>>()?,
+			created_at: self.created_at,
+			revoked_at: self.revoked_at,
+		})
+	}
+}
+
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::folder_stats)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct FolderStats {
+	pub folder_id: String,
+	pub content_folder_count: i64,
+	pub file_count: i64,
+	pub updated_at: DateTime<Utc>,
+}
+
+impl From<FolderStats> for dataverse_file_system::file::folder_stats::FolderStats {
+	fn from(value: FolderStats) -> Self {
+		Self {
+			folder_id: value.folder_id,
+			content_folder_count: value.content_folder_count,
+			file_count: value.file_count,
+			updated_at: value.updated_at,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Queryable, Selectable, Insertable, AsChangeset)]
+#[diesel(table_name = crate::schema::attestations)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Attestation {
+	pub tx_hash: String,
+	pub chain_id: i64,
+	pub contract: String,
+	pub stream_id: String,
+	pub commit_id: String,
+	pub status: String,
+	pub block_number: Option<i64>,
+	pub created_at: DateTime<Utc>,
+	pub updated_at: DateTime<Utc>,
+}
+
+impl From<&AttestationRecord> for Attestation {
+	fn from(value: &AttestationRecord) -> Self {
+		Self {
+			tx_hash: format!("{:?}", value.tx_hash),
+			chain_id: value.chain_id as i64,
+			contract: format!("{:?}", value.contract),
+			stream_id: value.stream_id.to_string(),
+			commit_id: value.commit.to_string(),
+			status: value.status.as_str().to_string(),
+			block_number: value.block_number.map(|x| x as i64),
+			created_at: value.created_at,
+			updated_at: value.updated_at,
+		}
+	}
+}
+
+impl TryInto<AttestationRecord> for Attestation {
+	type Error = anyhow::Error;
+
+	fn try_into(self) -> Result<AttestationRecord, Self::Error> {
+		Ok(AttestationRecord {
+			tx_hash: self.tx_hash.parse()?,
+			chain_id: self.chain_id as u64,
+			contract: self.contract.parse()?,
+			stream_id: StreamId::from_str(&self.stream_id)?,
+			commit: Cid::try_from(self.commit_id)?,
+			status: self.status.parse()?,
+			block_number: self.block_number.map(|x| x as u64),
+			created_at: self.created_at,
+			updated_at: self.updated_at,
+		})
+	}
+}
+
+#[derive(Debug, Clone, Queryable, Selectable, Insertable, AsChangeset)]
+#[diesel(table_name = crate::schema::query_jobs)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(treat_none_as_null = true)]
+pub struct QueryJob {
+	pub query_id: String,
+	pub data: Vec<u8>,
+	pub stream_ids: Vec<String>,
+	pub state: String,
+	pub tips: Vec<String>,
+	pub payload: Option<Vec<u8>>,
+	pub journal: Option<Vec<u8>>,
+	pub seal: Option<Vec<u8>>,
+	pub tx_hash: Option<String>,
+	pub attempts: i32,
+	pub error: Option<String>,
+	pub created_at: DateTime<Utc>,
+	pub updated_at: DateTime<Utc>,
+}
+
+impl From<&dataverse_core::computa::QueryJob> for QueryJob {
+	fn from(value: &dataverse_core::computa::QueryJob) -> Self {
+		Self {
+			query_id: value.query_id.clone(),
+			data: value.data.clone(),
+			stream_ids: value.stream_ids.iter().map(ToString::to_string).collect(),
+			state: value.state.as_str().to_string(),
+			tips: value.tips.iter().map(ToString::to_string).collect(),
+			payload: value.payload.clone(),
+			journal: value.journal.clone(),
+			seal: value.seal.clone(),
+			tx_hash: value.tx_hash.clone(),
+			attempts: value.attempts,
+			error: value.error.clone(),
+			created_at: value.created_at,
+			updated_at: value.updated_at,
+		}
+	}
+}
+
+impl TryInto<dataverse_core::computa::QueryJob> for QueryJob {
+	type Error = anyhow::Error;
+
+	fn try_into(self) -> Result<dataverse_core::computa::QueryJob, Self::Error> {
+		Ok(dataverse_core::computa::QueryJob {
+			query_id: self.query_id,
+			data: self.data,
+			stream_ids: self
+				.stream_ids
+				.iter()
+				.map(|x| StreamId::from_str(x))
+				.collect::<Result<_, _>>()?,
+			state: self.state.parse()?,
+			tips: self
+				.tips
+				.into_iter()
+				.map(Cid::try_from)
+				.collect::<Result<_, _>>()?,
+			payload: self.payload,
+			journal: self.journal,
+			seal: self.seal,
+			tx_hash: self.tx_hash,
+			attempts: self.attempts,
+			error: self.error,
+			created_at: self.created_at,
+			updated_at: self.updated_at,
 		})
 	}
 }
@@ -0,0 +1,112 @@
+use ceramic_core::StreamId;
+use dataverse_ceramic::{SortBy, StatesPage};
+use diesel::dsl::sql;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::query_builder::{AstPass, Query, QueryFragment, QueryId};
+use diesel::query_dsl::LoadQuery;
+use diesel::sql_types::Text;
+
+use crate::{schema, Client};
+
+/// Query of `load_stream` and the tip lookups, boxed with the same sql for
+/// every stream so each connection prepares it once
+pub(crate) fn stream_by_id(stream_id: &StreamId) -> schema::streams::BoxedQuery<'static, Pg> {
+	schema::streams::table
+		.filter(schema::streams::stream_id.eq(stream_id.to_string()))
+		.into_boxed()
+}
+
+/// Query of `load_events_from_db`, served by `events_genesis_idx`
+pub(crate) fn events_by_genesis(stream_id: &StreamId) -> schema::events::BoxedQuery<'static, Pg> {
+	schema::events::table
+		.filter(schema::events::genesis.eq(stream_id.cid.to_string()))
+		.into_boxed()
+}
+
+/// Page of the instances of a model, newest first by the same times as
+/// `StreamState::sort_time`, compared as ISO-8601 text
+pub(crate) fn model_streams_page(
+	model_id: &StreamId,
+	account: Option<String>,
+	page: StatesPage,
+) -> schema::streams::BoxedQuery<'static, Pg> {
+	let mut query = schema::streams::table
+		.filter(schema::streams::model_id.eq(model_id.to_string()))
+		.into_boxed();
+	if let Some(account) = account {
+		query = query.filter(schema::streams::account.eq(account));
+	}
+	let sort_time = match page.sort_by {
+		SortBy::AnchoredAt => {
+			"COALESCE(to_char(anchored_at AT TIME ZONE 'UTC', 'YYYY-MM-DD\"T\"HH24:MI:SS.MS\"Z\"'), \
+			 content->>'updatedAt') DESC NULLS LAST"
+		}
+		SortBy::UpdatedAt => "content->>'updatedAt' DESC NULLS LAST",
+	};
+	query
+		.order(sql::<Text>(sort_time))
+		.then_order_by(schema::streams::stream_id.asc())
+		.offset(page.offset as i64)
+		.limit(page.limit as i64)
+}
+
+/// `EXPLAIN` of a query, loaded as one row per line of the plan
+#[derive(QueryId)]
+pub struct Explain<Q>(pub Q);
+
+impl<Q: QueryFragment<Pg>> QueryFragment<Pg> for Explain<Q> {
+	fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, Pg>) -> QueryResult<()> {
+		out.push_sql("EXPLAIN ");
+		self.0.walk_ast(out.reborrow())
+	}
+}
+
+impl<Q> Query for Explain<Q> {
+	type SqlType = Text;
+}
+
+impl<Q> RunQueryDsl<PgConnection> for Explain<Q> {}
+
+impl Client {
+	/// Plan postgres picks for the query, run on the primary
+	pub fn explain<Q>(&self, query: Q) -> anyhow::Result<String>
+	where
+		for<'a> Explain<Q>: LoadQuery<'a, PgConnection, String>,
+	{
+		let conn = &mut self.pool.get()?;
+		let plan: Vec<String> = Explain(query).load(conn)?;
+		Ok(plan.join("\n"))
+	}
+
+	/// Log the plan of a hot query at debug level if `explain` is set
+	pub(crate) fn debug_plan<Q>(&self, name: &str, query: Q)
+	where
+		for<'a> Explain<Q>: LoadQuery<'a, PgConnection, String>,
+	{
+		if !self.explain {
+			return;
+		}
+		match self.explain(query) {
+			Ok(plan) => tracing::debug!(query = name, "query plan:\n{}", plan),
+			Err(err) => tracing::warn!(query = name, "failed to explain query: {}", err),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::models;
+
+	#[test]
+	fn hot_queries_are_cached() -> anyhow::Result<()> {
+		let stream_id: StreamId =
+			"kjzl6hvfrbw6c89f0p1lyd1e78tel33qebisfdsi0prhhapn4rye45j1uj72tju".parse()?;
+		let stream = stream_by_id(&stream_id).select(models::Stream::as_select());
+		assert!(stream.is_safe_to_cache_prepared(&Pg)?);
+		let events = events_by_genesis(&stream_id).select(models::Event::as_select());
+		assert!(events.is_safe_to_cache_prepared(&Pg)?);
+		Ok(())
+	}
+}
@@ -0,0 +1,29 @@
+use dataverse_core::computa::{QueryJob, QueryJobStore};
+use diesel::prelude::*;
+
+use crate::{models, schema, Client};
+
+#[async_trait::async_trait]
+impl QueryJobStore for Client {
+	async fn save_job(&self, job: &QueryJob) -> anyhow::Result<()> {
+		let conn = &mut self.pool.get()?;
+		let job = models::QueryJob::from(job);
+		diesel::insert_into(schema::query_jobs::table)
+			.values(&job)
+			.on_conflict(schema::query_jobs::query_id)
+			.do_update()
+			.set(&job)
+			.execute(conn)?;
+		Ok(())
+	}
+
+	async fn load_job(&self, query_id: &str) -> anyhow::Result<Option<QueryJob>> {
+		let conn = &mut self.pool.get()?;
+		let job: Option<models::QueryJob> = schema::query_jobs::table
+			.find(query_id)
+			.select(models::QueryJob::as_select())
+			.first(conn)
+			.optional()?;
+		job.map(TryInto::try_into).transpose()
+	}
+}
@@ -0,0 +1,252 @@
+use std::collections::{HashMap, HashSet};
+
+use ceramic_core::{Cid, StreamId};
+use chrono::{DateTime, Duration, Utc};
+use dataverse_ceramic::{CommitDag, Event, EventValue};
+use diesel::prelude::*;
+
+use crate::{models, plan, schema, Client};
+
+/// Retention configured for the streams of a model.
+///
+/// Only branches the tip of a stream does not descend from are pruned, once
+/// the log of the tip is anchored. The log itself, earlier anchors included,
+/// is always kept so the stream still loads from its events.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RetentionPolicy {
+	KeepAll,
+	/// keep only the branch of the tip, dropping every superseded branch
+	LatestBranch,
+	/// drop superseded branches whose events are all older than the duration
+	AnchoredOlderThan(Duration),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RetentionConfig {
+	pub policies: HashMap<StreamId, RetentionPolicy>,
+}
+
+impl RetentionConfig {
+	pub fn policy(&self, model_id: &StreamId) -> RetentionPolicy {
+		self.policies
+			.get(model_id)
+			.copied()
+			.unwrap_or(RetentionPolicy::KeepAll)
+	}
+}
+
+/// Stored events of a stream that can be dropped under the policy, in the
+/// order given. Branches are pruned whole, as a branch missing some of its
+/// events could no longer be loaded.
+pub fn prunable_events(
+	events: &[Event],
+	tip: Cid,
+	policy: RetentionPolicy,
+	created_at: &HashMap<Cid, DateTime<Utc>>,
+	now: DateTime<Utc>,
+) -> anyhow::Result<Vec<Cid>> {
+	let threshold = match policy {
+		RetentionPolicy::KeepAll => return Ok(vec![]),
+		RetentionPolicy::LatestBranch => None,
+		RetentionPolicy::AnchoredOlderThan(age) => Some(now - age),
+	};
+	let log = CommitDag::new(events.iter().cloned())?.log(tip)?;
+	if !log
+		.iter()
+		.any(|event| matches!(event.value, EventValue::Anchor(_)))
+	{
+		return Ok(vec![]);
+	}
+	let on_log: HashSet<Cid> = log.iter().map(|event| event.cid).collect();
+	let by_cid: HashMap<Cid, &Event> = events.iter().map(|event| (event.cid, event)).collect();
+
+	// events off the log grouped by the first event of their branch
+	let mut branches: HashMap<Cid, Vec<Cid>> = HashMap::new();
+	for event in events.iter().filter(|event| !on_log.contains(&event.cid)) {
+		let mut root = event;
+		while let Some(prev) = root.prev()? {
+			match by_cid.get(&prev) {
+				Some(prev) if !on_log.contains(&prev.cid) => root = prev,
+				_ => break,
+			}
+		}
+		branches.entry(root.cid).or_default().push(event.cid);
+	}
+
+	let prunable: HashSet<Cid> = branches
+		.into_values()
+		.filter(|branch| {
+			branch
+				.iter()
+				.all(|cid| match (threshold, created_at.get(cid)) {
+					(None, _) => true,
+					(Some(threshold), Some(created_at)) => *created_at < threshold,
+					(Some(_), None) => false,
+				})
+		})
+		.flatten()
+		.collect();
+	Ok(events
+		.iter()
+		.map(|event| event.cid)
+		.filter(|cid| prunable.contains(cid))
+		.collect())
+}
+
+impl Client {
+	pub async fn apply_retention(
+		&self,
+		model_id: &StreamId,
+		policy: RetentionPolicy,
+	) -> anyhow::Result<usize> {
+		if policy == RetentionPolicy::KeepAll {
+			return Ok(0);
+		}
+		let streams: Vec<models::Stream> = {
+			let conn = &mut self.pool.get()?;
+			schema::streams::table
+				.filter(schema::streams::model_id.eq(model_id.to_string()))
+				.select(models::Stream::as_select())
+				.load(conn)?
+		};
+
+		let mut pruned = 0;
+		for stream in streams {
+			let stream_id = stream.stream_id()?;
+			let conn = &mut self.pool.get()?;
+			// the stream row is locked so its tip cannot move while pruning
+			let cids = conn.transaction::<_, anyhow::Error, _>(|conn| {
+				let tip: String = schema::streams::table
+					.find(&stream.stream_id)
+					.select(schema::streams::tip)
+					.for_update()
+					.first(conn)?;
+				let tip = Cid::try_from(tip.as_str())?;
+				let events = plan::events_by_genesis(&stream_id)
+					.select(models::Event::as_select())
+					.load::<models::Event>(conn)?
+					.into_iter()
+					.map(TryInto::try_into)
+					.collect::<anyhow::Result<Vec<Event>>>()?;
+				let created_at: HashMap<Cid, DateTime<Utc>> = schema::events::table
+					.filter(schema::events::genesis.eq(stream_id.cid.to_string()))
+					.select((schema::events::cid, schema::events::created_at))
+					.load::<(String, DateTime<Utc>)>(conn)?
+					.into_iter()
+					.filter_map(|(cid, at)| Cid::try_from(cid).ok().map(|cid| (cid, at)))
+					.collect();
+
+				let cids: Vec<String> =
+					prunable_events(&events, tip, policy, &created_at, Utc::now())?
+						.iter()
+						.map(ToString::to_string)
+						.collect();
+				if !cids.is_empty() {
+					diesel::delete(schema::events::table)
+						.filter(schema::events::cid.eq_any(&cids))
+						.execute(conn)?;
+				}
+				Ok(cids)
+			})?;
+			if cids.is_empty() {
+				continue;
+			}
+			pruned += cids.len();
+			tracing::info!(
+				stream_id = stream_id.to_string(),
+				count = cids.len(),
+				"pruned superseded branches by retention policy"
+			);
+		}
+		Ok(pruned)
+	}
+
+	pub async fn apply_retention_config(&self, config: &RetentionConfig) -> anyhow::Result<usize> {
+		let mut pruned = 0;
+		for (model_id, policy) in &config.policies {
+			pruned += self.apply_retention(model_id, *policy).await?;
+		}
+		Ok(pruned)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::str::FromStr;
+
+	use dataverse_ceramic::commit::example;
+	use dataverse_ceramic::event::AnchorValue;
+
+	use super::*;
+
+	fn anchor(cid: &str, genesis: Cid, prev: Cid) -> Event {
+		Event {
+			cid: Cid::from_str(cid).unwrap(),
+			value: AnchorValue {
+				id: genesis,
+				prev,
+				..Default::default()
+			}
+			.into(),
+		}
+	}
+
+	#[test]
+	fn prune_superseded_branches() -> anyhow::Result<()> {
+		let events = example::events(2);
+		let (genesis, data) = (events[0].cid, events[1].cid);
+		let first = anchor(
+			"bafyreih3skzznsyro5i4bsfsyquhcewyqmsra6cx47nj2eb6aerm2nihuu",
+			genesis,
+			data,
+		);
+		let patch = serde_json::json!([{ "op": "replace", "path": "/text", "value": "next" }]);
+		let next = example::data_event(genesis, first.cid, patch)?;
+		let second = anchor(
+			"bafyreidnbzsaplrdpjx3schac4fjhwqjzv3kbvdswi52npq3kpdzpbv5qa",
+			genesis,
+			next.cid,
+		);
+		let patch = serde_json::json!([{ "op": "replace", "path": "/text", "value": "branch" }]);
+		let branch = example::data_event(genesis, data, patch.clone())?;
+		let branch_tip = example::data_event(genesis, branch.cid, patch)?;
+
+		let mut stored = events.clone();
+		stored.extend([
+			first,
+			next,
+			second.clone(),
+			branch.clone(),
+			branch_tip.clone(),
+		]);
+		let now = Utc::now();
+		let created_at = HashMap::from([
+			(branch.cid, now - Duration::days(10)),
+			(branch_tip.cid, now - Duration::days(2)),
+		]);
+		let prune = |tip, policy| prunable_events(&stored, tip, policy, &created_at, now);
+
+		assert!(prune(second.cid, RetentionPolicy::KeepAll)?.is_empty());
+		// nothing is pruned before the log of the tip is anchored
+		assert!(prune(data, RetentionPolicy::LatestBranch)?.is_empty());
+		// branches are kept until all their events are old enough
+		let policy = RetentionPolicy::AnchoredOlderThan(Duration::days(7));
+		assert!(prune(second.cid, policy)?.is_empty());
+		let policy = RetentionPolicy::AnchoredOlderThan(Duration::days(1));
+		assert_eq!(prune(second.cid, policy)?, vec![branch.cid, branch_tip.cid]);
+
+		let pruned = prune(second.cid, RetentionPolicy::LatestBranch)?;
+		assert_eq!(pruned, vec![branch.cid, branch_tip.cid]);
+		// the stream still loads from the remaining events, earlier anchor included
+		let remaining = stored
+			.iter()
+			.filter(|event| !pruned.contains(&event.cid))
+			.cloned();
+		let dag = CommitDag::new(remaining)?;
+		assert_eq!(dag.heads(), vec![second.cid]);
+		let log: Vec<Cid> = dag.log(second.cid)?.iter().map(|x| x.cid).collect();
+		assert_eq!(log.len(), 5);
+		assert_eq!(log, stored[..5].iter().map(|x| x.cid).collect::<Vec<_>>());
+		Ok(())
+	}
+}
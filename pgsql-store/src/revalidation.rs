@@ -0,0 +1,38 @@
+use ceramic_core::StreamId;
+use chrono::Utc;
+use dataverse_file_system::file::revalidate::{RevalidationReport, RevalidationReportStore};
+use diesel::prelude::*;
+use serde_json::Value;
+
+use crate::{schema, Client};
+
+#[async_trait::async_trait]
+impl RevalidationReportStore for Client {
+	async fn save_report(&self, report: &RevalidationReport) -> anyhow::Result<()> {
+		let conn = &mut self.pool.get()?;
+		let value = serde_json::to_value(report)?;
+		diesel::insert_into(schema::revalidation_reports::table)
+			.values((
+				schema::revalidation_reports::model_id.eq(&report.model_id),
+				schema::revalidation_reports::report.eq(&value),
+			))
+			.on_conflict(schema::revalidation_reports::model_id)
+			.do_update()
+			.set((
+				schema::revalidation_reports::report.eq(&value),
+				schema::revalidation_reports::updated_at.eq(Utc::now()),
+			))
+			.execute(conn)?;
+		Ok(())
+	}
+
+	async fn load_report(&self, model_id: &StreamId) -> anyhow::Result<Option<RevalidationReport>> {
+		let conn = &mut self.read_pool.get()?;
+		let report: Option<Value> = schema::revalidation_reports::table
+			.find(model_id.to_string())
+			.select(schema::revalidation_reports::report)
+			.first(conn)
+			.optional()?;
+		Ok(report.map(serde_json::from_value).transpose()?)
+	}
+}
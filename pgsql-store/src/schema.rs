@@ -6,6 +6,25 @@ pub mod sql_types {
 	pub struct FangTaskState;
 }
 
+diesel::table! {
+	attestations (tx_hash) {
+		#[max_length = 66]
+		tx_hash -> Bpchar,
+		chain_id -> Int8,
+		#[max_length = 42]
+		contract -> Bpchar,
+		#[max_length = 70]
+		stream_id -> Varchar,
+		#[max_length = 70]
+		commit_id -> Varchar,
+		#[max_length = 20]
+		status -> Varchar,
+		block_number -> Nullable<Int8>,
+		created_at -> Timestamptz,
+		updated_at -> Timestamptz,
+	}
+}
+
 diesel::table! {
 	events (cid) {
 		#[max_length = 70]
@@ -15,6 +34,7 @@ diesel::table! {
 		#[max_length = 70]
 		genesis -> Varchar,
 		blocks -> Array<Nullable<Bytea>>,
+		created_at -> Timestamptz,
 	}
 }
 
@@ -37,6 +57,92 @@ diesel::table! {
 	}
 }
 
+diesel::table! {
+	folder_changes (seq) {
+		seq -> Int8,
+		#[max_length = 70]
+		folder_id -> Varchar,
+		#[max_length = 70]
+		stream_id -> Varchar,
+		#[max_length = 70]
+		tip -> Varchar,
+		created_at -> Timestamptz,
+		removed -> Bool,
+	}
+}
+
+diesel::table! {
+	folder_stats (folder_id) {
+		#[max_length = 70]
+		folder_id -> Varchar,
+		content_folder_count -> Int8,
+		file_count -> Int8,
+		updated_at -> Timestamptz,
+	}
+}
+
+diesel::table! {
+	model_activity (model_id, hour) {
+		#[max_length = 70]
+		model_id -> Varchar,
+		hour -> Timestamptz,
+		commits -> Int8,
+	}
+}
+
+diesel::table! {
+	query_jobs (query_id) {
+		#[max_length = 100]
+		query_id -> Varchar,
+		data -> Bytea,
+		stream_ids -> Array<Text>,
+		#[max_length = 20]
+		state -> Varchar,
+		tips -> Array<Text>,
+		payload -> Nullable<Bytea>,
+		journal -> Nullable<Bytea>,
+		seal -> Nullable<Bytea>,
+		#[max_length = 66]
+		tx_hash -> Nullable<Bpchar>,
+		attempts -> Int4,
+		error -> Nullable<Text>,
+		created_at -> Timestamptz,
+		updated_at -> Timestamptz,
+	}
+}
+
+diesel::table! {
+	revalidation_reports (model_id) {
+		#[max_length = 70]
+		model_id -> Varchar,
+		report -> Jsonb,
+		updated_at -> Timestamptz,
+	}
+}
+
+diesel::table! {
+	service_tokens (id) {
+		id -> Uuid,
+		#[max_length = 100]
+		name -> Varchar,
+		#[max_length = 64]
+		token_hash -> Bpchar,
+		scopes -> Array<Text>,
+		created_at -> Timestamptz,
+		revoked_at -> Nullable<Timestamptz>,
+	}
+}
+
+diesel::table! {
+	stream_labels (stream_id, label) {
+		#[max_length = 70]
+		stream_id -> Varchar,
+		#[max_length = 64]
+		label -> Varchar,
+		created_at -> Timestamptz,
+	}
+}
+
 diesel::table! {
 	streams (stream_id) {
 		#[max_length = 70]
@@ -49,7 +155,24 @@ diesel::table! {
 		#[max_length = 70]
 		model_id -> Nullable<Varchar>,
 		content -> Jsonb,
+		published -> Jsonb,
+		anchored_at -> Nullable<Timestamptz>,
+		created_at -> Timestamptz,
+		updated_at -> Timestamptz,
+		forked -> Bool,
 	}
 }
 
-diesel::allow_tables_to_appear_in_same_query!(events, fang_tasks, streams,);
+diesel::allow_tables_to_appear_in_same_query!(
+	attestations,
+	events,
+	fang_tasks,
+	folder_changes,
+	folder_stats,
+	model_activity,
+	query_jobs,
+	revalidation_reports,
+	service_tokens,
+	stream_labels,
+	streams,
+);
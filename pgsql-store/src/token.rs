@@ -0,0 +1,35 @@
+use chrono::Utc;
+use dataverse_file_system::auth::token::{ServiceToken, ServiceTokenStore};
+use diesel::prelude::*;
+
+use crate::{models, schema, Client};
+
+#[async_trait::async_trait]
+impl ServiceTokenStore for Client {
+	async fn save_token(&self, token: &ServiceToken, token_hash: &str) -> anyhow::Result<()> {
+		let conn = &mut self.pool.get()?;
+		diesel::insert_into(schema::service_tokens::table)
+			.values(models::ServiceToken::new(token, token_hash))
+			.execute(conn)?;
+		Ok(())
+	}
+
+	async fn lookup_token(&self, token_hash: &str) -> anyhow::Result<Option<ServiceToken>> {
+		let conn = &mut self.pool.get()?;
+		let token: Option<models::ServiceToken> = schema::service_tokens::table
+			.filter(schema::service_tokens::token_hash.eq(token_hash))
+			.select(models::ServiceToken::as_select())
+			.first(conn)
+			.optional()?;
+		token.map(TryInto::try_into).transpose()
+	}
+
+	async fn revoke_token(&self, id: &uuid::Uuid) -> anyhow::Result<()> {
+		let conn = &mut self.pool.get()?;
+		diesel::update(schema::service_tokens::table.find(id))
+			.filter(schema::service_tokens::revoked_at.is_null())
+			.set(schema::service_tokens::revoked_at.eq(Utc::now()))
+			.execute(conn)?;
+		Ok(())
+	}
+}
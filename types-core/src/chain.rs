@@ -0,0 +1,327 @@
+use alloc::vec::Vec;
+
+use cid::Cid;
+use minicbor::data::Type;
+use minicbor::{Decoder, Encoder};
+use sha2::{Digest, Sha256};
+
+use crate::digest::{block_cid, verify_block, DAG_CBOR, DAG_JOSE};
+use crate::{Error, StreamId};
+
+/// Blocks of one event: the dag-jose envelope with its linked payload block
+/// for signed events, or the dag-cbor block alone for unsigned genesis and
+/// anchor commits
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventBlocks {
+	pub envelope: Vec<u8>,
+	pub linked: Option<Vec<u8>>,
+}
+
+/// Outcome of a verified chain, committed by guest programs to their journal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Journal {
+	pub tip: Cid,
+	pub model: StreamId,
+	/// sha2-256 over the cids of the data blocks from genesis to tip, which
+	/// determine the content at the tip
+	pub content_hash: [u8; 32],
+}
+
+impl Journal {
+	/// Dag-cbor array of the tip cid, model stream id and content hash
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut e = Encoder::new(Vec::new());
+		e.array(3)
+			.and_then(|e| e.bytes(&self.tip.to_bytes()))
+			.and_then(|e| e.bytes(&self.model.to_vec()))
+			.and_then(|e| e.bytes(&self.content_hash))
+			.expect("encoding into a vec is infallible");
+		e.into_writer()
+	}
+}
+
+/// Events from genesis to tip as a dag-cbor array of `[envelope, linked]`,
+/// `linked` being null for unsigned events
+pub fn encode_events(events: &[EventBlocks]) -> Vec<u8> {
+	let mut e = Encoder::new(Vec::new());
+	e.array(events.len() as u64)
+		.expect("encoding into a vec is infallible");
+	for event in events {
+		e.array(2)
+			.and_then(|e| e.bytes(&event.envelope))
+			.expect("encoding into a vec is infallible");
+		match &event.linked {
+			Some(linked) => e.bytes(linked),
+			None => e.null(),
+		}
+		.expect("encoding into a vec is infallible");
+	}
+	e.into_writer()
+}
+
+pub fn decode_events(bytes: &[u8]) -> Result<Vec<EventBlocks>, Error> {
+	let mut d = Decoder::new(bytes);
+	let len = d.array()?.ok_or(Error::Decode)?;
+	let mut events = Vec::new();
+	for _ in 0..len {
+		if d.array()? != Some(2) {
+			return Err(Error::Decode);
+		}
+		let envelope = d.bytes()?.to_vec();
+		let linked = match d.datatype()? {
+			Type::Null => {
+				d.null()?;
+				None
+			}
+			_ => Some(d.bytes()?.to_vec()),
+		};
+		events.push(EventBlocks { envelope, linked });
+	}
+	Ok(events)
+}
+
+#[derive(Default)]
+struct Commit {
+	id: Option<Cid>,
+	prev: Option<Cid>,
+	model: Option<Vec<u8>>,
+	proof: bool,
+}
+
+/// Dag-cbor link, tag 42 over the cid bytes prefixed with the identity
+/// multibase
+fn read_link(d: &mut Decoder) -> Result<Cid, Error> {
+	d.tag()?;
+	match d.bytes()? {
+		[0, cid @ ..] => Cid::read_bytes(cid).map_err(|_| Error::InvalidCid),
+		_ => Err(Error::InvalidCid),
+	}
+}
+
+fn read_model(d: &mut Decoder) -> Result<Option<Vec<u8>>, Error> {
+	let mut model = None;
+	for _ in 0..d.map()?.ok_or(Error::Decode)? {
+		match d.str()? {
+			"model" => model = Some(d.bytes()?.to_vec()),
+			_ => d.skip()?,
+		}
+	}
+	Ok(model)
+}
+
+fn read_commit(block: &[u8]) -> Result<Commit, Error> {
+	let mut d = Decoder::new(block);
+	let mut commit = Commit::default();
+	for _ in 0..d.map()?.ok_or(Error::Decode)? {
+		match d.str()? {
+			"id" => commit.id = Some(read_link(&mut d)?),
+			"prev" => commit.prev = Some(read_link(&mut d)?),
+			"header" => commit.model = read_model(&mut d)?,
+			"proof" => {
+				commit.proof = true;
+				d.skip()?;
+			}
+			_ => d.skip()?,
+		}
+	}
+	Ok(commit)
+}
+
+/// Cid of the linked block of a dag-jose envelope, None for dag-cbor blocks
+fn read_payload_link(envelope: &[u8]) -> Result<Option<Cid>, Error> {
+	let mut d = Decoder::new(envelope);
+	let mut payload = None;
+	let mut signed = false;
+	for _ in 0..d.map()?.ok_or(Error::Decode)? {
+		match d.str()? {
+			"payload" => payload = Some(d.bytes()?),
+			"signatures" => {
+				signed = true;
+				d.skip()?;
+			}
+			_ => d.skip()?,
+		}
+	}
+	match (payload, signed) {
+		(Some(payload), true) => Ok(Some(
+			Cid::read_bytes(payload).map_err(|_| Error::InvalidCid)?,
+		)),
+		_ => Ok(None),
+	}
+}
+
+/// Verify the events of a model instance from genesis to `expected_tip`.
+///
+/// Cids are recomputed from the blocks, each event must link its
+/// predecessor as prev and the genesis as id, and the genesis header must
+/// name `model_id`. Signatures and anchor proofs are not checked here.
+pub fn verify_chain(
+	events_bytes: &[u8],
+	expected_tip: &Cid,
+	model_id: &StreamId,
+) -> Result<Journal, Error> {
+	let model = model_id.to_vec();
+	let mut genesis: Option<Cid> = None;
+	let mut tip: Option<Cid> = None;
+	let mut content = Sha256::new();
+
+	for event in decode_events(events_bytes)? {
+		let (cid, commit, data) = match read_payload_link(&event.envelope)? {
+			Some(link) => {
+				let linked = event.linked.as_deref().ok_or(Error::MissingLinkedBlock)?;
+				verify_block(&link, linked)?;
+				let cid = block_cid(DAG_JOSE, &event.envelope);
+				(cid, read_commit(linked)?, Some(link))
+			}
+			None => {
+				let cid = block_cid(DAG_CBOR, &event.envelope);
+				let commit = read_commit(&event.envelope)?;
+				let data = (!commit.proof).then_some(cid);
+				(cid, commit, data)
+			}
+		};
+
+		match genesis {
+			None => {
+				if commit.id.is_some() || commit.prev.is_some() {
+					return Err(Error::InvalidGenesis(cid));
+				}
+				if commit.model.as_deref() != Some(model.as_slice()) {
+					return Err(Error::InvalidModel(cid));
+				}
+				genesis = Some(cid);
+			}
+			Some(genesis) => {
+				if commit.id != Some(genesis) {
+					return Err(Error::InvalidGenesis(cid));
+				}
+				if commit.prev != tip {
+					return Err(Error::InvalidPrevious(cid));
+				}
+			}
+		}
+		if let Some(data) = data {
+			content.update(data.to_bytes());
+		}
+		tip = Some(cid);
+	}
+
+	let tip = tip.ok_or(Error::EmptyChain)?;
+	if tip != *expected_tip {
+		return Err(Error::InvalidTip(tip));
+	}
+	Ok(Journal {
+		tip,
+		model: *model_id,
+		content_hash: content.finalize().into(),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::BTreeMap;
+
+	use libipld::cbor::DagCborCodec;
+	use libipld::prelude::Codec;
+	use libipld::Ipld;
+
+	use super::*;
+
+	fn map(entries: Vec<(&str, Ipld)>) -> Ipld {
+		Ipld::Map(BTreeMap::from_iter(
+			entries.into_iter().map(|(k, v)| (k.to_string(), v)),
+		))
+	}
+
+	fn encode(node: Ipld) -> Vec<u8> {
+		DagCborCodec.encode(&node).unwrap()
+	}
+
+	fn chain(model: &StreamId) -> (Vec<EventBlocks>, Cid) {
+		let genesis = encode(map(vec![
+			("data", Ipld::Null),
+			("header", map(vec![("model", Ipld::Bytes(model.to_vec()))])),
+		]));
+		let genesis_cid = block_cid(DAG_CBOR, &genesis);
+
+		let linked = encode(map(vec![
+			("data", Ipld::List(vec![])),
+			("header", map(vec![])),
+			("id", Ipld::Link(genesis_cid)),
+			("prev", Ipld::Link(genesis_cid)),
+		]));
+		let link = block_cid(DAG_CBOR, &linked);
+		let envelope = encode(map(vec![
+			("link", Ipld::Link(link)),
+			("payload", Ipld::Bytes(link.to_bytes())),
+			("signatures", Ipld::List(vec![])),
+		]));
+		let signed_cid = block_cid(DAG_JOSE, &envelope);
+
+		let anchor = encode(map(vec![
+			("id", Ipld::Link(genesis_cid)),
+			("path", Ipld::String("".to_string())),
+			("prev", Ipld::Link(signed_cid)),
+			("proof", Ipld::Link(block_cid(DAG_CBOR, b"proof"))),
+		]));
+		let anchor_cid = block_cid(DAG_CBOR, &anchor);
+
+		let events = vec![
+			EventBlocks {
+				envelope: genesis,
+				linked: None,
+			},
+			EventBlocks {
+				envelope,
+				linked: Some(linked),
+			},
+			EventBlocks {
+				envelope: anchor,
+				linked: None,
+			},
+		];
+		(events, anchor_cid)
+	}
+
+	#[test]
+	fn test_verify_chain() {
+		let model = StreamId {
+			r#type: 2,
+			cid: block_cid(DAG_CBOR, b"model"),
+		};
+		let (events, tip) = chain(&model);
+		let bytes = encode_events(&events);
+		assert_eq!(decode_events(&bytes), Ok(events.clone()));
+
+		let journal = verify_chain(&bytes, &tip, &model).unwrap();
+		assert_eq!(journal.tip, tip);
+		assert_eq!(journal, verify_chain(&bytes, &tip, &model).unwrap());
+
+		let other = StreamId {
+			r#type: 2,
+			cid: block_cid(DAG_CBOR, b"other"),
+		};
+		assert!(matches!(
+			verify_chain(&bytes, &tip, &other),
+			Err(Error::InvalidModel(_))
+		));
+		assert!(matches!(
+			verify_chain(&bytes, &model.cid, &model),
+			Err(Error::InvalidTip(_))
+		));
+
+		let mut tampered = events.clone();
+		tampered[1].linked = Some(b"tampered".to_vec());
+		assert!(matches!(
+			verify_chain(&encode_events(&tampered), &tip, &model),
+			Err(Error::DigestMismatch(_))
+		));
+
+		let mut reordered = events;
+		reordered.swap(1, 2);
+		assert!(matches!(
+			verify_chain(&encode_events(&reordered), &tip, &model),
+			Err(Error::InvalidPrevious(_))
+		));
+	}
+}
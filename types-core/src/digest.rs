@@ -0,0 +1,48 @@
+use cid::multihash::Multihash;
+use cid::Cid;
+use sha2::{Digest, Sha256};
+
+use crate::Error;
+
+pub const SHA2_256: u64 = 0x12;
+pub const DAG_CBOR: u64 = 0x71;
+pub const DAG_JOSE: u64 = 0x85;
+pub const RAW: u64 = 0x55;
+
+fn sha2_256(block: &[u8]) -> Multihash {
+	// a 32 bytes digest always fits the 64 bytes multihash
+	Multihash::wrap(SHA2_256, &Sha256::digest(block)).expect("sha2-256 digest fits multihash")
+}
+
+/// Cid v1 of a block hashed with sha2-256, as written by ceramic
+pub fn block_cid(codec: u64, block: &[u8]) -> Cid {
+	Cid::new_v1(codec, sha2_256(block))
+}
+
+/// Check that `block` hashes to `cid`, only sha2-256 is supported
+pub fn verify_block(cid: &Cid, block: &[u8]) -> Result<(), Error> {
+	if cid.hash().code() != SHA2_256 {
+		return Err(Error::UnsupportedHash(cid.hash().code()));
+	}
+	if cid.hash().digest() != sha2_256(block).digest() {
+		return Err(Error::DigestMismatch(*cid));
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_verify_block() {
+		let block = b"dataverse".to_vec();
+		let cid = block_cid(RAW, &block);
+		assert_eq!(cid.codec(), RAW);
+		assert_eq!(verify_block(&cid, &block), Ok(()));
+		assert_eq!(
+			verify_block(&cid, b"tampered"),
+			Err(Error::DigestMismatch(cid))
+		);
+	}
+}
@@ -0,0 +1,51 @@
+use core::fmt;
+
+use cid::Cid;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+	InvalidCid,
+	InvalidVarint,
+	InvalidStreamIdCodec(u64),
+	TrailingBytes(usize),
+	UnsupportedHash(u64),
+	DigestMismatch(Cid),
+	Decode,
+	EmptyChain,
+	MissingLinkedBlock,
+	InvalidGenesis(Cid),
+	InvalidModel(Cid),
+	InvalidPrevious(Cid),
+	InvalidTip(Cid),
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Error::InvalidCid => write!(f, "invalid cid"),
+			Error::InvalidVarint => write!(f, "invalid varint"),
+			Error::InvalidStreamIdCodec(codec) => {
+				write!(f, "invalid stream id codec {:#x}", codec)
+			}
+			Error::TrailingBytes(len) => write!(f, "{} trailing bytes", len),
+			Error::UnsupportedHash(code) => write!(f, "unsupported hash {:#x}", code),
+			Error::DigestMismatch(cid) => write!(f, "block digest differs from {}", cid),
+			Error::Decode => write!(f, "invalid dag-cbor block"),
+			Error::EmptyChain => write!(f, "no events in chain"),
+			Error::MissingLinkedBlock => write!(f, "signed event without linked block"),
+			Error::InvalidGenesis(cid) => write!(f, "event {} not of the genesis", cid),
+			Error::InvalidModel(cid) => write!(f, "genesis {} of another model", cid),
+			Error::InvalidPrevious(cid) => write!(f, "event {} not following its prev", cid),
+			Error::InvalidTip(cid) => write!(f, "chain ends at unexpected tip {}", cid),
+		}
+	}
+}
+
+impl From<minicbor::decode::Error> for Error {
+	fn from(_: minicbor::decode::Error) -> Self {
+		Error::Decode
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
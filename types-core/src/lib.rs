@@ -0,0 +1,21 @@
+//! Stream ids, cids, block digest checks and event chain verification of
+//! dataverse without std, for guest programs of zkvms proving that a file
+//! content existed at a commit.
+//!
+//! Build with `default-features = false` in `no_std` environments, `alloc` is
+//! still required.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod chain;
+pub mod digest;
+pub mod errors;
+pub mod stream_id;
+
+pub use chain::{verify_chain, EventBlocks, Journal};
+pub use cid::Cid;
+pub use digest::{block_cid, verify_block};
+pub use errors::Error;
+pub use stream_id::StreamId;
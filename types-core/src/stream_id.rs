@@ -0,0 +1,76 @@
+use alloc::vec::Vec;
+
+use cid::Cid;
+
+use crate::Error;
+
+/// Multicodec of stream ids
+pub const STREAM_ID_CODEC: u64 = 0xce;
+
+/// Stream id as its type and genesis cid, without the base36 encoding of
+/// `ceramic_core::StreamId`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StreamId {
+	pub r#type: u64,
+	pub cid: Cid,
+}
+
+fn read_varint(bytes: &[u8]) -> Result<(u64, &[u8]), Error> {
+	unsigned_varint::decode::u64(bytes).map_err(|_| Error::InvalidVarint)
+}
+
+fn write_varint(value: u64, bytes: &mut Vec<u8>) {
+	let mut buf = unsigned_varint::encode::u64_buffer();
+	bytes.extend_from_slice(unsigned_varint::encode::u64(value, &mut buf));
+}
+
+impl StreamId {
+	pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+		let (codec, rest) = read_varint(bytes)?;
+		if codec != STREAM_ID_CODEC {
+			return Err(Error::InvalidStreamIdCodec(codec));
+		}
+		let (r#type, mut rest) = read_varint(rest)?;
+		let cid = Cid::read_bytes(&mut rest).map_err(|_| Error::InvalidCid)?;
+		if !rest.is_empty() {
+			return Err(Error::TrailingBytes(rest.len()));
+		}
+		Ok(Self { r#type, cid })
+	}
+
+	pub fn to_vec(&self) -> Vec<u8> {
+		let mut bytes = Vec::new();
+		write_varint(STREAM_ID_CODEC, &mut bytes);
+		write_varint(self.r#type, &mut bytes);
+		bytes.extend(self.cid.to_bytes());
+		bytes
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::digest::{block_cid, DAG_CBOR};
+
+	#[test]
+	fn test_stream_id_bytes() {
+		let stream_id = StreamId {
+			r#type: 3,
+			cid: block_cid(DAG_CBOR, b"genesis"),
+		};
+		let bytes = stream_id.to_vec();
+		assert_eq!(&bytes[..3], [0xce, 0x01, 0x03]);
+		assert_eq!(StreamId::from_bytes(&bytes), Ok(stream_id));
+
+		let mut trailing = bytes.clone();
+		trailing.push(0);
+		assert_eq!(
+			StreamId::from_bytes(&trailing),
+			Err(Error::TrailingBytes(1))
+		);
+		assert_eq!(
+			StreamId::from_bytes(&bytes[2..]),
+			Err(Error::InvalidStreamIdCodec(3))
+		);
+	}
+}